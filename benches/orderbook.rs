@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::hint::black_box;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rand::distributions::Uniform;
+use rand::prelude::*;
+use uuid::Uuid;
+
+use orderbook::orderbook::command::Command;
+use orderbook::orderbook::config::OrderBookConfig;
+use orderbook::orderbook::id::{
+    IdGenerator, MonotonicIdGenerator, SnowflakeIdGenerator, UuidGenerator,
+};
+use orderbook::orderbook::ladder::LadderKind;
+use orderbook::orderbook::order::{Order, OrderType, Side};
+use orderbook::orderbook::order_queue::QueueBackendKind;
+use orderbook::orderbook::orderbook_impl::OrderBook;
+use orderbook::orderbook::ring::spsc_ring;
+use orderbook::orderbook::simulator::{MarketMaker, MomentumTrader, NoiseTrader, Simulator};
+use orderbook::orderbook::types::{Price, Quantity};
+
+const BACKENDS: [QueueBackendKind; 3] = [
+    QueueBackendKind::IntrusiveList,
+    QueueBackendKind::VecDeque,
+    QueueBackendKind::Slab,
+];
+
+// Operations timed individually for the percentile summary
+const PERCENTILE_SAMPLES: u64 = 100_000;
+
+fn new_orderbook(backend: QueueBackendKind) -> OrderBook {
+    OrderBook::with_config(OrderBookConfig {
+        queue_backend: backend,
+        ..OrderBookConfig::default()
+    })
+}
+
+fn gtc(side: Side, price: Price, quantity: Quantity) -> Arc<Order> {
+    Arc::new(Order::new(OrderType::GoodTillCancel, side, price, quantity))
+}
+
+// Non-crossing orders: bids in [90, 99], asks in [101, 110]
+fn random_resting_orders(n: u64) -> Vec<Arc<Order>> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let offset = Uniform::new_inclusive(1, 10);
+    let quantity = Uniform::new_inclusive(1, 100);
+    (0..n)
+        .map(|i| {
+            let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+            let price = match side {
+                Side::Buy => 100 - offset.sample(&mut rng),
+                Side::Sell => 100 + offset.sample(&mut rng),
+            };
+            gtc(side, price, quantity.sample(&mut rng))
+        })
+        .collect()
+}
+
+// Each workload returns the latency of every timed operation, so the same run feeds both
+// criterion (as the sum) and the percentile summary.
+fn add_latencies(backend: QueueBackendKind, n: u64) -> Vec<Duration> {
+    let mut book = new_orderbook(backend);
+    random_resting_orders(n)
+        .iter()
+        .map(|order| {
+            let start = Instant::now();
+            black_box(book.add_order(order).unwrap());
+            start.elapsed()
+        })
+        .collect()
+}
+
+fn cancel_latencies(backend: QueueBackendKind, n: u64) -> Vec<Duration> {
+    let mut book = new_orderbook(backend);
+    let orders = random_resting_orders(n);
+    for order in &orders {
+        book.add_order(order).unwrap();
+    }
+    orders
+        .iter()
+        .map(|order| {
+            let start = Instant::now();
+            book.cancel_order(order.order_id).unwrap();
+            start.elapsed()
+        })
+        .collect()
+}
+
+// There is no in-place amend yet, so a modify is a cancel followed by the replacement order
+fn modify_latencies(backend: QueueBackendKind, n: u64) -> Vec<Duration> {
+    let mut book = new_orderbook(backend);
+    let orders = random_resting_orders(n);
+    for order in &orders {
+        book.add_order(order).unwrap();
+    }
+    let replacements: Vec<(Uuid, Arc<Order>)> = orders
+        .iter()
+        .map(|order| {
+            let price = match order.side {
+                Side::Buy => order.price - 1,
+                Side::Sell => order.price + 1,
+            };
+            (
+                order.order_id,
+                gtc(order.side, price, order.remaining_quantity),
+            )
+        })
+        .collect();
+    replacements
+        .iter()
+        .map(|(order_id, replacement)| {
+            let start = Instant::now();
+            book.cancel_order(*order_id).unwrap();
+            black_box(book.add_order(replacement).unwrap());
+            start.elapsed()
+        })
+        .collect()
+}
+
+// Each aggressive sell fully fills exactly one resting bid
+fn match_latencies(backend: QueueBackendKind, n: u64) -> Vec<Duration> {
+    let mut book = new_orderbook(backend);
+    for _ in 0..n {
+        book.add_order(&gtc(Side::Buy, 100, 10)).unwrap();
+    }
+    let aggressors: Vec<Arc<Order>> = (0..n).map(|_| gtc(Side::Sell, 100, 10)).collect();
+    aggressors
+        .iter()
+        .map(|order| {
+            let start = Instant::now();
+            black_box(book.add_order(order).unwrap());
+            start.elapsed()
+        })
+        .collect()
+}
+
+type Workload = fn(QueueBackendKind, u64) -> Vec<Duration>;
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((sorted.len() as f64 - 1.0) * p).round() as usize;
+    sorted[rank]
+}
+
+fn print_percentiles(name: &str, backend: QueueBackendKind, mut latencies: Vec<Duration>) {
+    latencies.sort_unstable();
+    println!(
+        "{name}/{backend:?}: p50 {:?}  p99 {:?}  p99.9 {:?}  max {:?}",
+        percentile(&latencies, 0.50),
+        percentile(&latencies, 0.99),
+        percentile(&latencies, 0.999),
+        latencies[latencies.len() - 1],
+    );
+}
+
+fn bench_operations(c: &mut Criterion) {
+    let workloads: [(&str, Workload); 4] = [
+        ("add", add_latencies),
+        ("cancel", cancel_latencies),
+        ("modify", modify_latencies),
+        ("match", match_latencies),
+    ];
+
+    for (name, workload) in workloads {
+        let mut group = c.benchmark_group(name);
+        for backend in BACKENDS {
+            group.bench_function(BenchmarkId::from_parameter(format!("{backend:?}")), |b| {
+                b.iter_custom(|iters| workload(backend, iters).into_iter().sum())
+            });
+        }
+        group.finish();
+
+        for backend in BACKENDS {
+            print_percentiles(name, backend, workload(backend, PERCENTILE_SAMPLES));
+        }
+    }
+}
+
+// Total time for a gateway thread to hand `n` orders to the matching thread and have them applied
+fn ring_ingestion(n: u64) -> Duration {
+    let orders = random_resting_orders(n);
+    let (mut producer, mut consumer) = spsc_ring::<Command>(1024);
+    let mut book = OrderBook::new();
+    let start = Instant::now();
+    let gateway = thread::spawn(move || {
+        for order in orders {
+            let mut command = Command::NewOrder(order);
+            while let Err(rejected) = producer.try_push(command) {
+                command = rejected;
+                thread::yield_now();
+            }
+        }
+    });
+    let mut applied = 0;
+    while applied < n {
+        match consumer.try_pop() {
+            Some(command) => {
+                black_box(book.apply_command(&command).unwrap());
+                applied += 1;
+            }
+            None => thread::yield_now(),
+        }
+    }
+    gateway.join().unwrap();
+    start.elapsed()
+}
+
+fn channel_ingestion(n: u64) -> Duration {
+    let orders = random_resting_orders(n);
+    let (sender, receiver) = mpsc::sync_channel::<Command>(1024);
+    let mut book = OrderBook::new();
+    let start = Instant::now();
+    let gateway = thread::spawn(move || {
+        for order in orders {
+            sender.send(Command::NewOrder(order)).unwrap();
+        }
+    });
+    for command in receiver {
+        black_box(book.apply_command(&command).unwrap());
+    }
+    gateway.join().unwrap();
+    start.elapsed()
+}
+
+fn bench_ingestion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ingestion");
+    group.bench_function("spsc_ring", |b| b.iter_custom(ring_ingestion));
+    group.bench_function("mpsc_channel", |b| b.iter_custom(channel_ingestion));
+    group.finish();
+}
+
+// Resting orders then an equal number of sweeps through the top of book, per price ladder
+fn ladder_workload(ladder: LadderKind, n: u64) -> Duration {
+    let mut book = OrderBook::with_config(OrderBookConfig {
+        ladder,
+        ..OrderBookConfig::default()
+    });
+    let orders = random_resting_orders(n);
+    let sweeps: Vec<Arc<Order>> = orders
+        .iter()
+        .map(|order| match order.side {
+            Side::Buy => gtc(Side::Sell, 90, order.remaining_quantity),
+            Side::Sell => gtc(Side::Buy, 110, order.remaining_quantity),
+        })
+        .collect();
+    let start = Instant::now();
+    for order in &orders {
+        black_box(book.add_order(order).unwrap());
+    }
+    for order in &sweeps {
+        black_box(book.add_order(order).unwrap());
+    }
+    start.elapsed()
+}
+
+fn bench_ladders(c: &mut Criterion) {
+    let ladders = [
+        LadderKind::BTree,
+        LadderKind::TickArray {
+            min_price: 0,
+            max_price: 1_000,
+        },
+    ];
+    let mut group = c.benchmark_group("ladder");
+    for ladder in ladders {
+        let name = match ladder {
+            LadderKind::BTree => "BTree",
+            LadderKind::TickArray { .. } => "TickArray",
+        };
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| ladder_workload(ladder, iters))
+        });
+    }
+    group.finish();
+}
+
+fn bench_id_generators(c: &mut Criterion) {
+    let generators: [(&str, Box<dyn IdGenerator>); 3] = [
+        ("uuid_v4", Box::new(UuidGenerator)),
+        ("monotonic", Box::new(MonotonicIdGenerator::default())),
+        ("snowflake", Box::new(SnowflakeIdGenerator::new(1))),
+    ];
+    let mut group = c.benchmark_group("id_generation");
+    for (name, mut generator) in generators {
+        group.bench_function(name, |b| b.iter(|| black_box(generator.next_id())));
+    }
+    group.finish();
+}
+
+// Lookups of resting ids followed by a cancel and re-insert of each, the access pattern the book's
+// id index sees; run the cancel group with and without `--features fast-hash` for the book itself
+fn id_index_workload<S: BuildHasher + Default>(order_ids: &[Uuid], iters: u64) -> Duration {
+    let mut index: HashMap<Uuid, usize, S> = HashMap::default();
+    for (position, order_id) in order_ids.iter().enumerate() {
+        index.insert(*order_id, position);
+    }
+    let start = Instant::now();
+    for i in 0..iters as usize {
+        let order_id = &order_ids[i % order_ids.len()];
+        let position = black_box(index.get(order_id).copied()).unwrap();
+        black_box(index.remove(order_id));
+        index.insert(*order_id, position);
+    }
+    start.elapsed()
+}
+
+fn bench_id_hashing(c: &mut Criterion) {
+    let order_ids: Vec<Uuid> = (0..10_000).map(|_| Uuid::new_v4()).collect();
+    let mut group = c.benchmark_group("id_hashing");
+    group.bench_function("siphash", |b| {
+        b.iter_custom(|iters| {
+            id_index_workload::<std::collections::hash_map::RandomState>(&order_ids, iters)
+        })
+    });
+    group.bench_function("foldhash", |b| {
+        b.iter_custom(|iters| id_index_workload::<foldhash::fast::RandomState>(&order_ids, iters))
+    });
+    group.finish();
+}
+
+// Mixed agent flow: one market maker, several noise traders and a momentum taker
+fn bench_simulation(c: &mut Criterion) {
+    let mut simulator = Simulator::new(OrderBook::new(), 42);
+    let mut maker = MarketMaker::new(1, 1_000, 1);
+    maker.levels = 5;
+    simulator.add_agent(Box::new(maker));
+    for owner in 2..6 {
+        simulator.add_agent(Box::new(NoiseTrader {
+            owner,
+            initial_price: 1_000,
+            max_offset_ticks: 10,
+            max_quantity: 50,
+            market_probability: 0.1,
+            cancel_probability: 0.5,
+        }));
+    }
+    simulator.add_agent(Box::new(MomentumTrader::new(6, 20, 3)));
+    c.bench_function("simulation_step", |b| {
+        b.iter(|| black_box(simulator.step()))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_operations,
+    bench_ingestion,
+    bench_ladders,
+    bench_id_generators,
+    bench_id_hashing,
+    bench_simulation
+);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+fn main() {
+    // Compiled with protox, so building the gRPC service needs no protoc install
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/orderbook.proto");
+        let descriptors = protox::compile(["proto/orderbook.proto"], ["proto"])
+            .expect("failed to parse proto/orderbook.proto");
+        tonic_prost_build::configure()
+            .compile_fds(descriptors)
+            .expect("failed to generate the gRPC service");
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::orderbook::exec_report::ExecutionReport;
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::order::Order;
+use crate::orderbook::orderbook_impl::{MatchResult, OrderBook, OrderBookError, Trade};
+use crate::orderbook::price_level::BboUpdate;
+use crate::orderbook::types::OrderId;
+
+/// Book activity broadcast to every subscriber of an `AsyncOrderBook`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderBookEvent {
+    Accepted(Arc<Order>),
+    Rejected { order_id: OrderId, reason: String },
+    Trade(Trade),
+    Canceled(Arc<Order>),
+    ExecutionReport(ExecutionReport),
+    Bbo(BboUpdate),
+}
+
+type Query = Box<dyn FnOnce(&mut OrderBook) + Send>;
+
+enum BookCommand {
+    AddOrder(
+        Arc<Order>,
+        oneshot::Sender<Result<MatchResult, OrderBookError>>,
+    ),
+    CancelOrder(OrderId, oneshot::Sender<Result<(), OrderBookError>>),
+    Query(Query),
+}
+
+// Forwards the book's listener callbacks onto the broadcast channel
+struct EventForwarder(broadcast::Sender<OrderBookEvent>);
+
+impl OrderBookListener for EventForwarder {
+    // Send errors only mean nobody is subscribed
+    fn on_accept(&mut self, order: &Arc<Order>) {
+        let _ = self.0.send(OrderBookEvent::Accepted(order.clone()));
+    }
+
+    fn on_trade(&mut self, trade: &Trade) {
+        let _ = self.0.send(OrderBookEvent::Trade(trade.clone()));
+    }
+
+    fn on_cancel(&mut self, order: &Arc<Order>) {
+        let _ = self.0.send(OrderBookEvent::Canceled(order.clone()));
+    }
+
+    fn on_reject(&mut self, order: &Arc<Order>, error: &OrderBookError) {
+        let _ = self.0.send(OrderBookEvent::Rejected {
+            order_id: order.order_id,
+            reason: error.to_string(),
+        });
+    }
+
+    fn on_execution_report(&mut self, report: &ExecutionReport) {
+        let _ = self.0.send(OrderBookEvent::ExecutionReport(report.clone()));
+    }
+
+    fn on_bbo(&mut self, update: &BboUpdate) {
+        let _ = self.0.send(OrderBookEvent::Bbo(*update));
+    }
+}
+
+/// Handle to an `OrderBook` owned by its own tokio task. Commands are queued on an mpsc channel
+/// and applied one at a time, so async services share the book without a mutex; events are
+/// broadcast to subscribers. Handles are cheap to clone and the task stops once all are dropped.
+#[derive(Clone)]
+pub struct AsyncOrderBook {
+    commands: mpsc::Sender<BookCommand>,
+    events: broadcast::Sender<OrderBookEvent>,
+}
+
+impl AsyncOrderBook {
+    /// Move `book` onto a new task of the current tokio runtime. `capacity` bounds both the
+    /// command queue and how far a subscriber may lag before missing events.
+    pub fn spawn(mut book: OrderBook, capacity: usize) -> Self {
+        let (commands, mut receiver) = mpsc::channel(capacity);
+        let (events, _) = broadcast::channel(capacity);
+        book.add_listener(Box::new(EventForwarder(events.clone())));
+
+        tokio::spawn(async move {
+            while let Some(command) = receiver.recv().await {
+                match command {
+                    BookCommand::AddOrder(order, reply) => {
+                        let _ = reply.send(book.add_order(&order));
+                    }
+                    BookCommand::CancelOrder(order_id, reply) => {
+                        let _ = reply.send(book.cancel_order(order_id));
+                    }
+                    BookCommand::Query(query) => query(&mut book),
+                }
+            }
+        });
+        AsyncOrderBook { commands, events }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderBookEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn add_order(&self, order: Arc<Order>) -> Result<MatchResult, OrderBookError> {
+        let (reply, response) = oneshot::channel();
+        self.send(BookCommand::AddOrder(order, reply)).await?;
+        response.await.map_err(|_| OrderBookError::BookStopped)?
+    }
+
+    pub async fn cancel_order(&self, order_id: OrderId) -> Result<(), OrderBookError> {
+        let (reply, response) = oneshot::channel();
+        self.send(BookCommand::CancelOrder(order_id, reply)).await?;
+        response.await.map_err(|_| OrderBookError::BookStopped)?
+    }
+
+    /// Run `query` on the book task, in order with the commands sent before it.
+    pub async fn query<R, F>(&self, query: F) -> Result<R, OrderBookError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut OrderBook) -> R + Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        self.send(BookCommand::Query(Box::new(move |book| {
+            let _ = reply.send(query(book));
+        })))
+        .await?;
+        response.await.map_err(|_| OrderBookError::BookStopped)
+    }
+
+    async fn send(&self, command: BookCommand) -> Result<(), OrderBookError> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| OrderBookError::BookStopped)
+    }
+}
+
+#[cfg(test)]
+mod async_book_tests {
+    use super::*;
+    use crate::orderbook::order::{OrderType, Side, Status};
+
+    #[tokio::test]
+    async fn check_commands_and_events_through_book_task() {
+        let book = AsyncOrderBook::spawn(OrderBook::new(), 64);
+        let mut events = book.subscribe();
+
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 5));
+        assert_eq!(
+            book.add_order(ask.clone()).await.unwrap().status,
+            Status::New
+        );
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 2));
+        let result = book.add_order(bid).await.unwrap();
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.status, Status::Filled);
+
+        // handles share the same book
+        let other = book.clone();
+        other.cancel_order(ask.order_id).await.unwrap();
+        assert!(matches!(
+            book.cancel_order(ask.order_id).await,
+            Err(OrderBookError::OrderNotFound { .. })
+        ));
+        let resting = book.query(|book| book.resting_order_count()).await.unwrap();
+        assert_eq!(resting, 0);
+
+        let mut received: Vec<OrderBookEvent> = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert!(matches!(received[0], OrderBookEvent::Accepted(_)));
+        assert!(
+            received
+                .iter()
+                .any(|event| matches!(event, OrderBookEvent::Trade(_)))
+        );
+        assert!(matches!(received.last(), Some(OrderBookEvent::Bbo(_))));
+        assert!(
+            received
+                .iter()
+                .any(|event| matches!(event, OrderBookEvent::Canceled(order) if order.order_id == ask.order_id))
+        );
+    }
+}
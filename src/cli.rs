@@ -0,0 +1,239 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::orderbook::id::{IdGenerator, MonotonicIdGenerator};
+use crate::orderbook::journal::decode_order_type;
+use crate::orderbook::order::{Order, OrderType, Side};
+use crate::orderbook::orderbook_impl::{OrderBook, Trade};
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+pub const PROMPT: &str = "ob> ";
+
+pub const HELP: &str = "\
+buy <qty> [@ <price>] [type]   submit a buy, a market order without a price
+sell <qty> [@ <price>] [type]  submit a sell; type is LIMIT, IOC, FOK, GTC, POST or GTD:<millis>
+cancel <id>                    cancel a resting order
+depth [levels]                 show the ladder, 5 levels a side by default
+bbo                            show the best bid and ask
+orders                         list resting orders
+trades [count]                 show the latest trades, 10 by default
+help                           show this text
+quit                           leave";
+
+/// Line-oriented command interpreter over one book, as used by the `main` binary. Orders are
+/// numbered from 1 so they can be canceled by typing the number.
+pub struct Repl {
+    book: OrderBook,
+    order_ids: MonotonicIdGenerator,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new(OrderBook::new())
+    }
+}
+
+impl Repl {
+    pub fn new(book: OrderBook) -> Self {
+        Repl {
+            book,
+            order_ids: MonotonicIdGenerator::new(1),
+        }
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Run one command line, returning the text to print. Errors are the message to show, the
+    /// book is left untouched.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => Ok(String::new()),
+            ["buy", args @ ..] => self.submit(Side::Buy, args),
+            ["sell", args @ ..] => self.submit(Side::Sell, args),
+            ["cancel", order_id] => {
+                let order_id = parse_order_id(order_id)?;
+                self.book
+                    .cancel_order(order_id)
+                    .map_err(|error| error.to_string())?;
+                Ok(format!("canceled {}", display_id(order_id)))
+            }
+            ["depth"] => Ok(self.depth(5)),
+            ["depth", levels] => Ok(self.depth(parse(levels, "level count")?)),
+            ["bbo"] => Ok(self.bbo()),
+            ["orders"] => Ok(self.orders()),
+            ["trades"] => Ok(self.trades(10)),
+            ["trades", count] => Ok(self.trades(parse(count, "trade count")?)),
+            ["help"] => Ok(HELP.to_string()),
+            _ => Err(format!("unknown command {:?}, try help", line.trim())),
+        }
+    }
+
+    // <qty> [@ <price>] [type], also accepting the price glued to the `@`
+    fn submit(&mut self, side: Side, args: &[&str]) -> Result<String, String> {
+        let [quantity, rest @ ..] = args else {
+            return Err("expected a quantity".to_string());
+        };
+        let quantity: Quantity = parse(quantity, "quantity")?;
+        let (price, rest) = match rest {
+            ["@", price, rest @ ..] => (Some(parse(price, "price")?), rest),
+            [price, rest @ ..] if price.starts_with('@') => {
+                (Some(parse(&price[1..], "price")?), rest)
+            }
+            _ => (None, rest),
+        };
+        let order_type = match (rest, price) {
+            ([], Some(_)) => OrderType::LimitOrder,
+            ([], None) => OrderType::MarketOrder,
+            ([token], _) => decode_order_type(&token.to_uppercase())
+                .ok_or_else(|| format!("unknown order type {:?}", token))?,
+            _ => return Err(format!("unexpected {:?}", rest.join(" "))),
+        };
+        let price: Price = match (order_type, price) {
+            (OrderType::MarketOrder, price) => price.unwrap_or(0),
+            (_, Some(price)) => price,
+            (_, None) => return Err("expected @ <price>".to_string()),
+        };
+
+        let mut order = Order::new(order_type, side, price, quantity);
+        order.order_id = self.order_ids.next_id();
+        let result = self
+            .book
+            .add_order(&Arc::new(order))
+            .map_err(|error| error.to_string())?;
+        let mut output = format!(
+            "order {} {:?}, {} filled, {} remaining",
+            display_id(result.order_id),
+            result.status,
+            quantity - result.remaining,
+            result.remaining
+        );
+        for trade in result.trades.iter() {
+            let _ = write!(output, "\n  {}", format_trade(trade));
+        }
+        Ok(output)
+    }
+
+    fn depth(&self, levels: usize) -> String {
+        self.book.render(levels)
+    }
+
+    fn bbo(&self) -> String {
+        let bbo = self.book.bbo();
+        let price = |price: Option<Price>| price.map_or("-".to_string(), |price| price.to_string());
+        format!(
+            "bid {} x {}, ask {} x {}",
+            price(bbo.bid_price),
+            bbo.bid_qty,
+            price(bbo.ask_price),
+            bbo.ask_qty
+        )
+    }
+
+    fn orders(&self) -> String {
+        let orders = self.book.open_orders(0);
+        if orders.is_empty() {
+            return "no resting orders".to_string();
+        }
+        orders
+            .iter()
+            .map(|order| {
+                format!(
+                    "{} {:?} {:?} @ {} ({} of {} left)",
+                    display_id(order.order_id),
+                    order.side,
+                    order.order_type,
+                    order.price,
+                    order.remaining_quantity,
+                    order.original_quantity
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn trades(&self, count: usize) -> String {
+        let trades = self.book.trade_history().trades_between(i64::MIN, i64::MAX);
+        if trades.is_empty() {
+            return "no trades".to_string();
+        }
+        trades
+            .iter()
+            .rev()
+            .take(count)
+            .map(format_trade)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+fn format_trade(trade: &Trade) -> String {
+    format!(
+        "trade {} @ {} buy {} sell {}",
+        trade.quantity(),
+        trade.price(),
+        display_id(trade.bid_order_id()),
+        display_id(trade.ask_order_id())
+    )
+}
+
+fn parse<T: std::str::FromStr>(token: &str, what: &str) -> Result<T, String> {
+    token
+        .parse()
+        .map_err(|_| format!("invalid {} {:?}", what, token))
+}
+
+// Session order numbers, or full UUIDs for orders from elsewhere
+fn parse_order_id(token: &str) -> Result<OrderId, String> {
+    match token.parse::<u64>() {
+        Ok(number) => Ok(Uuid::from_u64_pair(0, number)),
+        Err(_) => Uuid::parse_str(token).map_err(|_| format!("invalid order id {:?}", token)),
+    }
+}
+
+fn display_id(order_id: OrderId) -> String {
+    match order_id.as_u64_pair() {
+        (0, number) => number.to_string(),
+        _ => order_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+
+    #[test]
+    fn check_repl_commands_drive_the_book() {
+        let mut repl = Repl::default();
+        assert_eq!(
+            repl.execute("sell 10 @ 101").unwrap(),
+            "order 1 New, 0 filled, 10 remaining"
+        );
+        assert!(repl.execute("sell 5 @102 ioc").is_ok());
+        assert_eq!(
+            repl.execute("buy 4").unwrap(),
+            "order 3 Filled, 4 filled, 0 remaining\n  trade 4 @ 101 buy 3 sell 1"
+        );
+        assert_eq!(repl.execute("bbo").unwrap(), "bid - x 0, ask 101 x 6");
+        assert_eq!(
+            repl.execute("depth 1").unwrap(),
+            "bid total  bid  price  ask  ask total\n                  101    6          6"
+        );
+        assert_eq!(
+            repl.execute("trades").unwrap(),
+            "trade 4 @ 101 buy 3 sell 1"
+        );
+
+        assert_eq!(repl.execute("cancel 1").unwrap(), "canceled 1");
+        assert!(repl.execute("cancel 1").is_err());
+        assert!(repl.execute("buy 5 @ 100 gtx").is_err());
+        assert!(repl.execute("buy ten").is_err());
+        assert!(repl.execute("buy 5 ioc").is_err());
+        assert_eq!(repl.execute("orders").unwrap(), "no resting orders");
+        assert!(repl.book().get_best_ask().is_none());
+    }
+}
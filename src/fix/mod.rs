@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::orderbook::order::{Order, OrderType, Side, Status};
+use crate::orderbook::orderbook_impl::{Liquidity, OrderBook, Trade};
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+pub const SOH: char = '\x01';
+
+// FIX 4.4 tags used by the gateway
+pub mod tags {
+    pub const BEGIN_STRING: u32 = 8;
+    pub const BODY_LENGTH: u32 = 9;
+    pub const CHECK_SUM: u32 = 10;
+    pub const CL_ORD_ID: u32 = 11;
+    pub const CUM_QTY: u32 = 14;
+    pub const EXEC_ID: u32 = 17;
+    pub const EXEC_INST: u32 = 18;
+    pub const LAST_PX: u32 = 31;
+    pub const LAST_QTY: u32 = 32;
+    pub const MSG_SEQ_NUM: u32 = 34;
+    pub const MSG_TYPE: u32 = 35;
+    pub const ORDER_ID: u32 = 37;
+    pub const ORDER_QTY: u32 = 38;
+    pub const ORD_STATUS: u32 = 39;
+    pub const ORD_TYPE: u32 = 40;
+    pub const ORIG_CL_ORD_ID: u32 = 41;
+    pub const PRICE: u32 = 44;
+    pub const SENDER_COMP_ID: u32 = 49;
+    pub const SENDING_TIME: u32 = 52;
+    pub const SIDE: u32 = 54;
+    pub const TARGET_COMP_ID: u32 = 56;
+    pub const TEXT: u32 = 58;
+    pub const TIME_IN_FORCE: u32 = 59;
+    pub const MIN_QTY: u32 = 110;
+    pub const EXPIRE_TIME: u32 = 126;
+    pub const EXEC_TYPE: u32 = 150;
+    pub const LEAVES_QTY: u32 = 151;
+    pub const LAST_LIQUIDITY_IND: u32 = 851;
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FixMessage {
+    fields: Vec<(u32, String)>,
+}
+
+impl FixMessage {
+    pub fn new(msg_type: &str) -> Self {
+        let mut message = FixMessage::default();
+        message.set(tags::MSG_TYPE, msg_type);
+        message
+    }
+
+    /// Parse a tag=value message delimited by SOH (or `|` for hand-typed messages).
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut fields: Vec<(u32, String)> = Vec::new();
+        for field in raw.split([SOH, '|']).filter(|f| !f.is_empty()) {
+            let (tag, value) = field.split_once('=')?;
+            fields.push((tag.trim().parse().ok()?, value.to_string()));
+        }
+        Some(FixMessage { fields })
+    }
+
+    pub fn get(&self, tag: u32) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set<V: ToString>(&mut self, tag: u32, value: V) -> &mut Self {
+        let value = value.to_string();
+        match self.fields.iter_mut().find(|(t, _)| *t == tag) {
+            Some(field) => field.1 = value,
+            None => self.fields.push((tag, value)),
+        }
+        self
+    }
+
+    pub fn msg_type(&self) -> Option<&str> {
+        self.get(tags::MSG_TYPE)
+    }
+
+    /// Encode with BeginString, BodyLength and CheckSum filled in.
+    pub fn encode(&self) -> String {
+        let mut body = String::new();
+        for (tag, value) in self.fields.iter().filter(|(tag, _)| {
+            !matches!(
+                *tag,
+                tags::BEGIN_STRING | tags::BODY_LENGTH | tags::CHECK_SUM
+            )
+        }) {
+            let _ = write!(body, "{}={}{}", tag, value, SOH);
+        }
+        let mut message = format!("8=FIX.4.4{}9={}{}{}", SOH, body.len(), SOH, body);
+        let checksum = message.bytes().map(|b| b as u32).sum::<u32>() % 256;
+        let _ = write!(message, "10={:03}{}", checksum, SOH);
+        message
+    }
+}
+
+// Gateway-side view of an order, keyed by engine order id
+#[derive(Clone)]
+struct GatewayOrder {
+    cl_ord_id: String,
+    side: Side,
+    order_quantity: Quantity,
+    cum_quantity: Quantity,
+}
+
+/// Translates FIX order-entry messages into book commands and answers with execution reports.
+pub struct FixGateway {
+    book: OrderBook,
+    sender_comp_id: String,
+    target_comp_id: String,
+    cl_ord_ids: HashMap<String, OrderId>,
+    orders: HashMap<OrderId, GatewayOrder>,
+    next_exec_id: u64,
+    next_seq_num: u64,
+}
+
+impl FixGateway {
+    pub fn new(book: OrderBook, sender_comp_id: &str, target_comp_id: &str) -> Self {
+        FixGateway {
+            book,
+            sender_comp_id: sender_comp_id.to_string(),
+            target_comp_id: target_comp_id.to_string(),
+            cl_ord_ids: HashMap::new(),
+            orders: HashMap::new(),
+            next_exec_id: 1,
+            next_seq_num: 1,
+        }
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Handle one raw inbound message, returning the encoded outbound replies.
+    pub fn handle_raw(&mut self, raw: &str) -> Vec<String> {
+        match FixMessage::parse(raw) {
+            Some(message) => self.handle(&message),
+            None => vec![self.business_reject("Malformed FIX message").encode()],
+        }
+    }
+
+    pub fn handle(&mut self, message: &FixMessage) -> Vec<String> {
+        let replies = match message.msg_type() {
+            Some("D") => self.on_new_order_single(message),
+            Some("F") => self.on_cancel_request(message),
+            Some("G") => self.on_cancel_replace_request(message),
+            _ => vec![self.business_reject("Unsupported MsgType")],
+        };
+        replies
+            .into_iter()
+            .map(|mut reply| {
+                self.stamp_header(&mut reply);
+                reply.encode()
+            })
+            .collect()
+    }
+
+    fn on_new_order_single(&mut self, message: &FixMessage) -> Vec<FixMessage> {
+        let cl_ord_id = message.get(tags::CL_ORD_ID).unwrap_or_default().to_string();
+        if let Err(reason) = self.check_cl_ord_id(&cl_ord_id) {
+            return vec![self.order_reject(&cl_ord_id, message, reason)];
+        }
+        match parse_order(message) {
+            Ok(order) => self.submit(order, cl_ord_id, "0"),
+            Err(reason) => vec![self.order_reject(&cl_ord_id, message, reason)],
+        }
+    }
+
+    fn on_cancel_request(&mut self, message: &FixMessage) -> Vec<FixMessage> {
+        let cl_ord_id = message.get(tags::CL_ORD_ID).unwrap_or_default().to_string();
+        let orig_cl_ord_id = message.get(tags::ORIG_CL_ORD_ID).unwrap_or_default();
+
+        let Some(&order_id) = self.cl_ord_ids.get(orig_cl_ord_id) else {
+            return vec![self.cancel_reject(&cl_ord_id, orig_cl_ord_id, "Unknown order")];
+        };
+        if let Err(error) = self.book.cancel_order(order_id) {
+            return vec![self.cancel_reject(&cl_ord_id, orig_cl_ord_id, &error.to_string())];
+        }
+
+        let Some(state) = self.forget(order_id) else {
+            return Vec::new();
+        };
+        let mut report = self.execution_report(order_id, &state, "4", "4");
+        report.set(tags::CL_ORD_ID, &cl_ord_id);
+        report.set(tags::ORIG_CL_ORD_ID, orig_cl_ord_id);
+        report.set(tags::LEAVES_QTY, 0);
+        vec![report]
+    }
+
+    // Cancel/replace is a cancel followed by a new order, so the replacement loses priority
+    fn on_cancel_replace_request(&mut self, message: &FixMessage) -> Vec<FixMessage> {
+        let cl_ord_id = message.get(tags::CL_ORD_ID).unwrap_or_default().to_string();
+        let orig_cl_ord_id = message.get(tags::ORIG_CL_ORD_ID).unwrap_or_default();
+
+        let Some(&order_id) = self.cl_ord_ids.get(orig_cl_ord_id) else {
+            return vec![self.cancel_reject(&cl_ord_id, orig_cl_ord_id, "Unknown order")];
+        };
+        if let Err(reason) = self.check_cl_ord_id(&cl_ord_id) {
+            return vec![self.cancel_reject(&cl_ord_id, orig_cl_ord_id, reason)];
+        }
+        let replacement = match parse_order(message) {
+            Ok(order) => order,
+            Err(reason) => return vec![self.cancel_reject(&cl_ord_id, orig_cl_ord_id, reason)],
+        };
+        let cum_quantity = self.orders.get(&order_id).map_or(0, |o| o.cum_quantity);
+        if replacement.original_quantity <= cum_quantity {
+            return vec![self.cancel_reject(
+                &cl_ord_id,
+                orig_cl_ord_id,
+                "OrderQty not above CumQty",
+            )];
+        }
+        if let Err(error) = self.book.cancel_order(order_id) {
+            return vec![self.cancel_reject(&cl_ord_id, orig_cl_ord_id, &error.to_string())];
+        }
+        self.forget(order_id);
+
+        // Only the quantity not yet filled goes back to the book
+        let mut remaining_order = Order::new(
+            replacement.order_type,
+            replacement.side,
+            replacement.price,
+            replacement.original_quantity - cum_quantity,
+        );
+        remaining_order.order_id = replacement.order_id;
+        let mut replies = self.submit(remaining_order, cl_ord_id, "5");
+        if let Some(first) = replies.first_mut() {
+            first.set(tags::ORIG_CL_ORD_ID, orig_cl_ord_id);
+        }
+        replies
+    }
+
+    fn submit(
+        &mut self,
+        order: Order,
+        cl_ord_id: String,
+        accept_exec_type: &str,
+    ) -> Vec<FixMessage> {
+        let order = Arc::new(order);
+        let result = match self.book.add_order(&order) {
+            Ok(result) => result,
+            Err(error) => {
+                let reason = error.to_string();
+                let mut reject = FixMessage::new("8");
+                reject
+                    .set(tags::ORDER_ID, order.order_id)
+                    .set(tags::CL_ORD_ID, &cl_ord_id)
+                    .set(tags::EXEC_ID, self.next_exec_id())
+                    .set(tags::EXEC_TYPE, "8")
+                    .set(tags::ORD_STATUS, "8")
+                    .set(tags::SIDE, encode_side(order.side))
+                    .set(tags::LEAVES_QTY, 0)
+                    .set(tags::CUM_QTY, 0)
+                    .set(tags::TEXT, reason);
+                return vec![reject];
+            }
+        };
+
+        self.cl_ord_ids.insert(cl_ord_id.clone(), order.order_id);
+        self.orders.insert(
+            order.order_id,
+            GatewayOrder {
+                cl_ord_id,
+                side: order.side,
+                order_quantity: order.original_quantity,
+                cum_quantity: 0,
+            },
+        );
+
+        let mut replies: Vec<FixMessage> = Vec::new();
+        let accepted = self.orders[&order.order_id].clone();
+        replies.push(self.execution_report(order.order_id, &accepted, accept_exec_type, "0"));
+
+        for trade in result.trades.iter() {
+            replies.extend(self.fill_reports(trade));
+        }
+
+        // A remainder that did not rest (market, FOK, IOC) is canceled back to the client
+        if result.status == Status::Canceled
+            && let Some(state) = self.forget(order.order_id)
+            && state.cum_quantity < state.order_quantity
+        {
+            replies.push(self.execution_report(order.order_id, &state, "4", "4"));
+        }
+        replies
+    }
+
+    fn fill_reports(&mut self, trade: &Trade) -> Vec<FixMessage> {
+        let mut reports: Vec<FixMessage> = Vec::new();
+        for order_id in [trade.bid_order_id(), trade.ask_order_id()] {
+            let Some(state) = self.orders.get_mut(&order_id) else {
+                continue;
+            };
+            state.cum_quantity += trade.quantity();
+            let filled = state.cum_quantity >= state.order_quantity;
+            let status = if filled { "2" } else { "1" };
+
+            let state = if filled {
+                self.forget(order_id).unwrap()
+            } else {
+                state.clone()
+            };
+            let mut report = self.execution_report(order_id, &state, "F", status);
+            report.set(tags::LAST_QTY, trade.quantity());
+            report.set(tags::LAST_PX, trade.price());
+            if let Some(liquidity) = trade.liquidity(order_id) {
+                report.set(tags::LAST_LIQUIDITY_IND, last_liquidity_ind(liquidity));
+            }
+            reports.push(report);
+        }
+        reports
+    }
+
+    // Live orders are looked up by ClOrdID, so a blank or reused one would orphan an order
+    fn check_cl_ord_id(&self, cl_ord_id: &str) -> Result<(), &'static str> {
+        if cl_ord_id.is_empty() {
+            Err("Missing ClOrdID")
+        } else if self.cl_ord_ids.contains_key(cl_ord_id) {
+            Err("Duplicate ClOrdID")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn forget(&mut self, order_id: OrderId) -> Option<GatewayOrder> {
+        let state = self.orders.remove(&order_id)?;
+        self.cl_ord_ids.remove(&state.cl_ord_id);
+        Some(state)
+    }
+
+    fn execution_report(
+        &mut self,
+        order_id: OrderId,
+        state: &GatewayOrder,
+        exec_type: &str,
+        ord_status: &str,
+    ) -> FixMessage {
+        let mut report = FixMessage::new("8");
+        report
+            .set(tags::ORDER_ID, order_id)
+            .set(tags::CL_ORD_ID, &state.cl_ord_id)
+            .set(tags::EXEC_ID, self.next_exec_id())
+            .set(tags::EXEC_TYPE, exec_type)
+            .set(tags::ORD_STATUS, ord_status)
+            .set(tags::SIDE, encode_side(state.side))
+            .set(
+                tags::LEAVES_QTY,
+                state.order_quantity.saturating_sub(state.cum_quantity),
+            )
+            .set(tags::CUM_QTY, state.cum_quantity);
+        report
+    }
+
+    fn order_reject(&mut self, cl_ord_id: &str, message: &FixMessage, reason: &str) -> FixMessage {
+        let mut reject = FixMessage::new("8");
+        reject
+            .set(tags::ORDER_ID, "NONE")
+            .set(tags::CL_ORD_ID, cl_ord_id)
+            .set(tags::EXEC_ID, self.next_exec_id())
+            .set(tags::EXEC_TYPE, "8")
+            .set(tags::ORD_STATUS, "8")
+            .set(tags::SIDE, message.get(tags::SIDE).unwrap_or_default())
+            .set(tags::LEAVES_QTY, 0)
+            .set(tags::CUM_QTY, 0)
+            .set(tags::TEXT, reason);
+        reject
+    }
+
+    fn cancel_reject(&self, cl_ord_id: &str, orig_cl_ord_id: &str, reason: &str) -> FixMessage {
+        let mut reject = FixMessage::new("9");
+        reject
+            .set(tags::ORDER_ID, "NONE")
+            .set(tags::CL_ORD_ID, cl_ord_id)
+            .set(tags::ORIG_CL_ORD_ID, orig_cl_ord_id)
+            .set(tags::ORD_STATUS, "8")
+            .set(tags::TEXT, reason);
+        reject
+    }
+
+    fn business_reject(&self, reason: &str) -> FixMessage {
+        let mut reject = FixMessage::new("j");
+        reject.set(tags::TEXT, reason);
+        reject
+    }
+
+    fn stamp_header(&mut self, message: &mut FixMessage) {
+        let mut header = FixMessage::new(message.msg_type().unwrap_or("8"));
+        header
+            .set(tags::SENDER_COMP_ID, &self.sender_comp_id)
+            .set(tags::TARGET_COMP_ID, &self.target_comp_id)
+            .set(tags::MSG_SEQ_NUM, self.next_seq_num)
+            .set(tags::SENDING_TIME, Utc::now().format("%Y%m%d-%H:%M:%S%.3f"));
+        self.next_seq_num += 1;
+        for (tag, value) in message
+            .fields
+            .drain(..)
+            .filter(|(t, _)| *t != tags::MSG_TYPE)
+        {
+            header.set(tag, value);
+        }
+        *message = header;
+    }
+
+    fn next_exec_id(&mut self) -> u64 {
+        let exec_id = self.next_exec_id;
+        self.next_exec_id += 1;
+        exec_id
+    }
+}
+
+fn parse_order(message: &FixMessage) -> Result<Order, &'static str> {
+    let side = match message.get(tags::SIDE) {
+        Some("1") => Side::Buy,
+        Some("2") => Side::Sell,
+        _ => return Err("Unsupported Side"),
+    };
+    let quantity: Quantity = message
+        .get(tags::ORDER_QTY)
+        .and_then(|qty| qty.parse().ok())
+        .ok_or("Invalid OrderQty")?;
+    let price: Option<Price> = message.get(tags::PRICE).and_then(|px| px.parse().ok());
+
+    let order_type = match message.get(tags::ORD_TYPE) {
+        Some("1") => OrderType::MarketOrder,
+        Some("2") => match message.get(tags::TIME_IN_FORCE) {
+            None | Some("0") => OrderType::LimitOrder,
+            Some("1") => OrderType::GoodTillCancel,
+            Some("3") => OrderType::ImmediateOrCancel,
+            Some("4") => OrderType::FillOrKill,
+            // At the Opening, At the Close
+            Some("2") | Some("7") => OrderType::GoodForAuction,
+            Some("6") => OrderType::GoodTillDate(
+                message
+                    .get(tags::EXPIRE_TIME)
+                    .and_then(parse_utc_timestamp)
+                    .ok_or("Invalid ExpireTime")?,
+            ),
+            _ => return Err("Unsupported TimeInForce"),
+        },
+        _ => return Err("Unsupported OrdType"),
+    };
+    // ExecInst 6 = Participate don't initiate
+    let order_type = match message.get(tags::EXEC_INST) {
+        Some(exec_inst) if exec_inst.split(' ').any(|i| i == "6") => OrderType::PostOnly,
+        _ => order_type,
+    };
+
+    let price = match order_type {
+        OrderType::MarketOrder => price.unwrap_or(0),
+        _ => price.ok_or("Limit order without Price")?,
+    };
+    let order = Order::new(order_type, side, price, quantity);
+    match message.get(tags::MIN_QTY) {
+        Some(min_qty) => Ok(order.with_min_qty(min_qty.parse().map_err(|_| "Invalid MinQty")?)),
+        None => Ok(order),
+    }
+}
+
+// UTCTimestamp (YYYYMMDD-HH:MM:SS[.sss]) to epoch millis
+fn parse_utc_timestamp(value: &str) -> Option<i64> {
+    let format = if value.contains('.') {
+        "%Y%m%d-%H:%M:%S%.3f"
+    } else {
+        "%Y%m%d-%H:%M:%S"
+    };
+    chrono::NaiveDateTime::parse_from_str(value, format)
+        .ok()
+        .map(|dt| dt.and_utc().timestamp_millis())
+}
+
+fn encode_side(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "1",
+        Side::Sell => "2",
+    }
+}
+
+fn last_liquidity_ind(liquidity: Liquidity) -> &'static str {
+    match liquidity {
+        Liquidity::Added => "1",
+        Liquidity::Removed => "2",
+        Liquidity::Auction => "4",
+    }
+}
+
+#[cfg(test)]
+mod fix_tests {
+    use super::*;
+
+    fn gateway() -> FixGateway {
+        FixGateway::new(OrderBook::new(), "EXCH", "CLIENT")
+    }
+
+    fn parse_all(replies: &[String]) -> Vec<FixMessage> {
+        replies
+            .iter()
+            .map(|raw| FixMessage::parse(raw).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn check_encode_sets_length_and_checksum() {
+        let mut message = FixMessage::new("D");
+        message.set(tags::CL_ORD_ID, "abc");
+        let encoded = message.encode();
+        assert!(encoded.starts_with("8=FIX.4.4\x019=12\x0135=D\x0111=abc\x01"));
+        let parsed = FixMessage::parse(&encoded).unwrap();
+        assert_eq!(parsed.get(tags::CL_ORD_ID), Some("abc"));
+        assert_eq!(parsed.get(tags::CHECK_SUM).map(|c| c.len()), Some(3));
+    }
+
+    #[test]
+    fn check_new_order_fill_reports() {
+        let mut gateway = gateway();
+        let replies = parse_all(&gateway.handle_raw("35=D|11=s1|54=2|38=10|40=2|44=100|59=1"));
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("0"));
+        assert_eq!(replies[0].get(tags::LEAVES_QTY), Some("10"));
+
+        let replies = parse_all(&gateway.handle_raw("35=D|11=b1|54=1|38=4|40=1"));
+        // ack, aggressor fill, passive partial fill
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[1].get(tags::CL_ORD_ID), Some("b1"));
+        assert_eq!(replies[1].get(tags::ORD_STATUS), Some("2"));
+        assert_eq!(replies[1].get(tags::LAST_PX), Some("100"));
+        assert_eq!(replies[1].get(tags::LAST_LIQUIDITY_IND), Some("2"));
+        assert_eq!(replies[2].get(tags::CL_ORD_ID), Some("s1"));
+        assert_eq!(replies[2].get(tags::ORD_STATUS), Some("1"));
+        assert_eq!(replies[2].get(tags::LEAVES_QTY), Some("6"));
+        assert_eq!(replies[2].get(tags::LAST_LIQUIDITY_IND), Some("1"));
+        assert_eq!(replies[2].get(tags::SENDER_COMP_ID), Some("EXCH"));
+    }
+
+    #[test]
+    fn check_cancel_and_replace() {
+        let mut gateway = gateway();
+        gateway.handle_raw("35=D|11=b1|54=1|38=10|40=2|44=99");
+
+        let replies = parse_all(&gateway.handle_raw("35=G|11=b2|41=b1|54=1|38=5|40=2|44=98"));
+        assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("5"));
+        assert_eq!(replies[0].get(tags::ORIG_CL_ORD_ID), Some("b1"));
+        assert_eq!(gateway.book().get_best_bid(), Some(98));
+
+        let replies = parse_all(&gateway.handle_raw("35=F|11=b3|41=b1|54=1"));
+        assert_eq!(replies[0].msg_type(), Some("9"));
+
+        let replies = parse_all(&gateway.handle_raw("35=F|11=b3|41=b2|54=1"));
+        assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("4"));
+        assert_eq!(gateway.book().get_best_bid(), None);
+    }
+
+    #[test]
+    fn check_invalid_order_rejected() {
+        let mut gateway = gateway();
+        let replies = parse_all(&gateway.handle_raw("35=D|11=x|54=1|38=10|40=2"));
+        assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("8"));
+        assert_eq!(
+            replies[0].get(tags::TEXT),
+            Some("Limit order without Price")
+        );
+
+        let replies = parse_all(&gateway.handle_raw("35=D|11=y|54=1|38=0|40=2|44=10"));
+        assert_eq!(replies[0].get(tags::ORD_STATUS), Some("8"));
+    }
+
+    #[test]
+    fn check_missing_or_duplicate_cl_ord_id_rejected() {
+        let mut gateway = gateway();
+        for raw in [
+            "35=D|54=1|38=10|40=2|44=99",
+            "35=D|11=|54=1|38=10|40=2|44=99",
+        ] {
+            let replies = parse_all(&gateway.handle_raw(raw));
+            assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("8"));
+            assert_eq!(replies[0].get(tags::TEXT), Some("Missing ClOrdID"));
+        }
+        assert_eq!(gateway.book().get_best_bid(), None);
+
+        gateway.handle_raw("35=D|11=b1|54=1|38=10|40=2|44=99");
+        let replies = parse_all(&gateway.handle_raw("35=D|11=b1|54=1|38=5|40=2|44=100"));
+        assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("8"));
+        assert_eq!(replies[0].get(tags::TEXT), Some("Duplicate ClOrdID"));
+        assert_eq!(gateway.book().get_best_bid(), Some(99));
+
+        gateway.handle_raw("35=D|11=b2|54=1|38=5|40=2|44=98");
+        let replies = parse_all(&gateway.handle_raw("35=G|11=b2|41=b1|54=1|38=5|40=2|44=97"));
+        assert_eq!(replies[0].msg_type(), Some("9"));
+        assert_eq!(replies[0].get(tags::TEXT), Some("Duplicate ClOrdID"));
+
+        // The first order is still reachable through its ClOrdID
+        let replies = parse_all(&gateway.handle_raw("35=F|11=b3|41=b1|54=1"));
+        assert_eq!(replies[0].get(tags::EXEC_TYPE), Some("4"));
+        assert_eq!(gateway.book().get_best_bid(), Some(98));
+    }
+}
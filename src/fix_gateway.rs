@@ -0,0 +1,30 @@
+use std::io::{self, BufRead, Write};
+
+use log::LevelFilter;
+
+use orderbook::fix::FixGateway;
+use orderbook::orderbook::orderbook_impl::OrderBook;
+
+// Reads one FIX message per line from stdin (SOH or `|` delimited) and writes
+// the execution reports to stdout, with `|` in place of SOH for readability.
+fn main() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Warn)
+        .init();
+
+    let mut gateway = FixGateway::new(OrderBook::new(), "ORDERBOOK", "CLIENT");
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        for reply in gateway.handle_raw(line.trim()) {
+            let _ = writeln!(out, "{}", reply.replace(orderbook::fix::SOH, "|"));
+        }
+    }
+}
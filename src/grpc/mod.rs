@@ -0,0 +1,388 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::orderbook::command::Command;
+use crate::orderbook::order::{Order, OrderType, Side, Status as OrderStatus};
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError, Trade};
+use crate::orderbook::price_level::LevelInfo;
+use crate::orderbook::types::OrderId;
+
+/// Messages and client/server stubs generated from `proto/orderbook.proto`.
+pub mod proto {
+    tonic::include_proto!("orderbook.v1");
+}
+
+use proto::matching_engine_server::{MatchingEngine, MatchingEngineServer};
+
+const DEFAULT_DEPTH_LEVELS: usize = 10;
+// Per stream, before a slow client starts losing trades
+const STREAM_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone)]
+enum MarketUpdate {
+    Trade(Trade),
+    // Some command changed the book, depth streams take a new snapshot
+    BookChanged,
+}
+
+/// gRPC front-end to one book: order entry plus depth and trade streams. Commands run under the
+/// book's mutex and publish their trades and a change notice before the reply is sent, so a
+/// client never sees its ack ahead of the market data.
+#[derive(Clone)]
+pub struct GrpcService {
+    book: Arc<Mutex<OrderBook>>,
+    updates: broadcast::Sender<MarketUpdate>,
+}
+
+impl GrpcService {
+    pub fn new(book: OrderBook) -> Self {
+        let (updates, _) = broadcast::channel(STREAM_BUFFER);
+        GrpcService {
+            book: Arc::new(Mutex::new(book)),
+            updates,
+        }
+    }
+
+    /// The tonic service to mount on a `tonic::transport::Server`.
+    pub fn into_server(self) -> MatchingEngineServer<Self> {
+        MatchingEngineServer::new(self)
+    }
+
+    fn depth_update(&self, levels: usize) -> proto::DepthUpdate {
+        let book = self.book.lock().unwrap();
+        let depth = book.get_depth(levels);
+        let levels = |levels: Vec<LevelInfo>| {
+            levels
+                .into_iter()
+                .map(|level| proto::LevelInfo {
+                    price: level.price,
+                    volume: level.volume,
+                })
+                .collect()
+        };
+        proto::DepthUpdate {
+            bids: levels(depth.bids),
+            asks: levels(depth.asks),
+            sequence: book.sequence(),
+        }
+    }
+
+    // Send errors only mean nobody is subscribed
+    fn publish(&self, trades: &[Trade]) {
+        for trade in trades {
+            let _ = self.updates.send(MarketUpdate::Trade(trade.clone()));
+        }
+        let _ = self.updates.send(MarketUpdate::BookChanged);
+    }
+}
+
+type UpdateStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl MatchingEngine for GrpcService {
+    async fn submit_order(
+        &self,
+        request: Request<proto::SubmitOrderRequest>,
+    ) -> Result<Response<proto::SubmitOrderResponse>, Status> {
+        let order = request
+            .into_inner()
+            .order
+            .ok_or_else(|| Status::invalid_argument("missing order"))?;
+        let order = Arc::new(decode_order(&order)?);
+        let result = {
+            let mut book = self.book.lock().unwrap();
+            let result = book.add_order(&order).map_err(to_status)?;
+            self.publish(&result.trades);
+            result
+        };
+        Ok(Response::new(proto::SubmitOrderResponse {
+            order_id: result.order_id.to_string(),
+            status: encode_status(result.status) as i32,
+            remaining: result.remaining,
+            trades: result.trades.iter().map(encode_trade).collect(),
+        }))
+    }
+
+    async fn cancel_order(
+        &self,
+        request: Request<proto::CancelOrderRequest>,
+    ) -> Result<Response<proto::CancelOrderResponse>, Status> {
+        let order_id: OrderId = request
+            .into_inner()
+            .order_id
+            .parse()
+            .map_err(|_| Status::invalid_argument("order_id is not a UUID"))?;
+        let result = {
+            let mut book = self.book.lock().unwrap();
+            let result = book
+                .apply_command(&Command::CancelOrder(order_id))
+                .map_err(to_status)?;
+            self.publish(&[]);
+            result
+        };
+        Ok(Response::new(proto::CancelOrderResponse {
+            order_id: order_id.to_string(),
+            canceled_quantity: result.remaining,
+        }))
+    }
+
+    type StreamDepthStream = UpdateStream<proto::DepthUpdate>;
+
+    async fn stream_depth(
+        &self,
+        request: Request<proto::StreamDepthRequest>,
+    ) -> Result<Response<Self::StreamDepthStream>, Status> {
+        let levels = match request.into_inner().levels {
+            0 => DEFAULT_DEPTH_LEVELS,
+            levels => levels as usize,
+        };
+        // Subscribed before the first snapshot so no change falls in between
+        let mut updates = self.updates.subscribe();
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let service = self.clone();
+        tokio::spawn(async move {
+            if sender.send(Ok(service.depth_update(levels))).await.is_err() {
+                return;
+            }
+            loop {
+                match updates.recv().await {
+                    // A lagging client skips straight to the latest snapshot
+                    Ok(MarketUpdate::BookChanged) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                        if sender.send(Ok(service.depth_update(levels))).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(MarketUpdate::Trade(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    type StreamTradesStream = UpdateStream<proto::Trade>;
+
+    async fn stream_trades(
+        &self,
+        _request: Request<proto::StreamTradesRequest>,
+    ) -> Result<Response<Self::StreamTradesStream>, Status> {
+        let mut updates = self.updates.subscribe();
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            loop {
+                let item = match updates.recv().await {
+                    Ok(MarketUpdate::Trade(trade)) => Ok(encode_trade(&trade)),
+                    Ok(MarketUpdate::BookChanged) => continue,
+                    // Trades cannot be recovered from a snapshot, end the stream instead
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => Err(Status::data_loss(
+                        format!("stream fell {} updates behind", skipped),
+                    )),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                let failed = item.is_err();
+                if sender.send(item).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+fn decode_order(order: &proto::Order) -> Result<Order, Status> {
+    let side = match proto::Side::try_from(order.side) {
+        Ok(proto::Side::Buy) => Side::Buy,
+        Ok(proto::Side::Sell) => Side::Sell,
+        _ => return Err(Status::invalid_argument("side must be BUY or SELL")),
+    };
+    let order_type = match proto::OrderType::try_from(order.order_type) {
+        Ok(proto::OrderType::Limit) => OrderType::LimitOrder,
+        Ok(proto::OrderType::Market) => OrderType::MarketOrder,
+        Ok(proto::OrderType::ImmediateOrCancel) => OrderType::ImmediateOrCancel,
+        Ok(proto::OrderType::FillOrKill) => OrderType::FillOrKill,
+        Ok(proto::OrderType::GoodTillCancel) => OrderType::GoodTillCancel,
+        Ok(proto::OrderType::GoodTillDate) => OrderType::GoodTillDate(order.expire_time),
+        Ok(proto::OrderType::PostOnly) => OrderType::PostOnly,
+        Ok(proto::OrderType::GoodForAuction) => OrderType::GoodForAuction,
+        Err(_) => return Err(Status::invalid_argument("unknown order type")),
+    };
+    let mut decoded =
+        Order::new(order_type, side, order.price, order.quantity).with_owner(order.owner);
+    if !order.client_order_id.is_empty() {
+        decoded = decoded.with_client_order_id(order.client_order_id.clone());
+    }
+    Ok(decoded)
+}
+
+fn encode_side(side: Side) -> proto::Side {
+    match side {
+        Side::Buy => proto::Side::Buy,
+        Side::Sell => proto::Side::Sell,
+    }
+}
+
+fn encode_status(status: OrderStatus) -> proto::OrderStatus {
+    match status {
+        OrderStatus::New => proto::OrderStatus::New,
+        OrderStatus::PartiallyFilled => proto::OrderStatus::PartiallyFilled,
+        OrderStatus::Filled => proto::OrderStatus::Filled,
+        OrderStatus::Canceled => proto::OrderStatus::Canceled,
+        OrderStatus::Expired => proto::OrderStatus::Expired,
+        OrderStatus::Rejected => proto::OrderStatus::Rejected,
+    }
+}
+
+fn encode_trade(trade: &Trade) -> proto::Trade {
+    proto::Trade {
+        trade_id: trade.trade_id().to_string(),
+        bid_order_id: trade.bid_order_id().to_string(),
+        ask_order_id: trade.ask_order_id().to_string(),
+        price: trade.price(),
+        quantity: trade.quantity(),
+        aggressor: trade
+            .aggressor_side()
+            .map_or(proto::Side::Unspecified, encode_side) as i32,
+        timestamp: trade.timestamp(),
+        sequence: trade.sequence(),
+    }
+}
+
+/// `OrderBookError` as a gRPC status, with the same classes as the REST API's HTTP codes.
+pub fn to_status(error: OrderBookError) -> Status {
+    let message = error.to_string();
+    match error {
+        OrderBookError::OrderNotFound { .. }
+        | OrderBookError::ClientOrderNotFound { .. }
+        | OrderBookError::SymbolNotFound { .. } => Status::not_found(message),
+        OrderBookError::InvalidPrice { .. }
+        | OrderBookError::InvalidQuantity { .. }
+        | OrderBookError::InvalidPriceFormat { .. }
+        | OrderBookError::InvalidTick { .. }
+        | OrderBookError::InvalidLot { .. }
+        | OrderBookError::InvalidClientOrderId { .. }
+        | OrderBookError::PriceOutsideBand { .. }
+        | OrderBookError::Reject { .. } => Status::invalid_argument(message),
+        OrderBookError::OrderAlreadyExists { .. }
+        | OrderBookError::DuplicateClientOrderId { .. }
+        | OrderBookError::SymbolAlreadyExists { .. } => Status::already_exists(message),
+        OrderBookError::PostOnlyWouldCross { .. }
+        | OrderBookError::MinQtyUnavailable { .. }
+        | OrderBookError::PegReferenceUnavailable { .. }
+        | OrderBookError::StaleValidationToken { .. }
+        | OrderBookError::InvalidStateTransition { .. } => Status::failed_precondition(message),
+        OrderBookError::MessageBudgetExceeded { .. } | OrderBookError::RateLimited { .. } => {
+            Status::resource_exhausted(message)
+        }
+        OrderBookError::MaxOrderSizeExceeded { .. }
+        | OrderBookError::MaxNotionalExceeded { .. }
+        | OrderBookError::MaxOpenOrdersExceeded { .. } => Status::permission_denied(message),
+        OrderBookError::InstrumentStateRejected { .. } => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+#[cfg(test)]
+mod grpc_tests {
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::Code;
+    use tonic::transport::Server;
+
+    use super::proto::matching_engine_client::MatchingEngineClient;
+    use super::*;
+
+    fn limit(side: proto::Side, price: i64, quantity: u64) -> proto::SubmitOrderRequest {
+        proto::SubmitOrderRequest {
+            order: Some(proto::Order {
+                side: side as i32,
+                order_type: proto::OrderType::Limit as i32,
+                price,
+                quantity,
+                ..proto::Order::default()
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn check_grpc_order_entry_and_streams() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = GrpcService::new(OrderBook::new());
+        tokio::spawn(
+            Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = MatchingEngineClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let mut depth = client
+            .stream_depth(proto::StreamDepthRequest { levels: 5 })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut trades = client
+            .stream_trades(proto::StreamTradesRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(depth.next().await.unwrap().unwrap().asks.is_empty());
+
+        let ask = client
+            .submit_order(limit(proto::Side::Sell, 101, 10))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(ask.status, proto::OrderStatus::New as i32);
+        let update = depth.next().await.unwrap().unwrap();
+        assert_eq!(
+            update.asks,
+            vec![proto::LevelInfo {
+                price: 101,
+                volume: 10
+            }]
+        );
+
+        let bid = client
+            .submit_order(limit(proto::Side::Buy, 101, 4))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            (bid.status, bid.trades.len()),
+            (proto::OrderStatus::Filled as i32, 1)
+        );
+        let trade = trades.next().await.unwrap().unwrap();
+        assert_eq!((trade.price, trade.quantity), (101, 4));
+        assert_eq!(trade.aggressor, proto::Side::Buy as i32);
+        assert_eq!(trade.ask_order_id, ask.order_id);
+
+        let canceled = client
+            .cancel_order(proto::CancelOrderRequest {
+                order_id: ask.order_id.clone(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(canceled.canceled_quantity, 6);
+        let error = client
+            .cancel_order(proto::CancelOrderRequest {
+                order_id: ask.order_id,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Code::NotFound);
+        let error = client
+            .submit_order(limit(proto::Side::Unspecified, 100, 1))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+    }
+}
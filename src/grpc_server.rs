@@ -0,0 +1,27 @@
+use std::env;
+
+use log::{LevelFilter, info};
+use tonic::transport::Server;
+
+use orderbook::grpc::GrpcService;
+use orderbook::orderbook::orderbook_impl::OrderBook;
+
+// Usage: grpc_server [addr], defaults to 127.0.0.1:50051
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Info)
+        .init();
+
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:50051".to_string())
+        .parse()
+        .expect("invalid listen address");
+    info!("Serving gRPC on {}", addr);
+    Server::builder()
+        .add_service(GrpcService::new(OrderBook::new()).into_server())
+        .serve(addr)
+        .await
+        .expect("server error");
+}
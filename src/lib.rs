@@ -1 +1,16 @@
+#[cfg(feature = "async")]
+pub mod async_book;
+pub mod cli;
+pub mod fix;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod market_data;
 pub mod orderbook;
+#[cfg(feature = "rest")]
+pub mod rest;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "server")]
+pub mod ws;
+#[cfg(feature = "zmq")]
+pub mod zmq;
@@ -1,16 +1,37 @@
-use std::sync::Arc;
+use std::io::{self, BufRead, Write};
 
-pub mod orderbook;
-use orderbook::order::{Order, OrderType, Side};
-use orderbook::orderbook_impl::OrderBook;
+use orderbook::cli::{PROMPT, Repl};
 
+// Interactive session against one book, e.g. `buy 10 @ 101`, `cancel 1`, `depth 5`; `help`
+// lists the commands. Also reads a script from a pipe.
 fn main() {
     env_logger::Builder::new()
-        .filter_level(log::LevelFilter::Info)
+        .filter_level(log::LevelFilter::Warn)
         .init();
 
-    let mut test_ob = OrderBook::new();
-    let limit_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
-    let trades = test_ob.add_order(&limit_order).unwrap();
-    println!("trades {:?}", trades);
+    let mut repl = Repl::default();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let _ = write!(out, "{}", PROMPT);
+    let _ = out.flush();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+        match repl.execute(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => {
+                let _ = writeln!(out, "{}", output);
+            }
+            Err(error) => {
+                let _ = writeln!(out, "error: {}", error);
+            }
+        }
+        let _ = write!(out, "{}", PROMPT);
+        let _ = out.flush();
+    }
 }
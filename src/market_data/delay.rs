@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::order::Side;
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::price_level::LevelInfo;
+use crate::orderbook::types::OrderId;
+
+/// Delays, in simulated microseconds, between a book event and its publication on each channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PublicationDelays {
+    pub private_fill: i64,
+    pub public_trade: i64,
+    pub depth_update: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Publication {
+    // Sent to the owner of `order_id` only
+    PrivateFill { order_id: OrderId, trade: Trade },
+    PublicTrade(Trade),
+    DepthUpdate { side: Side, level: LevelInfo },
+}
+
+struct Pending {
+    publish_at: i64,
+    sequence: u64,
+    publication: Publication,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        (self.publish_at, self.sequence) == (other.publish_at, other.sequence)
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.publish_at, self.sequence).cmp(&(other.publish_at, other.sequence))
+    }
+}
+
+struct QueueState {
+    delays: PublicationDelays,
+    now: i64,
+    next_sequence: u64,
+    pending: BinaryHeap<Reverse<Pending>>,
+}
+
+impl QueueState {
+    fn schedule(&mut self, delay: i64, publication: Publication) {
+        let pending = Pending {
+            publish_at: self.now + delay,
+            sequence: self.next_sequence,
+            publication,
+        };
+        self.next_sequence += 1;
+        self.pending.push(Reverse(pending));
+    }
+}
+
+/// Holds book events back until the simulated clock passes their publication time, so private
+/// fills, public prints and depth updates can reach subscribers with different delays.
+///
+/// Register a clone as a listener on the book; the simulation driver keeps the other clone and
+/// calls `advance_to` as its clock moves. Events are stamped with the clock value at the time
+/// the book emitted them.
+#[derive(Clone)]
+pub struct PublicationQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl PublicationQueue {
+    pub fn new(delays: PublicationDelays) -> Self {
+        PublicationQueue {
+            state: Arc::new(Mutex::new(QueueState {
+                delays,
+                now: 0,
+                next_sequence: 0,
+                pending: BinaryHeap::new(),
+            })),
+        }
+    }
+
+    pub fn delays(&self) -> PublicationDelays {
+        self.state.lock().unwrap().delays
+    }
+
+    pub fn now(&self) -> i64 {
+        self.state.lock().unwrap().now
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+
+    /// Move the clock to `now` and return every publication that is due, in publication order.
+    /// Events due at the same time keep the order in which the book emitted them.
+    pub fn advance_to(&self, now: i64) -> Vec<Publication> {
+        let mut state = self.state.lock().unwrap();
+        state.now = state.now.max(now);
+        let mut released: Vec<Publication> = Vec::new();
+        while let Some(Reverse(next)) = state.pending.peek() {
+            if next.publish_at > state.now {
+                break;
+            }
+            let Reverse(next) = state.pending.pop().unwrap();
+            released.push(next.publication);
+        }
+        released
+    }
+}
+
+impl OrderBookListener for PublicationQueue {
+    fn on_trade(&mut self, trade: &Trade) {
+        let mut state = self.state.lock().unwrap();
+        let delays = state.delays;
+        for order_id in [trade.bid_order_id(), trade.ask_order_id()] {
+            state.schedule(
+                delays.private_fill,
+                Publication::PrivateFill {
+                    order_id,
+                    trade: trade.clone(),
+                },
+            );
+        }
+        state.schedule(delays.public_trade, Publication::PublicTrade(trade.clone()));
+    }
+
+    fn on_level_change(&mut self, side: Side, level: &LevelInfo) {
+        let mut state = self.state.lock().unwrap();
+        let delay = state.delays.depth_update;
+        state.schedule(
+            delay,
+            Publication::DepthUpdate {
+                side,
+                level: level.clone(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod delay_tests {
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType};
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    #[test]
+    fn check_private_fills_lead_public_information() {
+        let queue = PublicationQueue::new(PublicationDelays {
+            private_fill: 10,
+            public_trade: 50,
+            depth_update: 30,
+        });
+        let mut book = OrderBook::new();
+        book.add_listener(Box::new(queue.clone()));
+
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 5));
+        let _ = book.add_order(&ask);
+        let depth = queue.advance_to(30);
+        assert_eq!(
+            depth,
+            vec![Publication::DepthUpdate {
+                side: Side::Sell,
+                level: LevelInfo {
+                    price: 100,
+                    volume: 5
+                },
+            }]
+        );
+
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 5));
+        let _ = book.add_order(&bid);
+        assert!(queue.advance_to(39).is_empty());
+
+        let private = queue.advance_to(40);
+        assert_eq!(private.len(), 2);
+        assert!(
+            private
+                .iter()
+                .all(|p| matches!(p, Publication::PrivateFill { .. }))
+        );
+
+        let depth = queue.advance_to(60);
+        assert!(matches!(depth[..], [Publication::DepthUpdate { .. }]));
+        let public = queue.advance_to(80);
+        assert!(matches!(public[..], [Publication::PublicTrade(_)]));
+        assert_eq!(queue.pending_count(), 0);
+    }
+}
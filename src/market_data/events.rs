@@ -0,0 +1,32 @@
+use crate::orderbook::order::Side;
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+/// Order-level book events, as published on a market data feed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookEvent {
+    Add {
+        order_id: OrderId,
+        side: Side,
+        price: Price,
+        quantity: Quantity,
+    },
+    Execute {
+        order_id: OrderId,
+        executed_quantity: Quantity,
+        match_id: u64,
+    },
+    // Partial cancel, the order keeps resting with less quantity
+    Cancel {
+        order_id: OrderId,
+        canceled_quantity: Quantity,
+    },
+    Delete {
+        order_id: OrderId,
+    },
+    Replace {
+        original_order_id: OrderId,
+        new_order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
+    },
+}
@@ -0,0 +1,287 @@
+use uuid::Uuid;
+
+use crate::market_data::events::BookEvent;
+use crate::orderbook::order::Side;
+use crate::orderbook::types::OrderId;
+
+// Every message is framed as: length (u16, excludes itself) | type (u8) | sequence (u64) |
+// timestamp nanos (u64) | body. All integers are big-endian, as in NASDAQ ITCH.
+const HEADER_LEN: usize = 1 + 8 + 8;
+const ORDER_REF_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItchMessage {
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub event: BookEvent,
+}
+
+/// Encodes book events into a contiguous ITCH-like binary stream with gap-free sequence numbers.
+pub struct ItchEncoder {
+    next_sequence: u64,
+    buffer: Vec<u8>,
+}
+
+impl ItchEncoder {
+    pub fn new() -> Self {
+        Self::starting_at(1)
+    }
+
+    pub fn starting_at(sequence: u64) -> Self {
+        ItchEncoder {
+            next_sequence: sequence,
+            buffer: Vec::with_capacity(4096),
+        }
+    }
+
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Append `event` to the stream, returning the sequence number it was given.
+    pub fn encode(&mut self, event: &BookEvent, timestamp: u64) -> u64 {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(&[0, 0]); // length, patched below
+        self.buffer.push(message_type(event));
+        self.buffer.extend_from_slice(&sequence.to_be_bytes());
+        self.buffer.extend_from_slice(&timestamp.to_be_bytes());
+
+        match event {
+            BookEvent::Add {
+                order_id,
+                side,
+                price,
+                quantity,
+            } => {
+                self.put_order_ref(order_id);
+                self.buffer.push(match side {
+                    Side::Buy => b'B',
+                    Side::Sell => b'S',
+                });
+                self.buffer.extend_from_slice(&quantity.to_be_bytes());
+                self.buffer.extend_from_slice(&price.to_be_bytes());
+            }
+            BookEvent::Execute {
+                order_id,
+                executed_quantity,
+                match_id,
+            } => {
+                self.put_order_ref(order_id);
+                self.buffer
+                    .extend_from_slice(&executed_quantity.to_be_bytes());
+                self.buffer.extend_from_slice(&match_id.to_be_bytes());
+            }
+            BookEvent::Cancel {
+                order_id,
+                canceled_quantity,
+            } => {
+                self.put_order_ref(order_id);
+                self.buffer
+                    .extend_from_slice(&canceled_quantity.to_be_bytes());
+            }
+            BookEvent::Delete { order_id } => self.put_order_ref(order_id),
+            BookEvent::Replace {
+                original_order_id,
+                new_order_id,
+                price,
+                quantity,
+            } => {
+                self.put_order_ref(original_order_id);
+                self.put_order_ref(new_order_id);
+                self.buffer.extend_from_slice(&quantity.to_be_bytes());
+                self.buffer.extend_from_slice(&price.to_be_bytes());
+            }
+        }
+
+        let length = (self.buffer.len() - start - 2) as u16;
+        self.buffer[start..start + 2].copy_from_slice(&length.to_be_bytes());
+        sequence
+    }
+
+    fn put_order_ref(&mut self, order_id: &OrderId) {
+        self.buffer.extend_from_slice(order_id.as_bytes());
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Hand out the encoded bytes and start a fresh buffer; sequence numbers continue.
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+impl Default for ItchEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn message_type(event: &BookEvent) -> u8 {
+    match event {
+        BookEvent::Add { .. } => b'A',
+        BookEvent::Execute { .. } => b'E',
+        BookEvent::Cancel { .. } => b'X',
+        BookEvent::Delete { .. } => b'D',
+        BookEvent::Replace { .. } => b'U',
+    }
+}
+
+/// Decode the first message of `bytes`, returning it with the number of bytes consumed.
+pub fn decode(bytes: &[u8]) -> Option<(ItchMessage, usize)> {
+    let length = u16::from_be_bytes(bytes.get(0..2)?.try_into().ok()?) as usize;
+    let frame = bytes.get(2..2 + length)?;
+    if frame.len() < HEADER_LEN {
+        return None;
+    }
+    let sequence = u64::from_be_bytes(frame[1..9].try_into().ok()?);
+    let timestamp = u64::from_be_bytes(frame[9..17].try_into().ok()?);
+    let mut reader = Reader {
+        bytes: &frame[HEADER_LEN..],
+    };
+
+    let event = match frame[0] {
+        b'A' => BookEvent::Add {
+            order_id: reader.order_ref()?,
+            side: match reader.u8()? {
+                b'B' => Side::Buy,
+                b'S' => Side::Sell,
+                _ => return None,
+            },
+            quantity: reader.u64()?,
+            price: reader.u64()? as i64,
+        },
+        b'E' => BookEvent::Execute {
+            order_id: reader.order_ref()?,
+            executed_quantity: reader.u64()?,
+            match_id: reader.u64()?,
+        },
+        b'X' => BookEvent::Cancel {
+            order_id: reader.order_ref()?,
+            canceled_quantity: reader.u64()?,
+        },
+        b'D' => BookEvent::Delete {
+            order_id: reader.order_ref()?,
+        },
+        b'U' => BookEvent::Replace {
+            original_order_id: reader.order_ref()?,
+            new_order_id: reader.order_ref()?,
+            quantity: reader.u64()?,
+            price: reader.u64()? as i64,
+        },
+        _ => return None,
+    };
+    Some((
+        ItchMessage {
+            sequence,
+            timestamp,
+            event,
+        },
+        2 + length,
+    ))
+}
+
+/// Decode every complete message in `bytes`.
+pub fn decode_all(mut bytes: &[u8]) -> Vec<ItchMessage> {
+    let mut messages: Vec<ItchMessage> = Vec::new();
+    while let Some((message, consumed)) = decode(bytes) {
+        messages.push(message);
+        bytes = &bytes[consumed..];
+    }
+    messages
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn order_ref(&mut self) -> Option<OrderId> {
+        Uuid::from_slice(self.take(ORDER_REF_LEN)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod itch_tests {
+    use super::*;
+
+    #[test]
+    fn check_encode_decode_round_trip() {
+        let order_id = Uuid::new_v4();
+        let events = [
+            BookEvent::Add {
+                order_id,
+                side: Side::Sell,
+                price: -25,
+                quantity: 100,
+            },
+            BookEvent::Execute {
+                order_id,
+                executed_quantity: 40,
+                match_id: 7,
+            },
+            BookEvent::Cancel {
+                order_id,
+                canceled_quantity: 10,
+            },
+            BookEvent::Replace {
+                original_order_id: order_id,
+                new_order_id: Uuid::new_v4(),
+                price: 26,
+                quantity: 50,
+            },
+            BookEvent::Delete { order_id },
+        ];
+
+        let mut encoder = ItchEncoder::starting_at(10);
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(encoder.encode(event, i as u64 * 1000), 10 + i as u64);
+        }
+        // Add message: 2 length + 17 header + 16 ref + 1 side + 8 qty + 8 price
+        assert_eq!(&encoder.as_bytes()[0..2], &50u16.to_be_bytes());
+
+        let decoded = decode_all(encoder.as_bytes());
+        assert_eq!(decoded.len(), events.len());
+        for (i, message) in decoded.iter().enumerate() {
+            assert_eq!(message.sequence, 10 + i as u64);
+            assert_eq!(message.timestamp, i as u64 * 1000);
+            assert_eq!(message.event, events[i]);
+        }
+    }
+
+    #[test]
+    fn check_truncated_stream_stops_decoding() {
+        let mut encoder = ItchEncoder::new();
+        encoder.encode(
+            &BookEvent::Delete {
+                order_id: Uuid::new_v4(),
+            },
+            0,
+        );
+        let bytes = encoder.take_bytes();
+        assert!(encoder.as_bytes().is_empty());
+        assert_eq!(encoder.next_sequence(), 2);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_none());
+    }
+}
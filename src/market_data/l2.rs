@@ -0,0 +1,234 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::orderbook::limit_order_book::LimitOrderBook;
+use crate::orderbook::order::{Order, OrderType, Side};
+use crate::orderbook::orderbook_impl::{OrderBookError, Trade};
+use crate::orderbook::price_level::{LevelInfo, OrderbookLevelInfos};
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+/// Lightweight aggregated (price -> volume) book, kept in sync from a depth feed via
+/// `apply_level_update`.
+///
+/// Orders submitted through `LimitOrderBook` walk the aggregated opposite side and rest their
+/// remainder as extra volume. Individual passive orders are unknown at L2, so trades carry
+/// `Uuid::nil()` as the counterparty id.
+#[derive(Debug, Default)]
+pub struct L2Book {
+    bids: BTreeMap<Reverse<Price>, Quantity>,
+    asks: BTreeMap<Price, Quantity>,
+    // Own resting orders: side, price and remaining quantity
+    own_orders: HashMap<OrderId, (Side, Price, Quantity)>,
+}
+
+impl L2Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overwrite a level with the feed's volume; volume 0 removes it.
+    pub fn apply_level_update(&mut self, side: Side, level: &LevelInfo) {
+        match side {
+            Side::Buy if level.volume == 0 => {
+                self.bids.remove(&Reverse(level.price));
+            }
+            Side::Buy => {
+                self.bids.insert(Reverse(level.price), level.volume);
+            }
+            Side::Sell if level.volume == 0 => {
+                self.asks.remove(&level.price);
+            }
+            Side::Sell => {
+                self.asks.insert(level.price, level.volume);
+            }
+        }
+    }
+
+    fn take_liquidity(&mut self, order: &Order) -> Vec<Trade> {
+        let mut remaining = order.remaining_quantity;
+        let mut trades: Vec<Trade> = Vec::new();
+        while remaining > 0 {
+            let best = match order.side {
+                Side::Buy => self.asks.first_key_value().map(|(p, v)| (*p, *v)),
+                Side::Sell => self.bids.first_key_value().map(|(Reverse(p), v)| (*p, *v)),
+            };
+            let Some((price, volume)) = best else {
+                break;
+            };
+            let crosses = order.order_type == OrderType::MarketOrder
+                || match order.side {
+                    Side::Buy => price <= order.price,
+                    Side::Sell => price >= order.price,
+                };
+            if !crosses {
+                break;
+            }
+
+            let fill = remaining.min(volume);
+            remaining -= fill;
+            let trade = match order.side {
+                Side::Buy => Trade::new(order.order_id, Uuid::nil(), price, fill),
+                Side::Sell => Trade::new(Uuid::nil(), order.order_id, price, fill),
+            };
+            trades.push(trade.with_aggressor(order.side));
+            let opposite = match order.side {
+                Side::Buy => Side::Sell,
+                Side::Sell => Side::Buy,
+            };
+            self.apply_level_update(
+                opposite,
+                &LevelInfo {
+                    price,
+                    volume: volume - fill,
+                },
+            );
+        }
+        trades
+    }
+
+    fn level_volume(&self, side: Side, price: Price) -> Quantity {
+        match side {
+            Side::Buy => self.bids.get(&Reverse(price)).copied().unwrap_or(0),
+            Side::Sell => self.asks.get(&price).copied().unwrap_or(0),
+        }
+    }
+}
+
+impl LimitOrderBook for L2Book {
+    fn submit(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        if order.remaining_quantity == 0 {
+            return Err(OrderBookError::InvalidQuantity {
+                quantity: order.remaining_quantity,
+            });
+        }
+        if self.own_orders.contains_key(&order.order_id) {
+            return Err(OrderBookError::OrderAlreadyExists {
+                order_id: order.order_id,
+            });
+        }
+
+        let trades = self.take_liquidity(order);
+        let filled: Quantity = trades.iter().map(|trade| trade.quantity()).sum();
+        let remaining = order.remaining_quantity.saturating_sub(filled);
+        if remaining > 0 && order.order_type != OrderType::MarketOrder {
+            let volume = self.level_volume(order.side, order.price) + remaining;
+            self.apply_level_update(
+                order.side,
+                &LevelInfo {
+                    price: order.price,
+                    volume,
+                },
+            );
+            self.own_orders
+                .insert(order.order_id, (order.side, order.price, remaining));
+        }
+        Ok(trades)
+    }
+
+    fn cancel(&mut self, order_id: OrderId) -> Result<(), OrderBookError> {
+        let (side, price, remaining) = self
+            .own_orders
+            .remove(&order_id)
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        // The feed may already have drained the level below our own size
+        let volume = self.level_volume(side, price).saturating_sub(remaining);
+        self.apply_level_update(side, &LevelInfo { price, volume });
+        Ok(())
+    }
+
+    fn best_bid(&self) -> Option<Price> {
+        self.bids.keys().next().map(|Reverse(price)| *price)
+    }
+
+    fn best_ask(&self) -> Option<Price> {
+        self.asks.keys().next().copied()
+    }
+
+    fn depth(&self, levels: usize) -> OrderbookLevelInfos {
+        OrderbookLevelInfos {
+            bids: self
+                .bids
+                .iter()
+                .take(levels)
+                .map(|(Reverse(price), volume)| LevelInfo {
+                    price: *price,
+                    volume: *volume,
+                })
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(levels)
+                .map(|(price, volume)| LevelInfo {
+                    price: *price,
+                    volume: *volume,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod l2_tests {
+    use super::*;
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    // Written once against the trait, run against both implementations
+    fn sweep_and_cancel<B: LimitOrderBook>(book: &mut B) {
+        for price in [101, 102] {
+            let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, price, 5));
+            assert!(book.submit(&ask).unwrap().is_empty());
+        }
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 101, 8));
+        let trades = book.submit(&bid).unwrap();
+        assert_eq!(trades.iter().map(|t| t.quantity()).sum::<Quantity>(), 5);
+        assert_eq!(book.best_bid(), Some(101));
+        assert_eq!(book.best_ask(), Some(102));
+        assert_eq!(
+            book.depth(1).bids,
+            vec![LevelInfo {
+                price: 101,
+                volume: 3
+            }]
+        );
+
+        book.cancel(bid.order_id).unwrap();
+        assert_eq!(book.best_bid(), None);
+        assert!(book.cancel(bid.order_id).is_err());
+    }
+
+    #[test]
+    fn check_trait_is_interchangeable() {
+        sweep_and_cancel(&mut OrderBook::new());
+        sweep_and_cancel(&mut L2Book::new());
+    }
+
+    #[test]
+    fn check_feed_updates_and_market_sweep() {
+        let mut book = L2Book::new();
+        book.apply_level_update(
+            Side::Sell,
+            &LevelInfo {
+                price: 100,
+                volume: 4,
+            },
+        );
+        book.apply_level_update(
+            Side::Sell,
+            &LevelInfo {
+                price: 105,
+                volume: 4,
+            },
+        );
+        let market = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 6));
+        let trades = book.submit(&market).unwrap();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[1].price(), 105);
+        assert_eq!(trades[1].ask_order_id(), Uuid::nil());
+        assert_eq!(book.depth(5).asks[0].volume, 2);
+        assert!(book.depth(5).bids.is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::market_data::events::BookEvent;
+use crate::orderbook::order::Side;
+use crate::orderbook::price_level::{LevelInfo, OrderbookLevelInfos};
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+/// Order-by-order book rebuilt from an L3 feed of `BookEvent`s, e.g. those an `OrderBook`
+/// emits through `OrderBookListener::on_book_event`. Each level keeps its orders in queue
+/// order, so a consumer knows exactly how much quantity is ahead of any order.
+#[derive(Debug, Default)]
+pub struct L3Book {
+    bids: BTreeMap<Price, VecDeque<(OrderId, Quantity)>>,
+    asks: BTreeMap<Price, VecDeque<(OrderId, Quantity)>>,
+    // Side and price of every order on the book
+    orders: HashMap<OrderId, (Side, Price)>,
+}
+
+impl L3Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply one feed event. Events for unknown orders are ignored, as they are for a consumer
+    /// that joined the feed late.
+    pub fn apply(&mut self, event: &BookEvent) {
+        match *event {
+            BookEvent::Add {
+                order_id,
+                side,
+                price,
+                quantity,
+            } => self.add(order_id, side, price, quantity),
+            // An order executed down to zero is gone, there is no separate delete
+            BookEvent::Execute {
+                order_id,
+                executed_quantity,
+                ..
+            } => self.reduce(order_id, executed_quantity),
+            BookEvent::Cancel {
+                order_id,
+                canceled_quantity,
+            } => self.reduce(order_id, canceled_quantity),
+            BookEvent::Delete { order_id } => {
+                self.remove(order_id);
+            }
+            // The replacement joins the back of the queue at its price
+            BookEvent::Replace {
+                original_order_id,
+                new_order_id,
+                price,
+                quantity,
+            } => {
+                if let Some((side, _)) = self.remove(original_order_id) {
+                    self.add(new_order_id, side, price, quantity);
+                }
+            }
+        }
+    }
+
+    /// Orders resting at `price` with their quantities, front of the queue first.
+    pub fn queue(&self, side: Side, price: Price) -> Vec<(OrderId, Quantity)> {
+        self.levels(side)
+            .get(&price)
+            .map_or_else(Vec::new, |queue| queue.iter().copied().collect())
+    }
+
+    /// Quantity queued ahead of `order_id` at its price.
+    pub fn quantity_ahead(&self, order_id: OrderId) -> Option<Quantity> {
+        let (side, price) = *self.orders.get(&order_id)?;
+        let queue = self.levels(side).get(&price)?;
+        Some(
+            queue
+                .iter()
+                .take_while(|(id, _)| *id != order_id)
+                .map(|(_, quantity)| quantity)
+                .sum(),
+        )
+    }
+
+    /// Top `depth` levels of each side aggregated, as an L2 feed would show them.
+    pub fn get_depth(&self, depth: usize) -> OrderbookLevelInfos {
+        let level_info = |(price, queue): (&Price, &VecDeque<(OrderId, Quantity)>)| LevelInfo {
+            price: *price,
+            volume: queue.iter().map(|(_, quantity)| quantity).sum(),
+        };
+        OrderbookLevelInfos {
+            bids: self.bids.iter().rev().take(depth).map(level_info).collect(),
+            asks: self.asks.iter().take(depth).map(level_info).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    fn levels(&self, side: Side) -> &BTreeMap<Price, VecDeque<(OrderId, Quantity)>> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<Price, VecDeque<(OrderId, Quantity)>> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    fn add(&mut self, order_id: OrderId, side: Side, price: Price, quantity: Quantity) {
+        self.orders.insert(order_id, (side, price));
+        self.levels_mut(side)
+            .entry(price)
+            .or_default()
+            .push_back((order_id, quantity));
+    }
+
+    fn reduce(&mut self, order_id: OrderId, quantity: Quantity) {
+        let Some(&(side, price)) = self.orders.get(&order_id) else {
+            return;
+        };
+        let Some(entry) = self
+            .levels_mut(side)
+            .get_mut(&price)
+            .and_then(|queue| queue.iter_mut().find(|(id, _)| *id == order_id))
+        else {
+            return;
+        };
+        entry.1 = entry.1.saturating_sub(quantity);
+        if entry.1 == 0 {
+            self.remove(order_id);
+        }
+    }
+
+    fn remove(&mut self, order_id: OrderId) -> Option<(Side, Price)> {
+        let (side, price) = self.orders.remove(&order_id)?;
+        let levels = self.levels_mut(side);
+        if let Some(queue) = levels.get_mut(&price) {
+            queue.retain(|(id, _)| *id != order_id);
+            if queue.is_empty() {
+                levels.remove(&price);
+            }
+        }
+        Some((side, price))
+    }
+}
+
+#[cfg(test)]
+mod l3_tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::orderbook::config::OrderBookConfig;
+    use crate::orderbook::listener::OrderBookListener;
+    use crate::orderbook::order::{Order, OrderType, PegType};
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    struct Feed(Arc<Mutex<L3Book>>, Arc<Mutex<Vec<BookEvent>>>);
+
+    impl OrderBookListener for Feed {
+        fn on_book_event(&mut self, event: &BookEvent) {
+            self.0.lock().unwrap().apply(event);
+            self.1.lock().unwrap().push(event.clone());
+        }
+    }
+
+    // Every displayed resting order of `book`, queue by queue, as (side, price, id, quantity)
+    fn resting(book: &OrderBook) -> Vec<(Side, Price, OrderId, Quantity)> {
+        let snapshot = book.snapshot();
+        let mut orders = Vec::new();
+        for (side, levels) in [(Side::Buy, &snapshot.bids), (Side::Sell, &snapshot.asks)] {
+            for level in levels.iter() {
+                for order in level.orders.iter().filter(|order| !order.hidden) {
+                    orders.push((side, level.price, order.order_id, order.remaining_quantity));
+                }
+            }
+        }
+        orders
+    }
+
+    fn mirrored(mirror: &L3Book) -> Vec<(Side, Price, OrderId, Quantity)> {
+        let mut orders = Vec::new();
+        for side in [Side::Buy, Side::Sell] {
+            let levels: Vec<Price> = match side {
+                Side::Buy => mirror.bids.keys().rev().copied().collect(),
+                Side::Sell => mirror.asks.keys().copied().collect(),
+            };
+            for price in levels {
+                for (order_id, quantity) in mirror.queue(side, price) {
+                    orders.push((side, price, order_id, quantity));
+                }
+            }
+        }
+        orders
+    }
+
+    #[test]
+    fn check_feed_rebuilds_exact_queues() {
+        let mirror = Arc::new(Mutex::new(L3Book::new()));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut book = OrderBook::with_config(OrderBookConfig::default());
+        book.add_listener(Box::new(Feed(mirror.clone(), events.clone())));
+
+        let limit = |side, price, quantity| {
+            Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+        };
+        let first = limit(Side::Sell, 101, 5);
+        let second = limit(Side::Sell, 101, 3);
+        book.add_order(&first).unwrap();
+        book.add_order(&second).unwrap();
+        book.add_order(&limit(Side::Sell, 102, 4)).unwrap();
+        book.add_order(&Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Sell, 101, 9).as_hidden(),
+        ))
+        .unwrap();
+        book.add_order(&limit(Side::Buy, 99, 2)).unwrap();
+        let pegged = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Buy, 0, 6).with_peg(PegType::Primary, 0),
+        );
+        book.add_order(&pegged).unwrap();
+        assert_eq!(
+            mirror.lock().unwrap().quantity_ahead(second.order_id),
+            Some(5)
+        );
+
+        // a sweep through 101, hidden order included, then the remainder rests there and the
+        // peg follows it up
+        book.add_order(&limit(Side::Buy, 101, 20)).unwrap();
+        book.cancel_order(first.order_id).unwrap_err();
+        let third = limit(Side::Sell, 102, 1);
+        book.add_order(&third).unwrap();
+        book.cancel_order(third.order_id).unwrap();
+        book.expire_due(i64::MAX).unwrap();
+
+        let mirror = mirror.lock().unwrap();
+        assert_eq!(mirrored(&mirror), resting(&book));
+        assert_eq!(mirror.get_depth(5).asks, book.get_depth(5).asks);
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BookEvent::Replace { original_order_id, .. } if *original_order_id == pegged.order_id
+        )));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BookEvent::Execute { order_id, executed_quantity: 5, .. } if *order_id == first.order_id
+        )));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BookEvent::Delete { order_id } if *order_id == third.order_id
+        )));
+    }
+}
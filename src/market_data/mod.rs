@@ -0,0 +1,7 @@
+pub mod delay;
+pub mod events;
+pub mod itch;
+pub mod l2;
+pub mod l3;
+#[cfg(feature = "redis")]
+pub mod redis;
@@ -0,0 +1,299 @@
+use std::io::{self, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::market_data::l2::L2Book;
+use crate::orderbook::limit_order_book::LimitOrderBook;
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::order::Side;
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::price_level::{BboUpdate, LevelInfo, OrderbookLevelInfos};
+
+// Commands waiting for the writer thread before the publisher gives up on the connection
+const QUEUE_LEN: usize = 65_536;
+// A server that takes longer than this to accept a write fails the connection
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Publishes a book's trades and BBO updates to Redis, as JSON on the `<prefix>:trades` and
+/// `<prefix>:bbo` channels, and optionally keeps the top levels of the book under the
+/// `<prefix>:depth` key. Commands are written in RESP to any writer; `connect` opens a
+/// connection and turns replies off, so publishing never waits on the server.
+///
+/// Listener callbacks only queue commands: a writer thread of its own does the writing, so a
+/// slow server never stalls matching. Callbacks cannot fail, so the first error is kept (see
+/// `error`) and nothing is sent after it: a write or serialization error, or a queue that has
+/// filled up because the server does not keep up. Dropping the publisher sends what is queued
+/// and waits for the writer thread.
+pub struct RedisPublisher {
+    // None once publishing has stopped
+    commands: Option<SyncSender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+    prefix: String,
+    // Levels per side mirrored into `<prefix>:depth`, None to only publish
+    depth_levels: Option<usize>,
+    book: L2Book,
+    // Depth last written, to skip changes below the mirrored levels
+    last_depth: OrderbookLevelInfos,
+    error: Arc<Mutex<Option<Arc<io::Error>>>>,
+}
+
+impl RedisPublisher {
+    /// Connect to the Redis server at `addr` and publish under `prefix`, e.g. a symbol.
+    pub fn connect<A: ToSocketAddrs>(addr: A, prefix: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let mut out = BufWriter::new(stream);
+        out.write_all(&command(&[b"CLIENT", b"REPLY", b"OFF"]))?;
+        out.flush()?;
+        Ok(RedisPublisher::new(out, prefix))
+    }
+
+    /// Publish to `out`, which must already be a connection the server does not reply on.
+    pub fn new<W: Write + Send + 'static>(out: W, prefix: &str) -> Self {
+        Self::with_queue_len(out, prefix, QUEUE_LEN)
+    }
+
+    /// Like `new`, giving up once `queue_len` commands are waiting to be written.
+    pub fn with_queue_len<W: Write + Send + 'static>(
+        out: W,
+        prefix: &str,
+        queue_len: usize,
+    ) -> Self {
+        let (commands, queued) = mpsc::sync_channel(queue_len);
+        let error = Arc::new(Mutex::new(None));
+        let writer_error = error.clone();
+        let writer = thread::spawn(move || {
+            if let Err(error) = write_queued(out, queued) {
+                writer_error.lock().unwrap().get_or_insert(Arc::new(error));
+            }
+        });
+        RedisPublisher {
+            commands: Some(commands),
+            writer: Some(writer),
+            prefix: prefix.to_string(),
+            depth_levels: None,
+            book: L2Book::new(),
+            last_depth: OrderbookLevelInfos::default(),
+            error,
+        }
+    }
+
+    /// Also keep the top `levels` of each side under `<prefix>:depth`, as JSON.
+    pub fn with_depth(mut self, levels: usize) -> Self {
+        self.depth_levels = Some(levels);
+        self
+    }
+
+    /// The error that stopped publishing, if any.
+    pub fn error(&self) -> Option<Arc<io::Error>> {
+        self.error.lock().unwrap().clone()
+    }
+
+    // Keep the first error and stop queueing; the writer thread finishes what is queued
+    fn fail(&mut self, error: io::Error) {
+        self.error.lock().unwrap().get_or_insert(Arc::new(error));
+        self.commands = None;
+    }
+
+    fn send(&mut self, args: &[&[u8]]) {
+        let Some(commands) = self.commands.as_ref() else {
+            return;
+        };
+        match commands.try_send(command(args)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => self.fail(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "Redis server fell behind the book",
+            )),
+            // The writer thread has failed and kept its error
+            Err(TrySendError::Disconnected(_)) => self.commands = None,
+        }
+    }
+
+    fn publish<T: Serialize>(&mut self, channel: &str, value: &T) {
+        match serde_json::to_string(value) {
+            Ok(message) => {
+                let channel = format!("{}:{}", self.prefix, channel);
+                self.send(&[b"PUBLISH", channel.as_bytes(), message.as_bytes()]);
+            }
+            Err(error) => self.fail(error.into()),
+        }
+    }
+}
+
+impl Drop for RedisPublisher {
+    fn drop(&mut self) {
+        self.commands = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl OrderBookListener for RedisPublisher {
+    fn on_trade(&mut self, trade: &Trade) {
+        self.publish("trades", trade);
+    }
+
+    fn on_bbo(&mut self, update: &BboUpdate) {
+        self.publish("bbo", update);
+    }
+
+    fn on_level_change(&mut self, side: Side, level: &LevelInfo) {
+        let Some(levels) = self.depth_levels else {
+            return;
+        };
+        if self.commands.is_none() {
+            return;
+        }
+        self.book.apply_level_update(side, level);
+        let depth = self.book.depth(levels);
+        if depth == self.last_depth {
+            return;
+        }
+        let value = match serde_json::to_string(&depth) {
+            Ok(value) => value,
+            Err(error) => return self.fail(error.into()),
+        };
+        let key = format!("{}:depth", self.prefix);
+        self.send(&[b"SET", key.as_bytes(), value.as_bytes()]);
+        self.last_depth = depth;
+    }
+}
+
+// Write commands as they are queued, flushing whenever the queue runs dry
+fn write_queued<W: Write>(mut out: W, queued: Receiver<Vec<u8>>) -> io::Result<()> {
+    while let Ok(command) = queued.recv() {
+        out.write_all(&command)?;
+        while let Ok(command) = queued.try_recv() {
+            out.write_all(&command)?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+// One command as a RESP array of bulk strings
+fn command(args: &[&[u8]]) -> Vec<u8> {
+    let mut encoded = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        encoded.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        encoded.extend_from_slice(arg);
+        encoded.extend_from_slice(b"\r\n");
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod redis_tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType};
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // The commands written so far, each as its arguments
+    fn commands(written: &[u8]) -> Vec<Vec<String>> {
+        let text = String::from_utf8(written.to_vec()).unwrap();
+        let mut lines = text.split("\r\n");
+        let mut commands = Vec::new();
+        while let Some(header) = lines.next().and_then(|line| line.strip_prefix('*')) {
+            let len: usize = header.parse().unwrap();
+            commands.push(
+                (0..len)
+                    .map(|_| {
+                        lines.next().unwrap();
+                        lines.next().unwrap().to_string()
+                    })
+                    .collect(),
+            );
+        }
+        commands
+    }
+
+    #[test]
+    fn check_trades_bbo_and_depth_are_published() {
+        let written = Shared::default();
+        let mut book = OrderBook::new();
+        book.add_listener(Box::new(
+            RedisPublisher::new(written.clone(), "BTCUSD").with_depth(1),
+        ));
+        for (side, price) in [(Side::Sell, 101), (Side::Sell, 102), (Side::Buy, 101)] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, 5));
+            book.add_order(&order).unwrap();
+        }
+        // dropping the publisher waits for everything queued to be written
+        drop(book);
+
+        let commands = commands(&written.0.lock().unwrap());
+        let sent = |verb: &str, target: &str| {
+            commands
+                .iter()
+                .filter(|command| command[0] == verb && command[1] == target)
+                .map(|command| command[2].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sent("PUBLISH", "BTCUSD:trades").len(), 1);
+        assert_eq!(sent("PUBLISH", "BTCUSD:bbo").len(), 2);
+        // the second ask sits below the mirrored level and is not written on its own
+        let depth = sent("SET", "BTCUSD:depth");
+        assert_eq!(depth.len(), 2);
+        assert_eq!(
+            depth.last().unwrap(),
+            "{\"bids\":[],\"asks\":[{\"price\":102,\"volume\":5}]}"
+        );
+    }
+
+    // A server that stops reading, until `release` is dropped
+    struct Stalled(mpsc::Receiver<()>);
+
+    impl Write for Stalled {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            let _ = self.0.recv();
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn check_a_stalled_server_stops_publishing_without_blocking() {
+        let (release, stalled) = mpsc::channel();
+        let mut publisher = RedisPublisher::with_queue_len(Stalled(stalled), "BTCUSD", 2);
+        let mut book = OrderBook::new();
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 101, 100));
+        book.add_order(&ask).unwrap();
+        for _ in 0..10 {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 101, 1));
+            for trade in book.add_order(&order).unwrap().trades {
+                publisher.on_trade(&trade);
+            }
+        }
+        let error = publisher.error().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        drop(release);
+        drop(publisher);
+    }
+}
@@ -0,0 +1,158 @@
+use std::cell::Cell;
+
+use crate::orderbook::order::Side;
+use crate::orderbook::types::{Price, Quantity};
+
+/// Volume resting in the top `levels` price levels of each side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Imbalance {
+    pub levels: usize,
+    pub bid_volume: Quantity,
+    pub ask_volume: Quantity,
+}
+
+impl Imbalance {
+    /// (bid - ask) / (bid + ask), from -1 (all asks) to 1 (all bids); None on an empty book.
+    pub fn ratio(&self) -> Option<f64> {
+        let total = self.bid_volume as f64 + self.ask_volume as f64;
+        (total > 0.0).then(|| (self.bid_volume as f64 - self.ask_volume as f64) / total)
+    }
+}
+
+/// Top-N volume of one side, as last computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SideTop {
+    pub volume: Quantity,
+    pub level_count: usize,
+    // Worst price still inside the top N, None if the side is empty
+    pub worst_price: Option<Price>,
+}
+
+/// Cached top-N volume per side, kept current from the book's level changes. Volume changes at
+/// levels inside the top N adjust the cache in place and changes deeper in the book are ignored;
+/// only a level appearing or disappearing inside the top N forces a rescan of N levels.
+pub struct DepthAnalytics {
+    levels: usize,
+    // None when the side has to be rescanned
+    bids: Cell<Option<SideTop>>,
+    asks: Cell<Option<SideTop>>,
+}
+
+impl DepthAnalytics {
+    pub fn new(levels: usize) -> Self {
+        DepthAnalytics {
+            levels,
+            bids: Cell::new(None),
+            asks: Cell::new(None),
+        }
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    fn side(&self, side: Side) -> &Cell<Option<SideTop>> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    /// Record a volume change at `price`; `structural` if the level was created or removed.
+    pub fn level_changed(
+        &self,
+        side: Side,
+        price: Price,
+        added: Quantity,
+        removed: Quantity,
+        structural: bool,
+    ) {
+        let cell = self.side(side);
+        let Some(mut top) = cell.get() else {
+            return;
+        };
+        let inside = top.level_count < self.levels
+            || top.worst_price.is_none_or(|worst| match side {
+                Side::Buy => price >= worst,
+                Side::Sell => price <= worst,
+            });
+        if !inside {
+            return;
+        }
+        if structural {
+            cell.set(None);
+        } else {
+            top.volume = top.volume.saturating_sub(removed).saturating_add(added);
+            cell.set(Some(top));
+        }
+    }
+
+    /// Cached top-N of `side`, computed with `rescan` if it is stale.
+    pub fn top(&self, side: Side, rescan: impl FnOnce(usize) -> SideTop) -> SideTop {
+        let cell = self.side(side);
+        cell.get().unwrap_or_else(|| {
+            let top = rescan(self.levels);
+            cell.set(Some(top));
+            top
+        })
+    }
+}
+
+/// Best bid and ask weighted by the volume on the opposite side: leans towards the ask when bids
+/// outweigh asks, as the next trade is then more likely to lift the offer.
+pub fn microprice(
+    bid_price: Price,
+    bid_qty: Quantity,
+    ask_price: Price,
+    ask_qty: Quantity,
+) -> Option<f64> {
+    let total = bid_qty as f64 + ask_qty as f64;
+    (total > 0.0)
+        .then(|| (bid_price as f64 * ask_qty as f64 + ask_price as f64 * bid_qty as f64) / total)
+}
+
+#[cfg(test)]
+mod analytics_tests {
+    use super::*;
+
+    #[test]
+    fn check_cache_adjusts_inside_top_levels_only() {
+        let analytics = DepthAnalytics::new(2);
+        let rescans = Cell::new(0);
+        let rescan = |_| {
+            rescans.set(rescans.get() + 1);
+            SideTop {
+                volume: 30,
+                level_count: 2,
+                worst_price: Some(99),
+            }
+        };
+        assert_eq!(analytics.top(Side::Buy, rescan).volume, 30);
+
+        // inside the top two levels, applied in place
+        analytics.level_changed(Side::Buy, 100, 5, 0, false);
+        // deeper in the book, ignored
+        analytics.level_changed(Side::Buy, 98, 0, 7, false);
+        analytics.level_changed(Side::Buy, 97, 4, 0, true);
+        assert_eq!(analytics.top(Side::Buy, rescan).volume, 35);
+        assert_eq!(rescans.get(), 1);
+
+        // a new best level pushes one out, rescan
+        analytics.level_changed(Side::Buy, 101, 4, 0, true);
+        analytics.top(Side::Buy, rescan);
+        assert_eq!(rescans.get(), 2);
+    }
+
+    #[test]
+    fn check_imbalance_and_microprice() {
+        let imbalance = Imbalance {
+            levels: 5,
+            bid_volume: 30,
+            ask_volume: 10,
+        };
+        assert_eq!(imbalance.ratio(), Some(0.5));
+        // heavy bid pulls the microprice towards the ask
+        assert_eq!(microprice(100, 30, 102, 10), Some(101.5));
+        assert_eq!(microprice(100, 0, 102, 0), None);
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use crate::orderbook::order::Order;
+use crate::orderbook::state::InstrumentState;
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+/// A state-changing request against the book. Besides order entry this covers the operator and
+/// timer actions that change the book without an order, so the journal records everything a
+/// replay has to repeat.
+#[derive(Debug, Clone)]
+pub enum Command {
+    NewOrder(Arc<Order>),
+    CancelOrder(OrderId),
+    // Amend a resting order to a new price and total quantity, see `OrderBook::modify_order`
+    ModifyOrder {
+        order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
+    },
+    // Drive the book's timers to a time in epoch millis, see `OrderBook::tick`
+    Tick(i64),
+    // See `OrderBook::set_instrument_state`
+    SetInstrumentState(InstrumentState),
+    // Run the call auction, see `OrderBook::uncross`
+    Uncross,
+    // Reverse a trade, see `OrderBook::bust_trade`. The trade's orders and quantity are carried
+    // along, so a replay restores them even when the trade history no longer has the trade
+    BustTrade {
+        trade_id: OrderId,
+        bid_order_id: OrderId,
+        ask_order_id: OrderId,
+        quantity: Quantity,
+    },
+}
+
+/// Proof that `command` passed validation against a specific version of the book.
+/// Only `OrderBook::validate` can create one.
+#[derive(Debug, Clone)]
+pub struct ValidationToken {
+    pub(crate) command: Command,
+    pub(crate) book_version: u64,
+}
+
+impl ValidationToken {
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use crate::orderbook::fees::FeeSchedule;
+use crate::orderbook::ladder::{LadderKind, MAX_TICK_ARRAY_SLOTS, tick_array_slots};
+use crate::orderbook::match_policy::{Fifo, MatchPolicy, ProRata, SizePriority};
+use crate::orderbook::order_queue::QueueBackendKind;
+use crate::orderbook::types::{Price, Quantity};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PostOnlyPolicy {
+    // Reject a post-only order that would cross the book
+    Reject,
+    // Reprice a crossing post-only order one tick away from the opposite best
+    Reprice,
+}
+
+/// How an incoming order's quantity is shared among the orders resting at a price level.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AllocationPolicy {
+    // Strict time priority
+    Fifo,
+    // In proportion to resting size; shares below `min_allocation` are dropped and whatever is
+    // left after rounding goes to the queue in time priority
+    ProRata { min_allocation: Quantity },
+    // Largest resting order first, time priority between equal sizes
+    SizePriority,
+}
+
+impl AllocationPolicy {
+    /// The `MatchPolicy` implementing this allocation.
+    pub fn match_policy(self) -> Box<dyn MatchPolicy> {
+        match self {
+            AllocationPolicy::Fifo => Box::new(Fifo),
+            AllocationPolicy::ProRata { min_allocation } => Box::new(ProRata { min_allocation }),
+            AllocationPolicy::SizePriority => Box::new(SizePriority),
+        }
+    }
+}
+
+/// What happens to an order whose `min_qty` cannot trade immediately.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MinQtyPolicy {
+    // Reject the order
+    Reject,
+    // Accept it anyway: orders that can rest go on as plain orders without the constraint,
+    // immediate ones (market, IOC, FOK) are canceled without trading
+    DropConstraint,
+}
+
+/// Where hidden orders queue at their price level.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum HiddenPriority {
+    // Same time priority as displayed orders
+    TimePriority,
+    // Behind every displayed order at the price, in time priority among themselves
+    BehindDisplayed,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BandBreachPolicy {
+    // Reject the offending order and keep trading
+    Reject,
+    // Reject the offending order and halt the instrument
+    Halt,
+}
+
+/// Dynamic price band around the book's reference price (the last trade price, or the one set
+/// through `OrderBook::set_reference_price`).
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct PriceBand {
+    // Allowed deviation from the reference price, in basis points
+    pub width_bps: u32,
+    pub breach_policy: BandBreachPolicy,
+}
+
+/// How far one incoming order may walk the opposite side. Whatever is left once the limit is
+/// reached is canceled rather than rested, as it would still cross.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SweepLimit {
+    // Price levels the order may trade at, counted from the touch; at least one
+    Levels(usize),
+    // Ticks the order may trade away from the touch
+    Ticks(Price),
+}
+
+/// A config no book can trade under, refused when the book is built.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Tick size must be positive, got {tick_size}")]
+    NonPositiveTickSize { tick_size: Price },
+
+    #[error("Lot size must be positive")]
+    ZeroLotSize,
+
+    #[error("Tick array range {min_price}..={max_price} is inverted")]
+    InvertedTickArrayRange { min_price: Price, max_price: Price },
+
+    #[error("Tick array range {min_price}..={max_price} is off the tick grid of {tick_size}")]
+    UnalignedTickArrayRange {
+        min_price: Price,
+        max_price: Price,
+        tick_size: Price,
+    },
+
+    #[error("Tick array range {min_price}..={max_price} is wider than a price can express")]
+    TickArrayRangeOverflow { min_price: Price, max_price: Price },
+
+    #[error("Tick array of {slots} slots exceeds the limit of {max_slots}")]
+    TickArrayTooLarge { slots: u64, max_slots: u64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct OrderBookConfig {
+    // Prices must be multiples of the tick size and quantities multiples of the lot size
+    pub tick_size: Price,
+    pub lot_size: Quantity,
+    pub post_only_policy: PostOnlyPolicy,
+    // Enables the latency watchdog when set
+    pub latency_budget: Option<Duration>,
+    // FIFO implementation backing every price level
+    pub queue_backend: QueueBackendKind,
+    // Structure indexing the price levels of each side
+    pub ladder: LadderKind,
+    pub allocation_policy: AllocationPolicy,
+    pub hidden_priority: HiddenPriority,
+    pub min_qty_policy: MinQtyPolicy,
+    // Static (low, high) limits, orders priced outside are always rejected
+    pub price_limits: Option<(Price, Price)>,
+    pub price_band: Option<PriceBand>,
+    // Bounds every sweep of the opposite side when set
+    pub sweep_limit: Option<SweepLimit>,
+    // Maker/taker fees stamped on every trade
+    pub fee_schedule: FeeSchedule,
+    // Trailing windows `OrderBook::stats` reports VWAP and TWAP over
+    pub stats_windows: Vec<Duration>,
+    // Price levels per side counted by `OrderBook::imbalance`
+    pub imbalance_levels: usize,
+    // Reprice pegged orders from `OrderBook::tick` at this interval instead of after every command
+    pub peg_reprice_interval: Option<Duration>,
+    // Publish the indicative uncross from `OrderBook::tick` at this interval during auctions
+    pub imbalance_interval: Option<Duration>,
+    // Decimal places of the instrument's prices: a book price is the mantissa at this exponent
+    #[cfg(feature = "fixed-point")]
+    pub price_exponent: u8,
+}
+
+impl OrderBookConfig {
+    /// Check the sizes every price and quantity is divided by, and that a tick array ladder
+    /// covers a range it can allocate.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.tick_size <= 0 {
+            return Err(ConfigError::NonPositiveTickSize {
+                tick_size: self.tick_size,
+            });
+        }
+        if self.lot_size == 0 {
+            return Err(ConfigError::ZeroLotSize);
+        }
+        if let LadderKind::TickArray {
+            min_price,
+            max_price,
+        } = self.ladder
+        {
+            if max_price < min_price {
+                return Err(ConfigError::InvertedTickArrayRange {
+                    min_price,
+                    max_price,
+                });
+            }
+            if min_price.rem_euclid(self.tick_size) != 0
+                || max_price.rem_euclid(self.tick_size) != 0
+            {
+                return Err(ConfigError::UnalignedTickArrayRange {
+                    min_price,
+                    max_price,
+                    tick_size: self.tick_size,
+                });
+            }
+            let slots = tick_array_slots(min_price, max_price, self.tick_size).ok_or(
+                ConfigError::TickArrayRangeOverflow {
+                    min_price,
+                    max_price,
+                },
+            )?;
+            if slots > MAX_TICK_ARRAY_SLOTS {
+                return Err(ConfigError::TickArrayTooLarge {
+                    slots,
+                    max_slots: MAX_TICK_ARRAY_SLOTS,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for OrderBookConfig {
+    fn default() -> Self {
+        OrderBookConfig {
+            tick_size: 1,
+            lot_size: 1,
+            post_only_policy: PostOnlyPolicy::Reject,
+            latency_budget: None,
+            queue_backend: QueueBackendKind::Slab,
+            ladder: LadderKind::BTree,
+            allocation_policy: AllocationPolicy::Fifo,
+            hidden_priority: HiddenPriority::BehindDisplayed,
+            min_qty_policy: MinQtyPolicy::Reject,
+            price_limits: None,
+            price_band: None,
+            sweep_limit: None,
+            fee_schedule: FeeSchedule::default(),
+            stats_windows: vec![Duration::from_secs(60)],
+            imbalance_levels: 5,
+            peg_reprice_interval: None,
+            imbalance_interval: None,
+            #[cfg(feature = "fixed-point")]
+            price_exponent: 0,
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::orderbook::command::Command;
+use crate::orderbook::config::OrderBookConfig;
+use crate::orderbook::order::Order;
+use crate::orderbook::orderbook_impl::{MatchResult, OrderBook, OrderBookError};
+use crate::orderbook::price_level::OrderbookLevelInfos;
+use crate::orderbook::rate_limit::{BurstPolicy, RateLimitConfig, RateLimiter};
+use crate::orderbook::types::{OrderId, ParticipantId, Quantity, Symbol};
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct ExchangeStats {
+    pub symbols: usize,
+    pub resting_orders: usize,
+    pub trades: usize,
+    pub traded_volume: Quantity,
+}
+
+struct QueuedCommand {
+    symbol: Symbol,
+    participant_id: ParticipantId,
+    command: Command,
+}
+
+/// A rate-limited command that `Exchange::release_queued` has now applied.
+#[derive(Debug)]
+pub struct ReleasedCommand {
+    pub symbol: Symbol,
+    pub command: Command,
+    pub result: Result<MatchResult, OrderBookError>,
+}
+
+/// A set of order books keyed by symbol, routing commands to the right instrument.
+#[derive(Default)]
+pub struct Exchange {
+    books: HashMap<Symbol, OrderBook>,
+    rate_limiter: Option<RateLimiter>,
+    queued: VecDeque<QueuedCommand>,
+}
+
+impl Exchange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_symbol(
+        &mut self,
+        symbol: &str,
+        config: OrderBookConfig,
+    ) -> Result<(), OrderBookError> {
+        if self.books.contains_key(symbol) {
+            return Err(OrderBookError::SymbolAlreadyExists {
+                symbol: symbol.to_string(),
+            });
+        }
+        self.books
+            .insert(symbol.to_string(), OrderBook::try_with_config(config)?);
+        Ok(())
+    }
+
+    pub fn book(&self, symbol: &str) -> Result<&OrderBook, OrderBookError> {
+        self.books
+            .get(symbol)
+            .ok_or_else(|| OrderBookError::SymbolNotFound {
+                symbol: symbol.to_string(),
+            })
+    }
+
+    pub fn book_mut(&mut self, symbol: &str) -> Result<&mut OrderBook, OrderBookError> {
+        self.books
+            .get_mut(symbol)
+            .ok_or_else(|| OrderBookError::SymbolNotFound {
+                symbol: symbol.to_string(),
+            })
+    }
+
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut symbols: Vec<&str> = self.books.keys().map(|s| s.as_str()).collect();
+        symbols.sort_unstable();
+        symbols
+    }
+
+    /// Throttle order entry and cancels per participant (the order's owner) with token buckets.
+    pub fn set_rate_limit(&mut self, config: RateLimitConfig) {
+        self.rate_limiter = Some(RateLimiter::new(config));
+    }
+
+    pub fn add_order(
+        &mut self,
+        symbol: &str,
+        order: &Arc<Order>,
+    ) -> Result<MatchResult, OrderBookError> {
+        self.book(symbol)?;
+        self.throttle(symbol, order.owner, Command::NewOrder(order.clone()))?;
+        self.book_mut(symbol)?.add_order(order)
+    }
+
+    pub fn cancel_order(&mut self, symbol: &str, order_id: OrderId) -> Result<(), OrderBookError> {
+        // Unknown orders are left for the book to reject
+        if let Some(owner) = self.book(symbol)?.order_owner(order_id) {
+            self.throttle(symbol, owner, Command::CancelOrder(order_id))?;
+        }
+        self.book_mut(symbol)?.cancel_order(order_id)
+    }
+
+    // Charge `participant_id` for `command`, queueing it on an empty bucket if the policy allows
+    fn throttle(
+        &mut self,
+        symbol: &str,
+        participant_id: ParticipantId,
+        command: Command,
+    ) -> Result<(), OrderBookError> {
+        let Some(limiter) = self.rate_limiter.as_mut() else {
+            return Ok(());
+        };
+        let Err(OrderBookError::RateLimited { retry_after, .. }) =
+            limiter.acquire(participant_id, symbol, Instant::now())
+        else {
+            return Ok(());
+        };
+
+        let queued = match limiter.config().burst_policy {
+            BurstPolicy::Reject => false,
+            BurstPolicy::Queue { max_queued } => {
+                let backlog = self
+                    .queued
+                    .iter()
+                    .filter(|queued| queued.participant_id == participant_id)
+                    .count();
+                backlog < max_queued
+            }
+        };
+        if queued {
+            self.queued.push_back(QueuedCommand {
+                symbol: symbol.to_string(),
+                participant_id,
+                command,
+            });
+        }
+        Err(OrderBookError::RateLimited {
+            participant_id,
+            retry_after,
+            queued,
+        })
+    }
+
+    /// Apply queued commands whose participants have tokens again, in arrival order.
+    pub fn release_queued(&mut self) -> Vec<ReleasedCommand> {
+        let Some(limiter) = self.rate_limiter.as_mut() else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let mut ready: Vec<QueuedCommand> = Vec::new();
+        let mut waiting: VecDeque<QueuedCommand> = VecDeque::new();
+        for queued in self.queued.drain(..) {
+            // Keep a participant's commands in order once one of them has to wait
+            let blocked = waiting
+                .iter()
+                .any(|waiting| waiting.participant_id == queued.participant_id);
+            if !blocked
+                && limiter
+                    .acquire(queued.participant_id, &queued.symbol, now)
+                    .is_ok()
+            {
+                ready.push(queued);
+            } else {
+                waiting.push_back(queued);
+            }
+        }
+        self.queued = waiting;
+
+        ready
+            .into_iter()
+            .map(|queued| {
+                let result = self.apply(&queued.symbol, &queued.command);
+                ReleasedCommand {
+                    symbol: queued.symbol,
+                    command: queued.command,
+                    result,
+                }
+            })
+            .collect()
+    }
+
+    pub fn queued_count(&self) -> usize {
+        self.queued.len()
+    }
+
+    fn apply(&mut self, symbol: &str, command: &Command) -> Result<MatchResult, OrderBookError> {
+        self.book_mut(symbol)?.apply_command(command)
+    }
+
+    /// Cancel every resting order of `symbol`, returning the canceled order ids.
+    pub fn cancel_all(&mut self, symbol: &str) -> Result<Vec<OrderId>, OrderBookError> {
+        self.book_mut(symbol)?.cancel_all()
+    }
+
+    pub fn get_depth(
+        &self,
+        symbol: &str,
+        depth: usize,
+    ) -> Result<OrderbookLevelInfos, OrderBookError> {
+        Ok(self.book(symbol)?.get_depth(depth))
+    }
+
+    pub fn stats(&self) -> ExchangeStats {
+        self.books
+            .values()
+            .fold(ExchangeStats::default(), |mut stats, book| {
+                let history = book.trade_history();
+                stats.symbols += 1;
+                stats.resting_orders += book.resting_order_count();
+                stats.trades += history.trade_count();
+                stats.traded_volume += history.total_volume();
+                stats
+            })
+    }
+}
+
+#[cfg(test)]
+mod exchange_tests {
+    use super::*;
+    use crate::orderbook::order::{OrderType, Side, Status};
+
+    #[test]
+    fn check_orders_routed_by_symbol() {
+        let mut exchange = Exchange::new();
+        exchange
+            .add_symbol("BTCUSDT", OrderBookConfig::default())
+            .unwrap();
+        exchange
+            .add_symbol("ETHUSDT", OrderBookConfig::default())
+            .unwrap();
+        assert!(
+            exchange
+                .add_symbol("BTCUSDT", OrderBookConfig::default())
+                .is_err()
+        );
+        assert_eq!(exchange.symbols(), vec!["BTCUSDT", "ETHUSDT"]);
+
+        let btc_sell = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 5));
+        let eth_buy = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 5));
+        exchange.add_order("BTCUSDT", &btc_sell).unwrap();
+        // same price on another symbol does not cross
+        let result = exchange.add_order("ETHUSDT", &eth_buy).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(result.status, Status::New);
+
+        let btc_buy = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 2));
+        assert_eq!(
+            exchange
+                .add_order("BTCUSDT", &btc_buy)
+                .unwrap()
+                .trades
+                .len(),
+            1
+        );
+        assert!(exchange.add_order("SOLUSDT", &btc_buy).is_err());
+
+        let stats = exchange.stats();
+        assert_eq!(stats.symbols, 2);
+        assert_eq!(stats.resting_orders, 2);
+        assert_eq!(stats.trades, 1);
+        assert_eq!(stats.traded_volume, 2);
+    }
+
+    #[test]
+    fn check_cancel_all_per_symbol() {
+        let mut exchange = Exchange::new();
+        exchange
+            .add_symbol("BTCUSDT", OrderBookConfig::default())
+            .unwrap();
+        exchange
+            .add_symbol("ETHUSDT", OrderBookConfig::default())
+            .unwrap();
+        for price in [98, 99, 101] {
+            let side = if price < 100 { Side::Buy } else { Side::Sell };
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, 1));
+            exchange.add_order("BTCUSDT", &order).unwrap();
+        }
+        let eth_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 1));
+        exchange.add_order("ETHUSDT", &eth_order).unwrap();
+
+        assert_eq!(exchange.cancel_all("BTCUSDT").unwrap().len(), 3);
+        let depth = exchange.get_depth("BTCUSDT", 5).unwrap();
+        assert!(depth.bids.is_empty() && depth.asks.is_empty());
+        assert_eq!(exchange.get_depth("ETHUSDT", 5).unwrap().bids.len(), 1);
+    }
+
+    #[test]
+    fn check_rate_limit_rejects_or_queues_bursts() {
+        let mut exchange = Exchange::new();
+        exchange
+            .add_symbol("BTCUSDT", OrderBookConfig::default())
+            .unwrap();
+        exchange.set_rate_limit(RateLimitConfig {
+            capacity: 2,
+            refill_per_second: 0,
+            per_instrument: false,
+            burst_policy: BurstPolicy::Queue { max_queued: 1 },
+        });
+        let order = |owner| {
+            Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 1).with_owner(owner))
+        };
+
+        let first = order(1);
+        exchange.add_order("BTCUSDT", &first).unwrap();
+        exchange.cancel_order("BTCUSDT", first.order_id).unwrap();
+        assert!(matches!(
+            exchange.add_order("BTCUSDT", &order(1)),
+            Err(OrderBookError::RateLimited {
+                participant_id: 1,
+                queued: true,
+                ..
+            })
+        ));
+        assert!(matches!(
+            exchange.add_order("BTCUSDT", &order(1)),
+            Err(OrderBookError::RateLimited { queued: false, .. })
+        ));
+        // other participants are not affected
+        exchange.add_order("BTCUSDT", &order(2)).unwrap();
+
+        // the bucket never refills, so the queued order stays queued
+        assert!(exchange.release_queued().is_empty());
+        assert_eq!(exchange.queued_count(), 1);
+        assert_eq!(exchange.book("BTCUSDT").unwrap().resting_order_count(), 1);
+    }
+
+    #[test]
+    fn check_queued_commands_released_once_tokens_refill() {
+        let mut exchange = Exchange::new();
+        exchange
+            .add_symbol("BTCUSDT", OrderBookConfig::default())
+            .unwrap();
+        exchange.set_rate_limit(RateLimitConfig {
+            capacity: 1,
+            refill_per_second: 1000,
+            per_instrument: false,
+            burst_policy: BurstPolicy::Queue { max_queued: 8 },
+        });
+        for _ in 0..3 {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 1));
+            let _ = exchange.add_order("BTCUSDT", &order);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        let released = exchange.release_queued();
+        assert!(!released.is_empty());
+        assert!(released.iter().all(|released| released.result.is_ok()));
+        assert_eq!(
+            exchange.book("BTCUSDT").unwrap().resting_order_count(),
+            1 + released.len()
+        );
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::orderbook::order::{Order, Side, Status};
+use crate::orderbook::orderbook_impl::{Liquidity, Trade};
+use crate::orderbook::types::{ClientOrderId, OrderId, OrderTag, ParticipantId, Price, Quantity};
+
+/// What happened to an order, as in FIX ExecType (150).
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ExecType {
+    New,
+    PartialFill,
+    Fill,
+    // User cancels, expiry and remainders the engine drops (market, IOC, FOK, auction-only)
+    Canceled,
+    // The engine moved the order to a new price, e.g. a post-only slide or a peg following its
+    // reference
+    Replaced,
+    Rejected,
+}
+
+/// One change in an order's state, modelled on the FIX ExecutionReport (35=8). The book emits
+/// one per change through `OrderBookListener::on_execution_report`, so a gateway can forward them
+/// as they come instead of working out transitions from trades and cancels.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExecutionReport {
+    // Increasing per book in the order reports are emitted
+    pub exec_id: u64,
+    pub exec_type: ExecType,
+    pub order_id: OrderId,
+    pub client_order_id: Option<ClientOrderId>,
+    pub owner: ParticipantId,
+    pub side: Side,
+    // Limit price the order has after this change
+    pub price: Price,
+    pub status: Status,
+    // This fill's quantity, price, trade and liquidity flag; zero and None for other reports
+    pub last_quantity: Quantity,
+    pub last_price: Option<Price>,
+    pub trade_id: Option<OrderId>,
+    pub liquidity: Option<Liquidity>,
+    pub cumulative_quantity: Quantity,
+    // Quantity still working, 0 once the order is done
+    pub leaves_quantity: Quantity,
+    // Why an order was rejected
+    pub reason: Option<String>,
+    // The order's `Order::tag`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tag: Option<OrderTag>,
+}
+
+impl ExecutionReport {
+    /// Report `order` as it now stands. The book assigns `exec_id` when it emits the report.
+    pub fn new(exec_type: ExecType, order: &Order) -> Self {
+        let status = match exec_type {
+            ExecType::PartialFill => Status::PartiallyFilled,
+            ExecType::Fill => Status::Filled,
+            ExecType::Rejected => Status::Rejected,
+            // Expired orders are reported as canceled and keep their status
+            ExecType::Canceled if order.status != Status::Expired => Status::Canceled,
+            _ => order.status,
+        };
+        let leaves_quantity = match exec_type {
+            ExecType::New | ExecType::PartialFill | ExecType::Replaced => order.remaining_quantity,
+            ExecType::Fill | ExecType::Canceled | ExecType::Rejected => 0,
+        };
+        ExecutionReport {
+            exec_id: 0,
+            exec_type,
+            order_id: order.order_id,
+            client_order_id: order.client_order_id.clone(),
+            owner: order.owner,
+            side: order.side,
+            price: order.price,
+            status,
+            last_quantity: 0,
+            last_price: None,
+            trade_id: None,
+            liquidity: None,
+            cumulative_quantity: order.executed_quantity,
+            leaves_quantity,
+            reason: None,
+            tag: order.tag.clone(),
+        }
+    }
+
+    /// Fill of `quantity` at `price` on `order`, whose quantities already include it.
+    pub fn fill(order: &Order, quantity: Quantity, price: Price) -> Self {
+        let exec_type = if order.remaining_quantity == 0 {
+            ExecType::Fill
+        } else {
+            ExecType::PartialFill
+        };
+        ExecutionReport {
+            last_quantity: quantity,
+            last_price: Some(price),
+            ..Self::new(exec_type, order)
+        }
+    }
+
+    pub fn rejected(order: &Order, reason: String) -> Self {
+        ExecutionReport {
+            reason: Some(reason),
+            ..Self::new(ExecType::Rejected, order)
+        }
+    }
+
+    pub(crate) fn with_trade(mut self, trade: &Trade) -> Self {
+        self.trade_id = Some(trade.trade_id());
+        self.liquidity = trade.liquidity(self.order_id);
+        self
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::orderbook::orderbook_impl::OrderBookError;
+use crate::orderbook::types::{ParticipantId, Price, Quantity};
+
+// Fees are charged in the same integer units as prices
+pub type Fee = i64;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MessageKind {
+    New,
+    Cancel,
+    Modify,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MessagePolicy {
+    Unlimited,
+    // Reject messages once a participant has sent `max_messages` in the current session
+    Budget {
+        max_messages: u64,
+    },
+    FeePerMessage {
+        new_fee: Fee,
+        cancel_fee: Fee,
+        modify_fee: Fee,
+    },
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct MessageCounts {
+    pub new: u64,
+    pub cancel: u64,
+    pub modify: u64,
+    pub fees: Fee,
+}
+
+/// Maker and taker fees in basis points of traded notional (price * quantity).
+/// A negative maker fee is a rebate.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct FeeSchedule {
+    pub maker_fee_bps: i64,
+    pub taker_fee_bps: i64,
+}
+
+impl FeeSchedule {
+    pub fn maker_fee(&self, price: Price, quantity: Quantity) -> Fee {
+        fee_on_notional(self.maker_fee_bps, price, quantity)
+    }
+
+    pub fn taker_fee(&self, price: Price, quantity: Quantity) -> Fee {
+        fee_on_notional(self.taker_fee_bps, price, quantity)
+    }
+}
+
+// Truncated towards zero, so a rebate never exceeds its exact value
+fn fee_on_notional(bps: i64, price: Price, quantity: Quantity) -> Fee {
+    (price as i128 * quantity as i128 * bps as i128 / 10_000) as Fee
+}
+
+pub struct FeeEngine {
+    message_policy: MessagePolicy,
+    message_counts: HashMap<ParticipantId, MessageCounts>,
+}
+
+impl MessageCounts {
+    pub fn total(&self) -> u64 {
+        self.new + self.cancel + self.modify
+    }
+}
+
+impl FeeEngine {
+    pub fn new(message_policy: MessagePolicy) -> Self {
+        FeeEngine {
+            message_policy,
+            message_counts: HashMap::new(),
+        }
+    }
+
+    /// Account a message sent by `participant_id`, returning the fee charged for it.
+    pub fn record_message(
+        &mut self,
+        participant_id: ParticipantId,
+        kind: MessageKind,
+    ) -> Result<Fee, OrderBookError> {
+        let counts = self.message_counts.entry(participant_id).or_default();
+
+        let fee = match self.message_policy {
+            MessagePolicy::Unlimited => 0,
+            MessagePolicy::Budget { max_messages } => {
+                if counts.total() >= max_messages {
+                    return Err(OrderBookError::MessageBudgetExceeded {
+                        participant_id,
+                        max_messages,
+                    });
+                }
+                0
+            }
+            MessagePolicy::FeePerMessage {
+                new_fee,
+                cancel_fee,
+                modify_fee,
+            } => match kind {
+                MessageKind::New => new_fee,
+                MessageKind::Cancel => cancel_fee,
+                MessageKind::Modify => modify_fee,
+            },
+        };
+
+        match kind {
+            MessageKind::New => counts.new += 1,
+            MessageKind::Cancel => counts.cancel += 1,
+            MessageKind::Modify => counts.modify += 1,
+        }
+        counts.fees += fee;
+        Ok(fee)
+    }
+
+    pub fn message_counts(&self, participant_id: ParticipantId) -> MessageCounts {
+        self.message_counts
+            .get(&participant_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Clear all message counts, e.g. at a session boundary.
+    pub fn reset_message_counts(&mut self) {
+        self.message_counts.clear();
+    }
+}
+
+impl Default for FeeEngine {
+    fn default() -> Self {
+        Self::new(MessagePolicy::Unlimited)
+    }
+}
+
+#[cfg(test)]
+mod fees_tests {
+    use super::*;
+
+    #[test]
+    fn check_message_counts_per_participant() {
+        let mut fee_engine = FeeEngine::default();
+        fee_engine.record_message(1, MessageKind::New).unwrap();
+        fee_engine.record_message(1, MessageKind::Cancel).unwrap();
+        fee_engine.record_message(2, MessageKind::Modify).unwrap();
+
+        let counts = fee_engine.message_counts(1);
+        assert_eq!(counts.new, 1);
+        assert_eq!(counts.cancel, 1);
+        assert_eq!(counts.total(), 2);
+        assert_eq!(fee_engine.message_counts(2).modify, 1);
+        assert_eq!(fee_engine.message_counts(3).total(), 0);
+    }
+
+    #[test]
+    fn check_message_budget_rejects_excess_messages() {
+        let mut fee_engine = FeeEngine::new(MessagePolicy::Budget { max_messages: 2 });
+        fee_engine.record_message(1, MessageKind::New).unwrap();
+        fee_engine.record_message(1, MessageKind::Cancel).unwrap();
+        assert!(fee_engine.record_message(1, MessageKind::New).is_err());
+        assert_eq!(fee_engine.message_counts(1).total(), 2);
+
+        fee_engine.reset_message_counts();
+        assert!(fee_engine.record_message(1, MessageKind::New).is_ok());
+    }
+
+    #[test]
+    fn check_fee_schedule_on_notional() {
+        let schedule = FeeSchedule {
+            maker_fee_bps: -2,
+            taker_fee_bps: 5,
+        };
+        // notional 100 * 1_000 = 100_000
+        assert_eq!(schedule.taker_fee(100, 1_000), 50);
+        assert_eq!(schedule.maker_fee(100, 1_000), -20);
+        assert_eq!(schedule.taker_fee(3, 3), 0);
+    }
+
+    #[test]
+    fn check_fee_per_message() {
+        let mut fee_engine = FeeEngine::new(MessagePolicy::FeePerMessage {
+            new_fee: 1,
+            cancel_fee: 3,
+            modify_fee: 2,
+        });
+        assert_eq!(fee_engine.record_message(1, MessageKind::New).unwrap(), 1);
+        assert_eq!(
+            fee_engine.record_message(1, MessageKind::Cancel).unwrap(),
+            3
+        );
+        assert_eq!(
+            fee_engine.record_message(1, MessageKind::Cancel).unwrap(),
+            3
+        );
+        assert_eq!(fee_engine.message_counts(1).fees, 7);
+    }
+}
@@ -0,0 +1,190 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::orderbook::orderbook_impl::OrderBookError;
+use crate::orderbook::types::Price;
+
+// 10^18 is the largest power of ten that fits in an i64
+pub const MAX_EXPONENT: u8 = 18;
+
+/// A decimal price stored as `mantissa * 10^-exponent` without floating point,
+/// e.g. 100.25 is `(10025, 2)` and 0.00000001 is `(1, 8)`.
+///
+/// Books keep matching on integer `Price`s; with a per-instrument exponent the mantissa at that
+/// exponent is the book price (see `OrderBookConfig::price_exponent`).
+#[derive(Debug, Clone, Copy)]
+pub struct FixedPrice {
+    mantissa: Price,
+    exponent: u8,
+}
+
+impl FixedPrice {
+    pub fn new(mantissa: Price, exponent: u8) -> Self {
+        assert!(
+            exponent <= MAX_EXPONENT,
+            "price exponent {exponent} exceeds {MAX_EXPONENT}"
+        );
+        FixedPrice { mantissa, exponent }
+    }
+
+    /// Parse a decimal string at the given exponent. Fails if the string has more significant
+    /// fractional digits than the exponent allows or does not fit.
+    pub fn parse(value: &str, exponent: u8) -> Result<Self, OrderBookError> {
+        let parsed: FixedPrice = value.parse()?;
+        parsed
+            .rescale(exponent)
+            .ok_or_else(|| OrderBookError::InvalidPriceFormat {
+                value: value.to_string(),
+            })
+    }
+
+    pub fn mantissa(&self) -> Price {
+        self.mantissa
+    }
+
+    pub fn exponent(&self) -> u8 {
+        self.exponent
+    }
+
+    /// The same value at another exponent, or `None` if precision would be lost or it overflows.
+    pub fn rescale(&self, exponent: u8) -> Option<Self> {
+        if exponent > MAX_EXPONENT {
+            return None;
+        }
+        let mantissa = match exponent.cmp(&self.exponent) {
+            Ordering::Equal => self.mantissa,
+            Ordering::Greater => self
+                .mantissa
+                .checked_mul(10i64.pow((exponent - self.exponent) as u32))?,
+            Ordering::Less => {
+                let divisor = 10i64.pow((self.exponent - exponent) as u32);
+                if self.mantissa % divisor != 0 {
+                    return None;
+                }
+                self.mantissa / divisor
+            }
+        };
+        Some(FixedPrice { mantissa, exponent })
+    }
+
+    /// Book price (mantissa) at an instrument's exponent.
+    pub fn to_book_price(&self, exponent: u8) -> Option<Price> {
+        self.rescale(exponent).map(|price| price.mantissa)
+    }
+
+    pub fn from_book_price(price: Price, exponent: u8) -> Self {
+        Self::new(price, exponent)
+    }
+
+    // Mantissa at `exponent >= self.exponent`, widened so it cannot overflow
+    fn widened(&self, exponent: u8) -> i128 {
+        self.mantissa as i128 * 10i128.pow((exponent - self.exponent) as u32)
+    }
+}
+
+impl PartialEq for FixedPrice {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FixedPrice {}
+
+impl PartialOrd for FixedPrice {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FixedPrice {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let exponent = self.exponent.max(other.exponent);
+        self.widened(exponent).cmp(&other.widened(exponent))
+    }
+}
+
+impl fmt::Display for FixedPrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exponent == 0 {
+            return write!(f, "{}", self.mantissa);
+        }
+        let divisor = 10u64.pow(self.exponent as u32);
+        let magnitude = self.mantissa.unsigned_abs();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        write!(
+            f,
+            "{sign}{}.{:0width$}",
+            magnitude / divisor,
+            magnitude % divisor,
+            width = self.exponent as usize
+        )
+    }
+}
+
+/// Parses at the exponent implied by the number of fractional digits, so "1.50" is `(150, 2)`.
+impl FromStr for FixedPrice {
+    type Err = OrderBookError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || OrderBookError::InvalidPriceFormat {
+            value: value.to_string(),
+        };
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > MAX_EXPONENT as usize {
+            return Err(invalid());
+        }
+
+        let mantissa: Price = format!("{integer}{fraction}")
+            .parse()
+            .map_err(|_| invalid())?;
+        Ok(FixedPrice {
+            mantissa: if negative { -mantissa } else { mantissa },
+            exponent: fraction.len() as u8,
+        })
+    }
+}
+
+#[cfg(test)]
+mod fixed_point_tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_and_display() {
+        let price: FixedPrice = "100.25".parse().unwrap();
+        assert_eq!((price.mantissa(), price.exponent()), (10025, 2));
+        assert_eq!(price.to_string(), "100.25");
+
+        let sats = FixedPrice::parse("0.00000001", 8).unwrap();
+        assert_eq!(sats.mantissa(), 1);
+        assert_eq!(sats.to_string(), "0.00000001");
+        assert_eq!(FixedPrice::new(-5, 2).to_string(), "-0.05");
+
+        assert!("1.2.3".parse::<FixedPrice>().is_err());
+        assert!("abc".parse::<FixedPrice>().is_err());
+        // 0.001 cannot be expressed in cents
+        assert!(FixedPrice::parse("0.001", 2).is_err());
+        assert_eq!(FixedPrice::parse("0.010", 2).unwrap().mantissa(), 1);
+    }
+
+    #[test]
+    fn check_rescale_and_ordering() {
+        let cents = FixedPrice::new(150, 2);
+        assert_eq!(cents.to_book_price(4), Some(15000));
+        assert_eq!(cents.to_book_price(1), Some(15));
+        assert_eq!(cents.to_book_price(0), None);
+        assert_eq!(FixedPrice::new(i64::MAX, 0).rescale(1), None);
+
+        assert_eq!(FixedPrice::new(150, 2), FixedPrice::new(15, 1));
+        assert!(FixedPrice::new(151, 2) > FixedPrice::new(15, 1));
+        assert!(FixedPrice::new(-1, 0) < FixedPrice::new(1, 8));
+    }
+}
@@ -0,0 +1,221 @@
+//! Golden-file matching tests. Every `tests/golden/<name>.csv` holds a canned sequence of
+//! order flow (see `flow::read_csv`); it is replayed into a fresh book with trade ids from a
+//! `MonotonicIdGenerator` and the clock pinned at each row's timestamp, and everything the book
+//! emits is rendered one event per line and compared with `tests/golden/<name>.golden`.
+//!
+//! After an intended change to matching, regenerate the expected files with
+//! `UPDATE_GOLDEN=1 cargo test golden` and review the diff before committing it.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::market_data::events::BookEvent;
+use crate::orderbook::exec_report::ExecutionReport;
+use crate::orderbook::flow;
+use crate::orderbook::id::MonotonicIdGenerator;
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::order::Side;
+use crate::orderbook::orderbook_impl::{OrderBook, Trade};
+use crate::orderbook::price_level::{BboUpdate, LevelInfo};
+use crate::orderbook::replay::Replayer;
+use crate::orderbook::types::{OrderId, Price};
+
+// Rendered lines shared between the listener and the harness
+struct GoldenLog(Arc<Mutex<Vec<String>>>);
+
+impl GoldenLog {
+    fn push(&mut self, line: String) {
+        self.0.lock().unwrap().push(line);
+    }
+}
+
+impl OrderBookListener for GoldenLog {
+    fn on_trade(&mut self, trade: &Trade) {
+        self.push(format!(
+            "trade {} {}x{} bid={} ask={} aggressor={:?} seq={} ts={}",
+            id(trade.trade_id()),
+            trade.price(),
+            trade.quantity(),
+            id(trade.bid_order_id()),
+            id(trade.ask_order_id()),
+            trade.aggressor_side(),
+            trade.sequence(),
+            trade.timestamp(),
+        ));
+    }
+
+    fn on_execution_report(&mut self, report: &ExecutionReport) {
+        let mut line = format!(
+            "report {} {:?} order={} {:?} {} status={:?} cum={} leaves={}",
+            report.exec_id,
+            report.exec_type,
+            id(report.order_id),
+            report.side,
+            report.price,
+            report.status,
+            report.cumulative_quantity,
+            report.leaves_quantity,
+        );
+        if let (Some(price), Some(trade_id)) = (report.last_price, report.trade_id) {
+            let _ = write!(
+                line,
+                " last={}x{} trade={} {:?}",
+                price,
+                report.last_quantity,
+                id(trade_id),
+                report.liquidity
+            );
+        }
+        if let Some(reason) = report.reason.as_ref() {
+            let _ = write!(line, " reason={}", reason);
+        }
+        self.push(line);
+    }
+
+    fn on_level_change(&mut self, side: Side, level: &LevelInfo) {
+        self.push(format!(
+            "level {:?} {} vol={}",
+            side, level.price, level.volume
+        ));
+    }
+
+    fn on_book_event(&mut self, event: &BookEvent) {
+        let line = match *event {
+            BookEvent::Add {
+                order_id,
+                side,
+                price,
+                quantity,
+            } => format!("l3 add {} {:?} {}x{}", id(order_id), side, price, quantity),
+            BookEvent::Execute {
+                order_id,
+                executed_quantity,
+                match_id,
+            } => format!(
+                "l3 execute {} qty={} match={}",
+                id(order_id),
+                executed_quantity,
+                match_id
+            ),
+            BookEvent::Cancel {
+                order_id,
+                canceled_quantity,
+            } => format!("l3 cancel {} qty={}", id(order_id), canceled_quantity),
+            BookEvent::Delete { order_id } => format!("l3 delete {}", id(order_id)),
+            BookEvent::Replace {
+                original_order_id,
+                new_order_id,
+                price,
+                quantity,
+            } => format!(
+                "l3 replace {} -> {} {}x{}",
+                id(original_order_id),
+                id(new_order_id),
+                price,
+                quantity
+            ),
+        };
+        self.push(line);
+    }
+
+    fn on_bbo(&mut self, update: &BboUpdate) {
+        self.push(format!(
+            "bbo {} / {} seq={}",
+            quote(update.bid_price, update.bid_qty),
+            quote(update.ask_price, update.ask_qty),
+            update.seq
+        ));
+    }
+}
+
+// Flow files number their orders, so ids print as those numbers
+fn id(order_id: OrderId) -> String {
+    match order_id.as_u64_pair() {
+        (0, number) => number.to_string(),
+        _ => order_id.to_string(),
+    }
+}
+
+fn quote(price: Option<Price>, quantity: u64) -> String {
+    price.map_or_else(
+        || "-".to_string(),
+        |price| format!("{}x{}", price, quantity),
+    )
+}
+
+// Replay the flow in `path`, returning the rendered events, rejections and final book
+fn run(path: &Path) -> String {
+    let events = flow::load(path).unwrap();
+    let mut book = OrderBook::new();
+    book.set_id_generator(Box::new(MonotonicIdGenerator::new(1)));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    book.add_listener(Box::new(GoldenLog(log.clone())));
+    let mut replayer = Replayer::new(book);
+
+    let mut output = String::new();
+    for event in events.iter() {
+        let result = replayer.apply_flow(event);
+        let _ = writeln!(output, "# line {}", event.line);
+        for line in log.lock().unwrap().drain(..) {
+            let _ = writeln!(output, "{}", line);
+        }
+        if let Err(error) = result {
+            let _ = writeln!(output, "rejected: {}", error);
+        }
+    }
+    let depth = replayer.book().get_depth(usize::MAX);
+    let _ = writeln!(output, "# final book");
+    for (side, levels) in [("bid", depth.bids), ("ask", depth.asks)] {
+        for level in levels {
+            let _ = writeln!(output, "{} {} vol={}", side, level.price, level.volume);
+        }
+    }
+    output
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+#[test]
+fn check_matching_against_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut scenarios: Vec<PathBuf> = std::fs::read_dir(golden_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "csv"))
+        .collect();
+    scenarios.sort();
+    assert!(!scenarios.is_empty(), "no scenarios in {:?}", golden_dir());
+
+    let mut mismatched = Vec::new();
+    for scenario in scenarios.iter() {
+        let produced = run(scenario);
+        let golden = scenario.with_extension("golden");
+        if update {
+            std::fs::write(&golden, &produced).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&golden).unwrap_or_default();
+        if produced != expected {
+            let line = produced
+                .lines()
+                .zip(expected.lines())
+                .position(|(found, expected)| found != expected)
+                .unwrap_or_else(|| produced.lines().count().min(expected.lines().count()));
+            mismatched.push(format!(
+                "{}: first difference at line {}\n  expected: {:?}\n  found:    {:?}",
+                golden.display(),
+                line + 1,
+                expected.lines().nth(line),
+                produced.lines().nth(line),
+            ));
+        }
+    }
+    assert!(
+        mismatched.is_empty(),
+        "golden files differ (rerun with UPDATE_GOLDEN=1 if intended):\n{}",
+        mismatched.join("\n")
+    );
+}
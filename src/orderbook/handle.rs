@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::orderbook::exec_report::{ExecType, ExecutionReport};
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::order::Status;
+use crate::orderbook::types::{OrderId, Quantity};
+
+// Report channels of the orders submitted through `OrderBook::submit` that are still working
+pub(crate) type Subscriptions = Arc<Mutex<HashMap<OrderId, Sender<ExecutionReport>>>>;
+
+/// Listener routing each execution report to the handle of its order, installed on the book by
+/// the first `OrderBook::submit`.
+pub(crate) struct HandleRouter(pub(crate) Subscriptions);
+
+impl OrderBookListener for HandleRouter {
+    fn on_execution_report(&mut self, report: &ExecutionReport) {
+        let mut subscriptions = self.0.lock().unwrap();
+        let Some(sender) = subscriptions.get(&report.order_id) else {
+            return;
+        };
+        let done = matches!(
+            report.exec_type,
+            ExecType::Fill | ExecType::Canceled | ExecType::Rejected
+        );
+        // A dropped handle no longer wants the order's reports
+        if sender.send(report.clone()).is_err() || done {
+            subscriptions.remove(&report.order_id);
+        }
+    }
+}
+
+pub(crate) fn subscribe(
+    subscriptions: &Subscriptions,
+    order_id: OrderId,
+) -> Receiver<ExecutionReport> {
+    let (sender, receiver) = mpsc::channel();
+    subscriptions.lock().unwrap().insert(order_id, sender);
+    receiver
+}
+
+/// One order submitted through `OrderBook::submit`, receiving that order's execution reports
+/// (fills, replacements and its final cancel or fill) without filtering the book's global
+/// streams. Reports queue up until read, from whichever thread holds the handle; the channel
+/// closes after the order's last report.
+pub struct OrderHandle {
+    order_id: OrderId,
+    reports: Receiver<ExecutionReport>,
+    // As of the last report read
+    status: Status,
+    filled: Quantity,
+    leaves: Quantity,
+}
+
+impl OrderHandle {
+    pub(crate) fn new(order_id: OrderId, reports: Receiver<ExecutionReport>) -> Self {
+        OrderHandle {
+            order_id,
+            reports,
+            status: Status::New,
+            filled: 0,
+            leaves: 0,
+        }
+    }
+
+    pub fn order_id(&self) -> OrderId {
+        self.order_id
+    }
+
+    /// The next report not read yet, None if there is none for now.
+    pub fn try_next(&mut self) -> Option<ExecutionReport> {
+        let report = self.reports.try_recv().ok()?;
+        Some(self.read(report))
+    }
+
+    /// The next report, waiting up to `timeout` for it, e.g. on a book driven by another thread.
+    /// None on timeout or once the order is done and every report has been read.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<ExecutionReport> {
+        let report = self.reports.recv_timeout(timeout).ok()?;
+        Some(self.read(report))
+    }
+
+    /// Read every report queued so far, returning the fills among them.
+    pub fn poll_fills(&mut self) -> Vec<ExecutionReport> {
+        std::iter::from_fn(|| self.try_next())
+            .filter(|report| report.last_quantity > 0)
+            .collect()
+    }
+
+    /// Status after the last report read.
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Quantity executed and quantity still working, after the last report read.
+    pub fn quantities(&self) -> (Quantity, Quantity) {
+        (self.filled, self.leaves)
+    }
+
+    /// Whether the last report read left the order filled, canceled, expired or rejected.
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self.status,
+            Status::Filled | Status::Canceled | Status::Expired | Status::Rejected
+        )
+    }
+
+    fn read(&mut self, report: ExecutionReport) -> ExecutionReport {
+        self.status = report.status;
+        self.filled = report.cumulative_quantity;
+        self.leaves = report.leaves_quantity;
+        report
+    }
+}
+
+#[cfg(test)]
+mod handle_tests {
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType, Side};
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    #[test]
+    fn check_handle_follows_its_order_to_the_end() {
+        let mut book = OrderBook::new();
+        let mut ask = book
+            .submit(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Sell,
+                10,
+                5,
+            )))
+            .unwrap();
+        assert_eq!(ask.try_next().unwrap().exec_type, ExecType::New);
+        assert!(ask.try_next().is_none());
+
+        let other = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 5));
+        book.add_order(&other).unwrap();
+        for quantity in [2, 3] {
+            let bid = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, quantity));
+            book.add_order(&bid).unwrap();
+        }
+        // only this order's fills, the second one completing it
+        let fills = ask.poll_fills();
+        assert_eq!(
+            fills
+                .iter()
+                .map(|fill| fill.last_quantity)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert!(ask.is_done());
+        assert_eq!((ask.status(), ask.quantities()), (Status::Filled, (5, 0)));
+        assert!(ask.next_timeout(Duration::from_millis(1)).is_none());
+
+        let mut bid = book
+            .submit(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Buy,
+                9,
+                4,
+            )))
+            .unwrap();
+        book.cancel_order(bid.order_id()).unwrap();
+        let reports: Vec<ExecType> = std::iter::from_fn(|| bid.try_next())
+            .map(|report| report.exec_type)
+            .collect();
+        assert_eq!(reports, vec![ExecType::New, ExecType::Canceled]);
+        assert_eq!(bid.status(), Status::Canceled);
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::orderbook::types::OrderId;
+
+/// Hasher of the book's maps and sets keyed by order id, which every cancel, fill and lookup
+/// goes through. The standard SipHash by default; with the `fast-hash` feature, foldhash, which
+/// is much cheaper on 16-byte UUID keys but is not built to resist deliberate collisions.
+#[cfg(feature = "fast-hash")]
+pub type IdBuildHasher = foldhash::fast::RandomState;
+#[cfg(not(feature = "fast-hash"))]
+pub type IdBuildHasher = std::collections::hash_map::RandomState;
+
+/// Map keyed by order id, hashed with [`IdBuildHasher`].
+pub type IdMap<V> = HashMap<OrderId, V, IdBuildHasher>;
+
+/// Set of order ids, hashed with [`IdBuildHasher`].
+pub type IdSet = HashSet<OrderId, IdBuildHasher>;
+
+#[cfg(test)]
+mod hash_tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn check_id_map_finds_and_removes_every_id() {
+        let order_ids: Vec<OrderId> = (0..1_000).map(|_| Uuid::new_v4()).collect();
+        let mut map: IdMap<usize> = IdMap::default();
+        let mut set: IdSet = IdSet::default();
+        for (index, order_id) in order_ids.iter().enumerate() {
+            map.insert(*order_id, index);
+            set.insert(*order_id);
+        }
+        assert_eq!(map.len(), order_ids.len());
+        for (index, order_id) in order_ids.iter().enumerate() {
+            assert_eq!(map.remove(order_id), Some(index));
+            assert!(set.remove(order_id));
+        }
+        assert!(map.is_empty() && set.is_empty());
+    }
+}
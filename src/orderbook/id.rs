@@ -0,0 +1,143 @@
+use std::cell::RefCell;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::orderbook::types::OrderId;
+
+/// Source of order and trade ids.
+pub trait IdGenerator: Send {
+    fn next_id(&mut self) -> OrderId;
+}
+
+/// Random v4 UUIDs, unique across processes without coordination. The default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UuidGenerator;
+
+impl IdGenerator for UuidGenerator {
+    fn next_id(&mut self) -> OrderId {
+        Uuid::new_v4()
+    }
+}
+
+/// Consecutive integers from `start`, carried in the low 64 bits of the id. Cheapest to generate
+/// and reproducible run to run, but only unique within one generator.
+#[derive(Debug, Clone)]
+pub struct MonotonicIdGenerator {
+    next: u64,
+}
+
+impl MonotonicIdGenerator {
+    pub fn new(start: u64) -> Self {
+        MonotonicIdGenerator { next: start }
+    }
+}
+
+impl Default for MonotonicIdGenerator {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl IdGenerator for MonotonicIdGenerator {
+    fn next_id(&mut self) -> OrderId {
+        let id = self.next;
+        self.next += 1;
+        Uuid::from_u64_pair(0, id)
+    }
+}
+
+const SNOWFLAKE_WORKER_BITS: u32 = 10;
+const SNOWFLAKE_SEQUENCE_BITS: u32 = 12;
+
+/// Twitter-style snowflake ids in the low 64 bits: epoch millis, then a 10-bit worker id, then a
+/// 12-bit sequence within the millisecond. Ids from distinct workers never collide and sort by
+/// creation time. Past 4096 ids in a millisecond, or if the clock steps back, the generator
+/// runs ahead of the clock rather than waiting for it.
+#[derive(Debug, Clone)]
+pub struct SnowflakeIdGenerator {
+    worker_id: u64,
+    last_millis: u64,
+    sequence: u64,
+}
+
+impl SnowflakeIdGenerator {
+    /// `worker_id` is truncated to its low 10 bits.
+    pub fn new(worker_id: u16) -> Self {
+        SnowflakeIdGenerator {
+            worker_id: worker_id as u64 & ((1 << SNOWFLAKE_WORKER_BITS) - 1),
+            last_millis: 0,
+            sequence: 0,
+        }
+    }
+}
+
+impl IdGenerator for SnowflakeIdGenerator {
+    fn next_id(&mut self) -> OrderId {
+        let now = Utc::now().timestamp_millis().max(0) as u64;
+        if now > self.last_millis {
+            self.last_millis = now;
+            self.sequence = 0;
+        } else {
+            self.sequence += 1;
+            if self.sequence == 1 << SNOWFLAKE_SEQUENCE_BITS {
+                self.last_millis += 1;
+                self.sequence = 0;
+            }
+        }
+        let id = (self.last_millis << (SNOWFLAKE_WORKER_BITS + SNOWFLAKE_SEQUENCE_BITS))
+            | (self.worker_id << SNOWFLAKE_SEQUENCE_BITS)
+            | self.sequence;
+        Uuid::from_u64_pair(0, id)
+    }
+}
+
+thread_local! {
+    static THREAD_GENERATOR: RefCell<Box<dyn IdGenerator>> = RefCell::new(Box::new(UuidGenerator));
+}
+
+/// Next id from this thread's generator, as used by `Order::new` and `Trade::new`.
+pub fn next_id() -> OrderId {
+    THREAD_GENERATOR.with(|generator| generator.borrow_mut().next_id())
+}
+
+/// Replace this thread's generator, returning the previous one.
+pub fn set_thread_id_generator(generator: Box<dyn IdGenerator>) -> Box<dyn IdGenerator> {
+    THREAD_GENERATOR.with(|current| current.replace(generator))
+}
+
+#[cfg(test)]
+mod id_tests {
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType, Side};
+
+    #[test]
+    fn check_monotonic_and_snowflake_ids() {
+        let mut monotonic = MonotonicIdGenerator::new(5);
+        assert_eq!(monotonic.next_id().as_u64_pair(), (0, 5));
+        assert_eq!(monotonic.next_id().as_u64_pair(), (0, 6));
+
+        // distinct workers never collide, one worker's ids keep increasing
+        let mut first = SnowflakeIdGenerator::new(1);
+        let mut second = SnowflakeIdGenerator::new(2);
+        let mut previous = first.next_id();
+        for _ in 0..10_000 {
+            let id = first.next_id();
+            assert!(id > previous);
+            assert_ne!(id, second.next_id());
+            previous = id;
+        }
+    }
+
+    #[test]
+    fn check_thread_generator_drives_order_ids() {
+        let previous = set_thread_id_generator(Box::new(MonotonicIdGenerator::new(100)));
+        let order = Order::new(OrderType::LimitOrder, Side::Buy, 10, 1);
+        assert_eq!(order.order_id, Uuid::from_u64_pair(0, 100));
+        set_thread_id_generator(previous);
+        assert_ne!(
+            Order::new(OrderType::LimitOrder, Side::Buy, 10, 1).order_id,
+            Uuid::from_u64_pair(0, 101)
+        );
+    }
+}
@@ -0,0 +1,556 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::orderbook::command::Command;
+use crate::orderbook::order::{Order, OrderType, Peg, PegType, Side, Status};
+use crate::orderbook::orderbook_impl::{MatchResult, OrderBook, OrderBookError};
+use crate::orderbook::state::InstrumentState;
+
+/// Append-only log of accepted commands, one line per command:
+///
+/// `N <order_id> <order_type> <side> <price> <original_qty> <executed_qty> <remaining_qty> <status> <timestamp> owner=<owner> [clid=<client_order_id>] [hidden=1] [minqty=<min_qty>] [peg=<peg_type>:<offset>] [prot=<protection_price>] [tag=<hex tag>] ts=<accepted_at> seq=<sequence>`
+/// `C <order_id> ts=<accepted_at> seq=<sequence>`
+/// `M <order_id> <price> <quantity> ts=<accepted_at> seq=<sequence>`
+/// `T <now> ts=<accepted_at> seq=<sequence>`
+/// `I <instrument_state> ts=<accepted_at> seq=<sequence>`
+/// `U ts=<accepted_at> seq=<sequence>`
+/// `B <trade_id> <bid_order_id> <ask_order_id> <quantity> ts=<accepted_at> seq=<sequence>`
+///
+/// `ts` is the book clock in epoch micros when the command was accepted.
+pub struct Journal {
+    writer: BufWriter<File>,
+    sync_on_append: bool,
+}
+
+impl Journal {
+    /// Open (or create) the journal at `path` for appending.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, OrderBookError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal {
+            writer: BufWriter::new(file),
+            sync_on_append: false,
+        })
+    }
+
+    /// fsync after every append instead of only flushing to the OS.
+    pub fn with_sync_on_append(mut self, sync_on_append: bool) -> Self {
+        self.sync_on_append = sync_on_append;
+        self
+    }
+
+    /// Record `command` under the book sequence number and time it was accepted with.
+    pub fn append(
+        &mut self,
+        sequence: u64,
+        timestamp: i64,
+        command: &Command,
+    ) -> Result<(), OrderBookError> {
+        writeln!(
+            self.writer,
+            "{} ts={} seq={}",
+            encode_command(command),
+            timestamp,
+            sequence
+        )?;
+        self.writer.flush()?;
+        if self.sync_on_append {
+            self.writer.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Read every command recorded at `path`, in order.
+    pub fn read_commands<P: AsRef<Path>>(path: P) -> Result<Vec<Command>, OrderBookError> {
+        Ok(Self::read_sequenced_commands(path)?
+            .into_iter()
+            .map(|(_, command)| command)
+            .collect())
+    }
+
+    /// Like `read_commands`, with the sequence number of every command that has one.
+    pub fn read_sequenced_commands<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Vec<(Option<u64>, Command)>, OrderBookError> {
+        Ok(Self::read_entries(path)?
+            .into_iter()
+            .map(|entry| (entry.sequence, entry.command))
+            .collect())
+    }
+
+    /// Every command recorded at `path` with its sequence number and acceptance time.
+    pub fn read_entries<P: AsRef<Path>>(path: P) -> Result<Vec<JournalEntry>, OrderBookError> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries: Vec<JournalEntry> = Vec::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let corrupted = || OrderBookError::JournalCorrupted {
+                line: line_number + 1,
+            };
+            entries.push(JournalEntry {
+                command: decode_command(&line).ok_or_else(corrupted)?,
+                sequence: decode_number(&line, "seq").ok_or_else(corrupted)?,
+                timestamp: decode_number(&line, "ts").ok_or_else(corrupted)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Rebuild a fresh book by replaying the journal at `path`.
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<OrderBook, OrderBookError> {
+        let mut book = OrderBook::new();
+        Self::replay_into(path, &mut book)?;
+        Ok(book)
+    }
+
+    /// Apply every journaled command to `book`, returning how many were applied. `book` must be
+    /// in the state the journal was started from: a recorded sequence number that does not match
+    /// the one the book assigns means the replay has diverged. Each command is applied with the
+    /// book clock pinned at its recorded time; the clock is left at the last one.
+    pub fn replay_into<P: AsRef<Path>>(
+        path: P,
+        book: &mut OrderBook,
+    ) -> Result<usize, OrderBookError> {
+        let entries = Self::read_entries(path)?;
+        for entry in entries.iter() {
+            entry.apply(book)?;
+        }
+        Ok(entries.len())
+    }
+}
+
+/// One journaled command with the sequence number and time the book accepted it under, either
+/// absent in journals written before they were recorded.
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub sequence: Option<u64>,
+    pub timestamp: Option<i64>,
+    pub command: Command,
+}
+
+impl JournalEntry {
+    /// Apply the command to `book` as it was originally, checking its sequence number first.
+    pub fn apply(&self, book: &mut OrderBook) -> Result<MatchResult, OrderBookError> {
+        if let Some(sequence) = self.sequence
+            && sequence != book.sequence() + 1
+        {
+            return Err(OrderBookError::SequenceMismatch {
+                expected: book.sequence() + 1,
+                found: sequence,
+            });
+        }
+        if self.timestamp.is_some() {
+            book.set_clock(self.timestamp);
+        }
+        book.apply_command(&self.command)
+    }
+}
+
+pub fn encode_command(command: &Command) -> String {
+    match command {
+        Command::NewOrder(order) => {
+            let mut line = format!(
+                "N {} {} {} {} {} {} {} {} {} owner={}",
+                order.order_id,
+                encode_order_type(order.order_type),
+                encode_side(order.side),
+                order.price,
+                order.original_quantity,
+                order.executed_quantity,
+                order.remaining_quantity,
+                encode_status(order.status),
+                order.timestamp,
+                order.owner
+            );
+            if let Some(client_order_id) = order.client_order_id.as_ref() {
+                line.push_str(&format!(" clid={}", client_order_id));
+            }
+            if order.hidden {
+                line.push_str(" hidden=1");
+            }
+            if let Some(min_qty) = order.min_qty {
+                line.push_str(&format!(" minqty={}", min_qty));
+            }
+            if let Some(peg) = order.peg {
+                line.push_str(&format!(" peg={}", encode_peg(peg)));
+            }
+            if let Some(protection_price) = order.protection_price {
+                line.push_str(&format!(" prot={}", protection_price));
+            }
+            if let Some(tag) = order.tag.as_ref() {
+                line.push_str(&format!(" tag={}", encode_hex(tag)));
+            }
+            line
+        }
+        Command::CancelOrder(order_id) => format!("C {}", order_id),
+        Command::ModifyOrder {
+            order_id,
+            price,
+            quantity,
+        } => format!("M {} {} {}", order_id, price, quantity),
+        Command::Tick(now) => format!("T {}", now),
+        Command::SetInstrumentState(state) => format!("I {}", encode_instrument_state(*state)),
+        Command::Uncross => "U".to_string(),
+        Command::BustTrade {
+            trade_id,
+            bid_order_id,
+            ask_order_id,
+            quantity,
+        } => format!(
+            "B {} {} {} {}",
+            trade_id, bid_order_id, ask_order_id, quantity
+        ),
+    }
+}
+
+pub fn decode_command(line: &str) -> Option<Command> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    match fields.as_slice() {
+        [
+            "N",
+            order_id,
+            order_type,
+            side,
+            price,
+            original,
+            executed,
+            remaining,
+            status,
+            timestamp,
+            extras @ ..,
+        ] => Some(Command::NewOrder(Arc::new(Order {
+            order_type: decode_order_type(order_type)?,
+            order_id: Uuid::parse_str(order_id).ok()?,
+            side: decode_side(side)?,
+            price: price.parse().ok()?,
+            status: decode_status(status)?,
+            original_quantity: original.parse().ok()?,
+            executed_quantity: executed.parse().ok()?,
+            remaining_quantity: remaining.parse().ok()?,
+            timestamp: timestamp.parse().ok()?,
+            owner: extra_field(extras, "owner").map_or(Some(0), |v| v.parse().ok())?,
+            client_order_id: extra_field(extras, "clid").map(str::to_string),
+            hidden: extra_field(extras, "hidden") == Some("1"),
+            min_qty: match extra_field(extras, "minqty") {
+                Some(value) => Some(value.parse().ok()?),
+                None => None,
+            },
+            peg: match extra_field(extras, "peg") {
+                Some(value) => Some(decode_peg(value)?),
+                None => None,
+            },
+            protection_price: match extra_field(extras, "prot") {
+                Some(value) => Some(value.parse().ok()?),
+                None => None,
+            },
+            tag: match extra_field(extras, "tag") {
+                Some(value) => Some(decode_hex(value)?),
+                None => None,
+            },
+        }))),
+        ["C", order_id, ..] => Some(Command::CancelOrder(Uuid::parse_str(order_id).ok()?)),
+        ["M", order_id, price, quantity, ..] => Some(Command::ModifyOrder {
+            order_id: Uuid::parse_str(order_id).ok()?,
+            price: price.parse().ok()?,
+            quantity: quantity.parse().ok()?,
+        }),
+        ["T", now, ..] => Some(Command::Tick(now.parse().ok()?)),
+        ["I", state, ..] => Some(Command::SetInstrumentState(decode_instrument_state(state)?)),
+        ["U", ..] => Some(Command::Uncross),
+        ["B", trade_id, bid_order_id, ask_order_id, quantity, ..] => Some(Command::BustTrade {
+            trade_id: Uuid::parse_str(trade_id).ok()?,
+            bid_order_id: Uuid::parse_str(bid_order_id).ok()?,
+            ask_order_id: Uuid::parse_str(ask_order_id).ok()?,
+            quantity: quantity.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+// Numeric `key=` field of a journal line, Some(None) for lines written before it was recorded
+// and None if it does not parse
+fn decode_number<T: std::str::FromStr>(line: &str, key: &str) -> Option<Option<T>> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    match extra_field(&fields, key) {
+        Some(value) => value.parse().ok().map(Some),
+        None => Some(None),
+    }
+}
+
+// Optional trailing `key=value` fields, absent in older journals
+fn extra_field<'a>(extras: &[&'a str], key: &str) -> Option<&'a str> {
+    extras.iter().find_map(|field| {
+        let (field_key, value) = field.split_once('=')?;
+        (field_key == key).then_some(value)
+    })
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn encode_order_type(order_type: OrderType) -> String {
+    match order_type {
+        OrderType::LimitOrder => "LIMIT".to_string(),
+        OrderType::MarketOrder => "MARKET".to_string(),
+        OrderType::ImmediateOrCancel => "IOC".to_string(),
+        OrderType::FillOrKill => "FOK".to_string(),
+        OrderType::GoodTillCancel => "GTC".to_string(),
+        OrderType::GoodTillDate(expiry) => format!("GTD:{}", expiry),
+        OrderType::PostOnly => "POST".to_string(),
+        OrderType::GoodForAuction => "GFA".to_string(),
+    }
+}
+
+pub(crate) fn decode_order_type(token: &str) -> Option<OrderType> {
+    match token {
+        "LIMIT" => Some(OrderType::LimitOrder),
+        "MARKET" => Some(OrderType::MarketOrder),
+        "IOC" => Some(OrderType::ImmediateOrCancel),
+        "FOK" => Some(OrderType::FillOrKill),
+        "GTC" => Some(OrderType::GoodTillCancel),
+        "POST" => Some(OrderType::PostOnly),
+        "GFA" => Some(OrderType::GoodForAuction),
+        _ => {
+            let expiry = token.strip_prefix("GTD:")?.parse().ok()?;
+            Some(OrderType::GoodTillDate(expiry))
+        }
+    }
+}
+
+fn encode_peg(peg: Peg) -> String {
+    let peg_type = match peg.peg_type {
+        PegType::Primary => "PRIMARY",
+        PegType::Midpoint => "MID",
+        PegType::Market => "MARKET",
+    };
+    format!("{}:{}", peg_type, peg.offset)
+}
+
+fn decode_peg(token: &str) -> Option<Peg> {
+    let (peg_type, offset) = token.split_once(':')?;
+    let peg_type = match peg_type {
+        "PRIMARY" => PegType::Primary,
+        "MID" => PegType::Midpoint,
+        "MARKET" => PegType::Market,
+        _ => return None,
+    };
+    Some(Peg {
+        peg_type,
+        offset: offset.parse().ok()?,
+    })
+}
+
+pub(crate) fn encode_instrument_state(state: InstrumentState) -> &'static str {
+    match state {
+        InstrumentState::Active => "ACTIVE",
+        InstrumentState::Auction => "AUCTION",
+        InstrumentState::CancelOnly => "CANCEL_ONLY",
+        InstrumentState::Halted => "HALTED",
+        InstrumentState::Closed => "CLOSED",
+    }
+}
+
+pub(crate) fn decode_instrument_state(token: &str) -> Option<InstrumentState> {
+    match token {
+        "ACTIVE" => Some(InstrumentState::Active),
+        "AUCTION" => Some(InstrumentState::Auction),
+        "CANCEL_ONLY" => Some(InstrumentState::CancelOnly),
+        "HALTED" => Some(InstrumentState::Halted),
+        "CLOSED" => Some(InstrumentState::Closed),
+        _ => None,
+    }
+}
+
+fn encode_side(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "B",
+        Side::Sell => "S",
+    }
+}
+
+fn decode_side(token: &str) -> Option<Side> {
+    match token {
+        "B" => Some(Side::Buy),
+        "S" => Some(Side::Sell),
+        _ => None,
+    }
+}
+
+fn encode_status(status: Status) -> &'static str {
+    match status {
+        Status::New => "NEW",
+        Status::PartiallyFilled => "PARTIAL",
+        Status::Filled => "FILLED",
+        Status::Canceled => "CANCELED",
+        Status::Expired => "EXPIRED",
+        Status::Rejected => "REJECTED",
+    }
+}
+
+fn decode_status(token: &str) -> Option<Status> {
+    match token {
+        "NEW" => Some(Status::New),
+        "PARTIAL" => Some(Status::PartiallyFilled),
+        "FILLED" => Some(Status::Filled),
+        "CANCELED" => Some(Status::Canceled),
+        "EXPIRED" => Some(Status::Expired),
+        "REJECTED" => Some(Status::Rejected),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod journal_tests {
+    use super::*;
+
+    fn temp_journal_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.journal", name, Uuid::new_v4()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn check_command_encoding_round_trip() {
+        let order =
+            Arc::new(Order::new(OrderType::GoodTillDate(42), Side::Sell, -5, 10).with_owner(7));
+        let line = encode_command(&Command::NewOrder(order.clone()));
+        match decode_command(&line) {
+            Some(Command::NewOrder(decoded)) => assert_eq!(decoded, order),
+            _ => panic!("failed to decode {}", line),
+        }
+        assert!(decode_command("X garbage").is_none());
+        let modify = Command::ModifyOrder {
+            order_id: order.order_id,
+            price: -7,
+            quantity: 4,
+        };
+        match decode_command(&encode_command(&modify)) {
+            Some(Command::ModifyOrder {
+                order_id,
+                price: -7,
+                quantity: 4,
+            }) => assert_eq!(order_id, order.order_id),
+            decoded => panic!("decoded {:?}", decoded),
+        }
+
+        let tagged = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Buy, 5, 1)
+                .with_client_order_id("client-7")
+                .as_hidden()
+                .with_min_qty(1)
+                .with_peg(PegType::Midpoint, -2)
+                .with_tag(vec![0x00, 0xab, 0xff]),
+        );
+        let protected = Arc::new(
+            Order::new(OrderType::MarketOrder, Side::Sell, 0, 3).with_protection_price(-4),
+        );
+        for order in [tagged, protected] {
+            match decode_command(&encode_command(&Command::NewOrder(order.clone()))) {
+                Some(Command::NewOrder(decoded)) => assert_eq!(decoded, order),
+                _ => panic!("failed to decode {:?}", order),
+            }
+        }
+
+        match decode_command(&encode_command(&Command::Tick(-3))) {
+            Some(Command::Tick(-3)) => {}
+            decoded => panic!("decoded {:?}", decoded),
+        }
+        for state in [InstrumentState::Auction, InstrumentState::CancelOnly] {
+            match decode_command(&encode_command(&Command::SetInstrumentState(state))) {
+                Some(Command::SetInstrumentState(decoded)) => assert_eq!(decoded, state),
+                decoded => panic!("decoded {:?}", decoded),
+            }
+        }
+        assert!(matches!(
+            decode_command(&encode_command(&Command::Uncross)),
+            Some(Command::Uncross)
+        ));
+        let bust = Command::BustTrade {
+            trade_id: order.order_id,
+            bid_order_id: Uuid::nil(),
+            ask_order_id: order.order_id,
+            quantity: 3,
+        };
+        match decode_command(&encode_command(&bust)) {
+            Some(Command::BustTrade {
+                trade_id,
+                bid_order_id,
+                ask_order_id,
+                quantity: 3,
+            }) => assert_eq!(
+                (trade_id, bid_order_id, ask_order_id),
+                (order.order_id, Uuid::nil(), order.order_id)
+            ),
+            decoded => panic!("decoded {:?}", decoded),
+        }
+
+        // Lines written before the owner field decode as unattributed
+        let legacy = line.rsplit_once(' ').unwrap().0;
+        match decode_command(legacy) {
+            Some(Command::NewOrder(decoded)) => assert_eq!(decoded.owner, 0),
+            _ => panic!("failed to decode {}", legacy),
+        }
+    }
+
+    #[test]
+    fn check_journaled_book_replays_to_same_state() {
+        let path = temp_journal_path("replay");
+        let mut book = OrderBook::new();
+        book.set_journal(Journal::open(&path).unwrap());
+
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 10));
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 10));
+        let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 11, 4));
+        book.add_order(&ask).unwrap();
+        book.add_order(&bid).unwrap();
+        book.add_order(&taker).unwrap();
+        book.cancel_order(bid.order_id).unwrap();
+        // rejected commands are not journaled
+        assert!(book.cancel_order(bid.order_id).is_err());
+
+        assert_eq!(Journal::read_commands(&path).unwrap().len(), 4);
+        // the fill took sequence number 4, between the taker and the cancel
+        let sequences: Vec<Option<u64>> = Journal::read_sequenced_commands(&path)
+            .unwrap()
+            .into_iter()
+            .map(|(sequence, _)| sequence)
+            .collect();
+        assert_eq!(sequences, vec![Some(1), Some(2), Some(3), Some(5)]);
+        let entries = Journal::read_entries(&path).unwrap();
+        assert!(entries.iter().all(|entry| entry.timestamp.is_some()));
+        let replayed = Journal::replay(&path).unwrap();
+        assert_eq!(replayed.snapshot().bids, book.snapshot().bids);
+        assert_eq!(replayed.snapshot().asks, book.snapshot().asks);
+        assert_eq!(replayed.sequence(), book.sequence());
+
+        // replaying onto a book that has moved on diverges at the first command
+        let mut diverged = OrderBook::new();
+        let other = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 1, 1));
+        diverged.add_order(&other).unwrap();
+        assert!(matches!(
+            Journal::replay_into(&path, &mut diverged),
+            Err(OrderBookError::SequenceMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use crate::orderbook::order::Side;
+use crate::orderbook::types::Price;
+
+/// Most slots a `LadderKind::TickArray` may span, about 4 million ticks.
+pub const MAX_TICK_ARRAY_SLOTS: u64 = 1 << 22;
+
+/// Structure holding the occupied price levels of one side of the book.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LadderKind {
+    // Sorted map of the occupied levels, any price, O(log n) lookups
+    BTree,
+    // One slot per tick between `min_price` and `max_price` (inclusive) with a bitset of the
+    // occupied ones: O(1) lookup and insertion, and only a level going away at the top of book
+    // scans for the next one, 64 ticks per step. Orders priced outside the range are rejected.
+    TickArray { min_price: Price, max_price: Price },
+}
+
+/// Price levels of one side of the book, iterated best price first.
+pub struct Ladder<V> {
+    side: Side,
+    levels: Levels<V>,
+}
+
+enum Levels<V> {
+    Tree(BTreeMap<Price, V>),
+    Array(TickLadder<V>),
+}
+
+impl<V> Ladder<V> {
+    pub fn new(side: Side, kind: LadderKind, tick_size: Price) -> Self {
+        let levels = match kind {
+            LadderKind::BTree => Levels::Tree(BTreeMap::new()),
+            LadderKind::TickArray {
+                min_price,
+                max_price,
+            } => Levels::Array(TickLadder::new(min_price, max_price, tick_size)),
+        };
+        Ladder { side, levels }
+    }
+
+    /// Whether a level at `price` can be stored at all.
+    pub fn can_hold(&self, price: Price) -> bool {
+        match &self.levels {
+            Levels::Tree(_) => true,
+            Levels::Array(ladder) => ladder.index(price).is_some(),
+        }
+    }
+
+    pub fn get(&self, price: Price) -> Option<&V> {
+        match &self.levels {
+            Levels::Tree(tree) => tree.get(&price),
+            Levels::Array(ladder) => ladder.get(price),
+        }
+    }
+
+    /// Store the level at `price`, which must be one `can_hold` accepts.
+    pub fn insert(&mut self, price: Price, value: V) {
+        match &mut self.levels {
+            Levels::Tree(tree) => {
+                tree.insert(price, value);
+            }
+            Levels::Array(ladder) => ladder.insert(price, value),
+        }
+    }
+
+    pub fn remove(&mut self, price: Price) -> Option<V> {
+        match &mut self.levels {
+            Levels::Tree(tree) => tree.remove(&price),
+            Levels::Array(ladder) => ladder.remove(price),
+        }
+    }
+
+    /// Best level: the highest bid or the lowest ask.
+    pub fn best(&self) -> Option<(Price, &V)> {
+        match (&self.levels, self.side) {
+            (Levels::Tree(tree), Side::Buy) => tree.iter().next_back().map(|(p, v)| (*p, v)),
+            (Levels::Tree(tree), Side::Sell) => tree.iter().next().map(|(p, v)| (*p, v)),
+            (Levels::Array(ladder), Side::Buy) => ladder.entry(ladder.high?),
+            (Levels::Array(ladder), Side::Sell) => ladder.entry(ladder.low?),
+        }
+    }
+
+    pub fn best_price(&self) -> Option<Price> {
+        self.best().map(|(price, _)| price)
+    }
+
+    /// Occupied levels, best price first.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Price, &V)> + '_> {
+        match (&self.levels, self.side) {
+            (Levels::Tree(tree), Side::Buy) => {
+                Box::new(tree.iter().rev().map(|(price, value)| (*price, value)))
+            }
+            (Levels::Tree(tree), Side::Sell) => {
+                Box::new(tree.iter().map(|(price, value)| (*price, value)))
+            }
+            (Levels::Array(ladder), Side::Buy) => Box::new(
+                std::iter::successors(ladder.high, |&index| {
+                    index
+                        .checked_sub(1)
+                        .and_then(|index| ladder.prev_occupied(index))
+                })
+                .filter_map(|index| ladder.entry(index)),
+            ),
+            (Levels::Array(ladder), Side::Sell) => Box::new(
+                std::iter::successors(ladder.low, |&index| ladder.next_occupied(index + 1))
+                    .filter_map(|index| ladder.entry(index)),
+            ),
+        }
+    }
+
+    /// Levels from the best price up to and including `limit`, e.g. every ask a buy limited to
+    /// `limit` could trade against.
+    pub fn iter_to(&self, limit: Price) -> impl Iterator<Item = (Price, &V)> + '_ {
+        let side = self.side;
+        self.iter().take_while(move |(price, _)| match side {
+            Side::Buy => *price >= limit,
+            Side::Sell => *price <= limit,
+        })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.levels {
+            Levels::Tree(tree) => tree.len(),
+            Levels::Array(ladder) => ladder.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Contiguous array of levels indexed by tick offset from `min_price`
+struct TickLadder<V> {
+    min_price: Price,
+    tick_size: Price,
+    levels: Vec<Option<V>>,
+    // Bit i set when levels[i] is occupied
+    occupied: Vec<u64>,
+    len: usize,
+    // Lowest and highest occupied index
+    low: Option<usize>,
+    high: Option<usize>,
+}
+
+/// Slots a tick array from `min_price` to `max_price` needs, None for an inverted range or one
+/// whose width overflows.
+pub(crate) fn tick_array_slots(
+    min_price: Price,
+    max_price: Price,
+    tick_size: Price,
+) -> Option<u64> {
+    let width = max_price
+        .checked_sub(min_price)
+        .filter(|width| *width >= 0)?;
+    Some(width as u64 / tick_size as u64 + 1)
+}
+
+impl<V> TickLadder<V> {
+    fn new(min_price: Price, max_price: Price, tick_size: Price) -> Self {
+        // Align the bottom of the range to the tick grid, prices off the grid are rejected anyway
+        let min_price = min_price.div_euclid(tick_size) * tick_size;
+        // A range `OrderBookConfig::validate` refuses holds nothing rather than overflowing
+        let slots = tick_array_slots(min_price, max_price, tick_size)
+            .filter(|slots| *slots <= MAX_TICK_ARRAY_SLOTS)
+            .map_or(0, |slots| slots as usize);
+        TickLadder {
+            min_price,
+            tick_size,
+            levels: std::iter::repeat_with(|| None).take(slots).collect(),
+            occupied: vec![0; slots.div_ceil(64)],
+            len: 0,
+            low: None,
+            high: None,
+        }
+    }
+
+    fn index(&self, price: Price) -> Option<usize> {
+        let offset = price.checked_sub(self.min_price)?;
+        if offset < 0 || offset % self.tick_size != 0 {
+            return None;
+        }
+        let index = (offset / self.tick_size) as usize;
+        (index < self.levels.len()).then_some(index)
+    }
+
+    fn price_at(&self, index: usize) -> Price {
+        self.min_price + index as Price * self.tick_size
+    }
+
+    fn entry(&self, index: usize) -> Option<(Price, &V)> {
+        Some((self.price_at(index), self.levels[index].as_ref()?))
+    }
+
+    fn get(&self, price: Price) -> Option<&V> {
+        self.levels[self.index(price)?].as_ref()
+    }
+
+    fn insert(&mut self, price: Price, value: V) {
+        let index = self
+            .index(price)
+            .expect("price outside the tick ladder range");
+        if self.levels[index].replace(value).is_none() {
+            self.occupied[index / 64] |= 1 << (index % 64);
+            self.len += 1;
+            self.low = Some(self.low.map_or(index, |low| low.min(index)));
+            self.high = Some(self.high.map_or(index, |high| high.max(index)));
+        }
+    }
+
+    fn remove(&mut self, price: Price) -> Option<V> {
+        let index = self.index(price)?;
+        let value = self.levels[index].take()?;
+        self.occupied[index / 64] &= !(1 << (index % 64));
+        self.len -= 1;
+        if self.low == Some(index) {
+            self.low = self.next_occupied(index);
+        }
+        if self.high == Some(index) {
+            self.high = self.prev_occupied(index);
+        }
+        Some(value)
+    }
+
+    // First occupied index at or after `from`
+    fn next_occupied(&self, from: usize) -> Option<usize> {
+        let mut word_index = from / 64;
+        let mut word = *self.occupied.get(word_index)? & (u64::MAX << (from % 64));
+        loop {
+            if word != 0 {
+                return Some(word_index * 64 + word.trailing_zeros() as usize);
+            }
+            word_index += 1;
+            word = *self.occupied.get(word_index)?;
+        }
+    }
+
+    // Last occupied index at or before `from`
+    fn prev_occupied(&self, from: usize) -> Option<usize> {
+        let mut word_index = from / 64;
+        let mut word = *self.occupied.get(word_index)? & (u64::MAX >> (63 - from % 64));
+        loop {
+            if word != 0 {
+                return Some(word_index * 64 + 63 - word.leading_zeros() as usize);
+            }
+            word_index = word_index.checked_sub(1)?;
+            word = self.occupied[word_index];
+        }
+    }
+}
+
+#[cfg(test)]
+mod ladder_tests {
+    use super::*;
+
+    const KINDS: [LadderKind; 2] = [
+        LadderKind::BTree,
+        LadderKind::TickArray {
+            min_price: 0,
+            max_price: 1_000,
+        },
+    ];
+
+    #[test]
+    fn check_best_first_order_on_both_sides() {
+        for kind in KINDS {
+            let mut bids: Ladder<u32> = Ladder::new(Side::Buy, kind, 5);
+            let mut asks: Ladder<u32> = Ladder::new(Side::Sell, kind, 5);
+            for price in [500, 5, 995, 320] {
+                bids.insert(price, price as u32);
+                asks.insert(price, price as u32);
+            }
+            let bid_prices: Vec<Price> = bids.iter().map(|(price, _)| price).collect();
+            assert_eq!(bid_prices, vec![995, 500, 320, 5], "{:?}", kind);
+            let ask_prices: Vec<Price> = asks.iter_to(320).map(|(price, _)| price).collect();
+            assert_eq!(ask_prices, vec![5, 320], "{:?}", kind);
+
+            // removing the best level moves the top of book to the next occupied one
+            assert_eq!(bids.remove(995), Some(995));
+            assert_eq!(bids.best(), Some((500, &500)), "{:?}", kind);
+            assert_eq!(asks.remove(5), Some(5));
+            assert_eq!(asks.best_price(), Some(320), "{:?}", kind);
+            assert_eq!(asks.remove(5), None);
+            assert_eq!(asks.len(), 3);
+            assert_eq!(asks.get(500), Some(&500));
+        }
+    }
+
+    #[test]
+    fn check_tick_array_bounds() {
+        let mut asks: Ladder<u32> = Ladder::new(
+            Side::Sell,
+            LadderKind::TickArray {
+                min_price: 100,
+                max_price: 200,
+            },
+            10,
+        );
+        assert!(asks.can_hold(100) && asks.can_hold(200));
+        assert!(!asks.can_hold(90) && !asks.can_hold(210) && !asks.can_hold(105));
+        asks.insert(200, 1);
+        asks.insert(100, 2);
+        asks.remove(100);
+        asks.remove(200);
+        assert!(asks.is_empty());
+        assert_eq!(asks.best(), None);
+    }
+}
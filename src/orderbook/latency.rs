@@ -0,0 +1,136 @@
+use std::fmt;
+use std::time::Duration;
+
+use hdrhistogram::Histogram;
+
+use crate::orderbook::watchdog::CommandKind;
+
+// Three significant digits, i.e. within 0.1% of the recorded value
+const SIGNIFICANT_DIGITS: u8 = 3;
+// Longer latencies are recorded as this, in nanoseconds
+const MAX_LATENCY_NANOS: u64 = 10_000_000_000;
+
+/// Latency distribution of one kind of command, as durations.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct LatencyPercentiles {
+    pub count: u64,
+    pub min: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+impl fmt::Display for LatencyPercentiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n={} min={:?} p50={:?} p90={:?} p99={:?} p99.9={:?} max={:?}",
+            self.count, self.min, self.p50, self.p90, self.p99, self.p999, self.max
+        )
+    }
+}
+
+/// HDR histograms of command latency in nanoseconds, one per `CommandKind`, covering 1ns to 10s.
+/// Recording is a few array increments and never allocates.
+pub struct LatencyHistograms {
+    add_order: Histogram<u64>,
+    cancel_order: Histogram<u64>,
+}
+
+impl Default for LatencyHistograms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistograms {
+    pub fn new() -> Self {
+        let histogram = || {
+            Histogram::new_with_bounds(1, MAX_LATENCY_NANOS, SIGNIFICANT_DIGITS)
+                .expect("valid histogram bounds")
+        };
+        LatencyHistograms {
+            add_order: histogram(),
+            cancel_order: histogram(),
+        }
+    }
+
+    fn histogram(&self, command: CommandKind) -> &Histogram<u64> {
+        match command {
+            CommandKind::AddOrder => &self.add_order,
+            CommandKind::CancelOrder => &self.cancel_order,
+        }
+    }
+
+    pub fn record(&mut self, command: CommandKind, elapsed: Duration) {
+        let histogram = match command {
+            CommandKind::AddOrder => &mut self.add_order,
+            CommandKind::CancelOrder => &mut self.cancel_order,
+        };
+        histogram.saturating_record(elapsed.as_nanos().min(MAX_LATENCY_NANOS as u128) as u64);
+    }
+
+    /// Percentiles of `command`, None until one has been recorded.
+    pub fn percentiles(&self, command: CommandKind) -> Option<LatencyPercentiles> {
+        let histogram = self.histogram(command);
+        if histogram.is_empty() {
+            return None;
+        }
+        let at = |quantile: f64| Duration::from_nanos(histogram.value_at_quantile(quantile));
+        Some(LatencyPercentiles {
+            count: histogram.len(),
+            min: Duration::from_nanos(histogram.min()),
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: Duration::from_nanos(histogram.max()),
+        })
+    }
+
+    /// One line per command kind recorded so far.
+    pub fn report(&self) -> String {
+        [CommandKind::AddOrder, CommandKind::CancelOrder]
+            .into_iter()
+            .filter_map(|command| {
+                let percentiles = self.percentiles(command)?;
+                Some(format!("{:?}: {}", command, percentiles))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn reset(&mut self) {
+        self.add_order.reset();
+        self.cancel_order.reset();
+    }
+}
+
+#[cfg(test)]
+mod latency_tests {
+    use super::*;
+
+    #[test]
+    fn check_percentiles_per_command_kind() {
+        let mut histograms = LatencyHistograms::new();
+        assert_eq!(histograms.percentiles(CommandKind::AddOrder), None);
+        for micros in 1..=1_000 {
+            histograms.record(CommandKind::AddOrder, Duration::from_micros(micros));
+        }
+        histograms.record(CommandKind::CancelOrder, Duration::from_nanos(700));
+
+        let add = histograms.percentiles(CommandKind::AddOrder).unwrap();
+        assert_eq!(add.count, 1_000);
+        assert_eq!(add.min, Duration::from_micros(1));
+        // within the 0.1% precision of the histogram
+        assert!(add.p50.abs_diff(Duration::from_micros(500)) <= Duration::from_nanos(500));
+        assert!(add.p99.abs_diff(Duration::from_micros(990)) <= Duration::from_nanos(990));
+        assert!(add.max >= Duration::from_micros(1_000));
+        assert_eq!(histograms.report().lines().count(), 2);
+
+        histograms.reset();
+        assert_eq!(histograms.percentiles(CommandKind::CancelOrder), None);
+    }
+}
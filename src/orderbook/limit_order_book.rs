@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use crate::orderbook::order::Order;
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError, Trade};
+use crate::orderbook::price_level::OrderbookLevelInfos;
+use crate::orderbook::types::{OrderId, Price};
+
+/// The minimal book interface strategy code needs. Implemented by the full matching engine and
+/// by the L2 mirror in `market_data::l2`, so either can be swapped in without code changes.
+pub trait LimitOrderBook {
+    fn submit(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError>;
+
+    fn cancel(&mut self, order_id: OrderId) -> Result<(), OrderBookError>;
+
+    fn best_bid(&self) -> Option<Price>;
+
+    fn best_ask(&self) -> Option<Price>;
+
+    fn depth(&self, levels: usize) -> OrderbookLevelInfos;
+}
+
+impl LimitOrderBook for OrderBook {
+    fn submit(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        Ok(self.add_order(order)?.trades)
+    }
+
+    fn cancel(&mut self, order_id: OrderId) -> Result<(), OrderBookError> {
+        self.cancel_order(order_id)
+    }
+
+    fn best_bid(&self) -> Option<Price> {
+        self.get_best_bid()
+    }
+
+    fn best_ask(&self) -> Option<Price> {
+        self.get_best_ask()
+    }
+
+    fn depth(&self, levels: usize) -> OrderbookLevelInfos {
+        self.get_depth(levels)
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use crate::market_data::events::BookEvent;
+use crate::orderbook::command::Command;
+use crate::orderbook::exec_report::ExecutionReport;
+use crate::orderbook::order::{Order, Side};
+use crate::orderbook::orderbook_impl::{IndicativeUncross, OrderBookError, Trade, TradeBust};
+use crate::orderbook::price_level::{BboUpdate, LevelInfo};
+use crate::orderbook::stats::SessionStats;
+
+/// Callbacks fired by `OrderBook` as orders move through their lifecycle.
+/// Every method has an empty default so listeners only implement what they need.
+pub trait OrderBookListener: Send {
+    // Fired once per accepted command with its sequence number, before any of its effects
+    fn on_command(&mut self, _sequence: u64, _command: &Command) {}
+
+    fn on_accept(&mut self, _order: &Arc<Order>) {}
+
+    fn on_trade(&mut self, _trade: &Trade) {}
+
+    // A trade reversed through `OrderBook::bust_trade`, fired before the level, book and BBO
+    // updates it causes
+    fn on_trade_bust(&mut self, _bust: &TradeBust) {}
+
+    // Fired for user cancels and engine-initiated removals such as GTD expiry
+    fn on_cancel(&mut self, _order: &Arc<Order>) {}
+
+    fn on_reject(&mut self, _order: &Arc<Order>, _error: &OrderBookError) {}
+
+    // Every order state change, fired after the callback for the event causing it
+    fn on_execution_report(&mut self, _report: &ExecutionReport) {}
+
+    // A level's aggregated volume changed; volume 0 means the level was removed
+    fn on_level_change(&mut self, _side: Side, _level: &LevelInfo) {}
+
+    // Order-level (L3) change to the displayed book: a resting order added, executed, repriced
+    // or deleted. Fired after the command's other callbacks, before `on_bbo`; hidden orders
+    // never appear
+    fn on_book_event(&mut self, _event: &BookEvent) {}
+
+    // Best bid or offer price or quantity changed, fired after the level changes causing it
+    fn on_bbo(&mut self, _update: &BboUpdate) {}
+
+    // Auction imbalance: where the auction would uncross and the volume left over there, fired
+    // from `OrderBook::tick` every `OrderBookConfig::imbalance_interval` while the orders cross
+    fn on_auction_imbalance(&mut self, _indicative: &IndicativeUncross) {}
+
+    // Stats of a session closed by `OrderBook::reset_session`
+    fn on_session_end(&mut self, _stats: &SessionStats) {}
+}
@@ -0,0 +1,495 @@
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::orderbook::config::OrderBookConfig;
+use crate::orderbook::order::{Order, OrderType, Peg, PegType, Side};
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError};
+use crate::orderbook::snapshot::{BookSnapshot, LevelSnapshot};
+use crate::orderbook::state::InstrumentState;
+use crate::orderbook::wire::{
+    decode_order_type, decode_side, decode_status, encode_order_type, encode_side, encode_status,
+    read_i64, read_id, read_u64,
+};
+
+// File layout, all integers little-endian:
+//
+// header: magic 8 | version u32 | state u32 (1 once a flush completed) | sequence u64
+//   | timestamp i64 (epoch millis) | level_count u64 | order_count u64 | string_len u64
+//   | checksum u64 (FNV-1a of everything after the header) | instrument_state u8 | padding 7
+// levels, bids best first then asks best first: price i64 | order_count u64 | side u8 | padding 7
+// orders, level by level in FIFO order: order_id 16 | owner u64 | price i64
+//   | original_quantity u64 | executed_quantity u64 | remaining_quantity u64 | timestamp i64
+//   | expire_time i64 | min_qty u64 (0 for none) | peg_offset i64 | client_order_id offset u32
+//   | client_order_id length u32 (0 for none) | side u8 | order_type u8 | status u8
+//   | flags u8 (bit 0 hidden, bit 1 pegged, bit 2 tagged) | peg_type u8 | padding 1
+//   | tag length u16
+// strings: each order's client order id then its tag, back to back
+const MAGIC: &[u8; 8] = b"OBMAPPED";
+const VERSION: u32 = 2;
+const HEADER_LEN: usize = 72;
+const LEVEL_LEN: usize = 24;
+const ORDER_LEN: usize = 104;
+
+const STATE_WRITING: u32 = 0;
+const STATE_VALID: u32 = 1;
+
+const FLAG_HIDDEN: u8 = 1;
+const FLAG_PEGGED: u8 = 2;
+const FLAG_TAGGED: u8 = 4;
+
+/// Resting book mirrored into a memory-mapped file, so a restarted process picks the book up
+/// from the last flush and only replays journal entries after its sequence number.
+///
+/// Nothing reaches the file until `flush`, the explicit persistence point. A flush first marks
+/// the header as being written, then rewrites the levels and orders and syncs them, and only
+/// then stores the new header with its checksum, so a crash midway leaves a file `load` refuses
+/// rather than a half-written book.
+pub struct MappedBook {
+    file: File,
+    map: MmapMut,
+}
+
+impl MappedBook {
+    /// Open (or create) the mapped file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, OrderBookError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if file.metadata()?.len() < HEADER_LEN as u64 {
+            file.set_len(HEADER_LEN as u64)?;
+        }
+        // SAFETY: the mapping is only accessed through this struct; other processes writing the
+        // same file at the same time are not supported
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(MappedBook { file, map })
+    }
+
+    /// Write every resting order of `book` to the file and sync it to disk.
+    pub fn flush(&mut self, book: &OrderBook) -> Result<(), OrderBookError> {
+        let snapshot = book.snapshot();
+        let levels: Vec<(Side, &LevelSnapshot)> = snapshot
+            .bids
+            .iter()
+            .map(|level| (Side::Buy, level))
+            .chain(snapshot.asks.iter().map(|level| (Side::Sell, level)))
+            .collect();
+        let order_count: usize = levels.iter().map(|(_, level)| level.orders.len()).sum();
+        let string_len: usize = levels
+            .iter()
+            .flat_map(|(_, level)| level.orders.iter())
+            .map(strings_len)
+            .sum();
+        let orders_start = HEADER_LEN + levels.len() * LEVEL_LEN;
+        let strings_start = orders_start + order_count * ORDER_LEN;
+        self.reserve(strings_start + string_len)?;
+
+        self.map[12..16].copy_from_slice(&STATE_WRITING.to_le_bytes());
+        self.map.flush_range(0, HEADER_LEN)?;
+
+        let mut level_offset = HEADER_LEN;
+        let mut order_offset = orders_start;
+        let mut string_offset = 0;
+        for (side, level) in levels.iter() {
+            let block = &mut self.map[level_offset..level_offset + LEVEL_LEN];
+            block[0..8].copy_from_slice(&level.price.to_le_bytes());
+            block[8..16].copy_from_slice(&(level.orders.len() as u64).to_le_bytes());
+            block[16] = encode_side(*side);
+            block[17..].fill(0);
+            level_offset += LEVEL_LEN;
+
+            for order in level.orders.iter() {
+                let client_order_id = order.client_order_id.as_deref().unwrap_or("");
+                let tag = order.tag.as_deref().unwrap_or(&[]);
+                let start = strings_start + string_offset;
+                let tag_start = start + client_order_id.len();
+                self.map[start..tag_start].copy_from_slice(client_order_id.as_bytes());
+                self.map[tag_start..tag_start + tag.len()].copy_from_slice(tag);
+                write_order(
+                    &mut self.map[order_offset..order_offset + ORDER_LEN],
+                    order,
+                    string_offset as u32,
+                );
+                order_offset += ORDER_LEN;
+                string_offset += strings_len(order);
+            }
+        }
+        let body_len = strings_start + string_len;
+        self.map.flush_range(HEADER_LEN, body_len - HEADER_LEN)?;
+
+        let checksum = fnv1a(&self.map[HEADER_LEN..body_len]);
+        let header = &mut self.map[..HEADER_LEN];
+        header[0..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[16..24].copy_from_slice(&snapshot.sequence.to_le_bytes());
+        header[24..32].copy_from_slice(&snapshot.timestamp.to_le_bytes());
+        header[32..40].copy_from_slice(&(levels.len() as u64).to_le_bytes());
+        header[40..48].copy_from_slice(&(order_count as u64).to_le_bytes());
+        header[48..56].copy_from_slice(&(string_len as u64).to_le_bytes());
+        header[56..64].copy_from_slice(&checksum.to_le_bytes());
+        header[64] = encode_instrument_state(snapshot.instrument_state);
+        header[12..16].copy_from_slice(&STATE_VALID.to_le_bytes());
+        self.map.flush_range(0, HEADER_LEN)?;
+        Ok(())
+    }
+
+    /// Whether the file holds a completed flush.
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Book sequence number of the last completed flush, journal entries after it still have to
+    /// be replayed.
+    pub fn sequence(&self) -> Option<u64> {
+        self.validate().ok().map(|_| read_u64(&self.map, 16))
+    }
+
+    /// The resting book as of the last completed flush, `None` for a file never flushed.
+    pub fn load(&self) -> Result<Option<BookSnapshot>, OrderBookError> {
+        if self.map[..HEADER_LEN].iter().all(|byte| *byte == 0) {
+            return Ok(None);
+        }
+        let (level_count, order_count) = self.validate()?;
+        let invalid = |reason: &str| OrderBookError::InvalidMappedBook {
+            reason: reason.to_string(),
+        };
+
+        let orders_start = HEADER_LEN + level_count * LEVEL_LEN;
+        let strings_start = orders_start + order_count * ORDER_LEN;
+        let mut snapshot = BookSnapshot {
+            timestamp: read_i64(&self.map, 24),
+            sequence: read_u64(&self.map, 16),
+            instrument_state: decode_instrument_state(self.map[64])
+                .ok_or_else(|| invalid("unknown instrument state"))?,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        };
+        let mut order_offset = orders_start;
+        for index in 0..level_count {
+            let block = &self.map[HEADER_LEN + index * LEVEL_LEN..][..LEVEL_LEN];
+            let side = decode_side(block[16]).ok_or_else(|| invalid("unknown side"))?;
+            let count = read_u64(block, 8) as usize;
+            if order_offset + count * ORDER_LEN > strings_start {
+                return Err(invalid("level order counts exceed the order table"));
+            }
+            let mut level = LevelSnapshot {
+                price: read_i64(block, 0),
+                orders: Vec::with_capacity(count),
+            };
+            for _ in 0..count {
+                let record = &self.map[order_offset..order_offset + ORDER_LEN];
+                level.orders.push(
+                    read_order(record, &self.map[strings_start..])
+                        .ok_or_else(|| invalid("malformed order record"))?,
+                );
+                order_offset += ORDER_LEN;
+            }
+            match side {
+                Side::Buy => snapshot.bids.push(level),
+                Side::Sell => snapshot.asks.push(level),
+            }
+        }
+        if order_offset != strings_start {
+            return Err(invalid("level order counts do not cover the order table"));
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Rebuild the book from the last completed flush, `None` for a file never flushed.
+    pub fn restore(&self, config: OrderBookConfig) -> Result<Option<OrderBook>, OrderBookError> {
+        self.load()?
+            .map(|snapshot| OrderBook::restore_with_config(snapshot, config))
+            .transpose()
+    }
+
+    // Grow the file and mapping to at least `len` bytes
+    fn reserve(&mut self, len: usize) -> Result<(), OrderBookError> {
+        if self.map.len() < len {
+            self.file.set_len(len.next_power_of_two() as u64)?;
+            // SAFETY: as in `open`
+            self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        }
+        Ok(())
+    }
+
+    // Level and order counts of a completed flush
+    fn validate(&self) -> Result<(usize, usize), OrderBookError> {
+        let invalid = |reason: &str| OrderBookError::InvalidMappedBook {
+            reason: reason.to_string(),
+        };
+        let header = &self.map[..HEADER_LEN];
+        if &header[0..8] != MAGIC {
+            return Err(invalid("bad magic"));
+        }
+        if header[8..12] != VERSION.to_le_bytes() {
+            return Err(invalid("unsupported version"));
+        }
+        if header[12..16] != STATE_VALID.to_le_bytes() {
+            return Err(invalid("flush did not complete"));
+        }
+        let level_count = read_u64(header, 32) as usize;
+        let order_count = read_u64(header, 40) as usize;
+        let string_len = read_u64(header, 48) as usize;
+        let body_len = level_count
+            .checked_mul(LEVEL_LEN)
+            .zip(order_count.checked_mul(ORDER_LEN))
+            .and_then(|(levels, orders)| levels.checked_add(orders)?.checked_add(string_len))
+            .filter(|body_len| *body_len <= self.map.len() - HEADER_LEN)
+            .ok_or_else(|| invalid("counts exceed the file"))?;
+        if fnv1a(&self.map[HEADER_LEN..HEADER_LEN + body_len]) != read_u64(header, 56) {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok((level_count, order_count))
+    }
+}
+
+fn encode_instrument_state(state: InstrumentState) -> u8 {
+    match state {
+        InstrumentState::Active => 0,
+        InstrumentState::Auction => 1,
+        InstrumentState::CancelOnly => 2,
+        InstrumentState::Halted => 3,
+        InstrumentState::Closed => 4,
+    }
+}
+
+fn decode_instrument_state(byte: u8) -> Option<InstrumentState> {
+    match byte {
+        0 => Some(InstrumentState::Active),
+        1 => Some(InstrumentState::Auction),
+        2 => Some(InstrumentState::CancelOnly),
+        3 => Some(InstrumentState::Halted),
+        4 => Some(InstrumentState::Closed),
+        _ => None,
+    }
+}
+
+// Bytes of the strings section taken by `order`
+fn strings_len(order: &Order) -> usize {
+    order.client_order_id.as_ref().map_or(0, |id| id.len())
+        + order.tag.as_ref().map_or(0, |tag| tag.len())
+}
+
+fn write_order(block: &mut [u8], order: &Order, client_order_id_offset: u32) {
+    let expire_time = match order.order_type {
+        OrderType::GoodTillDate(expire_time) => expire_time,
+        _ => 0,
+    };
+    let mut flags = 0;
+    if order.hidden {
+        flags |= FLAG_HIDDEN;
+    }
+    if order.peg.is_some() {
+        flags |= FLAG_PEGGED;
+    }
+    if order.tag.is_some() {
+        flags |= FLAG_TAGGED;
+    }
+    let client_order_id_len = order.client_order_id.as_ref().map_or(0, |id| id.len());
+    block[0..16].copy_from_slice(order.order_id.as_bytes());
+    block[16..24].copy_from_slice(&order.owner.to_le_bytes());
+    block[24..32].copy_from_slice(&order.price.to_le_bytes());
+    block[32..40].copy_from_slice(&order.original_quantity.to_le_bytes());
+    block[40..48].copy_from_slice(&order.executed_quantity.to_le_bytes());
+    block[48..56].copy_from_slice(&order.remaining_quantity.to_le_bytes());
+    block[56..64].copy_from_slice(&order.timestamp.to_le_bytes());
+    block[64..72].copy_from_slice(&expire_time.to_le_bytes());
+    block[72..80].copy_from_slice(&order.min_qty.unwrap_or(0).to_le_bytes());
+    block[80..88].copy_from_slice(&order.peg.map_or(0, |peg| peg.offset).to_le_bytes());
+    block[88..92].copy_from_slice(&client_order_id_offset.to_le_bytes());
+    block[92..96].copy_from_slice(&(client_order_id_len as u32).to_le_bytes());
+    block[96] = encode_side(order.side);
+    block[97] = encode_order_type(order.order_type);
+    block[98] = encode_status(order.status);
+    block[99] = flags;
+    block[100] = order.peg.map_or(0, |peg| encode_peg_type(peg.peg_type));
+    block[101] = 0;
+    // The book rejects tags longer than `MAX_TAG_LEN`, which fits in two bytes
+    let tag_len = order.tag.as_ref().map_or(0, |tag| tag.len()) as u16;
+    block[102..104].copy_from_slice(&tag_len.to_le_bytes());
+}
+
+fn read_order(block: &[u8], strings: &[u8]) -> Option<Order> {
+    let client_order_id_offset = u32::from_le_bytes(block[88..92].try_into().unwrap()) as usize;
+    let client_order_id_len = u32::from_le_bytes(block[92..96].try_into().unwrap()) as usize;
+    let client_order_id = if client_order_id_len == 0 {
+        None
+    } else {
+        let bytes = strings
+            .get(client_order_id_offset..)?
+            .get(..client_order_id_len)?;
+        Some(String::from_utf8(bytes.to_vec()).ok()?)
+    };
+    let tag = if block[99] & FLAG_TAGGED != 0 {
+        let tag_len = u16::from_le_bytes(block[102..104].try_into().unwrap()) as usize;
+        let tag_offset = client_order_id_offset + client_order_id_len;
+        Some(strings.get(tag_offset..)?.get(..tag_len)?.to_vec())
+    } else {
+        None
+    };
+    let flags = block[99];
+    let peg = if flags & FLAG_PEGGED != 0 {
+        Some(Peg {
+            peg_type: decode_peg_type(block[100])?,
+            offset: read_i64(block, 80),
+        })
+    } else {
+        None
+    };
+    let min_qty = read_u64(block, 72);
+    Some(Order {
+        order_type: decode_order_type(block[97], read_i64(block, 64))?,
+        order_id: read_id(block, 0),
+        side: decode_side(block[96])?,
+        price: read_i64(block, 24),
+        status: decode_status(block[98])?,
+        original_quantity: read_u64(block, 32),
+        executed_quantity: read_u64(block, 40),
+        remaining_quantity: read_u64(block, 48),
+        timestamp: read_i64(block, 56),
+        owner: read_u64(block, 16),
+        client_order_id,
+        hidden: flags & FLAG_HIDDEN != 0,
+        min_qty: (min_qty != 0).then_some(min_qty),
+        peg,
+        // Only market orders carry one, and they never rest
+        protection_price: None,
+        tag,
+    })
+}
+
+fn encode_peg_type(peg_type: PegType) -> u8 {
+    match peg_type {
+        PegType::Primary => 0,
+        PegType::Midpoint => 1,
+        PegType::Market => 2,
+    }
+}
+
+fn decode_peg_type(byte: u8) -> Option<PegType> {
+    match byte {
+        0 => Some(PegType::Primary),
+        1 => Some(PegType::Midpoint),
+        2 => Some(PegType::Market),
+        _ => None,
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod mapped_tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::orderbook::order::Status;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.book", name, uuid::Uuid::new_v4()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn check_book_survives_reopen() {
+        let path = temp_path("mapped");
+        let mut book = OrderBook::new();
+        let mut resting = Order::new(OrderType::GoodTillDate(i64::MAX), Side::Buy, 99, 10)
+            .with_owner(3)
+            .with_client_order_id("bid-1")
+            .with_tag(b"parent-9".to_vec());
+        resting.hidden = true;
+        book.add_order(&Arc::new(resting)).unwrap();
+        book.add_order(&Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Buy,
+            100,
+            5,
+        )))
+        .unwrap();
+        book.add_order(&Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Sell,
+            101,
+            7,
+        )))
+        .unwrap();
+        book.add_order(&Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Sell,
+            100,
+            2,
+        )))
+        .unwrap();
+
+        let mut mapped = MappedBook::open(&path).unwrap();
+        assert!(mapped.load().unwrap().is_none());
+        mapped.flush(&book).unwrap();
+        // later changes only reach the file at the next flush
+        book.add_order(&Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Sell,
+            102,
+            1,
+        )))
+        .unwrap();
+        drop(mapped);
+
+        let reopened = MappedBook::open(&path).unwrap();
+        assert_eq!(reopened.sequence(), Some(book.sequence() - 1));
+        let restored = reopened
+            .restore(OrderBookConfig::default())
+            .unwrap()
+            .unwrap();
+        let snapshot = restored.snapshot();
+        assert_eq!(snapshot.bids.len(), 2);
+        assert_eq!(snapshot.asks.len(), 1);
+        let bid = &snapshot.bids[1].orders[0];
+        assert_eq!(bid.client_order_id.as_deref(), Some("bid-1"));
+        assert_eq!(bid.tag.as_deref(), Some(b"parent-9".as_slice()));
+        assert_eq!((bid.owner, bid.hidden), (3, true));
+        assert_eq!(bid.order_type, OrderType::GoodTillDate(i64::MAX));
+        let bid = &snapshot.bids[0].orders[0];
+        assert_eq!((bid.remaining_quantity, bid.executed_quantity), (3, 2));
+        assert_eq!(bid.status, Status::PartiallyFilled);
+        assert_eq!(restored.sequence(), reopened.sequence().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn check_interrupted_or_corrupted_files_are_refused() {
+        let path = temp_path("mapped-corrupt");
+        let mut book = OrderBook::new();
+        book.add_order(&Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Buy,
+            100,
+            5,
+        )))
+        .unwrap();
+        let mut mapped = MappedBook::open(&path).unwrap();
+        mapped.flush(&book).unwrap();
+        assert!(mapped.is_valid());
+
+        // a flipped bit in an order record fails the checksum
+        mapped.map[HEADER_LEN + LEVEL_LEN + 30] ^= 1;
+        assert!(matches!(
+            mapped.load(),
+            Err(OrderBookError::InvalidMappedBook { .. })
+        ));
+        // a crash between marking the header and completing the flush leaves it refused too
+        mapped.flush(&book).unwrap();
+        mapped.map[12..16].copy_from_slice(&STATE_WRITING.to_le_bytes());
+        assert!(!mapped.is_valid());
+        assert_eq!(mapped.sequence(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use crate::orderbook::types::Quantity;
+
+/// How an incoming order's quantity is shared among the orders resting at one price level.
+/// Built-in policies are selected through `OrderBookConfig::allocation_policy`; a venue with its
+/// own rules implements this and installs it with `OrderBook::set_match_policy`.
+pub trait MatchPolicy: Send {
+    /// Split `quantity` across `resting`, the remaining quantities of the level's orders in time
+    /// priority. Returns one allocation per resting order, which should sum to the matchable
+    /// quantity: `quantity`, or the whole level when it holds less. The book never fills an order
+    /// beyond its remaining quantity nor the level beyond `quantity`, whatever is returned. What
+    /// is left unallocated is offered to the policy again, and when it allocates nothing at all
+    /// the level is filled in time priority, so a crossing order never rests against it.
+    fn allocate(&self, resting: &[Quantity], quantity: Quantity) -> Vec<Quantity>;
+
+    /// Whether the policy fills strictly front to back, which lets the book match one order at
+    /// a time without collecting the level first.
+    fn time_priority(&self) -> bool {
+        false
+    }
+}
+
+/// Strict price-time priority: the front order fills first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+impl MatchPolicy for Fifo {
+    fn allocate(&self, resting: &[Quantity], quantity: Quantity) -> Vec<Quantity> {
+        let mut left = quantity;
+        resting
+            .iter()
+            .map(|&size| {
+                let allocation = left.min(size);
+                left -= allocation;
+                allocation
+            })
+            .collect()
+    }
+
+    fn time_priority(&self) -> bool {
+        true
+    }
+}
+
+/// In proportion to resting size, as many futures markets allocate. Shares below
+/// `min_allocation` are dropped, and what rounding leaves over is handed out in time priority.
+/// When `quantity` covers the whole level every order is filled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProRata {
+    pub min_allocation: Quantity,
+}
+
+impl MatchPolicy for ProRata {
+    fn allocate(&self, resting: &[Quantity], quantity: Quantity) -> Vec<Quantity> {
+        let volume: Quantity = resting
+            .iter()
+            .fold(0, |sum, size| sum.saturating_add(*size));
+        if quantity >= volume {
+            return resting.to_vec();
+        }
+
+        let mut allocations: Vec<Quantity> = resting
+            .iter()
+            .map(|&size| {
+                let share = (size as u128 * quantity as u128 / volume as u128) as Quantity;
+                if share < self.min_allocation {
+                    0
+                } else {
+                    share
+                }
+            })
+            .collect();
+
+        let mut leftover = quantity.saturating_sub(allocations.iter().sum::<Quantity>());
+        for (allocation, &size) in allocations.iter_mut().zip(resting) {
+            if leftover == 0 {
+                break;
+            }
+            let extra = leftover.min(size - *allocation);
+            *allocation += extra;
+            leftover -= extra;
+        }
+        allocations
+    }
+}
+
+/// Largest resting order first, time priority between orders of the same size.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizePriority;
+
+impl MatchPolicy for SizePriority {
+    fn allocate(&self, resting: &[Quantity], quantity: Quantity) -> Vec<Quantity> {
+        let mut by_size: Vec<usize> = (0..resting.len()).collect();
+        // Stable, so equal sizes keep their queue order
+        by_size.sort_by_key(|&index| std::cmp::Reverse(resting[index]));
+        let mut allocations = vec![0; resting.len()];
+        let mut left = quantity;
+        for index in by_size {
+            if left == 0 {
+                break;
+            }
+            allocations[index] = left.min(resting[index]);
+            left -= allocations[index];
+        }
+        allocations
+    }
+}
+
+#[cfg(test)]
+mod match_policy_tests {
+    use super::*;
+
+    #[test]
+    fn check_pro_rata_allocations() {
+        let pro_rata = |min_allocation| ProRata { min_allocation };
+        // 100 split 60/30/10 by size
+        assert_eq!(pro_rata(0).allocate(&[300, 150, 50], 100), vec![60, 30, 10]);
+        // 10 is below the minimum and goes to the FIFO remainder, which the first order takes
+        assert_eq!(pro_rata(20).allocate(&[300, 150, 50], 100), vec![70, 30, 0]);
+        // rounding leftovers go to the queue in time priority
+        assert_eq!(pro_rata(0).allocate(&[1, 1, 1], 2), vec![1, 1, 0]);
+        assert_eq!(pro_rata(0).allocate(&[5, 7], 20), vec![5, 7]);
+    }
+
+    #[test]
+    fn check_fifo_and_size_priority_allocations() {
+        assert_eq!(Fifo.allocate(&[5, 30, 10], 20), vec![5, 15, 0]);
+        assert_eq!(SizePriority.allocate(&[5, 30, 10], 20), vec![0, 20, 0]);
+        // equal sizes keep their queue order, the remainder goes to the next largest
+        assert_eq!(SizePriority.allocate(&[10, 5, 10], 23), vec![10, 3, 10]);
+        assert_eq!(SizePriority.allocate(&[10, 5, 10], 50), vec![10, 5, 10]);
+    }
+}
@@ -1,5 +1,47 @@
+pub mod analytics;
+pub mod command;
+pub mod config;
 pub mod custom_errors;
+pub mod exchange;
+pub mod exec_report;
+pub mod fees;
+#[cfg(feature = "fixed-point")]
+pub mod fixed_point;
+pub mod flow;
+#[cfg(test)]
+mod golden;
+pub mod handle;
+pub mod hash;
+pub mod id;
+pub mod journal;
+pub mod ladder;
+#[cfg(feature = "latency-histograms")]
+pub mod latency;
+pub mod limit_order_book;
+pub mod listener;
+#[cfg(feature = "mmap")]
+pub mod mapped;
+pub mod match_policy;
 pub mod order;
+pub mod order_queue;
 pub mod orderbook_impl;
+pub mod parquet;
+pub mod pool;
 pub mod price_level;
+pub mod rate_limit;
+pub mod recovery;
+pub mod replay;
+pub mod ring;
+pub mod risk;
+pub mod router;
+pub mod sampler;
+pub mod shared;
+pub mod simulator;
+pub mod snapshot;
+pub mod state;
+pub mod stats;
+pub mod timer_wheel;
+pub mod trade_store;
 pub mod types;
+pub mod watchdog;
+pub mod wire;
@@ -1,34 +1,93 @@
 use chrono::Utc;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 use uuid::Uuid;
 
 use crate::orderbook::custom_errors::QuantityError;
-use crate::orderbook::types::{Price, Quantity};
+use crate::orderbook::id;
+use crate::orderbook::orderbook_impl::OrderBookError;
+use crate::orderbook::types::{ClientOrderId, OrderTag, ParticipantId, Price, Quantity};
+
+// Longest `Order::tag` the book accepts, so persisted formats can store its length in two bytes
+pub const MAX_TAG_LEN: usize = u16::MAX as usize;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum OrderType {
     LimitOrder,
     MarketOrder,
     ImmediateOrCancel,
     FillOrKill,
     GoodTillCancel,
+    GoodTillDate(i64), // expiry timestamp in epoch millis
+    PostOnly,
+    // Only takes part in an auction uncross, canceled once continuous trading resumes
+    GoodForAuction,
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Side {
     Buy,
     Sell,
 }
 
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+/// Reference price a pegged order follows.
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PegType {
+    // Best price on the order's own side
+    Primary,
+    // Midpoint of the best bid and ask, rounded to the tick away from the opposite side
+    Midpoint,
+    // Best price on the opposite side
+    Market,
+}
+
+/// Peg instruction: the order is priced at its reference plus `offset` and repriced as the
+/// reference moves.
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Peg {
+    pub peg_type: PegType,
+    pub offset: Price,
+}
+
+/// How long an order stays working, as set on an `OrderBuilder`.
 #[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TimeInForce {
+    GoodTillCancel,
+    ImmediateOrCancel,
+    FillOrKill,
+    // Expiry timestamp in epoch millis
+    GoodTillDate(i64),
+    GoodForAuction,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Status {
     New,
     PartiallyFilled,
     Filled,
     Canceled,
+    Expired,
+    // Only carried by execution reports, a rejected order never reaches the book
+    Rejected,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Order {
     pub order_type: OrderType,
     pub order_id: Uuid, // use uuid to replace u64
@@ -39,18 +98,43 @@ pub struct Order {
     pub executed_quantity: Quantity,
     pub remaining_quantity: Quantity,
     pub timestamp: i64,
+    // Submitting participant, 0 when unattributed
+    pub owner: ParticipantId,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub client_order_id: Option<ClientOrderId>,
+    // Rests and matches normally but never shows in depth, level updates or the BBO
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden: bool,
+    // Least quantity that must trade on arrival for the order to execute at all
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_qty: Option<Quantity>,
+    // Set on pegged orders, whose `price` is then maintained by the book
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub peg: Option<Peg>,
+    // Worst price a market order may trade at; the remainder is canceled there
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub protection_price: Option<Price>,
+    // Client metadata the book never reads, echoed on the order's trades and reports
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tag: Option<OrderTag>,
 }
 
 pub struct ModifyOrder {
     // order type by default Limit order / GTC
-    order_id: Uuid,
-    price: Price,
-    quantity: Quantity,
-    side: Side,
-    timestamp: i64,
+    pub order_id: Uuid,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub side: Side,
+    pub timestamp: i64,
 }
 
 impl Order {
+    /// Start an order field by field, with its combination of price, quantity and time in force
+    /// checked by `OrderBuilder::build`.
+    pub fn builder() -> OrderBuilder {
+        OrderBuilder::default()
+    }
+
     pub fn new(
         order_type: OrderType,
         side: Side,
@@ -59,7 +143,7 @@ impl Order {
     ) -> Self {
         Order {
             order_type,
-            order_id: Uuid::new_v4(),
+            order_id: id::next_id(),
             side,
             price,
             status: Status::New,
@@ -67,21 +151,103 @@ impl Order {
             executed_quantity: 0,
             remaining_quantity: original_quantity,
             timestamp: Utc::now().timestamp_millis(),
+            owner: 0,
+            client_order_id: None,
+            hidden: false,
+            min_qty: None,
+            peg: None,
+            protection_price: None,
+            tag: None,
+        }
+    }
+
+    pub fn with_owner(mut self, owner: ParticipantId) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Tag the order with the client's own id, so it can be canceled without knowing `order_id`.
+    pub fn with_client_order_id(mut self, client_order_id: impl Into<ClientOrderId>) -> Self {
+        self.client_order_id = Some(client_order_id.into());
+        self
+    }
+
+    /// Keep the order out of market data, see `HiddenPriority` for its queue position.
+    pub fn as_hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Only execute if at least `min_qty` can trade immediately, see `MinQtyPolicy`.
+    pub fn with_min_qty(mut self, min_qty: Quantity) -> Self {
+        self.min_qty = Some(min_qty);
+        self
+    }
+
+    /// Peg the order to `peg_type` plus `offset`; the price given to `new` is ignored.
+    pub fn with_peg(mut self, peg_type: PegType, offset: Price) -> Self {
+        self.peg = Some(Peg { peg_type, offset });
+        self
+    }
+
+    /// Stop matching at levels worse than `protection_price` and cancel whatever is left, which
+    /// bounds the slippage of a market order.
+    pub fn with_protection_price(mut self, protection_price: Price) -> Self {
+        self.protection_price = Some(protection_price);
+        self
+    }
+
+    /// Attach `tag`, e.g. the id of the strategy's parent order, to find on the order's trades
+    /// and execution reports. At most `MAX_TAG_LEN` bytes.
+    pub fn with_tag(mut self, tag: impl Into<OrderTag>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Checks that need nothing but the order itself: a quantity, a minimum quantity within it,
+    /// a client order id the journal can store and a tag within `MAX_TAG_LEN`. `OrderBuilder`
+    /// and `OrderBook::add_order` both run them.
+    pub fn validate(&self) -> Result<(), OrderBookError> {
+        // Client order ids are journaled as one whitespace-free token
+        if let Some(client_order_id) = self.client_order_id.as_ref()
+            && (client_order_id.is_empty() || client_order_id.contains(char::is_whitespace))
+        {
+            return Err(OrderBookError::InvalidClientOrderId {
+                client_order_id: client_order_id.clone(),
+            });
+        }
+        if let Some(tag) = self.tag.as_ref()
+            && tag.len() > MAX_TAG_LEN
+        {
+            return Err(OrderBookError::TagTooLong { len: tag.len() });
+        }
+        if self.original_quantity == 0 {
+            return Err(OrderBookError::InvalidQuantity {
+                quantity: self.original_quantity,
+            });
+        }
+        if let Some(min_qty) = self.min_qty
+            && (min_qty == 0 || min_qty > self.remaining_quantity)
+        {
+            return Err(OrderBookError::InvalidQuantity { quantity: min_qty });
         }
+        Ok(())
     }
 
     pub fn fill_qty(&mut self, quantity: Quantity) -> Result<(), QuantityError> {
-        if (self.original_quantity - self.executed_quantity) < quantity {
+        let remaining = self
+            .original_quantity
+            .saturating_sub(self.executed_quantity);
+        if remaining < quantity {
             Err(QuantityError {
                 message: format!(
                     "Quantity Error: remaining quantity {} ; fill quantity {}",
-                    (self.original_quantity - self.executed_quantity),
-                    quantity,
+                    remaining, quantity,
                 ),
             })
         } else {
             self.executed_quantity += quantity;
-            self.remaining_quantity = self.original_quantity - self.executed_quantity;
+            self.remaining_quantity = remaining - quantity;
             Ok(())
         }
     }
@@ -92,8 +258,151 @@ impl Order {
     }
 }
 
+/// Builds an `Order` from its parts, e.g.
+/// `Order::builder().side(Side::Buy).limit(101).qty(10).tif(TimeInForce::GoodTillCancel).build()?`,
+/// rejecting combinations the book would misread: market orders with a price, a peg or a time in
+/// force other than IOC, limit orders without a price, and post-only orders that could take.
+/// The book checks tick, lot and bands against its own config when the order arrives.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBuilder {
+    side: Option<Side>,
+    price: Option<Price>,
+    market: bool,
+    quantity: Quantity,
+    tif: Option<TimeInForce>,
+    post_only: bool,
+    owner: ParticipantId,
+    client_order_id: Option<ClientOrderId>,
+    hidden: bool,
+    min_qty: Option<Quantity>,
+    peg: Option<Peg>,
+    protection_price: Option<Price>,
+    tag: Option<OrderTag>,
+}
+
+impl OrderBuilder {
+    pub fn side(mut self, side: Side) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    pub fn limit(mut self, price: Price) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn market(mut self) -> Self {
+        self.market = true;
+        self
+    }
+
+    pub fn qty(mut self, quantity: Quantity) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    /// Limit orders without one rest until canceled, market orders are IOC.
+    pub fn tif(mut self, tif: TimeInForce) -> Self {
+        self.tif = Some(tif);
+        self
+    }
+
+    pub fn post_only(mut self) -> Self {
+        self.post_only = true;
+        self
+    }
+
+    pub fn owner(mut self, owner: ParticipantId) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn client_order_id(mut self, client_order_id: impl Into<ClientOrderId>) -> Self {
+        self.client_order_id = Some(client_order_id.into());
+        self
+    }
+
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub fn min_qty(mut self, min_qty: Quantity) -> Self {
+        self.min_qty = Some(min_qty);
+        self
+    }
+
+    /// Peg to `peg_type` plus `offset`, in place of a limit price.
+    pub fn peg(mut self, peg_type: PegType, offset: Price) -> Self {
+        self.peg = Some(Peg { peg_type, offset });
+        self
+    }
+
+    pub fn protection_price(mut self, protection_price: Price) -> Self {
+        self.protection_price = Some(protection_price);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<OrderTag>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Order, OrderBookError> {
+        let invalid = |reason: &'static str| Err(OrderBookError::InvalidOrder { reason });
+        let Some(side) = self.side else {
+            return invalid("no side");
+        };
+        let order_type = if self.market {
+            if self.price.is_some() {
+                return invalid("market orders carry no price");
+            }
+            if self.peg.is_some() {
+                return invalid("market orders cannot be pegged");
+            }
+            if self.post_only {
+                return invalid("market orders cannot be post-only");
+            }
+            match self.tif {
+                None | Some(TimeInForce::ImmediateOrCancel) => OrderType::MarketOrder,
+                Some(_) => return invalid("market orders are immediate-or-cancel"),
+            }
+        } else {
+            if self.price.is_some() == self.peg.is_some() {
+                return invalid("limit orders need either a price or a peg");
+            }
+            if self.protection_price.is_some() {
+                return invalid("only market orders carry a protection price");
+            }
+            match (self.tif, self.post_only) {
+                (None, false) => OrderType::LimitOrder,
+                (None | Some(TimeInForce::GoodTillCancel), true) => OrderType::PostOnly,
+                (Some(_), true) => return invalid("post-only orders rest until canceled"),
+                (Some(TimeInForce::GoodTillCancel), false) => OrderType::GoodTillCancel,
+                (Some(TimeInForce::ImmediateOrCancel), false) => OrderType::ImmediateOrCancel,
+                (Some(TimeInForce::FillOrKill), false) => OrderType::FillOrKill,
+                (Some(TimeInForce::GoodTillDate(expiry)), false) => OrderType::GoodTillDate(expiry),
+                (Some(TimeInForce::GoodForAuction), false) => OrderType::GoodForAuction,
+            }
+        };
+
+        let order = Order {
+            owner: self.owner,
+            client_order_id: self.client_order_id,
+            hidden: self.hidden,
+            min_qty: self.min_qty,
+            peg: self.peg,
+            protection_price: self.protection_price,
+            tag: self.tag,
+            ..Order::new(order_type, side, self.price.unwrap_or(0), self.quantity)
+        };
+        order.validate()?;
+        Ok(order)
+    }
+}
+
 impl ModifyOrder {
-    fn new(order_id: Uuid, price: Price, quantity: Quantity, side: Side) -> Self {
+    pub fn new(order_id: Uuid, price: Price, quantity: Quantity, side: Side) -> Self {
         let now = Utc::now().timestamp_millis();
         ModifyOrder {
             order_id,
@@ -112,6 +421,95 @@ impl ModifyOrder {
 mod order_tests {
     use super::*;
 
+    #[test]
+    fn check_builder_validates_combinations() {
+        let order = Order::builder()
+            .side(Side::Buy)
+            .limit(101)
+            .qty(10)
+            .tif(TimeInForce::GoodTillCancel)
+            .build()
+            .unwrap();
+        assert_eq!(
+            (
+                order.order_type,
+                order.side,
+                order.price,
+                order.remaining_quantity
+            ),
+            (OrderType::GoodTillCancel, Side::Buy, 101, 10)
+        );
+        let order = Order::builder()
+            .side(Side::Sell)
+            .market()
+            .qty(3)
+            .build()
+            .unwrap();
+        assert_eq!(order.order_type, OrderType::MarketOrder);
+        let order = Order::builder()
+            .side(Side::Sell)
+            .limit(99)
+            .qty(3)
+            .post_only()
+            .build()
+            .unwrap();
+        assert_eq!(order.order_type, OrderType::PostOnly);
+
+        let rejected = |builder: OrderBuilder| builder.build().unwrap_err();
+        assert!(matches!(
+            rejected(Order::builder().side(Side::Buy).market().limit(101).qty(1)),
+            OrderBookError::InvalidOrder { .. }
+        ));
+        assert!(matches!(
+            rejected(
+                Order::builder()
+                    .side(Side::Buy)
+                    .market()
+                    .qty(1)
+                    .tif(TimeInForce::FillOrKill)
+            ),
+            OrderBookError::InvalidOrder { .. }
+        ));
+        assert!(matches!(
+            rejected(Order::builder().side(Side::Buy).qty(1)),
+            OrderBookError::InvalidOrder { .. }
+        ));
+        assert!(matches!(
+            rejected(
+                Order::builder()
+                    .side(Side::Buy)
+                    .limit(101)
+                    .qty(1)
+                    .post_only()
+                    .tif(TimeInForce::ImmediateOrCancel)
+            ),
+            OrderBookError::InvalidOrder { .. }
+        ));
+        assert!(matches!(
+            rejected(Order::builder().limit(101).qty(1)),
+            OrderBookError::InvalidOrder { .. }
+        ));
+        assert!(matches!(
+            rejected(
+                Order::builder()
+                    .side(Side::Buy)
+                    .limit(101)
+                    .tif(TimeInForce::FillOrKill)
+            ),
+            OrderBookError::InvalidQuantity { quantity: 0 }
+        ));
+        assert!(matches!(
+            rejected(
+                Order::builder()
+                    .side(Side::Buy)
+                    .limit(101)
+                    .qty(5)
+                    .min_qty(6)
+            ),
+            OrderBookError::InvalidQuantity { quantity: 6 }
+        ));
+    }
+
     #[test]
     fn check_new_order() {
         let test_order: Order = Order::new(OrderType::GoodTillCancel, Side::Buy, 100, 10);
@@ -128,6 +526,24 @@ mod order_tests {
         let _ = test_order.fill_qty(10);
         assert_eq!(test_order.executed_quantity, 10);
         assert_eq!(test_order.remaining_quantity, 0);
-        assert_eq!(test_order.is_filled(), true);
+        assert!(test_order.is_filled());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_orders_round_trip_through_json() {
+        let order = Order::new(OrderType::LimitOrder, Side::Sell, 100, 10)
+            .with_min_qty(4)
+            .with_protection_price(105);
+        let json = serde_json::to_string(&order).unwrap();
+        assert!(json.contains("\"side\":\"Sell\""));
+        assert_eq!(serde_json::from_str::<Order>(&json).unwrap(), order);
+
+        // optional fields may be left out
+        let json = json.replace(",\"protection_price\":105", "");
+        let restored: Order = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.protection_price, None);
+        let status: Status = serde_json::from_str("\"PartiallyFilled\"").unwrap();
+        assert_eq!(status, Status::PartiallyFilled);
     }
 }
@@ -0,0 +1,436 @@
+use std::collections::VecDeque;
+
+use intrusive_collections::{KeyAdapter, RBTree, RBTreeLink, intrusive_adapter};
+
+use crate::orderbook::pool::OrderKey;
+
+/// Position of an order inside the queue that handed it out, valid until that order leaves the
+/// queue. A sequence number or slot index rather than a pointer, so a stale handle is at worst
+/// looked up in vain, never dereferenced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueueHandle(usize);
+
+/// FIFO of resting orders at one price level, by their key in the book's `OrderPool`.
+pub trait OrderQueue {
+    fn push_back(&mut self, order: OrderKey) -> QueueHandle;
+
+    fn remove(&mut self, handle: QueueHandle) -> Option<OrderKey>;
+
+    fn front(&self) -> Option<OrderKey>;
+
+    fn pop_front(&mut self) -> Option<OrderKey>;
+
+    fn iter(&self) -> Box<dyn Iterator<Item = OrderKey> + '_>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum QueueBackendKind {
+    IntrusiveList,
+    VecDeque,
+    Slab,
+}
+
+#[derive(Debug)]
+pub struct OrderNode {
+    pub link: RBTreeLink,
+    // Arrival number within the queue, the node's key
+    pub sequence: usize,
+    pub order: OrderKey,
+}
+
+impl OrderNode {
+    pub fn new(sequence: usize, order: OrderKey) -> Self {
+        Self {
+            link: RBTreeLink::new(),
+            sequence,
+            order,
+        }
+    }
+}
+
+// Register adapter
+intrusive_adapter!(
+    pub OrderNodeAdapter = Box<OrderNode>: OrderNode { link: RBTreeLink }
+);
+
+impl<'a> KeyAdapter<'a> for OrderNodeAdapter {
+    type Key = usize;
+
+    fn get_key(&self, node: &'a OrderNode) -> usize {
+        node.sequence
+    }
+}
+
+/// Intrusive red-black tree of nodes keyed by arrival number, so FIFO order is key order;
+/// handles are arrival numbers, found again in O(log n) without any pointer into the tree.
+pub struct IntrusiveQueue {
+    orders: RBTree<OrderNodeAdapter>,
+    next_sequence: usize,
+    len: usize,
+}
+
+impl IntrusiveQueue {
+    pub fn new() -> Self {
+        IntrusiveQueue {
+            orders: RBTree::new(OrderNodeAdapter::new()),
+            next_sequence: 0,
+            len: 0,
+        }
+    }
+}
+
+impl Default for IntrusiveQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderQueue for IntrusiveQueue {
+    fn push_back(&mut self, order: OrderKey) -> QueueHandle {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        // The tree owns the node, the newest key always lands at the back
+        self.orders
+            .insert(Box::new(OrderNode::new(sequence, order)));
+        self.len += 1;
+        QueueHandle(sequence)
+    }
+
+    fn remove(&mut self, QueueHandle(sequence): QueueHandle) -> Option<OrderKey> {
+        let node = self.orders.find_mut(&sequence).remove()?;
+        self.len -= 1;
+        Some(node.order)
+    }
+
+    fn front(&self) -> Option<OrderKey> {
+        self.orders.front().get().map(|node| node.order)
+    }
+
+    fn pop_front(&mut self) -> Option<OrderKey> {
+        let node = self.orders.front_mut().remove()?;
+        self.len -= 1;
+        Some(node.order)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = OrderKey> + '_> {
+        Box::new(self.orders.iter().map(|node| node.order))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Ring buffer with tombstones; handles are sequence numbers, so removal is O(1)
+/// and leading tombstones are trimmed as the front advances.
+#[derive(Default)]
+pub struct VecDequeQueue {
+    orders: VecDeque<Option<OrderKey>>,
+    head_sequence: usize, // sequence number of orders[0]
+    len: usize,
+}
+
+impl VecDequeQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn trim_front(&mut self) {
+        while let Some(None) = self.orders.front() {
+            self.orders.pop_front();
+            self.head_sequence += 1;
+        }
+        if self.orders.is_empty() {
+            // reuse the buffer from the start once drained
+            self.head_sequence = 0;
+        }
+    }
+}
+
+impl OrderQueue for VecDequeQueue {
+    fn push_back(&mut self, order: OrderKey) -> QueueHandle {
+        let sequence = self.head_sequence + self.orders.len();
+        self.orders.push_back(Some(order));
+        self.len += 1;
+        QueueHandle(sequence)
+    }
+
+    fn remove(&mut self, QueueHandle(sequence): QueueHandle) -> Option<OrderKey> {
+        let index = sequence.checked_sub(self.head_sequence)?;
+        let order = self.orders.get_mut(index)?.take()?;
+        self.len -= 1;
+        self.trim_front();
+        Some(order)
+    }
+
+    fn front(&self) -> Option<OrderKey> {
+        // leading tombstones are always trimmed
+        *self.orders.front()?
+    }
+
+    fn pop_front(&mut self) -> Option<OrderKey> {
+        let order = self.orders.pop_front()??;
+        self.head_sequence += 1;
+        self.len -= 1;
+        self.trim_front();
+        Some(order)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = OrderKey> + '_> {
+        Box::new(self.orders.iter().flatten().copied())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+const NIL: usize = usize::MAX;
+
+struct SlabSlot {
+    order: Option<OrderKey>,
+    prev: usize,
+    next: usize,
+}
+
+/// Doubly linked list threaded through a Vec of slots; handles are slot indices and
+/// freed slots are reused, so steady-state operation does not allocate.
+pub struct SlabQueue {
+    slots: Vec<SlabSlot>,
+    free_slots: Vec<usize>,
+    head: usize,
+    tail: usize,
+    len: usize,
+}
+
+impl SlabQueue {
+    pub fn new() -> Self {
+        SlabQueue {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            len: 0,
+        }
+    }
+
+    fn unlink(&mut self, index: usize) -> Option<OrderKey> {
+        let order = self.slots.get_mut(index)?.order.take()?;
+        let (prev, next) = (self.slots[index].prev, self.slots[index].next);
+        match prev {
+            NIL => self.head = next,
+            _ => self.slots[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            _ => self.slots[next].prev = prev,
+        }
+        self.free_slots.push(index);
+        self.len -= 1;
+        Some(order)
+    }
+}
+
+impl Default for SlabQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderQueue for SlabQueue {
+    fn push_back(&mut self, order: OrderKey) -> QueueHandle {
+        let slot = SlabSlot {
+            order: Some(order),
+            prev: self.tail,
+            next: NIL,
+        };
+        let index = match self.free_slots.pop() {
+            Some(index) => {
+                self.slots[index] = slot;
+                index
+            }
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        };
+        match self.tail {
+            NIL => self.head = index,
+            tail => self.slots[tail].next = index,
+        }
+        self.tail = index;
+        self.len += 1;
+        QueueHandle(index)
+    }
+
+    fn remove(&mut self, QueueHandle(index): QueueHandle) -> Option<OrderKey> {
+        self.unlink(index)
+    }
+
+    fn front(&self) -> Option<OrderKey> {
+        self.slots.get(self.head)?.order
+    }
+
+    fn pop_front(&mut self) -> Option<OrderKey> {
+        self.unlink(self.head)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = OrderKey> + '_> {
+        let mut index = self.head;
+        Box::new(std::iter::from_fn(move || {
+            let slot = self.slots.get(index)?;
+            index = slot.next;
+            slot.order
+        }))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// The queue backend chosen through `OrderBookConfig`, dispatching to its implementation.
+pub enum QueueBackend {
+    IntrusiveList(IntrusiveQueue),
+    VecDeque(VecDequeQueue),
+    Slab(SlabQueue),
+}
+
+impl QueueBackend {
+    pub fn new(kind: QueueBackendKind) -> Self {
+        match kind {
+            QueueBackendKind::IntrusiveList => QueueBackend::IntrusiveList(IntrusiveQueue::new()),
+            QueueBackendKind::VecDeque => QueueBackend::VecDeque(VecDequeQueue::new()),
+            QueueBackendKind::Slab => QueueBackend::Slab(SlabQueue::new()),
+        }
+    }
+
+    fn queue(&self) -> &dyn OrderQueue {
+        match self {
+            QueueBackend::IntrusiveList(queue) => queue,
+            QueueBackend::VecDeque(queue) => queue,
+            QueueBackend::Slab(queue) => queue,
+        }
+    }
+
+    fn queue_mut(&mut self) -> &mut dyn OrderQueue {
+        match self {
+            QueueBackend::IntrusiveList(queue) => queue,
+            QueueBackend::VecDeque(queue) => queue,
+            QueueBackend::Slab(queue) => queue,
+        }
+    }
+}
+
+impl OrderQueue for QueueBackend {
+    fn push_back(&mut self, order: OrderKey) -> QueueHandle {
+        self.queue_mut().push_back(order)
+    }
+
+    fn remove(&mut self, handle: QueueHandle) -> Option<OrderKey> {
+        self.queue_mut().remove(handle)
+    }
+
+    fn front(&self) -> Option<OrderKey> {
+        self.queue().front()
+    }
+
+    fn pop_front(&mut self) -> Option<OrderKey> {
+        self.queue_mut().pop_front()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = OrderKey> + '_> {
+        self.queue().iter()
+    }
+
+    fn len(&self) -> usize {
+        self.queue().len()
+    }
+}
+
+impl std::fmt::Debug for QueueBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod order_queue_tests {
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType, Side};
+    use crate::orderbook::pool::OrderPool;
+    use crate::orderbook::types::Quantity;
+
+    const ALL_BACKENDS: [QueueBackendKind; 3] = [
+        QueueBackendKind::IntrusiveList,
+        QueueBackendKind::VecDeque,
+        QueueBackendKind::Slab,
+    ];
+
+    fn order(pool: &mut OrderPool, quantity: Quantity) -> OrderKey {
+        pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 10, quantity))
+    }
+
+    fn quantity(pool: &OrderPool, key: OrderKey) -> Quantity {
+        pool.get(key).unwrap().remaining_quantity
+    }
+
+    fn quantities(pool: &OrderPool, queue: &QueueBackend) -> Vec<Quantity> {
+        queue.iter().map(|key| quantity(pool, key)).collect()
+    }
+
+    #[test]
+    fn check_fifo_order_and_removal() {
+        for kind in ALL_BACKENDS {
+            let mut pool = OrderPool::new();
+            let mut queue = QueueBackend::new(kind);
+            let first = queue.push_back(order(&mut pool, 1));
+            let second = queue.push_back(order(&mut pool, 2));
+            queue.push_back(order(&mut pool, 3));
+            assert_eq!(quantities(&pool, &queue), vec![1, 2, 3], "{:?}", kind);
+
+            assert_eq!(quantity(&pool, queue.remove(second).unwrap()), 2);
+            assert!(queue.remove(second).is_none(), "{:?}", kind);
+            assert_eq!(quantities(&pool, &queue), vec![1, 3], "{:?}", kind);
+
+            assert_eq!(quantity(&pool, queue.remove(first).unwrap()), 1);
+            assert_eq!(quantity(&pool, queue.front().unwrap()), 3);
+            assert_eq!(queue.len(), 1);
+
+            assert_eq!(quantity(&pool, queue.pop_front().unwrap()), 3);
+            assert!(queue.is_empty());
+            assert!(queue.pop_front().is_none());
+        }
+    }
+
+    #[test]
+    fn check_fills_in_the_pool_keep_position() {
+        for kind in ALL_BACKENDS {
+            let mut pool = OrderPool::new();
+            let mut queue = QueueBackend::new(kind);
+            let front = order(&mut pool, 5);
+            let front_handle = queue.push_back(front);
+            let middle = order(&mut pool, 6);
+            queue.push_back(middle);
+            queue.push_back(order(&mut pool, 7));
+
+            // a partial fill only touches the pooled order, the queue is left as it is
+            pool.get_mut(middle).unwrap().remaining_quantity = 3;
+            pool.get_mut(front).unwrap().remaining_quantity = 4;
+            assert_eq!(quantities(&pool, &queue), vec![4, 3, 7], "{:?}", kind);
+
+            assert_eq!(queue.remove(front_handle), Some(front));
+            assert_eq!(quantities(&pool, &queue), vec![3, 7], "{:?}", kind);
+
+            // slots freed above are reused without disturbing FIFO order
+            queue.push_back(order(&mut pool, 8));
+            assert_eq!(quantities(&pool, &queue), vec![3, 7, 8], "{:?}", kind);
+        }
+    }
+}
@@ -1,19 +1,58 @@
-use std::cmp::Reverse;
-use std::collections::{BTreeMap, HashMap, VecDeque};
-use std::ptr::NonNull;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::sync::Arc;
-use std::{thread, time::Duration};
+use std::time::{Duration, Instant};
 
 use chrono::Utc;
-use intrusive_collections::LinkedListLink;
-use log::{error, info};
-use uuid::Uuid;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
 
-use crate::orderbook::order::{Order, OrderType, Side, Status};
-use crate::orderbook::price_level::{OrderEntry, OrderNode, PriceLevel};
-use crate::orderbook::types::{OrderId, Price, Quantity};
+use crate::market_data::events::BookEvent;
+use crate::orderbook::analytics::{self, DepthAnalytics, Imbalance, SideTop};
+use crate::orderbook::command::{Command, ValidationToken};
+use crate::orderbook::config::{
+    BandBreachPolicy, ConfigError, MinQtyPolicy, OrderBookConfig, PostOnlyPolicy, SweepLimit,
+};
+use crate::orderbook::exec_report::{ExecType, ExecutionReport};
+use crate::orderbook::fees::Fee;
+use crate::orderbook::handle::{self, HandleRouter, OrderHandle, Subscriptions};
+use crate::orderbook::hash::{IdMap, IdSet};
+use crate::orderbook::id::{self, IdGenerator};
+use crate::orderbook::journal::Journal;
+use crate::orderbook::ladder::{Ladder, LadderKind};
+#[cfg(feature = "latency-histograms")]
+use crate::orderbook::latency::{LatencyHistograms, LatencyPercentiles};
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::match_policy::{Fifo, MatchPolicy};
+use crate::orderbook::order::{MAX_TAG_LEN, Order, OrderType, Peg, PegType, Side, Status};
+use crate::orderbook::pool::{OrderKey, OrderPool};
+use crate::orderbook::price_level::{
+    BboUpdate, LevelInfo, OrderView, OrderbookLevelInfos, PriceLevel, QueueEntry,
+};
+use crate::orderbook::risk::RiskCheck;
+use crate::orderbook::snapshot::{BookSnapshot, LevelSnapshot};
+use crate::orderbook::state::{BookState, InstrumentState, InstrumentStateChange};
+use crate::orderbook::stats::{SessionStats, TradeStats, WindowStats};
+use crate::orderbook::timer_wheel::TimerWheel;
+use crate::orderbook::trade_store::{InMemoryTradeStore, TradeStore};
+use crate::orderbook::types::{ClientOrderId, OrderId, OrderTag, ParticipantId, Price, Quantity};
+use crate::orderbook::watchdog::{CommandKind, LatencyWatchdog, WatchdogStats};
+
+/// How an order took part in a trade, as in FIX LastLiquidityInd.
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Liquidity {
+    // Resting order, the maker
+    Added,
+    // Incoming order, the taker
+    Removed,
+    // Matched in an auction uncross, neither side aggressed
+    Auction,
+}
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Trade {
     trade_id: OrderId,
     bid_order_id: OrderId,
@@ -21,6 +60,74 @@ pub struct Trade {
     price: Price,
     quantity: Quantity,
     timestamp: i64,
+    maker_fee: Fee,
+    taker_fee: Fee,
+    sequence: u64,
+    // Side of the incoming order, None for auction trades
+    aggressor: Option<Side>,
+    // `Order::tag` of each side
+    #[cfg_attr(feature = "serde", serde(default))]
+    bid_tag: Option<OrderTag>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    ask_tag: Option<OrderTag>,
+}
+
+/// A trade reversed by `OrderBook::bust_trade`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TradeBust {
+    pub trade: Trade,
+    // Orders of the trade still resting, which got the busted quantity back; an order that has
+    // left the book is not restored and only learns of the bust from this event
+    pub restored: Vec<OrderId>,
+    pub sequence: u64,
+    // Book clock in epoch micros when the trade was busted
+    pub timestamp: i64,
+}
+
+/// Where the call auction would uncross if it ran now.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IndicativeUncross {
+    pub price: Price,
+    // Volume executing at `price`
+    pub volume: Quantity,
+    // Demand and supply at `price` left unmatched, on `surplus_side`; None when they balance
+    pub surplus: Quantity,
+    pub surplus_side: Option<Side>,
+}
+
+/// Outcome of one accepted order: its trades and where it ended up.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MatchResult {
+    pub order_id: OrderId,
+    pub trades: Vec<Trade>,
+    // Quantity left unfilled, resting on the book unless `status` is Canceled
+    pub remaining: Quantity,
+    pub status: Status,
+    // Pool key of the resting remainder for `OrderBook::cancel_by_key`, None if nothing rests.
+    // Only meaningful to the book that returned it, so it is not serialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub key: Option<OrderKey>,
+}
+
+/// Why an order was refused where it used to get as far as matching and panic there, carried
+/// by `OrderBookError::Reject`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RejectReason {
+    // A price the book derives for the order, e.g. a post-only slid a tick away from the
+    // opposite side, falls outside the `Price` range
+    PriceOutOfRange,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::PriceOutOfRange => write!(f, "derived price out of range"),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -28,12 +135,41 @@ pub enum OrderBookError {
     #[error("Order not found: {order_id}")]
     OrderNotFound { order_id: OrderId },
 
+    #[error("No resting order of participant {owner} with client order id {client_order_id}")]
+    ClientOrderNotFound {
+        owner: ParticipantId,
+        client_order_id: ClientOrderId,
+    },
+
+    #[error("Invalid client order id: {client_order_id:?}")]
+    InvalidClientOrderId { client_order_id: ClientOrderId },
+
+    #[error(
+        "Participant {owner} already has a resting order with client order id {client_order_id}"
+    )]
+    DuplicateClientOrderId {
+        owner: ParticipantId,
+        client_order_id: ClientOrderId,
+    },
+
+    #[error("Order tag of {len} bytes exceeds {MAX_TAG_LEN}")]
+    TagTooLong { len: usize },
+
+    #[error("Invalid order: {reason}")]
+    InvalidOrder { reason: &'static str },
+
     #[error("Invalid price: {price}")]
     InvalidPrice { price: Price },
 
     #[error("Invalid quantity: {quantity}")]
     InvalidQuantity { quantity: Quantity },
 
+    #[error("Quantity {quantity} at price {price} overflows the book's quantity range")]
+    QuantityOverflow { price: Price, quantity: Quantity },
+
+    #[error("Trade not found: {trade_id}")]
+    TradeNotFound { trade_id: OrderId },
+
     #[error("Order already exists: {order_id}")]
     OrderAlreadyExists { order_id: OrderId },
 
@@ -42,585 +178,5895 @@ pub enum OrderBookError {
 
     #[error("No PriceLevelRef not found: {price}")]
     PriceLevelRefNotFound { price: Price },
+
+    #[error("Order queued at price level {price} is missing from the order pool")]
+    OrderKeyNotFound { price: Price },
+
+    #[error("Order key {key:?} no longer names a resting order")]
+    StaleOrderKey { key: OrderKey },
+
+    #[error("Message budget of {max_messages} exceeded by participant {participant_id}")]
+    MessageBudgetExceeded {
+        participant_id: ParticipantId,
+        max_messages: u64,
+    },
+
+    #[error("Order {order_id} needs {min_qty} to trade immediately, only {available} available")]
+    MinQtyUnavailable {
+        order_id: OrderId,
+        min_qty: Quantity,
+        available: Quantity,
+    },
+
+    #[error("Pegged order {order_id} has no reference price to follow")]
+    PegReferenceUnavailable { order_id: OrderId },
+
+    #[error("Post-only order {order_id} would cross the book at price {price}")]
+    PostOnlyWouldCross { order_id: OrderId, price: Price },
+
+    #[error("Command not accepted in instrument state {state:?}")]
+    InstrumentStateRejected { state: InstrumentState },
+
+    #[error("Cannot move the book from {from:?} to {to:?}")]
+    InvalidStateTransition { from: BookState, to: BookState },
+
+    #[error(
+        "Validation token is stale: validated at book version {token_version}, book is at {book_version}"
+    )]
+    StaleValidationToken {
+        token_version: u64,
+        book_version: u64,
+    },
+
+    #[error("Symbol not found: {symbol}")]
+    SymbolNotFound { symbol: String },
+
+    #[error("Symbol already exists: {symbol}")]
+    SymbolAlreadyExists { symbol: String },
+
+    #[error("Journal I/O error: {source}")]
+    JournalIo {
+        #[from]
+        source: std::io::Error,
+    },
+
+    #[error("Journal corrupted at line {line}")]
+    JournalCorrupted { line: usize },
+
+    // `line` counts as in a snapshot file: the header, then the bids and asks one order a line
+    #[error("Snapshot corrupted at line {line}: {reason}")]
+    SnapshotCorrupted { line: usize, reason: String },
+
+    #[error("Mapped book file is invalid: {reason}")]
+    InvalidMappedBook { reason: String },
+
+    #[error("Invalid order flow record at line {line}: {reason}")]
+    InvalidFlowRecord { line: usize, reason: String },
+
+    #[error("Order book task has stopped")]
+    BookStopped,
+
+    #[error("Sequence mismatch: expected {expected}, found {found}")]
+    SequenceMismatch { expected: u64, found: u64 },
+
+    #[error(
+        "State hash mismatch at sequence {sequence}: expected {expected:016x}, found {found:016x}"
+    )]
+    StateHashMismatch {
+        sequence: u64,
+        expected: u64,
+        found: u64,
+    },
+
+    #[error("Invalid price format: {value}")]
+    InvalidPriceFormat { value: String },
+
+    #[error("Invalid config: {source}")]
+    InvalidConfig {
+        #[from]
+        source: ConfigError,
+    },
+
+    #[error("Price {price} is not a multiple of the tick size {tick_size}")]
+    InvalidTick { price: Price, tick_size: Price },
+
+    #[error("Quantity {quantity} is not a multiple of the lot size {lot_size}")]
+    InvalidLot {
+        quantity: Quantity,
+        lot_size: Quantity,
+    },
+
+    #[error("Price {price} outside band [{low}, {high}]")]
+    PriceOutsideBand {
+        price: Price,
+        low: Price,
+        high: Price,
+    },
+
+    #[error("Rate limit exceeded by participant {participant_id}, retry after {retry_after:?}")]
+    RateLimited {
+        participant_id: ParticipantId,
+        retry_after: Duration,
+        // Held by the exchange and applied once tokens are available, instead of dropped
+        queued: bool,
+    },
+
+    #[error("Order size {quantity} exceeds the limit of {max_order_size} for participant {owner}")]
+    MaxOrderSizeExceeded {
+        owner: ParticipantId,
+        quantity: Quantity,
+        max_order_size: Quantity,
+    },
+
+    #[error(
+        "Order notional {notional} exceeds the limit of {max_notional} for participant {owner}"
+    )]
+    MaxNotionalExceeded {
+        owner: ParticipantId,
+        notional: u128,
+        max_notional: u128,
+    },
+
+    #[error(
+        "Participant {owner} already has {open_orders} open orders, limit is {max_open_orders}"
+    )]
+    MaxOpenOrdersExceeded {
+        owner: ParticipantId,
+        open_orders: usize,
+        max_open_orders: usize,
+    },
+
+    #[error("Rejected: {reason}")]
+    Reject { reason: RejectReason },
 }
 
 #[derive(Debug, Clone, Copy)]
 struct PriceLevelRef {
     index: usize,
-    price: Price,
+}
+
+#[derive(Debug)]
+enum Timer {
+    Expire(OrderId),
+    RepricePegs,
+    PublishImbalance,
 }
 
 pub struct OrderBook {
-    bids: BTreeMap<Reverse<Price>, PriceLevelRef>,
-    asks: BTreeMap<Price, PriceLevelRef>,
-    orders: HashMap<OrderId, OrderEntry>,
-    by_price: HashMap<Price, PriceLevelRef>,
+    bids: Ladder<PriceLevelRef>,
+    asks: Ladder<PriceLevelRef>,
+    // Best level of each side with its slot, kept in step with the ladders on every insert and
+    // remove so the top of book and the matching loop skip the ladder lookup
+    best_bid: Option<(Price, PriceLevelRef)>,
+    best_ask: Option<(Price, PriceLevelRef)>,
+    // the only index by id; queues and everything else refer to resting orders by key
+    orders: IdMap<OrderKey>,
+    // the resting orders themselves along with their queue positions
+    pool: OrderPool,
+    // resting order ids by participant
+    owners: HashMap<ParticipantId, IdSet>,
+    // resting order ids by participant and client order id
+    client_orders: HashMap<ParticipantId, HashMap<ClientOrderId, OrderId>>,
+    // resting orders with a peg, repriced whenever their reference moves
+    pegged_orders: IdSet,
+    // resting good-for-auction orders, canceled when continuous trading resumes
+    auction_orders: IdSet,
+    // fill reports of resting orders matched by the current command, only kept with listeners
+    maker_fills: VecDeque<ExecutionReport>,
+    // order-level changes to the displayed book by the current command, only kept with listeners
+    book_events: Vec<BookEvent>,
+    last_exec_id: u64,
     price_levels: Vec<Option<PriceLevel>>,
     free_indices: VecDeque<usize>,
+    // GTD expiries and the peg reprice timer, entries of orders no longer resting are skipped
+    timers: TimerWheel<Timer>,
+    // Whether a `Timer::RepricePegs` is scheduled, only used with a peg reprice interval
+    peg_timer_pending: bool,
+    // Whether a `Timer::PublishImbalance` is scheduled
+    imbalance_timer_pending: bool,
+    config: OrderBookConfig,
+    instrument_state: InstrumentState,
+    watchdog: Option<LatencyWatchdog>,
+    #[cfg(feature = "latency-histograms")]
+    latency: LatencyHistograms,
+    listeners: Vec<Box<dyn OrderBookListener>>,
+    // Report channels of `submit` handles, routed by a listener added with the first one
+    subscriptions: Option<Subscriptions>,
+    // Top of book as last published to listeners
+    last_bbo: Option<BboUpdate>,
+    risk_checks: Vec<Box<dyn RiskCheck>>,
+    // Shares an incoming order across a level, from `config.allocation_policy` unless replaced
+    match_policy: Box<dyn MatchPolicy>,
+    trade_store: Box<dyn TradeStore>,
+    trade_stats: TradeStats,
+    session_stats: SessionStats,
+    analytics: DepthAnalytics,
+    // bumped on every state change, used to expire validation tokens
+    book_version: u64,
+    // Last sequence number handed out to an accepted command or a trade
+    sequence: u64,
+    // Epoch micros the clock is pinned at, None for the wall clock
+    pinned_clock: Option<i64>,
+    // When the command being applied was accepted, stamped on its trades
+    command_time: i64,
+    journal: Option<Journal>,
+    // centre of the dynamic price band, follows the last trade price
+    reference_price: Option<Price>,
+    last_trade_price: Option<Price>,
+    // Equilibrium of the crossing orders while in auction, refreshed after every command
+    indicative: Option<IndicativeUncross>,
+    // Mark set through `set_mark_price`, None to derive it from the last trade and the BBO
+    mark_price: Option<Price>,
+    // trade ids, this thread's generator when unset
+    id_generator: Option<Box<dyn IdGenerator>>,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Levels a side shown by `Display` unless a precision is given
+const DISPLAY_DEPTH: usize = 10;
+
+/// The ladder from `OrderBook::render`, 10 levels a side; the precision picks another depth,
+/// e.g. `{:.3}`.
+impl fmt::Display for OrderBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(f.precision().unwrap_or(DISPLAY_DEPTH)))
+    }
 }
 
 impl Trade {
+    /// Trade with an id from this thread's generator, see `id::set_thread_id_generator`.
     pub fn new(
         bid_order_id: OrderId,
         ask_order_id: OrderId,
         price: Price,
         quantity: Quantity,
+    ) -> Self {
+        Self::with_id(id::next_id(), bid_order_id, ask_order_id, price, quantity)
+    }
+
+    pub fn with_id(
+        trade_id: OrderId,
+        bid_order_id: OrderId,
+        ask_order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
     ) -> Self {
         Trade {
-            trade_id: Uuid::new_v4(),
+            trade_id,
             bid_order_id,
             ask_order_id,
             price,
             quantity,
             timestamp: Utc::now().timestamp_micros(),
+            maker_fee: 0,
+            taker_fee: 0,
+            sequence: 0,
+            aggressor: None,
+            bid_tag: None,
+            ask_tag: None,
+        }
+    }
+
+    pub fn with_aggressor(mut self, aggressor: Side) -> Self {
+        self.aggressor = Some(aggressor);
+        self
+    }
+
+    pub fn with_fees(mut self, maker_fee: Fee, taker_fee: Fee) -> Self {
+        self.maker_fee = maker_fee;
+        self.taker_fee = taker_fee;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    pub fn with_tags(mut self, bid_tag: Option<OrderTag>, ask_tag: Option<OrderTag>) -> Self {
+        self.bid_tag = bid_tag;
+        self.ask_tag = ask_tag;
+        self
+    }
+
+    pub fn trade_id(&self) -> OrderId {
+        self.trade_id
+    }
+
+    pub fn bid_order_id(&self) -> OrderId {
+        self.bid_order_id
+    }
+
+    pub fn ask_order_id(&self) -> OrderId {
+        self.ask_order_id
+    }
+
+    pub fn price(&self) -> Price {
+        self.price
+    }
+
+    pub fn quantity(&self) -> Quantity {
+        self.quantity
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    // Fee charged to the resting (maker) order, negative for a rebate
+    pub fn maker_fee(&self) -> Fee {
+        self.maker_fee
+    }
+
+    // Fee charged to the incoming (taker) order
+    pub fn taker_fee(&self) -> Fee {
+        self.taker_fee
+    }
+
+    // Tag of the buying order, as given to `Order::with_tag`
+    pub fn bid_tag(&self) -> Option<&[u8]> {
+        self.bid_tag.as_deref()
+    }
+
+    pub fn ask_tag(&self) -> Option<&[u8]> {
+        self.ask_tag.as_deref()
+    }
+
+    pub fn aggressor_side(&self) -> Option<Side> {
+        self.aggressor
+    }
+
+    pub fn maker_order_id(&self) -> Option<OrderId> {
+        match self.aggressor? {
+            Side::Buy => Some(self.ask_order_id),
+            Side::Sell => Some(self.bid_order_id),
+        }
+    }
+
+    pub fn taker_order_id(&self) -> Option<OrderId> {
+        match self.aggressor? {
+            Side::Buy => Some(self.bid_order_id),
+            Side::Sell => Some(self.ask_order_id),
+        }
+    }
+
+    /// Liquidity flag of `order_id` in this trade, None if it is not one of the two orders.
+    pub fn liquidity(&self, order_id: OrderId) -> Option<Liquidity> {
+        if order_id != self.bid_order_id && order_id != self.ask_order_id {
+            return None;
+        }
+        match self.taker_order_id() {
+            None => Some(Liquidity::Auction),
+            Some(taker_order_id) if taker_order_id == order_id => Some(Liquidity::Removed),
+            Some(_) => Some(Liquidity::Added),
         }
     }
+
+    // Position in the book's sequence, shared with accepted commands; 0 if not stamped by a book
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
 }
 
 impl OrderBook {
     pub fn new() -> Self {
+        Self::with_config(OrderBookConfig::default())
+    }
+
+    /// Book trading under `config`.
+    ///
+    /// # Panics
+    ///
+    /// If `config` fails `OrderBookConfig::validate`; use `try_with_config` for configs that
+    /// come from outside the program.
+    pub fn with_config(config: OrderBookConfig) -> Self {
+        match Self::try_with_config(config) {
+            Ok(book) => book,
+            Err(err) => panic!("invalid OrderBookConfig: {err}"),
+        }
+    }
+
+    /// Book trading under `config`, refusing a tick or lot size that prices and quantities
+    /// could not be checked against.
+    pub fn try_with_config(config: OrderBookConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
         let init_capacity: usize = 1024;
         let price_levels: Vec<Option<PriceLevel>> = Vec::with_capacity(init_capacity);
         let free_indices: VecDeque<usize> = VecDeque::with_capacity(init_capacity);
 
-        OrderBook {
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
-            orders: HashMap::new(),
-            by_price: HashMap::new(),
+        Ok(OrderBook {
+            bids: Ladder::new(Side::Buy, config.ladder, config.tick_size),
+            asks: Ladder::new(Side::Sell, config.ladder, config.tick_size),
+            best_bid: None,
+            best_ask: None,
+            orders: IdMap::default(),
+            pool: OrderPool::with_capacity(init_capacity),
+            owners: HashMap::new(),
+            client_orders: HashMap::new(),
+            pegged_orders: IdSet::default(),
+            auction_orders: IdSet::default(),
+            maker_fills: VecDeque::new(),
+            book_events: Vec::new(),
+            last_exec_id: 0,
             price_levels,
             free_indices,
+            timers: TimerWheel::new(),
+            peg_timer_pending: false,
+            imbalance_timer_pending: false,
+            watchdog: config.latency_budget.map(LatencyWatchdog::new),
+            #[cfg(feature = "latency-histograms")]
+            latency: LatencyHistograms::new(),
+            trade_stats: TradeStats::new(&config.stats_windows),
+            session_stats: SessionStats::default(),
+            analytics: DepthAnalytics::new(config.imbalance_levels),
+            match_policy: config.allocation_policy.match_policy(),
+            config,
+            instrument_state: InstrumentState::Active,
+            listeners: Vec::new(),
+            subscriptions: None,
+            last_bbo: None,
+            risk_checks: Vec::new(),
+            trade_store: Box::new(InMemoryTradeStore::new()),
+            book_version: 0,
+            sequence: 0,
+            pinned_clock: None,
+            command_time: 0,
+            journal: None,
+            reference_price: None,
+            last_trade_price: None,
+            indicative: None,
+            mark_price: None,
+            id_generator: None,
+        })
+    }
+
+    // Rest a new order, announcing it on the order-level feed
+    fn rest_order(&mut self, order: Order) -> Result<(), OrderBookError> {
+        let added = (!order.hidden).then_some(BookEvent::Add {
+            order_id: order.order_id,
+            side: order.side,
+            price: order.price,
+            quantity: order.remaining_quantity,
+        });
+        self.add_order_to_book(order)?;
+        if let Some(added) = added {
+            self.record_book_event(added);
+        }
+        Ok(())
+    }
+
+    fn record_book_event(&mut self, event: BookEvent) {
+        if !self.listeners.is_empty() {
+            self.book_events.push(event);
         }
     }
 
-    fn add_order_to_book(&mut self, order: &Arc<Order>) {
-        let price_level_ref = match self.by_price.get(&order.price) {
+    fn add_order_to_book(&mut self, order: Order) -> Result<(), OrderBookError> {
+        if !self.ladder(order.side).can_hold(order.price) {
+            let (low, high) = self.ladder_range().unwrap_or((Price::MIN, Price::MAX));
+            return Err(OrderBookError::PriceOutsideBand {
+                price: order.price,
+                low,
+                high,
+            });
+        }
+        let displayed_before = self.displayed_volume_at(order.side, order.price);
+        let price_level_ref = match self.ladder(order.side).get(order.price).copied() {
             None => {
-                let index: usize =
-                    if (!self.free_indices.is_empty()) && (self.price_levels.len() == 1024) {
-                        let index = self
-                            .free_indices
-                            .pop_front()
-                            .expect("Free indices Vector Cannot be None!");
-                        self.price_levels[index] = Some(PriceLevel::new(order.price));
-                        index
-                    } else {
-                        let index = self.price_levels.len();
-                        self.price_levels.push(Some(PriceLevel::new(order.price)));
+                let level = PriceLevel::new(
+                    order.price,
+                    self.config.queue_backend,
+                    self.config.hidden_priority,
+                );
+                let free_index = if self.price_levels.len() == 1024 {
+                    self.free_indices.pop_front()
+                } else {
+                    None
+                };
+                let index: usize = match free_index {
+                    Some(index) => {
+                        self.price_levels[index] = Some(level);
                         index
-                        // self.price_levels.len() - 1
-                    };
-
-                // Create new level reference and append it to HashMap
-                let level_ref = PriceLevelRef {
-                    index,
-                    price: order.price,
+                    }
+                    None => {
+                        self.price_levels.push(Some(level));
+                        self.price_levels.len() - 1
+                    }
                 };
-                self.by_price.insert(order.price, level_ref);
-                level_ref
-            }
-            Some(price_level_ref) => *price_level_ref,
-        };
 
-        // Find the PriceLevel using Index in PriceLevelRef
-        let cursor = self.price_levels[price_level_ref.index]
-            .as_mut()
-            .expect("Price Level cannot be None!")
-            .add_order_return_ptr(order.clone());
-        let order_entry = OrderEntry {
-            order: order.clone(),
-            cursor,
+                PriceLevelRef { index }
+            }
+            Some(price_level_ref) => price_level_ref,
         };
-        self.orders.insert(order.order_id, order_entry);
+        if self
+            .price_levels
+            .get(price_level_ref.index)
+            .is_none_or(Option::is_none)
+        {
+            return Err(OrderBookError::PriceLevelNotFound { price: order.price });
+        }
 
         // add the Level Reference by side
-        match order.side {
-            Side::Buy => self.bids.insert(Reverse(order.price), price_level_ref),
-            Side::Sell => self.asks.insert(order.price, price_level_ref),
+        self.ladder_mut(order.side)
+            .insert(order.price, price_level_ref);
+        let improves = match (order.side, self.best_level(order.side)) {
+            (_, None) => true,
+            (Side::Buy, Some((best, _))) => order.price > best,
+            (Side::Sell, Some((best, _))) => order.price < best,
         };
-    }
-    // Should rename to handle order
-    pub fn add_order(&mut self, order: &Arc<Order>) -> Result<Vec<Option<Trade>>, OrderBookError> {
-        if self.orders.contains_key(&order.order_id) {
-            return Err(OrderBookError::OrderAlreadyExists {
-                order_id: order.order_id,
-            });
+        if improves {
+            *self.best_level_mut(order.side) = Some((order.price, price_level_ref));
         }
-        if order.original_quantity == 0 {
-            return Err(OrderBookError::InvalidQuantity {
-                quantity: order.original_quantity,
-            });
+        self.owners
+            .entry(order.owner)
+            .or_default()
+            .insert(order.order_id);
+        if let Some(client_order_id) = order.client_order_id.as_ref() {
+            self.client_orders
+                .entry(order.owner)
+                .or_default()
+                .insert(client_order_id.clone(), order.order_id);
         }
+        if let OrderType::GoodTillDate(expiry) = order.order_type {
+            self.timers.schedule(expiry, Timer::Expire(order.order_id));
+        }
+        if order.peg.is_some() {
+            self.pegged_orders.insert(order.order_id);
+            if let Some(interval) = self.config.peg_reprice_interval
+                && !self.peg_timer_pending
+            {
+                let due = self.clock() / 1_000 + interval.as_millis() as i64;
+                self.timers.schedule(due, Timer::RepricePegs);
+                self.peg_timer_pending = true;
+            }
+        }
+        if order.order_type == OrderType::GoodForAuction {
+            self.auction_orders.insert(order.order_id);
+        }
+
+        // Find the PriceLevel using Index in PriceLevelRef
+        let (order_id, side, price) = (order.order_id, order.side, order.price);
+        let (quantity, hidden) = (order.remaining_quantity, order.hidden);
+        let key = self.pool.insert(order);
+        // Checked above, before anything was indexed
+        if let Some(level) = self.price_levels[price_level_ref.index].as_mut() {
+            let handle = level.add_order(key, quantity, hidden);
+            self.pool.set_handle(key, handle);
+            self.orders.insert(order_id, key);
+        }
+        self.displayed_level_changed(side, price, displayed_before);
+        Ok(())
+    }
 
-        let mut trades: Vec<Option<Trade>> = Vec::with_capacity(self.orders.len());
+    // Displayed volume resting at `price`, 0 if there is no level
+    fn displayed_volume_at(&self, side: Side, price: Price) -> Quantity {
+        self.resting_level(side, price)
+            .map_or(0, PriceLevel::displayed_volume)
+    }
 
-        match order.order_type {
-            OrderType::MarketOrder => trades = self.match_market(order).unwrap(),
-            OrderType::ImmediateOrCancel => {}
-            OrderType::FillOrKill => trades = self.match_fill_or_kill(order).unwrap(),
-            _ => trades = self.match_and_add_to_book(order).unwrap(),
+    // Feed the analytics cache the displayed volume change at `price`; to the market a level
+    // appears or disappears with its displayed volume, whatever hidden volume rests there
+    fn displayed_level_changed(&self, side: Side, price: Price, displayed_before: Quantity) {
+        let displayed_after = self.displayed_volume_at(side, price);
+        self.analytics.level_changed(
+            side,
+            price,
+            displayed_after.saturating_sub(displayed_before),
+            displayed_before.saturating_sub(displayed_after),
+            (displayed_before == 0) != (displayed_after == 0),
+        );
+    }
+
+    fn ladder(&self, side: Side) -> &Ladder<PriceLevelRef> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
         }
+    }
 
-        Ok(trades)
+    fn best_level_mut(&mut self, side: Side) -> &mut Option<(Price, PriceLevelRef)> {
+        match side {
+            Side::Buy => &mut self.best_bid,
+            Side::Sell => &mut self.best_ask,
+        }
     }
 
-    pub fn cancel_order(&mut self, order_id: OrderId) -> Result<(), OrderBookError> {
-        let order_entry = self
-            .orders
-            .remove(&order_id)
-            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+    fn best_level(&self, side: Side) -> Option<(Price, PriceLevelRef)> {
+        match side {
+            Side::Buy => self.best_bid,
+            Side::Sell => self.best_ask,
+        }
+    }
 
-        let order = &order_entry.order;
+    fn best_price(&self, side: Side) -> Option<Price> {
+        self.best_level(side).map(|(price, _)| price)
+    }
 
-        match order.side {
-            Side::Buy => {
-                let price_level_ref = { self.bids.get(&Reverse(order.price)) };
-                let index: usize = price_level_ref.unwrap().index;
-                let target_level = self.price_levels[index].as_mut().unwrap();
-                target_level.remove_by_ptr(order_entry.cursor);
-                if target_level.order_count == 0 {
-                    self.price_levels[index] = None;
-                    self.free_indices.push_back(index);
-                    self.by_price.remove(&order.price);
-                }
-            }
-            Side::Sell => {
-                let price_level_ref = { self.bids.get(&Reverse(order.price)) };
-                let index: usize = price_level_ref.unwrap().index;
-                let target_level = self.price_levels[index].as_mut().unwrap();
-                target_level.remove_by_ptr(order_entry.cursor);
-                if target_level.order_count == 0 {
-                    self.price_levels[index] = None;
-                    self.free_indices.push_back(index);
-                    self.by_price.remove(&order.price);
-                }
-            }
+    // Slot of the level at `price`, straight from the cache when it is the top of book
+    fn level_ref(&self, side: Side, price: Price) -> Option<PriceLevelRef> {
+        match self.best_level(side) {
+            Some((best_price, level_ref)) if best_price == price => Some(level_ref),
+            _ => self.ladder(side).get(price).copied(),
         }
-        Ok(())
     }
 
-    fn match_order(&mut self, order: &Arc<Order>) -> Result<Vec<Option<Trade>>, OrderBookError> {
-        let mut trades: Vec<Option<Trade>> = Vec::with_capacity(self.orders.len());
-        let order_price: Price = order.price;
-        let mut remaining_quantity: Quantity = order.remaining_quantity;
-        let order_type: OrderType = order.order_type;
+    fn ladder_mut(&mut self, side: Side) -> &mut Ladder<PriceLevelRef> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
 
-        match order.side {
-            Side::Buy => {
-                while remaining_quantity > 0 {
-                    let best_ask = if let Some((&price, _)) = self.asks.iter().next() {
-                        price
-                    } else {
-                        // Price level does not exist -> break matching
-                        break;
-                    };
+    // Resting order behind `order_id`, as currently filled
+    pub(crate) fn resting_order(&self, order_id: OrderId) -> Option<&Order> {
+        self.pool.get(*self.orders.get(&order_id)?)
+    }
+    /// Match `order` and rest whatever is left of it, returning its trades and whether it
+    /// rested, filled or was canceled.
+    pub fn add_order(&mut self, order: &Arc<Order>) -> Result<MatchResult, OrderBookError> {
+        self.submit_order(order, false)
+    }
 
-                    if order_price >= best_ask || order_type == OrderType::MarketOrder {
-                        let trade = self
-                            .match_at_price_level_optimized(best_ask, order, remaining_quantity)
-                            .unwrap();
-                        remaining_quantity -= trade.quantity;
-                        trades.push(Some(trade));
-                    } else {
-                        break;
-                    };
-                    // sleep 0.5s for debug purpose
-                    // thread::sleep(Duration::from_millis(500));
-                }
+    /// Like `add_order`, returning a handle that receives this order's execution reports, from
+    /// its acceptance to its final fill or cancel, instead of the caller filtering the book's
+    /// listener callbacks by order id.
+    pub fn submit(&mut self, order: &Arc<Order>) -> Result<OrderHandle, OrderBookError> {
+        let subscriptions = match self.subscriptions.as_ref() {
+            Some(subscriptions) => subscriptions.clone(),
+            None => {
+                let subscriptions = Subscriptions::default();
+                self.add_listener(Box::new(HandleRouter(subscriptions.clone())));
+                self.subscriptions = Some(subscriptions.clone());
+                subscriptions
             }
-            Side::Sell => {
-                while remaining_quantity > 0 {
-                    let best_bid = if let Some((&Reverse(price), _)) = self.bids.iter().next() {
-                        price
-                    } else {
-                        // Price level does not exist -> break matching
-                        break;
-                    };
-
-                    if order_price <= best_bid || order_type == OrderType::MarketOrder {
-                        let trade = self
-                            .match_at_price_level_optimized(best_bid, order, remaining_quantity)
-                            .unwrap();
-                        remaining_quantity -= trade.quantity;
-                        trades.push(Some(trade));
-                    } else {
-                        break;
-                    };
-                    // sleep 0.5s for debug purpose
-                    // thread::sleep(Duration::from_millis(500));
-                }
+        };
+        // Subscribing would take over the reports of the working order with this id
+        if subscriptions.lock().unwrap().contains_key(&order.order_id) {
+            return Err(OrderBookError::OrderAlreadyExists {
+                order_id: order.order_id,
+            });
+        }
+        let reports = handle::subscribe(&subscriptions, order.order_id);
+        match self.add_order(order) {
+            Ok(_) => Ok(OrderHandle::new(order.order_id, reports)),
+            Err(error) => {
+                subscriptions.lock().unwrap().remove(&order.order_id);
+                Err(error)
             }
         }
-        Ok(trades)
     }
 
-    fn match_at_price_level(
+    /// Apply `command` as if it had come through `add_order`, `cancel_order`, `modify_order`,
+    /// `tick`, `set_instrument_state`, `uncross` or `bust_trade`. A cancel reports the canceled
+    /// order with no trades and the quantity it withdrew as `remaining`. Operator and timer
+    /// commands carry no order: their result has a nil `order_id`, nothing remaining, and the
+    /// trades of an uncross.
+    pub fn apply_command(&mut self, command: &Command) -> Result<MatchResult, OrderBookError> {
+        match command {
+            Command::NewOrder(order) => self.add_order(order),
+            Command::CancelOrder(order_id) => self.submit_cancel(*order_id, false),
+            Command::ModifyOrder {
+                order_id,
+                price,
+                quantity,
+            } => self.submit_modify(*order_id, *price, *quantity, false),
+            Command::Tick(now) => self.tick(*now).map(|_| operator_result(Vec::new())),
+            Command::SetInstrumentState(state) => self
+                .set_instrument_state(*state)
+                .map(|_| operator_result(Vec::new())),
+            Command::Uncross => self.uncross().map(operator_result),
+            Command::BustTrade {
+                trade_id,
+                bid_order_id,
+                ask_order_id,
+                quantity,
+            } => self
+                .submit_bust(*trade_id, *bid_order_id, *ask_order_id, *quantity)
+                .map(|_| operator_result(Vec::new())),
+        }
+    }
+
+    /// Submit a batch of orders in one call, e.g. a ladder of quotes, returning one result per
+    /// order in submission order. A rejected order does not stop the rest of the batch.
+    pub fn add_orders(
         &mut self,
-        best_price: Price,
-        order: &Arc<Order>,
-        max_quantity: Quantity,
-    ) -> Result<Trade, OrderBookError> {
-        let price_level_ref_opt = match order.side {
-            Side::Buy => self.asks.get_mut(&best_price),
-            Side::Sell => self.bids.get_mut(&Reverse(best_price)),
-        };
+        orders: &[Arc<Order>],
+    ) -> Vec<Result<MatchResult, OrderBookError>> {
+        // Grow the indexes once for the whole batch rather than order by order
+        self.orders.reserve(orders.len());
+        let mut results: Vec<Result<MatchResult, OrderBookError>> =
+            Vec::with_capacity(orders.len());
+        for order in orders {
+            results.push(self.submit_order(order, false));
+        }
+        results
+    }
 
-        let price_level_ref = match price_level_ref_opt {
-            Some(level) => level,
+    // Where `order` ended up after matching produced `trades`
+    fn match_result(&self, order: &Arc<Order>, trades: Vec<Trade>) -> MatchResult {
+        let key = self.orders.get(&order.order_id).copied();
+        let (remaining, status) = match key.and_then(|key| self.pool.get(key)) {
+            Some(resting_order) => (resting_order.remaining_quantity, resting_order.status),
             None => {
-                error!("Price Level Not Found at price {}", best_price);
-                return Err(OrderBookError::PriceLevelNotFound { price: best_price });
+                let filled: Quantity = trades.iter().map(|trade| trade.quantity).sum();
+                let remaining = order.remaining_quantity.saturating_sub(filled);
+                // Whatever did not fill and did not rest (market, IOC, FOK) is canceled
+                let status = if remaining == 0 {
+                    Status::Filled
+                } else {
+                    Status::Canceled
+                };
+                (remaining, status)
             }
         };
+        MatchResult {
+            order_id: order.order_id,
+            trades,
+            remaining,
+            status,
+            key,
+        }
+    }
 
-        // 2️⃣ Get cursor to front node
-        let index = price_level_ref.index;
-        let target_level = self.price_levels[index].as_mut().unwrap();
-        let front_cursor = target_level.orders.front();
-        let node_ptr = match front_cursor.get() {
-            Some(node) => node as *const OrderNode as *mut OrderNode,
-            None => {
-                return Err(OrderBookError::OrderNotFound {
-                    order_id: order.order_id,
-                });
+    /// Run the book-side checks for `command` without applying it. The returned token can be
+    /// committed later as long as the book has not changed in between.
+    pub fn validate(&self, command: Command) -> Result<ValidationToken, OrderBookError> {
+        let command = match command {
+            Command::NewOrder(order) => {
+                let order = self.peg_order(&order)?;
+                self.validate_order(&order)?;
+                Command::NewOrder(order)
+            }
+            Command::CancelOrder(order_id) => {
+                self.validate_cancel(order_id)?;
+                command
+            }
+            Command::ModifyOrder {
+                order_id,
+                price,
+                quantity,
+            } => {
+                self.validate_modify(order_id, price, quantity)?;
+                command
+            }
+            Command::BustTrade {
+                bid_order_id,
+                ask_order_id,
+                quantity,
+                ..
+            } => {
+                self.validate_bust(bid_order_id, ask_order_id, quantity)?;
+                command
             }
+            // Operator and timer commands have no book-side checks to run ahead of time
+            Command::Tick(_) | Command::SetInstrumentState(_) | Command::Uncross => command,
         };
-        let node_ptr = unsafe { NonNull::new_unchecked(node_ptr) };
+        Ok(ValidationToken {
+            command,
+            book_version: self.book_version,
+        })
+    }
 
-        // 3️⃣ Build mutable cursor from pointer
-        let mut cursor = unsafe { target_level.orders.cursor_mut_from_ptr(node_ptr.as_ptr()) };
+    /// Apply a previously validated command without re-running its checks.
+    pub fn commit(&mut self, token: ValidationToken) -> Result<MatchResult, OrderBookError> {
+        if token.book_version != self.book_version {
+            return Err(OrderBookError::StaleValidationToken {
+                token_version: token.book_version,
+                book_version: self.book_version,
+            });
+        }
+        match token.command {
+            Command::NewOrder(order) => self.submit_order(&order, true),
+            Command::CancelOrder(order_id) => self.submit_cancel(order_id, true),
+            Command::ModifyOrder {
+                order_id,
+                price,
+                quantity,
+            } => self.submit_modify(order_id, price, quantity, true),
+            command => self.apply_command(&command),
+        }
+    }
 
-        // 4️⃣ Get old node
-        let old_order_arc = cursor.get().expect("Node must exist").order.clone();
+    /// Write every accepted command to `journal` before it is applied.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
 
-        // 5️⃣ Compute trade quantity and trade
-        let trade_quantity = max_quantity.min(old_order_arc.remaining_quantity);
-        let trade_price = old_order_arc.price;
+    pub fn book_version(&self) -> u64 {
+        self.book_version
+    }
 
-        let trade = Trade::new(
-            order.order_id,
-            old_order_arc.order_id,
-            trade_price,
-            trade_quantity,
+    /// Sequence number of the last accepted command or emitted trade. Numbers are strictly
+    /// increasing and gap-free across both, so replaying the same commands reproduces them.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    fn next_sequence(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(
+            name = "add_order",
+            level = "debug",
+            skip_all,
+            fields(
+                order_id = %order.order_id,
+                side = ?order.side,
+                price = order.price,
+                quantity = order.remaining_quantity
+            )
+        )
+    )]
+    fn submit_order(
+        &mut self,
+        order: &Arc<Order>,
+        validated: bool,
+    ) -> Result<MatchResult, OrderBookError> {
+        let start = self.timing_commands().then(Instant::now);
+        // Reports and results describe the order as priced, pegs included
+        let (order, result) = match self.peg_order(order) {
+            Ok(priced_order) => {
+                let result = self.process_order(&priced_order, validated);
+                (priced_order, result)
+            }
+            Err(error) => (order.clone(), Err(error)),
+        };
+        let order = &order;
+
+        let elapsed = start.map(|start| start.elapsed());
+        #[cfg(feature = "latency-histograms")]
+        if let Some(elapsed) = elapsed {
+            self.latency.record(CommandKind::AddOrder, elapsed);
+        }
+        if let (Some(watchdog), Some(elapsed)) = (self.watchdog.as_mut(), elapsed) {
+            let (levels_touched, fills) = match &result {
+                Ok(trades) => {
+                    let mut prices: Vec<Price> = trades.iter().map(|t| t.price).collect();
+                    prices.dedup();
+                    (prices.len(), trades.len())
+                }
+                Err(_) => (0, 0),
+            };
+            watchdog.record(CommandKind::AddOrder, elapsed, levels_touched, fills);
+        }
+
+        if !self.listeners.is_empty() {
+            self.publish_order_result(order, &result);
+        }
+        // Taken before repricing, which may move a pegged remainder but never fills it
+        let result = result.map(|trades| self.match_result(order, trades));
+        if result.is_ok() {
+            self.reprice_after_command();
+        }
+        self.debug_check_consistency();
+        result
+    }
+
+    fn record_trade(&mut self, trade: &Trade) {
+        self.trade_store.record(trade);
+        self.trade_stats.record(trade);
+        self.session_stats.record(trade);
+        self.reference_price = Some(trade.price);
+        self.last_trade_price = Some(trade.price);
+    }
+
+    /// Rolling VWAP/TWAP for each of `OrderBookConfig::stats_windows`, as of now.
+    pub fn stats(&self) -> Vec<WindowStats> {
+        self.stats_at(self.clock())
+    }
+
+    /// Open, high, low, last, volume, trade count and notional since the session started.
+    pub fn session_stats(&self) -> &SessionStats {
+        &self.session_stats
+    }
+
+    /// Close the trading session, returning its stats and starting the next one from zero.
+    /// Listeners get the closed session through `on_session_end`. Called when the book leaves
+    /// `BookState::Closed`; books that never close call it at their own session boundary.
+    pub fn reset_session(&mut self) -> SessionStats {
+        let closed = std::mem::take(&mut self.session_stats);
+        if !self.listeners.is_empty() {
+            self.notify_listeners(|listener| listener.on_session_end(&closed));
+        }
+        closed
+    }
+
+    /// Pin the book's clock at `micros` since the epoch, e.g. at a recorded command's time while
+    /// replaying it; `None` goes back to the wall clock.
+    pub fn set_clock(&mut self, micros: Option<i64>) {
+        self.pinned_clock = micros;
+    }
+
+    /// The book's current time in epoch micros, which trades, journal entries, snapshots and
+    /// state changes are stamped with.
+    pub fn clock(&self) -> i64 {
+        self.pinned_clock
+            .unwrap_or_else(|| Utc::now().timestamp_micros())
+    }
+
+    /// Like `stats`, as of `now` in epoch micros.
+    pub fn stats_at(&self, now: i64) -> Vec<WindowStats> {
+        self.trade_stats.stats_at(now)
+    }
+
+    /// Trades executed by this book, in execution order.
+    pub fn trade_history(&self) -> &dyn TradeStore {
+        self.trade_store.as_ref()
+    }
+
+    /// Replace the trade store, e.g. with a persistent implementation.
+    pub fn set_trade_store(&mut self, trade_store: Box<dyn TradeStore>) {
+        self.trade_store = trade_store;
+    }
+
+    /// Reverse the trade `trade_id`, e.g. one executed in error: it leaves the trade history,
+    /// and each of its orders still resting gets the quantity back, at the back of its queue as
+    /// for any quantity increase. Rolling and session trade statistics are not rewound. The bust
+    /// is numbered and journaled as `Command::BustTrade`.
+    pub fn bust_trade(&mut self, trade_id: OrderId) -> Result<TradeBust, OrderBookError> {
+        let trade = self
+            .trade_store
+            .trade(trade_id)
+            .ok_or(OrderBookError::TradeNotFound { trade_id })?;
+        let (bid_order_id, ask_order_id, quantity) =
+            (trade.bid_order_id, trade.ask_order_id, trade.quantity);
+        self.submit_bust(trade_id, bid_order_id, ask_order_id, quantity)?
+            .ok_or(OrderBookError::TradeNotFound { trade_id })
+    }
+
+    // Give `quantity` back to whichever of `bid_order_id` and `ask_order_id` still rest and take
+    // `trade_id` off the tape. A replayed bust may find the trade gone from the history, e.g.
+    // after a restart from a snapshot; the orders are restored all the same, but there is no
+    // `TradeBust` to report.
+    fn submit_bust(
+        &mut self,
+        trade_id: OrderId,
+        bid_order_id: OrderId,
+        ask_order_id: OrderId,
+        quantity: Quantity,
+    ) -> Result<Option<TradeBust>, OrderBookError> {
+        // Every check runs before the bust is numbered or the book touched, so a bust that
+        // cannot be applied leaves no trace
+        self.validate_bust(bid_order_id, ask_order_id, quantity)?;
+        let resting: Vec<OrderId> = [bid_order_id, ask_order_id]
+            .into_iter()
+            .filter(|order_id| self.orders.contains_key(order_id))
+            .collect();
+        self.accept_command(&Command::BustTrade {
+            trade_id,
+            bid_order_id,
+            ask_order_id,
+            quantity,
+        })?;
+        let trade = self.trade_store.bust(trade_id);
+
+        let mut moved: Vec<(Side, Price, Order)> = Vec::with_capacity(resting.len());
+        for order_id in &resting {
+            let mut order = self.remove_resting_order(*order_id)?;
+            order.executed_quantity = order.executed_quantity.saturating_sub(quantity);
+            order.remaining_quantity = order.remaining_quantity.saturating_add(quantity);
+            order.status = if order.executed_quantity == 0 {
+                Status::New
+            } else {
+                Status::PartiallyFilled
+            };
+            if !order.hidden {
+                self.record_book_event(BookEvent::Replace {
+                    original_order_id: *order_id,
+                    new_order_id: *order_id,
+                    price: order.price,
+                    quantity: order.remaining_quantity,
+                });
+            }
+            moved.push((order.side, order.price, order.clone()));
+            self.add_order_to_book(order)?;
+        }
+
+        let bust = trade.map(|trade| TradeBust {
+            trade,
+            restored: resting,
+            sequence: self.sequence,
+            timestamp: self.command_time,
+        });
+        if !self.listeners.is_empty() {
+            if let Some(bust) = bust.as_ref() {
+                self.notify_listeners(|listener| listener.on_trade_bust(bust));
+            }
+            for (side, price, order) in moved {
+                self.publish_execution_report(ExecutionReport::new(ExecType::Replaced, &order));
+                self.publish_level_change(side, price);
+            }
+            self.publish_book_events();
+            self.publish_bbo();
+        }
+        self.refresh_indicative();
+        self.debug_check_consistency();
+        Ok(bust)
+    }
+
+    pub fn add_listener(&mut self, listener: Box<dyn OrderBookListener>) {
+        self.listeners.push(listener);
+    }
+
+    /// Run `risk_check` on every new order before it reaches matching, after the checks added
+    /// before it.
+    pub fn add_risk_check(&mut self, risk_check: Box<dyn RiskCheck>) {
+        self.risk_checks.push(risk_check);
+    }
+
+    fn notify_listeners<F>(&mut self, mut callback: F)
+    where
+        F: FnMut(&mut dyn OrderBookListener),
+    {
+        for listener in self.listeners.iter_mut() {
+            callback(listener.as_mut());
+        }
+    }
+
+    fn publish_order_result(
+        &mut self,
+        order: &Arc<Order>,
+        result: &Result<Vec<Trade>, OrderBookError>,
+    ) {
+        let trades = match result {
+            Ok(trades) => trades,
+            Err(error) => {
+                self.notify_listeners(|listener| listener.on_reject(order, error));
+                self.publish_execution_report(ExecutionReport::rejected(order, error.to_string()));
+                return;
+            }
+        };
+
+        let opposite_side = match order.side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let mut taker = order.as_ref().clone();
+        let mut touched_prices: Vec<Price> = Vec::new();
+        for trade in trades.iter() {
+            self.notify_listeners(|listener| listener.on_trade(trade));
+            fill_in_place(&mut taker, trade.quantity);
+            let taker_fill = ExecutionReport::fill(&taker, trade.quantity, trade.price);
+            self.publish_execution_report(taker_fill.with_trade(trade));
+            if let Some(maker_fill) = self.maker_fills.pop_front() {
+                self.publish_execution_report(maker_fill.with_trade(trade));
+            }
+            if touched_prices.last() != Some(&trade.price) {
+                touched_prices.push(trade.price);
+            }
+        }
+        for price in touched_prices {
+            self.publish_level_change(opposite_side, price);
+        }
+
+        // The remainder may have rested at a different (repriced) price, or not at all
+        match self.resting_order(order.order_id) {
+            Some(resting_order) => {
+                let price = resting_order.price;
+                if price != order.price {
+                    let replaced = ExecutionReport::new(ExecType::Replaced, resting_order);
+                    self.publish_execution_report(replaced);
+                }
+                self.publish_level_change(order.side, price);
+            }
+            None if taker.remaining_quantity > 0 => {
+                self.publish_execution_report(ExecutionReport::new(ExecType::Canceled, &taker));
+            }
+            None => {}
+        }
+        self.publish_book_events();
+        self.publish_bbo();
+    }
+
+    fn publish_execution_report(&mut self, mut report: ExecutionReport) {
+        self.last_exec_id += 1;
+        report.exec_id = self.last_exec_id;
+        self.notify_listeners(|listener| listener.on_execution_report(&report));
+    }
+
+    // Fire `on_bbo` if the top of book differs from the last one published
+    fn publish_bbo(&mut self) {
+        let bbo = self.bbo();
+        if self
+            .last_bbo
+            .is_some_and(|last_bbo| last_bbo.same_quote(&bbo))
+        {
+            return;
+        }
+        self.last_bbo = Some(bbo);
+        self.notify_listeners(|listener| listener.on_bbo(&bbo));
+    }
+
+    fn publish_level_change(&mut self, side: Side, price: Price) {
+        let level_info = self
+            .resting_level(side, price)
+            .map(|level| level.get_level_info())
+            .unwrap_or(LevelInfo { price, volume: 0 });
+        self.notify_listeners(|listener| listener.on_level_change(side, &level_info));
+    }
+
+    fn publish_cancel(&mut self, order: &Arc<Order>) {
+        self.notify_listeners(|listener| listener.on_cancel(order));
+        self.publish_execution_report(ExecutionReport::new(ExecType::Canceled, order));
+        self.publish_level_change(order.side, order.price);
+        if !order.hidden {
+            self.book_events.push(BookEvent::Delete {
+                order_id: order.order_id,
+            });
+        }
+        self.publish_book_events();
+        self.publish_bbo();
+    }
+
+    // Fire `on_book_event` for the order-level changes made since the last call
+    fn publish_book_events(&mut self) {
+        let events = std::mem::take(&mut self.book_events);
+        for event in events.iter() {
+            self.notify_listeners(|listener| listener.on_book_event(event));
+        }
+    }
+
+    fn validate_order(&self, order: &Arc<Order>) -> Result<(), OrderBookError> {
+        if !self.instrument_state.accepts_new_orders() {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        if self.orders.contains_key(&order.order_id) {
+            return Err(OrderBookError::OrderAlreadyExists {
+                order_id: order.order_id,
+            });
+        }
+        order.validate()?;
+        if let Some(client_order_id) = order.client_order_id.as_ref()
+            && self
+                .order_id_for_client(order.owner, client_order_id)
+                .is_some()
+        {
+            return Err(OrderBookError::DuplicateClientOrderId {
+                owner: order.owner,
+                client_order_id: client_order_id.clone(),
+            });
+        }
+        if !order.original_quantity.is_multiple_of(self.config.lot_size) {
+            return Err(OrderBookError::InvalidLot {
+                quantity: order.original_quantity,
+                lot_size: self.config.lot_size,
+            });
+        }
+        // Market orders carry no meaningful price
+        if order.order_type != OrderType::MarketOrder
+            && order.price.rem_euclid(self.config.tick_size) != 0
+        {
+            return Err(OrderBookError::InvalidTick {
+                price: order.price,
+                tick_size: self.config.tick_size,
+            });
+        }
+        if let Some(protection_price) = order.protection_price
+            && protection_price.rem_euclid(self.config.tick_size) != 0
+        {
+            return Err(OrderBookError::InvalidTick {
+                price: protection_price,
+                tick_size: self.config.tick_size,
+            });
+        }
+        // Immediate orders have nothing to execute against until the auction uncrosses
+        let immediate = matches!(
+            order.order_type,
+            OrderType::MarketOrder | OrderType::ImmediateOrCancel | OrderType::FillOrKill
         );
+        if immediate && self.instrument_state == InstrumentState::Auction {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        if order.order_type == OrderType::GoodForAuction
+            && self.instrument_state != InstrumentState::Auction
+        {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        if order.order_type != OrderType::MarketOrder {
+            self.check_price_band(order.price)?;
+        }
+        if self.config.min_qty_policy == MinQtyPolicy::Reject
+            && let Some(min_qty) = order.min_qty
+            && !self.min_qty_available(order)
+        {
+            return Err(OrderBookError::MinQtyUnavailable {
+                order_id: order.order_id,
+                min_qty,
+                available: self.executable_quantity(order),
+            });
+        }
+        // A post-only order is rejected or repriced here, before it is numbered and journaled
+        let rest_price = if order.order_type == OrderType::PostOnly
+            && self.instrument_state != InstrumentState::Auction
+        {
+            self.post_only_price(order)?
+        } else {
+            order.price
+        };
+        self.check_level_room(order.side, rest_price, order.remaining_quantity)?;
+        for risk_check in self.risk_checks.iter() {
+            risk_check.check(order, self)?;
+        }
+        Ok(())
+    }
+
+    // Resting volumes are summed per level, which has to stay in range
+    fn check_level_room(
+        &self,
+        side: Side,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<(), OrderBookError> {
+        if let Some(level) = self.resting_level(side, price)
+            && level.volume().checked_add(quantity).is_none()
+        {
+            return Err(OrderBookError::QuantityOverflow { price, quantity });
+        }
+        Ok(())
+    }
+
+    // One tick short of the opposite best, where post-only and pegged orders stop so as not to
+    // cross; None without an opposite side
+    fn tick_inside(&self, side: Side) -> Result<Option<Price>, OrderBookError> {
+        let tick_size = self.config.tick_size;
+        let price = match side {
+            Side::Buy => self
+                .get_best_ask()
+                .map(|best_ask| best_ask.checked_sub(tick_size)),
+            Side::Sell => self
+                .get_best_bid()
+                .map(|best_bid| best_bid.checked_add(tick_size)),
+        };
+        price
+            .map(|price| {
+                price.ok_or(OrderBookError::Reject {
+                    reason: RejectReason::PriceOutOfRange,
+                })
+            })
+            .transpose()
+    }
+
+    fn check_price_band(&self, price: Price) -> Result<(), OrderBookError> {
+        for (low, high) in [
+            self.config.price_limits,
+            self.dynamic_band(),
+            self.ladder_range(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if price < low || price > high {
+                return Err(OrderBookError::PriceOutsideBand { price, low, high });
+            }
+        }
+        Ok(())
+    }
+
+    // Prices a tick array ladder can hold, the whole price range for a BTree ladder
+    fn ladder_range(&self) -> Option<(Price, Price)> {
+        match self.config.ladder {
+            LadderKind::BTree => None,
+            LadderKind::TickArray {
+                min_price,
+                max_price,
+            } => Some((min_price, max_price)),
+        }
+    }
+
+    // (low, high) around the reference price, if a band is configured and a reference is known
+    fn dynamic_band(&self) -> Option<(Price, Price)> {
+        let band = self.config.price_band?;
+        let reference = self.reference_price?;
+        let width = reference.unsigned_abs() as u128 * band.width_bps as u128 / 10_000;
+        let width = Price::try_from(width).unwrap_or(Price::MAX);
+        Some((
+            reference.saturating_sub(width),
+            reference.saturating_add(width),
+        ))
+    }
+
+    // A breach of the dynamic band may halt the instrument, a breach of the static limits never does
+    fn halt_on_band_breach(&mut self, price: Price) {
+        let halts = self
+            .config
+            .price_band
+            .is_some_and(|band| band.breach_policy == BandBreachPolicy::Halt);
+        let breached = self
+            .dynamic_band()
+            .is_some_and(|(low, high)| price < low || price > high);
+        if halts && breached {
+            warn!(price, "price band breached, halting");
+            // The breaching order is rejected and never journaled, so the halt is journaled itself
+            if let Err(error) = self.set_instrument_state(InstrumentState::Halted) {
+                warn!(%error, "halt on band breach not journaled, instrument left running");
+            }
+        }
+    }
+
+    /// Allocate fills within a price level with `match_policy` instead of the built-in policy
+    /// selected by `OrderBookConfig::allocation_policy`.
+    pub fn set_match_policy(&mut self, match_policy: Box<dyn MatchPolicy>) {
+        self.match_policy = match_policy;
+    }
+
+    /// Generate this book's trade ids with `id_generator` instead of the thread's generator,
+    /// e.g. a `MonotonicIdGenerator` so a replayed journal yields the same trade ids.
+    pub fn set_id_generator(&mut self, id_generator: Box<dyn IdGenerator>) {
+        self.id_generator = Some(id_generator);
+    }
+
+    fn next_trade_id(&mut self) -> OrderId {
+        match self.id_generator.as_mut() {
+            Some(id_generator) => id_generator.next_id(),
+            None => id::next_id(),
+        }
+    }
+
+    /// Centre of the dynamic price band until the next trade moves it.
+    pub fn set_reference_price(&mut self, price: Price) {
+        self.reference_price = Some(price);
+    }
+
+    pub fn reference_price(&self) -> Option<Price> {
+        self.reference_price
+    }
+
+    /// Price of the latest trade, None before the first. Busts do not move it back.
+    pub fn last_trade_price(&self) -> Option<Price> {
+        self.last_trade_price
+    }
+
+    /// Pin the mark price, e.g. to an index or settlement price; None goes back to deriving it.
+    pub fn set_mark_price(&mut self, price: Option<Price>) {
+        self.mark_price = price;
+    }
+
+    /// The pinned mark price, otherwise the last trade price held inside the displayed best bid
+    /// and ask, so a stale print outside the spread marks at the nearest touch. During an auction
+    /// or while the book is crossed there is no spread to hold it in, so the mark is the
+    /// indicative uncross price, None if the book does not cross. None until the first trade
+    /// unless pinned.
+    pub fn mark_price(&self) -> Option<Price> {
+        if self.mark_price.is_some() {
+            return self.mark_price;
+        }
+        let bbo = self.bbo();
+        let crossed =
+            matches!((bbo.bid_price, bbo.ask_price), (Some(bid), Some(ask)) if bid >= ask);
+        if crossed || self.instrument_state == InstrumentState::Auction {
+            return self.equilibrium().map(|uncross| uncross.price);
+        }
+        let mut mark = self.last_trade_price?;
+        if let Some(bid) = bbo.bid_price {
+            mark = mark.max(bid);
+        }
+        if let Some(ask) = bbo.ask_price {
+            mark = mark.min(ask);
+        }
+        Some(mark)
+    }
+
+    pub fn config(&self) -> &OrderBookConfig {
+        &self.config
+    }
+
+    // `order` priced off its peg's reference, unchanged if it is not pegged
+    fn peg_order(&self, order: &Arc<Order>) -> Result<Arc<Order>, OrderBookError> {
+        let Some(peg) = order.peg else {
+            return Ok(order.clone());
+        };
+        let price =
+            self.peg_price(order.side, peg)
+                .ok_or(OrderBookError::PegReferenceUnavailable {
+                    order_id: order.order_id,
+                })?;
+        let mut pegged_order = order.as_ref().clone();
+        pegged_order.price = price;
+        Ok(Arc::new(pegged_order))
+    }
 
+    fn peg_price(&self, side: Side, peg: Peg) -> Option<Price> {
+        let reference = match peg.peg_type {
+            PegType::Primary => self.peg_reference(side)?,
+            PegType::Market => self.peg_reference(side.opposite())?,
+            PegType::Midpoint => {
+                // In i128 so no pair of prices overflows, the mid itself always fits back
+                let twice_mid = self.peg_reference(Side::Buy)? as i128
+                    + self.peg_reference(Side::Sell)? as i128;
+                let tick_size = self.config.tick_size as i128;
+                let mid = match side {
+                    Side::Buy => twice_mid.div_euclid(2 * tick_size) * tick_size,
+                    Side::Sell => -(-twice_mid).div_euclid(2 * tick_size) * tick_size,
+                };
+                Price::try_from(mid).ok()?
+            }
+        };
+        Some(reference.saturating_add(peg.offset))
+    }
+
+    // Best price of `side` among displayed orders that are not pegged themselves. Pegged orders
+    // never set a reference, so repricing one cannot move another and a single pass settles
+    fn peg_reference(&self, side: Side) -> Option<Price> {
+        self.ladder(side)
+            .iter()
+            .find(|(_, level_ref)| {
+                self.price_levels[level_ref.index]
+                    .as_ref()
+                    .is_some_and(|level| {
+                        level
+                            .iter()
+                            .filter_map(|key| self.pool.get(key))
+                            .any(|order| order.peg.is_none() && !order.hidden)
+                    })
+            })
+            .map(|(price, _)| price)
+    }
+
+    // Pegs follow their reference after every command, unless they are repriced on a timer
+    fn reprice_after_command(&mut self) {
+        if self.config.peg_reprice_interval.is_none() {
+            self.reprice_pegged_orders();
+        }
+        self.refresh_indicative();
+    }
+
+    fn refresh_indicative(&mut self) {
+        self.indicative = if self.instrument_state == InstrumentState::Auction {
+            self.equilibrium()
+        } else {
+            None
+        };
+    }
+
+    // Move every pegged order whose reference has moved, behind the orders already at its new
+    // price. A repriced order never crosses: it stops a tick short of the opposite best
+    fn reprice_pegged_orders(&mut self) {
+        if self.pegged_orders.is_empty() || self.instrument_state == InstrumentState::Auction {
+            return;
+        }
+        // Orders backing away from the opposite side go first, to make room for those moving
+        // towards it; then oldest first, so orders moving to one price keep their priority
+        let mut repricings: Vec<(bool, i64, OrderId, Price)> = self
+            .pegged_orders
+            .iter()
+            .filter_map(|order_id| self.resting_order(*order_id))
+            .filter_map(|order| {
+                let target = self.peg_price(order.side, order.peg?)?;
+                let advancing = match order.side {
+                    Side::Buy => target > order.price,
+                    Side::Sell => target < order.price,
+                };
+                (target != order.price).then_some((
+                    advancing,
+                    order.timestamp,
+                    order.order_id,
+                    target,
+                ))
+            })
+            .collect();
+        repricings.sort_unstable();
+
+        let mut moved: Vec<(Side, Price, Price, Option<ExecutionReport>)> = Vec::new();
+        for (_, _, order_id, target) in repricings {
+            let Some(order) = self.resting_order(order_id) else {
+                continue;
+            };
+            let (side, price, quantity) = (order.side, order.price, order.remaining_quantity);
+            let target = match (side, self.tick_inside(side)) {
+                (_, Ok(None)) => target,
+                (Side::Buy, Ok(Some(limit))) => target.min(limit),
+                (Side::Sell, Ok(Some(limit))) => target.max(limit),
+                // No price left short of the opposite side
+                (_, Err(_)) => continue,
+            };
+            if target == price
+                || self.check_price_band(target).is_err()
+                || self.check_level_room(side, target, quantity).is_err()
+            {
+                continue;
+            }
+            let Ok(mut order) = self.remove_resting_order(order_id) else {
+                continue;
+            };
+            order.price = target;
+            let replaced = (!self.listeners.is_empty())
+                .then(|| ExecutionReport::new(ExecType::Replaced, &order));
+            // Same id at the new price, at the back of its queue
+            let replace = (!order.hidden).then_some(BookEvent::Replace {
+                original_order_id: order_id,
+                new_order_id: order_id,
+                price: target,
+                quantity: order.remaining_quantity,
+            });
+            // The target passed the same checks as any order resting there
+            if let Err(error) = self.add_order_to_book(order) {
+                warn!(%order_id, %error, "pegged order dropped while repricing");
+                continue;
+            }
+            if let Some(replace) = replace {
+                self.record_book_event(replace);
+            }
+            moved.push((side, price, target, replaced));
+        }
+
+        if moved.is_empty() {
+            return;
+        }
+        self.book_version += 1;
+        if !self.listeners.is_empty() {
+            for (side, from, to, replaced) in moved {
+                if let Some(replaced) = replaced {
+                    self.publish_execution_report(replaced);
+                }
+                self.publish_level_change(side, from);
+                self.publish_level_change(side, to);
+            }
+            self.publish_book_events();
+            self.publish_bbo();
+        }
+    }
+
+    // The orders of a busted trade still resting must have room for the quantity coming back
+    fn validate_bust(
+        &self,
+        bid_order_id: OrderId,
+        ask_order_id: OrderId,
+        quantity: Quantity,
+    ) -> Result<(), OrderBookError> {
+        for order in [bid_order_id, ask_order_id]
+            .into_iter()
+            .filter_map(|order_id| self.resting_order(order_id))
+        {
+            self.check_level_room(order.side, order.price, quantity)?;
+        }
+        Ok(())
+    }
+
+    fn validate_cancel(&self, order_id: OrderId) -> Result<(), OrderBookError> {
+        if !self.instrument_state.accepts_cancels() {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        if !self.orders.contains_key(&order_id) {
+            return Err(OrderBookError::OrderNotFound { order_id });
+        }
+        Ok(())
+    }
+
+    fn validate_modify(
+        &self,
+        order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<(), OrderBookError> {
+        if !self.instrument_state.accepts_cancels() {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        let order = self
+            .resting_order(order_id)
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        // The amended order has to leave something to rest
+        if quantity <= order.executed_quantity {
+            return Err(OrderBookError::InvalidQuantity { quantity });
+        }
+        if !quantity.is_multiple_of(self.config.lot_size) {
+            return Err(OrderBookError::InvalidLot {
+                quantity,
+                lot_size: self.config.lot_size,
+            });
+        }
+        let leaves = quantity - order.executed_quantity;
+        if price == order.price && leaves <= order.remaining_quantity {
+            return Ok(());
+        }
+
+        // Anything else re-enters the book like a new order
+        if !self.instrument_state.accepts_new_orders() {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        if price.rem_euclid(self.config.tick_size) != 0 {
+            return Err(OrderBookError::InvalidTick {
+                price,
+                tick_size: self.config.tick_size,
+            });
+        }
+        self.check_price_band(price)?;
+        let replacement = Arc::new(Order {
+            price,
+            original_quantity: quantity,
+            remaining_quantity: leaves,
+            ..order.clone()
+        });
+        replacement.validate()?;
+        for risk_check in self.risk_checks.iter() {
+            risk_check.check_amendment(&replacement, self)?;
+        }
+        // Where the replacement will actually rest, one tick inside if a post-only reprices
+        let price = if replacement.order_type == OrderType::PostOnly
+            && self.instrument_state != InstrumentState::Auction
         {
-            target_level.volume -= trade_quantity;
+            self.post_only_price(&replacement)?
+        } else {
+            price
+        };
+        // At its own price the order's current quantity leaves the level first
+        let resting_volume = self
+            .resting_level(order.side, price)
+            .map_or(0, PriceLevel::volume);
+        let resting_volume = if price == order.price {
+            resting_volume - order.remaining_quantity
+        } else {
+            resting_volume
+        };
+        if resting_volume.checked_add(leaves).is_none() {
+            return Err(OrderBookError::QuantityOverflow {
+                price,
+                quantity: leaves,
+            });
+        }
+        Ok(())
+    }
+
+    fn process_order(
+        &mut self,
+        order: &Arc<Order>,
+        validated: bool,
+    ) -> Result<Vec<Trade>, OrderBookError> {
+        if !validated && let Err(error) = self.validate_order(order) {
+            if let OrderBookError::PriceOutsideBand { price, .. } = error {
+                self.halt_on_band_breach(price);
+            }
+            return Err(error);
+        }
+        let command = Command::NewOrder(order.clone());
+        self.accept_command(&command)?;
+
+        if !self.listeners.is_empty() {
+            self.notify_listeners(|listener| listener.on_accept(order));
+            self.publish_execution_report(ExecutionReport::new(ExecType::New, order));
+        }
+
+        let mut trades: Vec<Trade> = Vec::with_capacity(self.orders.len());
+
+        match order.order_type {
+            _ if self.instrument_state == InstrumentState::Auction => {
+                self.rest_order(order.as_ref().clone())?
+            }
+            // Only reached under `MinQtyPolicy::DropConstraint`, `Reject` stops these in validation
+            OrderType::MarketOrder | OrderType::ImmediateOrCancel | OrderType::FillOrKill
+                if !self.min_qty_available(order) =>
+            {
+                debug!(order_id = %order.order_id, "canceled, min qty not available");
+            }
+            OrderType::MarketOrder => trades = self.match_market(order)?,
+            OrderType::ImmediateOrCancel => trades = self.match_order(order)?,
+            OrderType::FillOrKill => trades = self.match_fill_or_kill(order)?,
+            OrderType::PostOnly => trades = self.add_post_only(order)?,
+            _ => trades = self.match_and_add_to_book(order)?,
+        }
+
+        for trade in trades.iter() {
+            self.record_trade(trade);
+        }
+        Ok(trades)
+    }
+
+    pub fn cancel_order(&mut self, order_id: OrderId) -> Result<(), OrderBookError> {
+        self.submit_cancel(order_id, false).map(|_| ())
+    }
+
+    /// Cancel the resting order at `key`, the `MatchResult::key` it got when it rested. The
+    /// order is found through its pool slot and unlinked through the queue position stored
+    /// there, the id index is only updated. A key whose order has left the book is refused as
+    /// stale, even once its slot holds another order.
+    pub fn cancel_by_key(&mut self, key: OrderKey) -> Result<MatchResult, OrderBookError> {
+        if !self.instrument_state.accepts_cancels() {
+            return Err(OrderBookError::InstrumentStateRejected {
+                state: self.instrument_state,
+            });
+        }
+        let order_id = self
+            .pool
+            .get(key)
+            .map(|order| order.order_id)
+            .ok_or(OrderBookError::StaleOrderKey { key })?;
+        self.cancel_resting(order_id, key)
+    }
+
+    /// Cancel the resting order `owner` submitted under `client_order_id`.
+    pub fn cancel_by_client_order_id(
+        &mut self,
+        owner: ParticipantId,
+        client_order_id: &str,
+    ) -> Result<(), OrderBookError> {
+        let order_id = self
+            .order_id_for_client(owner, client_order_id)
+            .ok_or_else(|| OrderBookError::ClientOrderNotFound {
+                owner,
+                client_order_id: client_order_id.to_string(),
+            })?;
+        self.cancel_order(order_id)
+    }
+
+    /// Amend a resting order to `price` and `quantity`, its new total quantity including what
+    /// has already executed. A reduction at the same price keeps the order's place in the queue;
+    /// a price change or an increase sends it to the back of the queue at its (new) price, where
+    /// it matches first if it now crosses.
+    pub fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<MatchResult, OrderBookError> {
+        self.submit_modify(order_id, price, quantity, false)
+    }
+
+    /// Book id of the resting order `owner` submitted under `client_order_id`.
+    pub fn order_id_for_client(
+        &self,
+        owner: ParticipantId,
+        client_order_id: &str,
+    ) -> Option<OrderId> {
+        self.client_orders
+            .get(&owner)?
+            .get(client_order_id)
+            .copied()
+    }
+
+    // Number and journal a command that passed validation, before any of its effects
+    fn accept_command(&mut self, command: &Command) -> Result<(), OrderBookError> {
+        let sequence = self.sequence + 1;
+        let command_time = self.clock();
+        if let Some(journal) = self.journal.as_mut() {
+            journal.append(sequence, command_time, command)?;
+        }
+        self.command_time = command_time;
+        self.sequence = sequence;
+        self.book_version += 1;
+        if !self.listeners.is_empty() {
+            self.notify_listeners(|listener| listener.on_command(sequence, command));
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(
+            name = "cancel_order",
+            level = "debug",
+            skip_all,
+            fields(order_id = %order_id)
+        )
+    )]
+    fn submit_cancel(
+        &mut self,
+        order_id: OrderId,
+        validated: bool,
+    ) -> Result<MatchResult, OrderBookError> {
+        if !validated {
+            self.validate_cancel(order_id)?;
+        }
+        let key = self
+            .orders
+            .get(&order_id)
+            .copied()
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        self.cancel_resting(order_id, key)
+    }
+
+    // Cancel the validated resting order `order_id`, found at `key`
+    fn cancel_resting(
+        &mut self,
+        order_id: OrderId,
+        key: OrderKey,
+    ) -> Result<MatchResult, OrderBookError> {
+        self.accept_command(&Command::CancelOrder(order_id))?;
+
+        let start = self.timing_commands().then(Instant::now);
+        let result = self.remove_resting_key(key);
+        let elapsed = start.map(|start| start.elapsed());
+        #[cfg(feature = "latency-histograms")]
+        if let Some(elapsed) = elapsed {
+            self.latency.record(CommandKind::CancelOrder, elapsed);
+        }
+        if let (Some(watchdog), Some(elapsed)) = (self.watchdog.as_mut(), elapsed) {
+            watchdog.record(CommandKind::CancelOrder, elapsed, 1, 0);
+        }
+
+        let mut canceled_order = result?;
+        canceled_order.status = Status::Canceled;
+        let canceled = MatchResult {
+            order_id,
+            trades: Vec::new(),
+            remaining: canceled_order.remaining_quantity,
+            status: Status::Canceled,
+            key: None,
+        };
+        if !self.listeners.is_empty() {
+            self.publish_cancel(&Arc::new(canceled_order));
+        }
+        self.reprice_after_command();
+        self.debug_check_consistency();
+        Ok(canceled)
+    }
+
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(
+            name = "modify_order",
+            level = "debug",
+            skip_all,
+            fields(order_id = %order_id, price = price, quantity = quantity)
+        )
+    )]
+    fn submit_modify(
+        &mut self,
+        order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
+        validated: bool,
+    ) -> Result<MatchResult, OrderBookError> {
+        if !validated {
+            self.validate_modify(order_id, price, quantity)?;
+        }
+        self.accept_command(&Command::ModifyOrder {
+            order_id,
+            price,
+            quantity,
+        })?;
+
+        let order = self
+            .resting_order(order_id)
+            .cloned()
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        let leaves = quantity - order.executed_quantity;
+        let (replacement, trades) = if price == order.price && leaves <= order.remaining_quantity {
+            (self.reduce_in_place(&order, quantity)?, Vec::new())
+        } else {
+            self.move_to_back(order_id, price, quantity)?
+        };
+        self.reprice_after_command();
+        self.debug_check_consistency();
+        Ok(self.match_result(&replacement, trades))
+    }
+
+    // Cut a resting order down to `quantity` in total without moving it in its queue
+    fn reduce_in_place(
+        &mut self,
+        order: &Order,
+        quantity: Quantity,
+    ) -> Result<Arc<Order>, OrderBookError> {
+        let (order_id, side, price, hidden) =
+            (order.order_id, order.side, order.price, order.hidden);
+        let canceled_quantity = order.remaining_quantity - (quantity - order.executed_quantity);
+        let displayed_before = self.displayed_volume_at(side, price);
+        let level_ref = self
+            .level_ref(side, price)
+            .ok_or(OrderBookError::PriceLevelRefNotFound { price })?;
+        if self
+            .price_levels
+            .get(level_ref.index)
+            .is_none_or(Option::is_none)
+        {
+            return Err(OrderBookError::PriceLevelNotFound { price });
+        }
+        let resting_order = self
+            .orders
+            .get(&order_id)
+            .and_then(|key| self.pool.get_mut(*key))
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        resting_order.original_quantity = quantity;
+        resting_order.remaining_quantity -= canceled_quantity;
+        let amended = Arc::new(resting_order.clone());
+        // Checked above, before the order changed
+        if let Some(level) = self.price_levels[level_ref.index].as_mut() {
+            level.fill(canceled_quantity, hidden);
+        }
+        self.displayed_level_changed(side, price, displayed_before);
+
+        if !hidden && canceled_quantity > 0 {
+            self.record_book_event(BookEvent::Cancel {
+                order_id: order.order_id,
+                canceled_quantity,
+            });
+        }
+        if !self.listeners.is_empty() {
+            self.publish_execution_report(ExecutionReport::new(ExecType::Replaced, &amended));
+            self.publish_level_change(side, price);
+            self.publish_book_events();
+            self.publish_bbo();
+        }
+        Ok(amended)
+    }
+
+    // Take a resting order off the book and enter it again at `price` for `quantity` in total,
+    // behind everything already there and matching first if it now crosses
+    fn move_to_back(
+        &mut self,
+        order_id: OrderId,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<(Arc<Order>, Vec<Trade>), OrderBookError> {
+        let order = self.remove_resting_order(order_id)?;
+        if !order.hidden {
+            self.record_book_event(BookEvent::Delete { order_id });
+        }
+        let replacement = Arc::new(Order {
+            price,
+            original_quantity: quantity,
+            remaining_quantity: quantity - order.executed_quantity,
+            timestamp: self.command_time / 1_000,
+            // Only meaningful on arrival, which the order is long past
+            min_qty: None,
+            ..order.clone()
+        });
+        if !self.listeners.is_empty() {
+            self.publish_execution_report(ExecutionReport::new(ExecType::Replaced, &replacement));
+            self.publish_level_change(order.side, order.price);
+        }
+
+        let trades = match replacement.order_type {
+            _ if self.instrument_state == InstrumentState::Auction => {
+                self.rest_order(replacement.as_ref().clone())?;
+                Vec::new()
+            }
+            OrderType::PostOnly => self.add_post_only(&replacement)?,
+            _ => self.match_and_add_to_book(&replacement)?,
+        };
+        for trade in trades.iter() {
+            self.record_trade(trade);
+        }
+        let result = Ok(trades);
+        if !self.listeners.is_empty() {
+            self.publish_order_result(&replacement, &result);
+        }
+        result.map(|trades| (replacement, trades))
+    }
+
+    /// Cancel every resting order, returning the canceled order ids.
+    pub fn cancel_all(&mut self) -> Result<Vec<OrderId>, OrderBookError> {
+        let order_ids: Vec<OrderId> = self.orders.keys().copied().collect();
+        for order_id in order_ids.iter() {
+            self.cancel_order(*order_id)?;
+        }
+        Ok(order_ids)
+    }
+
+    /// Cancel every resting order of `owner`, returning the canceled order ids.
+    pub fn cancel_all_for(&mut self, owner: ParticipantId) -> Result<Vec<OrderId>, OrderBookError> {
+        let order_ids: Vec<OrderId> = self
+            .owners
+            .get(&owner)
+            .map(|order_ids| order_ids.iter().copied().collect())
+            .unwrap_or_default();
+        for order_id in order_ids.iter() {
+            self.cancel_order(*order_id)?;
+        }
+        Ok(order_ids)
+    }
+
+    /// Resting orders of `owner` in their current state, oldest first.
+    pub fn open_orders(&self, owner: ParticipantId) -> Vec<Arc<Order>> {
+        let mut open_orders: Vec<Arc<Order>> = self
+            .owners
+            .get(&owner)
+            .into_iter()
+            .flatten()
+            .filter_map(|order_id| self.resting_order(*order_id))
+            .map(|order| Arc::new(order.clone()))
+            .collect();
+        open_orders.sort_by_key(|order| order.timestamp);
+        open_orders
+    }
+
+    pub fn open_order_count(&self, owner: ParticipantId) -> usize {
+        self.owners.get(&owner).map_or(0, IdSet::len)
+    }
+
+    pub fn order_owner(&self, order_id: OrderId) -> Option<ParticipantId> {
+        self.resting_order(order_id).map(|order| order.owner)
+    }
+
+    pub fn contains_order(&self, order_id: OrderId) -> bool {
+        self.orders.contains_key(&order_id)
+    }
+
+    pub fn resting_order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Latency watchdog counters, if a latency budget is configured.
+    pub fn watchdog_stats(&self) -> Option<WatchdogStats> {
+        self.watchdog.as_ref().map(|watchdog| watchdog.stats())
+    }
+
+    // Whether command latencies are measured at all
+    fn timing_commands(&self) -> bool {
+        cfg!(feature = "latency-histograms") || self.watchdog.is_some()
+    }
+
+    /// Latency percentiles of every `command` processed since the book was created or the
+    /// histograms were last reset, None if there was none.
+    #[cfg(feature = "latency-histograms")]
+    pub fn latency_percentiles(&self, command: CommandKind) -> Option<LatencyPercentiles> {
+        self.latency.percentiles(command)
+    }
+
+    /// Latency histograms of the book, e.g. to print `report()` at the end of a run.
+    #[cfg(feature = "latency-histograms")]
+    pub fn latency_histograms(&self) -> &LatencyHistograms {
+        &self.latency
+    }
+
+    #[cfg(feature = "latency-histograms")]
+    pub fn reset_latency_histograms(&mut self) {
+        self.latency.reset();
+    }
+
+    pub fn instrument_state(&self) -> InstrumentState {
+        self.instrument_state
+    }
+
+    /// Move the instrument to `state`, returning the change event if the state actually changed.
+    /// A change is journaled as a command, and fails only if the journal cannot be written.
+    pub fn set_instrument_state(
+        &mut self,
+        state: InstrumentState,
+    ) -> Result<Option<InstrumentStateChange>, OrderBookError> {
+        if self.instrument_state == state {
+            return Ok(None);
+        }
+        self.accept_command(&Command::SetInstrumentState(state))?;
+        let change = InstrumentStateChange {
+            previous: self.instrument_state,
+            current: state,
+            timestamp: self.clock() / 1_000,
+        };
+        info!(previous = ?change.previous, current = ?change.current, "instrument state change");
+        self.instrument_state = state;
+        // Commands validated under the previous state must be checked again
+        self.book_version += 1;
+        self.refresh_indicative();
+        if state == InstrumentState::Auction {
+            self.schedule_imbalance();
+        }
+        if state == InstrumentState::Active {
+            self.cancel_auction_orders();
+            self.reprice_after_command();
+            self.debug_check_consistency();
+        }
+        Ok(Some(change))
+    }
+
+    // First imbalance publication of an auction, one interval from now
+    fn schedule_imbalance(&mut self) {
+        if let Some(interval) = self.config.imbalance_interval
+            && !self.imbalance_timer_pending
+        {
+            let due = self.clock() / 1_000 + interval.as_millis() as i64;
+            self.timers.schedule(due, Timer::PublishImbalance);
+            self.imbalance_timer_pending = true;
+        }
+    }
+
+    // Good-for-auction orders left over once continuous trading resumes
+    fn cancel_auction_orders(&mut self) {
+        let mut order_ids: Vec<OrderId> = self.auction_orders.drain().collect();
+        order_ids.sort_unstable();
+        for order_id in order_ids {
+            if let Ok(mut canceled_order) = self.remove_resting_order(order_id)
+                && !self.listeners.is_empty()
+            {
+                canceled_order.status = Status::Canceled;
+                self.publish_cancel(&Arc::new(canceled_order));
+            }
+        }
+    }
+
+    pub fn book_state(&self) -> BookState {
+        BookState::from(self.instrument_state)
+    }
+
+    /// Move the book to the trading phase `state`. Leaving `Closed` starts a new session (see
+    /// `reset_session`), and opening from `PreOpen` first uncrosses the orders collected during
+    /// the auction; the opening trades are returned. The uncross and the state change are each
+    /// journaled.
+    pub fn transition_to(&mut self, state: BookState) -> Result<Vec<Trade>, OrderBookError> {
+        let current = self.book_state();
+        if !current.can_transition_to(state) {
+            return Err(OrderBookError::InvalidStateTransition {
+                from: current,
+                to: state,
+            });
+        }
+        if current == BookState::Closed {
+            self.reset_session();
+        }
+        let trades = if current == BookState::PreOpen && state == BookState::Open {
+            self.uncross()?
+        } else {
+            Vec::new()
+        };
+        self.set_instrument_state(state.instrument_state())?;
+        Ok(trades)
+    }
+
+    /// Cancel every GTD order whose expiry is at or before `now` (epoch millis),
+    /// returning the expired orders with `Status::Expired`.
+    pub fn expire_due(&mut self, now: i64) -> Result<Vec<Arc<Order>>, OrderBookError> {
+        self.tick(now)
+    }
+
+    /// Drive the book's timers to `now` (epoch millis): GTD orders due by then expire, with a
+    /// peg reprice interval configured, pegged orders are repriced once it has elapsed, and with
+    /// an imbalance interval, auctions publish their imbalance. The expired orders are returned
+    /// with `Status::Expired`. A tick that expires or reprices anything is journaled as a
+    /// command first, and fails only if the journal cannot be written.
+    pub fn tick(&mut self, now: i64) -> Result<Vec<Arc<Order>>, OrderBookError> {
+        let timers = self.timers.advance(now);
+        // Publishing the imbalance leaves the book as it is, expiries and reprices do not
+        if timers
+            .iter()
+            .any(|timer| !matches!(timer, Timer::PublishImbalance))
+            && let Err(error) = self.accept_command(&Command::Tick(now))
+        {
+            // Not applied, so they fire again on the next tick
+            for timer in timers {
+                self.timers.schedule(now, timer);
+            }
+            return Err(error);
+        }
+        let mut expired: Vec<Arc<Order>> = Vec::new();
+        for timer in timers {
+            match timer {
+                // Entries of orders already filled or canceled are skipped here
+                Timer::Expire(order_id) => {
+                    if let Ok(mut expired_order) = self.remove_resting_order(order_id) {
+                        self.book_version += 1;
+                        expired_order.status = Status::Expired;
+                        let expired_order = Arc::new(expired_order);
+                        if !self.listeners.is_empty() {
+                            self.publish_cancel(&expired_order);
+                        }
+                        expired.push(expired_order);
+                    }
+                }
+                Timer::RepricePegs => {
+                    self.peg_timer_pending = false;
+                    self.reprice_pegged_orders();
+                    if let Some(interval) = self.config.peg_reprice_interval
+                        && !self.pegged_orders.is_empty()
+                    {
+                        let due = now + interval.as_millis() as i64;
+                        self.timers.schedule(due, Timer::RepricePegs);
+                        self.peg_timer_pending = true;
+                    }
+                }
+                Timer::PublishImbalance => {
+                    self.imbalance_timer_pending = false;
+                    if self.instrument_state != InstrumentState::Auction {
+                        continue;
+                    }
+                    if let Some(indicative) = self.indicative
+                        && !self.listeners.is_empty()
+                    {
+                        self.notify_listeners(|listener| {
+                            listener.on_auction_imbalance(&indicative)
+                        });
+                    }
+                    if let Some(interval) = self.config.imbalance_interval {
+                        let due = now + interval.as_millis() as i64;
+                        self.timers.schedule(due, Timer::PublishImbalance);
+                        self.imbalance_timer_pending = true;
+                    }
+                }
+            }
+        }
+        self.reprice_after_command();
+        self.debug_check_consistency();
+        Ok(expired)
+    }
+
+    /// Unlink a resting order from its price level, releasing the level if it becomes empty.
+    fn remove_resting_order(&mut self, order_id: OrderId) -> Result<Order, OrderBookError> {
+        let key = self
+            .orders
+            .get(&order_id)
+            .copied()
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        self.remove_resting_key(key)
+    }
+
+    // Like `remove_resting_order` for the order at `key`, which the pool finds by slot
+    fn remove_resting_key(&mut self, key: OrderKey) -> Result<Order, OrderBookError> {
+        let handle = self
+            .pool
+            .handle(key)
+            .ok_or(OrderBookError::StaleOrderKey { key })?;
+        let order = self
+            .pool
+            .remove(key)
+            .ok_or(OrderBookError::StaleOrderKey { key })?;
+        let order_id = order.order_id;
+        self.orders.remove(&order_id);
+        forget_owner(&mut self.owners, &order);
+        forget_client_order(&mut self.client_orders, &order);
+        self.pegged_orders.remove(&order_id);
+        self.auction_orders.remove(&order_id);
+
+        let price = order.price;
+        let side = order.side;
+        let price_level_ref = self
+            .ladder(side)
+            .get(price)
+            .copied()
+            .ok_or(OrderBookError::PriceLevelRefNotFound { price })?;
+
+        let displayed_before = self.displayed_volume_at(side, price);
+        let target_level = self.price_levels[price_level_ref.index]
+            .as_mut()
+            .ok_or(OrderBookError::PriceLevelNotFound { price })?;
+        target_level
+            .remove_order(handle, order.remaining_quantity, order.hidden)
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+
+        if target_level.is_empty() {
+            self.release_level(side, price);
+        }
+        self.displayed_level_changed(side, price, displayed_before);
+        Ok(order)
+    }
+
+    // Drop an empty level from its side and recycle its slot
+    fn release_level(&mut self, side: Side, price: Price) {
+        let level_ref = self.ladder_mut(side).remove(price);
+        if self.best_price(side) == Some(price) {
+            let next_best = self
+                .ladder(side)
+                .best()
+                .map(|(price, level_ref)| (price, *level_ref));
+            *self.best_level_mut(side) = next_best;
+        }
+        if let Some(level_ref) = level_ref {
+            self.price_levels[level_ref.index] = None;
+            self.free_indices.push_back(level_ref.index);
+        }
+    }
+
+    // Furthest opposite price an order on `side` may trade at under the sweep limit, None when
+    // it may go as deep as the book
+    fn sweep_bound(&self, side: Side) -> Option<Price> {
+        let opposite = side.opposite();
+        match self.config.sweep_limit? {
+            SweepLimit::Levels(levels) => self
+                .ladder(opposite)
+                .iter()
+                .nth(levels.max(1) - 1)
+                .map(|(price, _)| price),
+            SweepLimit::Ticks(ticks) => {
+                let touch = self.best_price(opposite)?;
+                let distance = ticks.saturating_mul(self.config.tick_size);
+                Some(match side {
+                    Side::Buy => touch.saturating_add(distance),
+                    Side::Sell => touch.saturating_sub(distance),
+                })
+            }
+        }
+    }
+
+    // Furthest opposite price `order` may trade at, the tighter of the sweep limit and its
+    // protection price
+    fn match_bound(&self, order: &Order) -> Option<Price> {
+        let sweep_bound = self.sweep_bound(order.side);
+        match (sweep_bound, order.protection_price) {
+            (Some(sweep_bound), Some(protection_price)) => Some(match order.side {
+                Side::Buy => sweep_bound.min(protection_price),
+                Side::Sell => sweep_bound.max(protection_price),
+            }),
+            (bound, None) | (None, bound) => bound,
+        }
+    }
+
+    fn match_order(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        let mut trades: Vec<Trade> = Vec::with_capacity(self.orders.len());
+        let bound = self.match_bound(order);
+        let order_price: Price = order.price;
+        let mut remaining_quantity: Quantity = order.remaining_quantity;
+        let order_type: OrderType = order.order_type;
+
+        match order.side {
+            Side::Buy => {
+                while remaining_quantity > 0 {
+                    let best_ask = if let Some(price) = self.best_price(Side::Sell) {
+                        price
+                    } else {
+                        // Price level does not exist -> break matching
+                        break;
+                    };
+                    if !within_sweep(Side::Buy, best_ask, bound) {
+                        break;
+                    }
+
+                    if order_price >= best_ask || order_type == OrderType::MarketOrder {
+                        let filled =
+                            self.match_at_level(best_ask, order, remaining_quantity, &mut trades)?;
+                        if filled == 0 {
+                            break;
+                        }
+                        remaining_quantity = remaining_quantity.saturating_sub(filled);
+                    } else {
+                        break;
+                    };
+                    // sleep 0.5s for debug purpose
+                    // thread::sleep(Duration::from_millis(500));
+                }
+            }
+            Side::Sell => {
+                while remaining_quantity > 0 {
+                    let best_bid = if let Some(price) = self.best_price(Side::Buy) {
+                        price
+                    } else {
+                        // Price level does not exist -> break matching
+                        break;
+                    };
+                    if !within_sweep(Side::Sell, best_bid, bound) {
+                        break;
+                    }
+
+                    if order_price <= best_bid || order_type == OrderType::MarketOrder {
+                        let filled =
+                            self.match_at_level(best_bid, order, remaining_quantity, &mut trades)?;
+                        if filled == 0 {
+                            break;
+                        }
+                        remaining_quantity = remaining_quantity.saturating_sub(filled);
+                    } else {
+                        break;
+                    };
+                    // sleep 0.5s for debug purpose
+                    // thread::sleep(Duration::from_millis(500));
+                }
+            }
+        }
+        Ok(trades)
+    }
+
+    // Match against one opposite level under the configured allocation policy, returning the
+    // quantity filled. One iteration of the matching loop.
+    #[cfg_attr(
+        feature = "instrument",
+        tracing::instrument(
+            name = "match_level",
+            level = "trace",
+            skip_all,
+            fields(order_id = %incoming_order.order_id, price = price, max_quantity = max_quantity)
+        )
+    )]
+    fn match_at_level(
+        &mut self,
+        price: Price,
+        incoming_order: &Arc<Order>,
+        max_quantity: Quantity,
+        trades: &mut Vec<Trade>,
+    ) -> Result<Quantity, OrderBookError> {
+        if self.match_policy.time_priority() {
+            let trade = self.match_at_price_level_optimized(price, incoming_order, max_quantity)?;
+            let quantity = trade.quantity;
+            trades.push(trade);
+            return Ok(quantity);
+        }
+        Ok(self.match_allocated(price, incoming_order, max_quantity, trades))
+    }
+
+    // Fill the level at `price` as the match policy allocates `max_quantity` across it
+    fn match_allocated(
+        &mut self,
+        price: Price,
+        incoming_order: &Arc<Order>,
+        max_quantity: Quantity,
+        trades: &mut Vec<Trade>,
+    ) -> Quantity {
+        let resting_side = incoming_order.side.opposite();
+        let Some(level) = self.resting_level(resting_side, price) else {
+            return 0;
+        };
+        let resting: Vec<(OrderId, Quantity, Option<OrderTag>)> = level
+            .iter()
+            .filter_map(|key| self.pool.get(key))
+            .map(|order| (order.order_id, order.remaining_quantity, order.tag.clone()))
+            .collect();
+        let quantities: Vec<Quantity> = resting.iter().map(|(_, quantity, _)| *quantity).collect();
+        let mut allocations = self.match_policy.allocate(&quantities, max_quantity);
+        // A policy that allocates nothing of a crossing order would leave the book crossed once
+        // the order rests, so the level is filled in time priority instead. What a policy leaves
+        // over otherwise is offered to it again by the matching loop.
+        if allocations.iter().all(|allocation| *allocation == 0) {
+            allocations = Fifo.allocate(&quantities, max_quantity);
+        }
+
+        let mut filled: Quantity = 0;
+        for ((order_id, remaining, tag), allocation) in resting.into_iter().zip(allocations) {
+            // Whatever the policy returns, no order or level is overfilled
+            let allocation = allocation.min(remaining).min(max_quantity - filled);
+            if allocation == 0 || self.fill_resting_order(order_id, allocation).is_none() {
+                continue;
+            }
+            filled += allocation;
+            let trade = self.taker_trade(incoming_order, order_id, tag, price, allocation);
+            trades.push(trade);
+        }
+        filled
+    }
+
+    // Execute `quantity` of a resting order wherever it sits in its queue, keeping its position
+    fn fill_resting_order(&mut self, order_id: OrderId, quantity: Quantity) -> Option<()> {
+        let key = *self.orders.get(&order_id)?;
+        if !self.pool.get(key)?.hidden {
+            self.record_book_event(BookEvent::Execute {
+                order_id,
+                executed_quantity: quantity,
+                match_id: self.sequence + 1,
+            });
+        }
+        let resting_order = self.pool.get_mut(key)?;
+        if quantity >= resting_order.remaining_quantity {
+            let mut filled_order = self.remove_resting_order(order_id).ok()?;
+            if !self.listeners.is_empty() {
+                fill_in_place(&mut filled_order, quantity);
+                let price = filled_order.price;
+                let maker_fill = ExecutionReport::fill(&filled_order, quantity, price);
+                self.maker_fills.push_back(maker_fill);
+            }
+            return Some(());
+        }
+
+        // Filled in place, the order keeps its slot and its queue position
+        fill_in_place(resting_order, quantity);
+        if !self.listeners.is_empty() {
+            let price = resting_order.price;
+            let maker_fill = ExecutionReport::fill(resting_order, quantity, price);
+            self.maker_fills.push_back(maker_fill);
+        }
+        let (side, price, hidden) = (
+            resting_order.side,
+            resting_order.price,
+            resting_order.hidden,
+        );
+        let displayed_before = self.displayed_volume_at(side, price);
+        let level_ref = *self.ladder(side).get(price)?;
+        self.price_levels[level_ref.index]
+            .as_mut()?
+            .fill(quantity, hidden);
+        self.displayed_level_changed(side, price, displayed_before);
+        Some(())
+    }
+
+    fn match_at_price_level_optimized(
+        &mut self,
+        best_price: Price,
+        incoming_order: &Arc<Order>,
+        max_quantity: Quantity,
+    ) -> Result<Trade, OrderBookError> {
+        let (resting_order_id, trade_quantity, resting_tag) =
+            self.fill_front_order(incoming_order.side.opposite(), best_price, max_quantity)?;
+
+        Ok(self.taker_trade(
+            incoming_order,
+            resting_order_id,
+            resting_tag,
+            best_price,
+            trade_quantity,
+        ))
+    }
+
+    // Trade between an incoming order and a resting one, sides and fees assigned accordingly
+    fn taker_trade(
+        &mut self,
+        taker: &Order,
+        maker_order_id: OrderId,
+        maker_tag: Option<OrderTag>,
+        price: Price,
+        quantity: Quantity,
+    ) -> Trade {
+        let (bid_order_id, ask_order_id) = match taker.side {
+            Side::Buy => (taker.order_id, maker_order_id),
+            Side::Sell => (maker_order_id, taker.order_id),
+        };
+        let (bid_tag, ask_tag) = match taker.side {
+            Side::Buy => (taker.tag.clone(), maker_tag),
+            Side::Sell => (maker_tag, taker.tag.clone()),
+        };
+        let fees = self.config.fee_schedule;
+        let trade_id = self.next_trade_id();
+        Trade::with_id(trade_id, bid_order_id, ask_order_id, price, quantity)
+            .with_fees(
+                fees.maker_fee(price, quantity),
+                fees.taker_fee(price, quantity),
+            )
+            .with_aggressor(taker.side)
+            .with_timestamp(self.command_time)
+            .with_sequence(self.next_sequence())
+            .with_tags(bid_tag, ask_tag)
+    }
+
+    /// Execute up to `max_quantity` against the front order of the `side` level at `price`.
+    /// Returns the front order's id, the filled quantity and the order's tag.
+    fn fill_front_order(
+        &mut self,
+        side: Side,
+        price: Price,
+        max_quantity: Quantity,
+    ) -> Result<(OrderId, Quantity, Option<OrderTag>), OrderBookError> {
+        let displayed_before = self.displayed_volume_at(side, price);
+        let level_ref = self
+            .level_ref(side, price)
+            .ok_or(OrderBookError::PriceLevelRefNotFound { price })?;
+        let price_level = self
+            .price_levels
+            .get_mut(level_ref.index)
+            .and_then(Option::as_mut)
+            .ok_or(OrderBookError::PriceLevelNotFound { price })?;
+
+        let key = price_level
+            .front()
+            .ok_or(OrderBookError::PriceLevelNotFound { price })?;
+        let resting_order = self
+            .pool
+            .get_mut(key)
+            .ok_or(OrderBookError::OrderKeyNotFound { price })?;
+        let order_id = resting_order.order_id;
+        let hidden = resting_order.hidden;
+        let tag = resting_order.tag.clone();
+        let fill_quantity = max_quantity.min(resting_order.remaining_quantity);
+        if !hidden && !self.listeners.is_empty() {
+            self.book_events.push(BookEvent::Execute {
+                order_id,
+                executed_quantity: fill_quantity,
+                match_id: self.sequence + 1,
+            });
+        }
+
+        if fill_quantity == resting_order.remaining_quantity {
+            // Full fill - remove order
+            price_level.pop_front(fill_quantity, hidden);
+            self.orders.remove(&order_id);
+            self.pegged_orders.remove(&order_id);
+            self.auction_orders.remove(&order_id);
+            if let Some(mut filled_order) = self.pool.remove(key) {
+                forget_owner(&mut self.owners, &filled_order);
+                forget_client_order(&mut self.client_orders, &filled_order);
+                if !self.listeners.is_empty() {
+                    fill_in_place(&mut filled_order, fill_quantity);
+                    let maker_fill = ExecutionReport::fill(&filled_order, fill_quantity, price);
+                    self.maker_fills.push_back(maker_fill);
+                }
+            }
+        } else {
+            // Partial fill - update the pooled order, its queue node stays where it is
+            fill_in_place(resting_order, fill_quantity);
+            price_level.fill(fill_quantity, hidden);
+            if !self.listeners.is_empty() {
+                let maker_fill = ExecutionReport::fill(resting_order, fill_quantity, price);
+                self.maker_fills.push_back(maker_fill);
+            }
+        }
+
+        if price_level.is_empty() {
+            self.release_level(side, price);
+        }
+        self.displayed_level_changed(side, price, displayed_before);
+
+        Ok((order_id, fill_quantity, tag))
+    }
+
+    fn match_and_add_to_book(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        let trades: Vec<Trade> = self.match_order(order)?;
+
+        let overflow = || OrderBookError::QuantityOverflow {
+            price: order.price,
+            quantity: order.remaining_quantity,
+        };
+        let traded_quantity = trades
+            .iter()
+            .try_fold(0, |traded: Quantity, trade| {
+                traded.checked_add(trade.quantity)
+            })
+            .ok_or_else(overflow)?;
+        let remaining_quantity = order
+            .remaining_quantity
+            .checked_sub(traded_quantity)
+            .ok_or_else(overflow)?;
+
+        // Stopped by the sweep limit or protection price with opposite orders still in reach
+        let bounded = self.config.sweep_limit.is_some() || order.protection_price.is_some();
+        if remaining_quantity > 0 && bounded && self.would_cross(order) {
+            debug!(
+                order_id = %order.order_id,
+                remaining_quantity,
+                "match bound reached, remainder canceled"
+            );
+            return Ok(trades);
+        }
+        if remaining_quantity > 0 {
+            let mut remaining_order = order.as_ref().clone();
+            remaining_order.remaining_quantity = remaining_quantity;
+            if traded_quantity > 0 {
+                remaining_order.executed_quantity = order
+                    .executed_quantity
+                    .checked_add(traded_quantity)
+                    .ok_or_else(overflow)?;
+                remaining_order.status = Status::PartiallyFilled;
+            }
+            self.rest_order(remaining_order)?;
+        }
+
+        Ok(trades)
+    }
+
+    fn match_market(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        let aggressive_price = match order.side {
+            Side::Buy => Price::MAX,  // buy at any price
+            Side::Sell => Price::MIN, // sell at any price, negative prices included
+        };
+
+        let mut order_arc = order.as_ref().clone();
+        order_arc.price = aggressive_price;
+        self.match_order(&Arc::new(order_arc))
+    }
+
+    fn match_fill_or_kill(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        let available_quantity: Quantity = self.executable_quantity(order);
+
+        if available_quantity < order.remaining_quantity {
+            debug!(
+                order_id = %order.order_id,
+                available_quantity,
+                "FOK canceled, insufficient quantity"
+            );
+            Ok(Vec::new())
+        } else {
+            self.match_order(order)
+        }
+    }
+
+    /// Run the call auction: execute every crossing order at the single equilibrium price that
+    /// maximizes executed volume, in price-time priority. The instrument state is left unchanged,
+    /// move it back to `Active` to resume continuous matching.
+    pub fn uncross(&mut self) -> Result<Vec<Trade>, OrderBookError> {
+        if self.equilibrium().is_none() {
+            return Ok(Vec::new());
+        }
+        self.accept_command(&Command::Uncross)?;
+
+        let mut trades: Vec<Trade> = Vec::new();
+        let mut touched_levels: Vec<(Side, Price)> = Vec::new();
+        // Cumulative volumes saturate at `Quantity::MAX`, crossing beyond that takes more rounds
+        while let Some(IndicativeUncross { price, volume, .. }) = self.equilibrium() {
+            let executed = trades.len();
+            let mut remaining = volume;
+            while remaining > 0 {
+                let (Some(bid_price), Some(ask_price)) =
+                    (self.best_price(Side::Buy), self.best_price(Side::Sell))
+                else {
+                    break;
+                };
+                let front_order = |side: Side, price: Price| {
+                    let key = self.resting_level(side, price)?.front()?;
+                    self.pool
+                        .get(key)
+                        .map(|order| (order.order_id, order.remaining_quantity))
+                };
+                let (Some((bid_order_id, bid_quantity)), Some((ask_order_id, ask_quantity))) = (
+                    front_order(Side::Buy, bid_price),
+                    front_order(Side::Sell, ask_price),
+                ) else {
+                    break;
+                };
+
+                let quantity = remaining.min(bid_quantity).min(ask_quantity);
+                let (_, _, bid_tag) = self.fill_front_order(Side::Buy, bid_price, quantity)?;
+                let (_, _, ask_tag) = self.fill_front_order(Side::Sell, ask_price, quantity)?;
+                // No aggressor in an auction, both sides pay the maker fee
+                let maker_fee = self.config.fee_schedule.maker_fee(price, quantity);
+                let trade_id = self.next_trade_id();
+                trades.push(
+                    Trade::with_id(trade_id, bid_order_id, ask_order_id, price, quantity)
+                        .with_fees(maker_fee, maker_fee)
+                        .with_timestamp(self.command_time)
+                        .with_sequence(self.next_sequence())
+                        .with_tags(bid_tag, ask_tag),
+                );
+                remaining -= quantity;
+
+                for level in [(Side::Buy, bid_price), (Side::Sell, ask_price)] {
+                    if !touched_levels.contains(&level) {
+                        touched_levels.push(level);
+                    }
+                }
+            }
+            if trades.len() == executed {
+                break;
+            }
+        }
+
+        self.book_version += 1;
+        for trade in trades.iter() {
+            self.record_trade(trade);
+        }
+        if !self.listeners.is_empty() {
+            for trade in trades.iter() {
+                self.notify_listeners(|listener| listener.on_trade(trade));
+                // Both sides rested, their fills were recorded bid first
+                for _ in 0..2 {
+                    if let Some(fill) = self.maker_fills.pop_front() {
+                        self.publish_execution_report(fill.with_trade(trade));
+                    }
+                }
+            }
+            for (side, price) in touched_levels {
+                self.publish_level_change(side, price);
+            }
+            self.publish_book_events();
+            self.publish_bbo();
+        }
+        self.refresh_indicative();
+        self.debug_check_consistency();
+        Ok(trades)
+    }
+
+    /// The price and volume the call auction would uncross at now, with the surplus left over,
+    /// kept up to date as orders arrive while the instrument is in `Auction`. None outside an
+    /// auction or while the collected orders do not cross.
+    pub fn indicative_uncross(&self) -> Option<IndicativeUncross> {
+        self.indicative
+    }
+
+    // Auction price and volume: the price executing the most volume, ties broken by the
+    // smallest surplus, then towards the side holding the surplus, then the lowest price.
+    // None if the book does not cross.
+    fn equilibrium(&self) -> Option<IndicativeUncross> {
+        let best_bid = self.best_price(Side::Buy)?;
+        let best_ask = self.best_price(Side::Sell)?;
+        if best_bid < best_ask {
+            return None;
+        }
+
+        let volume_at = |level_ref: &PriceLevelRef| {
+            self.price_levels[level_ref.index]
+                .as_ref()
+                .map_or(0, |level| level.volume())
+        };
+        let mut candidates: Vec<Price> = self
+            .bids
+            .iter_to(best_ask)
+            .map(|(price, _)| price)
+            .chain(self.asks.iter_to(best_bid).map(|(price, _)| price))
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let cumulative = |ladder: &Ladder<PriceLevelRef>, price: Price| {
+            ladder
+                .iter_to(price)
+                .map(|(_, r)| volume_at(r))
+                .fold(0, Quantity::saturating_add)
+        };
+        // (price, executable volume, demand, supply)
+        let crossings: Vec<(Price, Quantity, Quantity, Quantity)> = candidates
+            .into_iter()
+            .map(|price| {
+                let demand = cumulative(&self.bids, price);
+                let supply = cumulative(&self.asks, price);
+                (price, demand.min(supply), demand, supply)
+            })
+            .collect();
+
+        let max_volume = crossings.iter().map(|c| c.1).max()?;
+        let best_volume: Vec<_> = crossings.iter().filter(|c| c.1 == max_volume).collect();
+        let min_surplus = best_volume.iter().map(|c| c.2.abs_diff(c.3)).min()?;
+        let best_surplus: Vec<_> = best_volume
+            .into_iter()
+            .filter(|c| c.2.abs_diff(c.3) == min_surplus)
+            .collect();
+
+        let &&(price, volume, demand, supply) = if best_surplus.iter().all(|c| c.2 > c.3) {
+            best_surplus.iter().max_by_key(|c| c.0)?
+        } else {
+            best_surplus.iter().min_by_key(|c| c.0)?
+        };
+        Some(IndicativeUncross {
+            price,
+            volume,
+            surplus: demand.abs_diff(supply),
+            surplus_side: match demand.cmp(&supply) {
+                std::cmp::Ordering::Greater => Some(Side::Buy),
+                std::cmp::Ordering::Less => Some(Side::Sell),
+                std::cmp::Ordering::Equal => None,
+            },
+        })
+    }
+
+    // Pre-match check: would the order trade against the opposite side at its limit price?
+    fn would_cross(&self, order: &Order) -> bool {
+        match order.side {
+            Side::Buy => self
+                .get_best_ask()
+                .is_some_and(|best_ask| order.price >= best_ask),
+            Side::Sell => self
+                .get_best_bid()
+                .is_some_and(|best_bid| order.price <= best_bid),
+        }
+    }
+
+    // Price a post-only `order` rests at: its own unless it would cross, when the policy either
+    // rejects it or moves it one tick inside the opposite best
+    fn post_only_price(&self, order: &Order) -> Result<Price, OrderBookError> {
+        if !self.would_cross(order) {
+            return Ok(order.price);
+        }
+        match self.config.post_only_policy {
+            PostOnlyPolicy::Reject => Err(OrderBookError::PostOnlyWouldCross {
+                order_id: order.order_id,
+                price: order.price,
+            }),
+            PostOnlyPolicy::Reprice => {
+                // would_cross guarantees the opposite side is not empty
+                let price = self.tick_inside(order.side)?.unwrap_or(order.price);
+                // One tick away may fall outside the limits the order itself was checked against
+                self.check_price_band(price)?;
+                Ok(price)
+            }
+        }
+    }
+
+    // Validation has already turned away a crossing order or checked where it reprices to
+    fn add_post_only(&mut self, order: &Arc<Order>) -> Result<Vec<Trade>, OrderBookError> {
+        let mut resting_order = order.as_ref().clone();
+        resting_order.price = self.post_only_price(order)?;
+        self.rest_order(resting_order)?;
+        Ok(Vec::new())
+    }
+
+    // Opposite volume `order` could trade against right now, up to its limit price and the sweep
+    // limit
+    fn executable_quantity(&self, order: &Order) -> Quantity {
+        let bound = self.match_bound(order);
+        let opposite = self.ladder(order.side.opposite());
+        let level_refs: Box<dyn Iterator<Item = (Price, &PriceLevelRef)>> =
+            if order.order_type == OrderType::MarketOrder {
+                opposite.iter()
+            } else {
+                Box::new(opposite.iter_to(order.price))
+            };
+        level_refs
+            .take_while(|(price, _)| within_sweep(order.side, *price, bound))
+            .filter_map(|(_, level_ref)| self.price_levels[level_ref.index].as_ref())
+            .map(|level| level.volume())
+            .fold(0, Quantity::saturating_add)
+    }
+
+    // Whether the order's min qty, if any, could trade immediately; only meaningful for orders
+    // about to match, as orders collected for an auction do not
+    fn min_qty_available(&self, order: &Order) -> bool {
+        self.instrument_state == InstrumentState::Auction
+            || order
+                .min_qty
+                .is_none_or(|min_qty| self.executable_quantity(order) >= min_qty)
+    }
+
+    /// Status, fills and queue position of a resting order; None once it has left the book.
+    pub fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        let order = self.resting_order(order_id)?;
+        let level = self.resting_level(order.side, order.price)?;
+        let (queue_position, quantity_ahead) = level
+            .iter()
+            .filter_map(|key| self.pool.get(key))
+            .take_while(|ahead| ahead.order_id != order_id)
+            .fold((0, 0 as Quantity), |(position, quantity), ahead| {
+                (
+                    position + 1,
+                    quantity.saturating_add(ahead.remaining_quantity),
+                )
+            });
+        Some(OrderView {
+            order_id,
+            side: order.side,
+            price: order.price,
+            status: order.status,
+            original_quantity: order.original_quantity,
+            executed_quantity: order.executed_quantity,
+            remaining_quantity: order.remaining_quantity,
+            queue_position,
+            quantity_ahead,
+        })
+    }
+
+    /// Orders resting ahead of `order_id` at its price level, in priority order.
+    pub fn orders_ahead(&self, order_id: OrderId) -> Result<Vec<QueueEntry>, OrderBookError> {
+        let order = self
+            .resting_order(order_id)
+            .ok_or(OrderBookError::OrderNotFound { order_id })?;
+        let price = order.price;
+        let level = self
+            .resting_level(order.side, price)
+            .ok_or(OrderBookError::PriceLevelNotFound { price })?;
+
+        Ok(level
+            .iter()
+            .filter_map(|key| self.pool.get(key))
+            .take_while(|order| order.order_id != order_id)
+            .map(|order| QueueEntry {
+                order_id: order.order_id,
+                remaining_quantity: order.remaining_quantity,
+                timestamp: order.timestamp,
+            })
+            .collect())
+    }
+
+    fn resting_level(&self, side: Side, price: Price) -> Option<&PriceLevel> {
+        let level_ref = self.level_ref(side, price)?;
+        self.price_levels[level_ref.index].as_ref()
+    }
+
+    /// Copy every resting order, level by level in FIFO order.
+    pub fn snapshot(&self) -> BookSnapshot {
+        BookSnapshot {
+            timestamp: self.clock() / 1_000,
+            sequence: self.sequence,
+            instrument_state: self.instrument_state,
+            bids: self.collect_level_snapshots(self.bids.values()),
+            asks: self.collect_level_snapshots(self.asks.values()),
+        }
+    }
+
+    fn collect_level_snapshots<'a, I>(&self, level_refs: I) -> Vec<LevelSnapshot>
+    where
+        I: Iterator<Item = &'a PriceLevelRef>,
+    {
+        level_refs
+            .filter_map(|level_ref| self.price_levels[level_ref.index].as_ref())
+            .map(|level| LevelSnapshot {
+                price: level.price(),
+                orders: level
+                    .iter()
+                    .filter_map(|key| self.pool.get(key))
+                    .cloned()
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Rebuild a book from `snapshot` with the default config.
+    pub fn restore(snapshot: BookSnapshot) -> Result<Self, OrderBookError> {
+        Self::restore_with_config(snapshot, OrderBookConfig::default())
+    }
+
+    /// Rebuild a book from `snapshot`, failing on an invalid `config` or an order its ladder
+    /// cannot hold. A snapshot no book could have produced is refused with `SnapshotCorrupted`:
+    /// a duplicated order id, an order with nothing remaining or filed under the wrong side or
+    /// level, a level whose volume overflows, or a crossed book outside an auction.
+    pub fn restore_with_config(
+        snapshot: BookSnapshot,
+        config: OrderBookConfig,
+    ) -> Result<Self, OrderBookError> {
+        let mut book = Self::try_with_config(config)?;
+        let corrupted = |line: usize, reason: &str| OrderBookError::SnapshotCorrupted {
+            line,
+            reason: reason.to_string(),
+        };
+        let bid_orders: usize = snapshot.bids.iter().map(|level| level.orders.len()).sum();
+        let levels = snapshot
+            .bids
+            .into_iter()
+            .map(|level| (Side::Buy, level))
+            .chain(snapshot.asks.into_iter().map(|level| (Side::Sell, level)));
+        // The header takes the first line
+        let mut line = 1;
+        for (side, level) in levels {
+            for order in level.orders {
+                line += 1;
+                if order.side != side || order.price != level.price {
+                    return Err(corrupted(line, "order filed under another side or level"));
+                }
+                if order.remaining_quantity == 0 {
+                    return Err(corrupted(line, "order with nothing remaining"));
+                }
+                if book.orders.contains_key(&order.order_id) {
+                    return Err(corrupted(line, "duplicated order id"));
+                }
+                if book
+                    .check_level_room(side, order.price, order.remaining_quantity)
+                    .is_err()
+                {
+                    return Err(corrupted(line, "level volume overflows"));
+                }
+                // orders go straight onto the book, they rested without crossing when snapshotted
+                book.add_order_to_book(order)?;
+            }
+        }
+        // Only an auction rests crossed orders
+        if snapshot.instrument_state != InstrumentState::Auction
+            && let (Some(bid), Some(ask)) = (book.get_best_bid(), book.get_best_ask())
+            && bid >= ask
+        {
+            return Err(corrupted(bid_orders + 2, "crossed book outside an auction"));
+        }
+        book.sequence = snapshot.sequence;
+        // Taken over as it was rather than changed into, nothing is canceled or journaled
+        book.instrument_state = snapshot.instrument_state;
+        if book.instrument_state == InstrumentState::Auction {
+            book.schedule_imbalance();
+        }
+        book.refresh_indicative();
+        Ok(book)
+    }
+
+    /// Independent copy of the resting book, config and instrument state, for branching
+    /// what-if runs from the same point. Listeners and trade history are not carried over.
+    pub fn fork(&self) -> Result<Self, OrderBookError> {
+        let mut forked = Self::restore_with_config(self.snapshot(), self.config.clone())?;
+        forked.reference_price = self.reference_price;
+        forked.last_trade_price = self.last_trade_price;
+        forked.mark_price = self.mark_price;
+        forked.refresh_indicative();
+        Ok(forked)
+    }
+
+    /// Top `depth` bid and ask levels with their aggregated displayed volume. Levels holding
+    /// only hidden orders are left out.
+    pub fn get_depth(&self, depth: usize) -> OrderbookLevelInfos {
+        OrderbookLevelInfos {
+            bids: self.collect_level_infos(Side::Buy, depth),
+            asks: self.collect_level_infos(Side::Sell, depth),
+        }
+    }
+
+    /// The top `depth` levels as an aligned text ladder, asks above bids with the best prices
+    /// in the middle, and each side's volume summed from the touch outwards:
+    ///
+    /// ```text
+    /// bid total  bid  price  ask  ask total
+    ///                   102    5         11
+    ///                   101    6          6
+    ///         4    4    100
+    /// ```
+    pub fn render(&self, depth: usize) -> String {
+        let levels = self.get_depth(depth);
+        let cumulative = |levels: &[LevelInfo]| {
+            levels
+                .iter()
+                .scan(0, |total: &mut Quantity, level| {
+                    *total = total.saturating_add(level.volume);
+                    Some((level.price, level.volume, *total))
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut rows = vec![["bid total", "bid", "price", "ask", "ask total"].map(String::from)];
+        for (price, volume, total) in cumulative(&levels.asks).into_iter().rev() {
+            rows.push([
+                String::new(),
+                String::new(),
+                price.to_string(),
+                volume.to_string(),
+                total.to_string(),
+            ]);
+        }
+        for (price, volume, total) in cumulative(&levels.bids) {
+            rows.push([
+                total.to_string(),
+                volume.to_string(),
+                price.to_string(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+
+        let mut widths = [0; 5];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        rows.iter()
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .zip(widths)
+                    .map(|(cell, width)| format!("{:>width$}", cell))
+                    .collect();
+                cells.join("  ").trim_end().to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Like `get_depth`, with levels summed into buckets `bucket_size` wide (e.g. 5 ticks is
+    /// `5 * tick_size`), for depth charts and coarse liquidity on books with sparse levels. Bids
+    /// are filed under the bucket's lower bound and asks under its upper bound, so a bucket
+    /// never shows a better price than the levels in it. Up to `depth` buckets a side; a
+    /// `bucket_size` below 1 is treated as 1.
+    pub fn get_depth_bucketed(&self, bucket_size: Price, depth: usize) -> OrderbookLevelInfos {
+        OrderbookLevelInfos {
+            bids: self.collect_buckets(Side::Buy, bucket_size.max(1), depth),
+            asks: self.collect_buckets(Side::Sell, bucket_size.max(1), depth),
+        }
+    }
+
+    fn collect_buckets(&self, side: Side, bucket_size: Price, depth: usize) -> Vec<LevelInfo> {
+        let mut buckets: Vec<LevelInfo> = Vec::new();
+        for level in self.displayed_levels(side) {
+            let price = match side {
+                Side::Buy => level.price().div_euclid(bucket_size) * bucket_size,
+                Side::Sell => -(-level.price()).div_euclid(bucket_size) * bucket_size,
+            };
+            if let Some(bucket) = buckets.last_mut()
+                && bucket.price == price
+            {
+                bucket.volume = bucket.volume.saturating_add(level.displayed_volume());
+                continue;
+            }
+            if buckets.len() == depth {
+                break;
+            }
+            buckets.push(LevelInfo {
+                price,
+                volume: level.displayed_volume(),
+            });
+        }
+        buckets
+    }
+
+    fn collect_level_infos(&self, side: Side, depth: usize) -> Vec<LevelInfo> {
+        self.displayed_levels(side)
+            .take(depth)
+            .map(|level| level.get_level_info())
+            .collect()
+    }
+
+    // Levels of `side` showing some volume to the market, best first
+    fn displayed_levels(&self, side: Side) -> impl Iterator<Item = &PriceLevel> + '_ {
+        self.ladder(side)
+            .values()
+            .filter_map(|level_ref| self.price_levels[level_ref.index].as_ref())
+            .filter(|level| level.displayed_volume() > 0)
+    }
+
+    /// Best bid price, hidden orders included; `bbo` only shows displayed prices.
+    pub fn get_best_bid(&self) -> Option<Price> {
+        self.best_price(Side::Buy)
+    }
+
+    /// Best ask price, hidden orders included; `bbo` only shows displayed prices.
+    pub fn get_best_ask(&self) -> Option<Price> {
+        self.best_price(Side::Sell)
+    }
+
+    /// Bid and ask volume in the top `OrderBookConfig::imbalance_levels` levels of each side.
+    pub fn imbalance(&self) -> Imbalance {
+        let bids = self
+            .analytics
+            .top(Side::Buy, |levels| self.scan_top(Side::Buy, levels));
+        let asks = self
+            .analytics
+            .top(Side::Sell, |levels| self.scan_top(Side::Sell, levels));
+        Imbalance {
+            levels: self.analytics.levels(),
+            bid_volume: bids.volume,
+            ask_volume: asks.volume,
+        }
+    }
+
+    /// Midpoint of the displayed best bid and ask, None unless both sides quote.
+    pub fn mid_price(&self) -> Option<f64> {
+        let bbo = self.bbo();
+        Some((bbo.bid_price? as f64 + bbo.ask_price? as f64) / 2.0)
+    }
+
+    /// Best bid and ask weighted by the volume resting opposite, None unless both sides quote.
+    pub fn microprice(&self) -> Option<f64> {
+        let bbo = self.bbo();
+        analytics::microprice(bbo.bid_price?, bbo.bid_qty, bbo.ask_price?, bbo.ask_qty)
+    }
+
+    fn scan_top(&self, side: Side, levels: usize) -> SideTop {
+        let mut top = SideTop {
+            volume: 0,
+            level_count: 0,
+            worst_price: None,
+        };
+        for level in self.displayed_levels(side).take(levels) {
+            top.volume = top.volume.saturating_add(level.displayed_volume());
+            top.level_count += 1;
+            top.worst_price = Some(level.price());
+        }
+        top
+    }
+
+    /// Check the ladders, price levels, order pool and order index against each other, panicking
+    /// on the first disagreement. Walks the whole book, so only meant for debugging and tests.
+    #[cfg(any(debug_assertions, feature = "consistency-checks"))]
+    pub fn assert_consistent(&self) {
+        // Orders collected for an auction may cross until it uncrosses
+        if self.instrument_state != InstrumentState::Auction
+            && let (Some(best_bid), Some(best_ask)) = (self.get_best_bid(), self.get_best_ask())
+        {
+            assert!(best_bid < best_ask, "crossed book {best_bid} / {best_ask}");
+        }
+
+        let mut queued = 0;
+        let mut live_indices: HashSet<usize> = HashSet::new();
+        for side in [Side::Buy, Side::Sell] {
+            assert_eq!(
+                self.best_level(side)
+                    .map(|(price, level_ref)| (price, level_ref.index)),
+                self.ladder(side)
+                    .best()
+                    .map(|(price, level_ref)| (price, level_ref.index)),
+                "stale {side:?} top of book cache"
+            );
+            for (price, level_ref) in self.ladder(side).iter() {
+                assert!(
+                    live_indices.insert(level_ref.index),
+                    "level slot {} used twice",
+                    level_ref.index
+                );
+                let level = self.price_levels[level_ref.index]
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("{side:?} {price} points at a released level"));
+                assert_eq!(
+                    level.price(),
+                    price,
+                    "{side:?} level filed under the wrong price"
+                );
+                assert!(
+                    !level.is_empty(),
+                    "empty {side:?} level {price} left on the ladder"
+                );
+
+                let mut volume = 0;
+                let mut hidden_volume = 0;
+                let mut count = 0;
+                for key in level.iter() {
+                    let order = self
+                        .pool
+                        .get(key)
+                        .unwrap_or_else(|| panic!("{side:?} level {price} holds a stale key"));
+                    assert_eq!(
+                        (order.side, order.price),
+                        (side, price),
+                        "order {} queued at the wrong level",
+                        order.order_id
+                    );
+                    assert_eq!(
+                        self.orders.get(&order.order_id),
+                        Some(&key),
+                        "order {} missing from the order index",
+                        order.order_id
+                    );
+                    assert!(
+                        self.pool.handle(key).is_some(),
+                        "order {} has no queue position",
+                        order.order_id
+                    );
+                    assert!(order.remaining_quantity > 0, "filled order left resting");
+                    volume += order.remaining_quantity;
+                    if order.hidden {
+                        hidden_volume += order.remaining_quantity;
+                    }
+                    count += 1;
+                }
+                assert_eq!(level.volume(), volume, "{side:?} level {price} volume");
+                assert_eq!(
+                    level.volume() - level.displayed_volume(),
+                    hidden_volume,
+                    "{side:?} level {price} hidden volume"
+                );
+                assert_eq!(
+                    level.order_count(),
+                    count,
+                    "{side:?} level {price} order count"
+                );
+                queued += count;
+            }
+        }
+        assert_eq!(queued, self.orders.len(), "indexed orders not on any level");
+        assert_eq!(
+            self.pool.len(),
+            self.orders.len(),
+            "pooled orders not indexed"
+        );
+        let owned: usize = self.owners.values().map(IdSet::len).sum();
+        assert_eq!(owned, self.orders.len(), "owner index out of step");
+        for (owner, client_orders) in self.client_orders.iter() {
+            for (client_order_id, order_id) in client_orders.iter() {
+                let order = self
+                    .resting_order(*order_id)
+                    .expect("client order id of an order no longer resting");
+                assert_eq!(order.owner, *owner, "client order id under the wrong owner");
+                assert_eq!(
+                    order.client_order_id.as_ref(),
+                    Some(client_order_id),
+                    "client order id index out of step"
+                );
+            }
+        }
+
+        let mut free: HashSet<usize> = HashSet::new();
+        for &index in self.free_indices.iter() {
+            assert!(free.insert(index), "free level slot {index} listed twice");
+            assert!(
+                !live_indices.contains(&index),
+                "live level slot {index} marked free"
+            );
+            assert!(
+                self.price_levels[index].is_none(),
+                "free level slot {index} still holds a level"
+            );
+        }
+    }
+
+    // Tests check the whole book after every command
+    fn debug_check_consistency(&self) {
+        #[cfg(test)]
+        self.assert_consistent();
+    }
+
+    /// Current best displayed bid and offer with their displayed volumes.
+    pub fn bbo(&self) -> BboUpdate {
+        let best = |side: Side| {
+            self.displayed_levels(side)
+                .next()
+                .map(|level| level.get_level_info())
+        };
+        let (bid, ask) = (best(Side::Buy), best(Side::Sell));
+        BboUpdate {
+            bid_price: bid.as_ref().map(|level| level.price),
+            bid_qty: bid.map_or(0, |level| level.volume),
+            ask_price: ask.as_ref().map(|level| level.price),
+            ask_qty: ask.map_or(0, |level| level.volume),
+            seq: self.sequence,
+        }
+    }
+}
+
+// Result of an operator or timer command, which has no order of its own
+fn operator_result(trades: Vec<Trade>) -> MatchResult {
+    MatchResult {
+        order_id: OrderId::nil(),
+        trades,
+        remaining: 0,
+        status: Status::New,
+        key: None,
+    }
+}
+
+// Execute `quantity` of a resting order that stays on the book
+fn fill_in_place(order: &mut Order, quantity: Quantity) {
+    order.remaining_quantity = order.remaining_quantity.saturating_sub(quantity);
+    order.executed_quantity = order.executed_quantity.saturating_add(quantity);
+    order.status = Status::PartiallyFilled;
+}
+
+// Drop an order that left the book from the by-owner index
+fn forget_owner(owners: &mut HashMap<ParticipantId, IdSet>, order: &Order) {
+    if let Some(order_ids) = owners.get_mut(&order.owner) {
+        order_ids.remove(&order.order_id);
+        if order_ids.is_empty() {
+            owners.remove(&order.owner);
+        }
+    }
+}
+
+// Whether an opposite level at `price` is within the sweep `bound` of an order on `side`
+fn within_sweep(side: Side, price: Price, bound: Option<Price>) -> bool {
+    bound.is_none_or(|bound| match side {
+        Side::Buy => price <= bound,
+        Side::Sell => price >= bound,
+    })
+}
+
+// Drop an order that left the book from the by-client-order-id index
+fn forget_client_order(
+    client_orders: &mut HashMap<ParticipantId, HashMap<ClientOrderId, OrderId>>,
+    order: &Order,
+) {
+    let Some(client_order_id) = order.client_order_id.as_ref() else {
+        return;
+    };
+    if let Some(order_ids) = client_orders.get_mut(&order.owner) {
+        order_ids.remove(client_order_id);
+        if order_ids.is_empty() {
+            client_orders.remove(&order.owner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod orderbook_tests {
+    use super::*;
+    use crate::market_data::l3::L3Book;
+    use crate::orderbook::config::{AllocationPolicy, HiddenPriority, MinQtyPolicy, PriceBand};
+    use crate::orderbook::fees::FeeSchedule;
+    use crate::orderbook::id::MonotonicIdGenerator;
+    use crate::orderbook::ladder::MAX_TICK_ARRAY_SLOTS;
+    use crate::orderbook::order_queue::QueueBackendKind;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl OrderBookListener for RecordingListener {
+        fn on_accept(&mut self, order: &Arc<Order>) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("accept {:?}", order.side));
+        }
+
+        fn on_trade(&mut self, trade: &Trade) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("trade {}@{}", trade.quantity, trade.price));
+        }
+
+        fn on_cancel(&mut self, order: &Arc<Order>) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("cancel {:?}", order.status));
+        }
+
+        fn on_reject(&mut self, _order: &Arc<Order>, _error: &OrderBookError) {
+            self.events.lock().unwrap().push("reject".to_string());
+        }
+
+        fn on_level_change(&mut self, side: Side, level: &LevelInfo) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("level {:?} {}x{}", side, level.volume, level.price));
+        }
+    }
+
+    #[test]
+    fn check_add_new_limit_order() {
+        let mut test_ob = OrderBook::new();
+        let limit_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        let result = test_ob.add_order(&limit_order).unwrap();
+        assert_eq!(result.trades, Vec::new());
+        assert_eq!(result.remaining, 10);
+        assert_eq!(result.status, Status::New);
+    }
+
+    #[test]
+    fn check_add_new_limit_order_and_later_comsumed_by_market_order() {
+        let mut test_ob = OrderBook::new();
+        let limit_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        let market_order = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 10, 10));
+
+        // limit order first arrives to the OB
+        {
+            test_ob.add_order(&limit_order).unwrap();
+        }
+        // Market Order arrives later to consume the OB
+        let result = test_ob.add_order(&market_order).unwrap();
+        assert_eq!(result.status, Status::Filled);
+        let trades = result.trades;
+        assert_eq!(trades.first().unwrap().price, 10);
+        assert_eq!(trades.first().unwrap().quantity, 10);
+        assert_eq!(trades.len(), 1);
+    }
+
+    #[test]
+    fn check_get_best_bid_ask_in_multiple_limit_orders() {
+        let mut test_ob = OrderBook::new();
+        {
+            let buy_order_1 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 10));
+            let buy_order_2 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 8, 5));
+            let buy_order_3 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 7, 3));
+
+            test_ob.add_order(&buy_order_1).unwrap();
+            test_ob.add_order(&buy_order_2).unwrap();
+            test_ob.add_order(&buy_order_3).unwrap();
+        }
+
+        {
+            let sell_order_1 = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+            let sell_order_2 = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 5));
+            let sell_order_3 = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 12, 3));
+
+            test_ob.add_order(&sell_order_1).unwrap();
+            test_ob.add_order(&sell_order_2).unwrap();
+            test_ob.add_order(&sell_order_3).unwrap();
+        }
+        assert_eq!(test_ob.get_best_bid().unwrap(), 9);
+        assert_eq!(test_ob.get_best_ask().unwrap(), 10);
+    }
+
+    #[test]
+    fn check_add_multiples_limit_order_and_later_comsumed_by_an_market_order() {
+        let mut test_ob = OrderBook::new();
+        let market_order = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 0, 10));
+
+        // limit order first arrives to the OB
+        {
+            let buy_order_1 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 3));
+            let buy_order_2 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 8, 5));
+            let buy_order_3 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 7, 10));
+
+            test_ob.add_order(&buy_order_1).unwrap();
+            test_ob.add_order(&buy_order_2).unwrap();
+            test_ob.add_order(&buy_order_3).unwrap();
+        }
+        // Market Order arrives later to consume the OB
+        let trades = test_ob.add_order(&market_order).unwrap().trades;
+        assert_eq!(trades.len(), 3);
+    }
+
+    #[test]
+    fn check_cancel_sell_order_after_partial_fill() {
+        let mut test_ob = OrderBook::new();
+        let sell_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 4));
+
+        test_ob.add_order(&sell_order).unwrap();
+        test_ob.add_order(&buy_order).unwrap();
+        test_ob.cancel_order(sell_order.order_id).unwrap();
+
+        assert_eq!(test_ob.get_best_ask(), None);
+        assert!(test_ob.cancel_order(sell_order.order_id).is_err());
+    }
+
+    #[test]
+    fn check_negative_prices_match_like_any_other() {
+        let tick_array = LadderKind::TickArray {
+            min_price: -50,
+            max_price: 50,
+        };
+        for ladder in [LadderKind::BTree, tick_array] {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                ladder,
+                ..OrderBookConfig::default()
+            });
+            let order = |order_type, side, price, quantity| {
+                Arc::new(Order::new(order_type, side, price, quantity))
+            };
+            for (side, price) in [(Side::Buy, -12), (Side::Buy, -10), (Side::Sell, -5)] {
+                let resting = order(OrderType::LimitOrder, side, price, 5);
+                test_ob.add_order(&resting).unwrap();
+            }
+            assert_eq!(test_ob.get_best_bid(), Some(-10));
+            assert_eq!(test_ob.get_best_ask(), Some(-5));
+            let midpoint = Arc::new(
+                Order::new(OrderType::LimitOrder, Side::Buy, 0, 1).with_peg(PegType::Midpoint, 0),
+            );
+            test_ob.add_order(&midpoint).unwrap();
+            assert_eq!(test_ob.get_order(midpoint.order_id).unwrap().price, -8);
+
+            // a market sell walks the bids down through negative prices
+            let result = test_ob
+                .add_order(&order(OrderType::MarketOrder, Side::Sell, 0, 8))
+                .unwrap();
+            let fills: Vec<(Price, Quantity)> = result
+                .trades
+                .iter()
+                .map(|trade| (trade.price(), trade.quantity()))
+                .collect();
+            assert_eq!(fills, vec![(-8, 1), (-10, 5), (-12, 2)], "{:?}", ladder);
+            assert_eq!(test_ob.get_best_bid(), Some(-12));
+        }
+    }
+
+    #[test]
+    fn check_level_volume_overflow_is_rejected() {
+        let mut test_ob = OrderBook::new();
+        let limit =
+            |side, quantity| Arc::new(Order::new(OrderType::LimitOrder, side, 100, quantity));
+        test_ob
+            .add_order(&limit(Side::Buy, Quantity::MAX - 1))
+            .unwrap();
+        test_ob.add_order(&limit(Side::Buy, 1)).unwrap();
+        assert!(matches!(
+            test_ob.add_order(&limit(Side::Buy, 1)),
+            Err(OrderBookError::QuantityOverflow {
+                price: 100,
+                quantity: 1
+            })
+        ));
+        assert_eq!(test_ob.get_depth(1).bids[0].volume, Quantity::MAX);
+
+        // the level drains back down without wrapping
+        let result = test_ob.add_order(&limit(Side::Sell, 5)).unwrap();
+        assert_eq!(result.status, Status::Filled);
+        assert_eq!(test_ob.get_depth(1).bids[0].volume, Quantity::MAX - 5);
+    }
+
+    #[test]
+    fn check_expire_due_cancels_expired_gtd_orders() {
+        let mut test_ob = OrderBook::new();
+        let early_order = Arc::new(Order::new(OrderType::GoodTillDate(100), Side::Buy, 9, 10));
+        let late_order = Arc::new(Order::new(OrderType::GoodTillDate(200), Side::Buy, 8, 10));
+        let gtc_order = Arc::new(Order::new(OrderType::GoodTillCancel, Side::Buy, 7, 10));
+
+        test_ob.add_order(&early_order).unwrap();
+        test_ob.add_order(&late_order).unwrap();
+        test_ob.add_order(&gtc_order).unwrap();
+
+        assert!(test_ob.expire_due(99).unwrap().is_empty());
+
+        let expired = test_ob.expire_due(150).unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].order_id, early_order.order_id);
+        assert_eq!(expired[0].status, Status::Expired);
+        assert_eq!(test_ob.get_best_bid().unwrap(), 8);
+
+        // canceled GTD orders are skipped when their expiry comes due
+        test_ob.cancel_order(late_order.order_id).unwrap();
+        assert!(test_ob.expire_due(300).unwrap().is_empty());
+        assert_eq!(test_ob.get_best_bid().unwrap(), 7);
+    }
+
+    #[test]
+    fn check_post_only_order_rejected_when_crossing() {
+        let mut test_ob = OrderBook::new();
+        let sell_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        test_ob.add_order(&sell_order).unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        test_ob.add_listener(Box::new(RecordingListener {
+            events: events.clone(),
+        }));
+        let sequence = test_ob.sequence();
+
+        let crossing_order = Arc::new(Order::new(OrderType::PostOnly, Side::Buy, 10, 5));
+        assert!(matches!(
+            test_ob.add_order(&crossing_order),
+            Err(OrderBookError::PostOnlyWouldCross { .. })
+        ));
+        // Turned away before acceptance: not numbered, journaled or reported as new
+        assert_eq!(test_ob.sequence(), sequence);
+        assert_eq!(*events.lock().unwrap(), vec!["reject".to_string()]);
+        events.lock().unwrap().clear();
+
+        let passive_order = Arc::new(Order::new(OrderType::PostOnly, Side::Buy, 9, 5));
+        assert_eq!(
+            test_ob.add_order(&passive_order).unwrap().trades,
+            Vec::new()
+        );
+        assert_eq!(test_ob.get_best_bid().unwrap(), 9);
+        assert_eq!(test_ob.get_best_ask().unwrap(), 10);
+    }
+
+    #[test]
+    fn check_post_only_order_repriced_when_crossing() {
+        let config = OrderBookConfig {
+            post_only_policy: PostOnlyPolicy::Reprice,
+            ..OrderBookConfig::default()
+        };
+        let mut test_ob = OrderBook::with_config(config);
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        test_ob.add_order(&buy_order).unwrap();
+
+        let crossing_order = Arc::new(Order::new(OrderType::PostOnly, Side::Sell, 8, 5));
+        assert_eq!(
+            test_ob.add_order(&crossing_order).unwrap().trades,
+            Vec::new()
+        );
+        assert_eq!(test_ob.get_best_ask().unwrap(), 11);
+        assert_eq!(test_ob.get_best_bid().unwrap(), 10);
+    }
+
+    #[test]
+    fn check_post_only_repriced_out_of_range_is_rejected() {
+        let config = OrderBookConfig {
+            post_only_policy: PostOnlyPolicy::Reprice,
+            ..OrderBookConfig::default()
+        };
+        let mut test_ob = OrderBook::with_config(config);
+        let ask = Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Sell,
+            Price::MIN,
+            10,
+        ));
+        test_ob.add_order(&ask).unwrap();
+
+        // No price a tick below the best ask to slide to
+        let post_only = Arc::new(Order::new(OrderType::PostOnly, Side::Buy, 0, 5));
+        assert!(matches!(
+            test_ob.add_order(&post_only),
+            Err(OrderBookError::Reject {
+                reason: RejectReason::PriceOutOfRange
+            })
+        ));
+        assert_eq!(test_ob.get_best_bid(), None);
+        assert_eq!(test_ob.get_best_ask(), Some(Price::MIN));
+    }
+
+    #[test]
+    fn check_cancel_only_state_gates_commands() {
+        let mut test_ob = OrderBook::new();
+        let resting_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        test_ob.add_order(&resting_order).unwrap();
+
+        let change = test_ob
+            .set_instrument_state(InstrumentState::CancelOnly)
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.previous, InstrumentState::Active);
+        assert_eq!(change.current, InstrumentState::CancelOnly);
+        assert!(
+            test_ob
+                .set_instrument_state(InstrumentState::CancelOnly)
+                .unwrap()
+                .is_none()
+        );
+
+        let new_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 10));
+        assert!(matches!(
+            test_ob.add_order(&new_order),
+            Err(OrderBookError::InstrumentStateRejected { .. })
+        ));
+        test_ob.cancel_order(resting_order.order_id).unwrap();
+    }
+
+    #[test]
+    fn check_halted_state_rejects_cancels() {
+        let mut test_ob = OrderBook::new();
+        let resting_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        test_ob.add_order(&resting_order).unwrap();
+
+        test_ob
+            .set_instrument_state(InstrumentState::Halted)
+            .unwrap();
+        assert!(test_ob.cancel_order(resting_order.order_id).is_err());
+
+        test_ob
+            .set_instrument_state(InstrumentState::Active)
+            .unwrap();
+        test_ob.cancel_order(resting_order.order_id).unwrap();
+    }
+
+    #[test]
+    fn check_commands_and_trades_share_one_sequence() {
+        let mut test_ob = OrderBook::new();
+        for price in [10, 11] {
+            let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, price, 5));
+            test_ob.add_order(&ask).unwrap();
+        }
+        assert_eq!(test_ob.sequence(), 2);
+
+        // rejected commands do not take a number
+        let empty = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 11, 0));
+        assert!(test_ob.add_order(&empty).is_err());
+        assert_eq!(test_ob.sequence(), 2);
+
+        let sweep = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 11, 8));
+        let trades = test_ob.add_order(&sweep).unwrap().trades;
+        let sequences: Vec<u64> = trades.iter().map(Trade::sequence).collect();
+        assert_eq!(sequences, vec![4, 5]);
+
+        assert!(test_ob.cancel_order(sweep.order_id).is_err());
+        let restored = OrderBook::restore(test_ob.snapshot()).unwrap();
+        assert_eq!(restored.sequence(), 5);
+    }
+
+    #[test]
+    fn check_book_state_transitions_gate_entry_points() {
+        let mut test_ob = OrderBook::new();
+        assert_eq!(test_ob.book_state(), BookState::Open);
+        test_ob.transition_to(BookState::Closed).unwrap();
+        assert!(matches!(
+            test_ob.transition_to(BookState::Halted),
+            Err(OrderBookError::InvalidStateTransition {
+                from: BookState::Closed,
+                to: BookState::Halted
+            })
+        ));
+        let order = |side, price| Arc::new(Order::new(OrderType::LimitOrder, side, price, 5));
+        assert!(test_ob.add_order(&order(Side::Buy, 10)).is_err());
+
+        // PreOpen collects crossing orders, opening uncrosses them
+        test_ob.transition_to(BookState::PreOpen).unwrap();
+        let bid = order(Side::Buy, 11);
+        assert!(test_ob.add_order(&bid).unwrap().trades.is_empty());
+        assert!(
+            test_ob
+                .add_order(&order(Side::Sell, 10))
+                .unwrap()
+                .trades
+                .is_empty()
+        );
+        let resting = order(Side::Sell, 12);
+        test_ob.add_order(&resting).unwrap();
+        let opening_trades = test_ob.transition_to(BookState::Open).unwrap();
+        assert_eq!(opening_trades.len(), 1);
+        assert!(!test_ob.contains_order(bid.order_id));
+
+        // A token validated while open is stale once the book is halted
+        let token = test_ob
+            .validate(Command::NewOrder(order(Side::Buy, 9)))
+            .unwrap();
+        test_ob.transition_to(BookState::Halted).unwrap();
+        assert!(test_ob.commit(token).is_err());
+        assert!(test_ob.add_order(&order(Side::Buy, 9)).is_err());
+        test_ob.cancel_order(resting.order_id).unwrap();
+    }
+
+    #[test]
+    fn check_indicative_uncross_follows_auction_orders() {
+        let mut test_ob = OrderBook::new();
+        let order = |side, price, quantity| {
+            Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+        };
+        test_ob.add_order(&order(Side::Buy, 101, 4)).unwrap();
+        test_ob
+            .set_instrument_state(InstrumentState::Auction)
+            .unwrap();
+        assert_eq!(test_ob.indicative_uncross(), None);
+
+        let ask = order(Side::Sell, 100, 10);
+        test_ob.add_order(&ask).unwrap();
+        assert_eq!(
+            test_ob.indicative_uncross(),
+            Some(IndicativeUncross {
+                price: 100,
+                volume: 4,
+                surplus: 6,
+                surplus_side: Some(Side::Sell),
+            })
+        );
+        // more demand balances the book
+        test_ob.add_order(&order(Side::Buy, 102, 6)).unwrap();
+        assert_eq!(
+            test_ob.indicative_uncross(),
+            Some(IndicativeUncross {
+                price: 100,
+                volume: 10,
+                surplus: 0,
+                surplus_side: None,
+            })
+        );
+        test_ob.cancel_order(ask.order_id).unwrap();
+        assert_eq!(test_ob.indicative_uncross(), None);
+
+        // 101 and 102 both execute 3, 102 leaves the smaller surplus
+        test_ob.add_order(&order(Side::Sell, 101, 3)).unwrap();
+        assert_eq!(test_ob.indicative_uncross().unwrap().price, 102);
+        test_ob.uncross().unwrap();
+        assert_eq!(test_ob.indicative_uncross(), None);
+    }
+
+    #[test]
+    fn check_auction_uncrosses_at_single_equilibrium_price() {
+        let mut test_ob = OrderBook::new();
+        test_ob
+            .set_instrument_state(InstrumentState::Auction)
+            .unwrap();
+        for (side, price, quantity) in [
+            (Side::Buy, 102, 10),
+            (Side::Buy, 101, 6),
+            (Side::Buy, 100, 10),
+            (Side::Sell, 99, 5),
+            (Side::Sell, 100, 10),
+            (Side::Sell, 101, 10),
+        ] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            assert!(test_ob.add_order(&order).unwrap().trades.is_empty());
+        }
+        // Bids and asks may share a price while collecting orders
+        assert_eq!(test_ob.get_best_bid(), Some(102));
+        assert_eq!(test_ob.get_best_ask(), Some(99));
+
+        let market_order = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 1));
+        assert!(matches!(
+            test_ob.add_order(&market_order),
+            Err(OrderBookError::InstrumentStateRejected { .. })
+        ));
+
+        // 101 executes 16 (demand 16, supply 25); 100 only 15
+        let journal_path = std::env::temp_dir().join(format!("uncross-{}.journal", Uuid::new_v4()));
+        test_ob.set_journal(Journal::open(&journal_path).unwrap());
+        let trades = test_ob.uncross().unwrap();
+        // stamped with the uncross command's time, as a replay of the journal stamps them
+        let uncrossed_at = Journal::read_entries(&journal_path).unwrap()[0].timestamp;
+        assert!(
+            trades
+                .iter()
+                .all(|trade| Some(trade.timestamp()) == uncrossed_at)
+        );
+        std::fs::remove_file(&journal_path).unwrap();
+        assert!(trades.iter().all(|trade| trade.price() == 101));
+        assert!(trades.iter().all(|trade| trade.aggressor_side().is_none()
+            && trade.liquidity(trade.bid_order_id()) == Some(Liquidity::Auction)));
+        assert_eq!(
+            trades
+                .iter()
+                .map(|trade| trade.quantity())
+                .sum::<Quantity>(),
+            16
+        );
+        assert_eq!(test_ob.trade_history().trade_count(), trades.len());
+
+        let depth = test_ob.get_depth(5);
+        assert_eq!(
+            depth.bids,
+            vec![LevelInfo {
+                price: 100,
+                volume: 10
+            }]
+        );
+        assert_eq!(
+            depth.asks,
+            vec![LevelInfo {
+                price: 101,
+                volume: 9
+            }]
+        );
+        assert!(test_ob.uncross().unwrap().is_empty());
+
+        test_ob
+            .set_instrument_state(InstrumentState::Active)
+            .unwrap();
+        let crossing_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 4));
+        assert_eq!(test_ob.add_order(&crossing_order).unwrap().trades.len(), 1);
+    }
+
+    #[test]
+    fn check_auction_only_orders_canceled_when_trading_resumes() {
+        let mut test_ob = OrderBook::new();
+        let auction_order = |side, price, quantity| {
+            Arc::new(Order::new(OrderType::GoodForAuction, side, price, quantity))
+        };
+        assert!(matches!(
+            test_ob.add_order(&auction_order(Side::Buy, 100, 5)),
+            Err(OrderBookError::InstrumentStateRejected {
+                state: InstrumentState::Active
+            })
+        ));
+
+        test_ob.transition_to(BookState::PreOpen).unwrap();
+        let partly_filled = auction_order(Side::Buy, 101, 10);
+        let untouched = auction_order(Side::Sell, 105, 3);
+        let resting = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 99, 2));
+        for order in [&partly_filled, &untouched, &resting] {
+            test_ob.add_order(order).unwrap();
+        }
+        let seller = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 4));
+        test_ob.add_order(&seller).unwrap();
+
+        // the uncross fills 4, then both auction-only remainders go away and the limit order stays
+        test_ob.transition_to(BookState::Open).unwrap();
+        assert_eq!(test_ob.trade_history().trade_count(), 1);
+        assert!(test_ob.get_order(partly_filled.order_id).is_none());
+        assert!(test_ob.get_order(untouched.order_id).is_none());
+        assert_eq!(test_ob.resting_order_count(), 1);
+        assert_eq!(test_ob.get_best_bid(), Some(99));
+        assert_eq!(test_ob.get_best_ask(), None);
+        assert!(test_ob.add_order(&auction_order(Side::Buy, 99, 1)).is_err());
+    }
+
+    #[test]
+    fn check_pro_rata_matching_on_every_queue_backend() {
+        for backend in [
+            QueueBackendKind::IntrusiveList,
+            QueueBackendKind::VecDeque,
+            QueueBackendKind::Slab,
+        ] {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                queue_backend: backend,
+                allocation_policy: AllocationPolicy::ProRata { min_allocation: 1 },
+                ..OrderBookConfig::default()
+            });
+            let small = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 10));
+            let large = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 30));
+            test_ob.add_order(&small).unwrap();
+            test_ob.add_order(&large).unwrap();
+
+            let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 20));
+            let trades = test_ob.add_order(&bid).unwrap().trades;
+            let fills: Vec<(OrderId, Quantity)> = trades
+                .iter()
+                .map(|trade| (trade.ask_order_id(), trade.quantity()))
+                .collect();
+            assert_eq!(fills, vec![(small.order_id, 5), (large.order_id, 15)]);
+
+            // Both keep their place; the partially filled orders still rest in FIFO order
+            let ahead = test_ob.orders_ahead(large.order_id).unwrap();
+            assert_eq!(ahead.len(), 1);
+            assert_eq!(ahead[0].remaining_quantity, 5);
+            assert_eq!(test_ob.get_depth(1).asks[0].volume, 20);
+
+            // Sweeping more than the level fills everyone and moves on
+            let sweep = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 25));
+            assert_eq!(test_ob.add_order(&sweep).unwrap().trades.len(), 2);
+            assert_eq!(test_ob.get_best_ask(), None);
+            assert_eq!(test_ob.get_best_bid(), Some(100));
+            assert_eq!(test_ob.resting_order_count(), 1);
+        }
+    }
+
+    #[test]
+    fn check_size_priority_and_custom_match_policies() {
+        // Newest order first, asking for more than each order holds
+        struct Lifo;
+        impl MatchPolicy for Lifo {
+            fn allocate(&self, resting: &[Quantity], quantity: Quantity) -> Vec<Quantity> {
+                let mut allocations = vec![0; resting.len()];
+                if let Some(last) = allocations.last_mut() {
+                    *last = quantity;
+                }
+                allocations
+            }
+        }
+
+        let fills = |test_ob: &mut OrderBook| {
+            let orders: Vec<Arc<Order>> = [10, 30, 20]
+                .into_iter()
+                .map(|quantity| {
+                    Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, quantity))
+                })
+                .collect();
+            for order in orders.iter() {
+                test_ob.add_order(order).unwrap();
+            }
+            let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 35));
+            let trades = test_ob.add_order(&bid).unwrap().trades;
+            let position = |order_id| orders.iter().position(|order| order.order_id == order_id);
+            trades
+                .iter()
+                .map(|trade| (position(trade.ask_order_id()).unwrap(), trade.quantity()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut size_priority = OrderBook::with_config(OrderBookConfig {
+            allocation_policy: AllocationPolicy::SizePriority,
+            ..OrderBookConfig::default()
+        });
+        assert_eq!(fills(&mut size_priority), vec![(1, 30), (2, 5)]);
+        assert_eq!(size_priority.get_depth(1).asks[0].volume, 25);
+
+        // the book caps a policy's allocations at what each order holds and keeps allocating
+        // what is left
+        let mut custom = OrderBook::new();
+        custom.set_match_policy(Box::new(Lifo));
+        assert_eq!(fills(&mut custom), vec![(2, 20), (1, 15)]);
+        assert_eq!(custom.get_best_bid(), None);
+        assert_eq!(custom.get_depth(1).asks[0].volume, 25);
+    }
+
+    #[test]
+    fn check_a_policy_allocating_nothing_never_leaves_the_book_crossed() {
+        struct Nothing;
+        impl MatchPolicy for Nothing {
+            fn allocate(&self, resting: &[Quantity], _: Quantity) -> Vec<Quantity> {
+                vec![0; resting.len()]
+            }
+        }
+
+        let mut test_ob = OrderBook::new();
+        test_ob.set_match_policy(Box::new(Nothing));
+        let first = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 4));
+        let second = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 6));
+        test_ob.add_order(&first).unwrap();
+        test_ob.add_order(&second).unwrap();
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 105, 12));
+        let result = test_ob.add_order(&bid).unwrap();
+        let fills: Vec<(OrderId, Quantity)> = result
+            .trades
+            .iter()
+            .map(|trade| (trade.ask_order_id(), trade.quantity()))
+            .collect();
+        assert_eq!(fills, vec![(first.order_id, 4), (second.order_id, 6)]);
+        assert_eq!(
+            (result.remaining, result.status),
+            (2, Status::PartiallyFilled)
+        );
+        assert_eq!(
+            (test_ob.get_best_bid(), test_ob.get_best_ask()),
+            (Some(105), None)
+        );
+    }
+
+    #[test]
+    fn check_modify_keeps_priority_only_on_reductions() {
+        struct Mirror(Arc<Mutex<L3Book>>);
+        impl OrderBookListener for Mirror {
+            fn on_book_event(&mut self, event: &BookEvent) {
+                self.0.lock().unwrap().apply(event);
+            }
+        }
+
+        let mut test_ob = OrderBook::new();
+        let mirror = Arc::new(Mutex::new(L3Book::new()));
+        test_ob.add_listener(Box::new(Mirror(mirror.clone())));
+        let asks: Vec<Arc<Order>> = (0..3)
+            .map(|_| Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 101, 10)))
+            .collect();
+        for order in asks.iter() {
+            test_ob.add_order(order).unwrap();
+        }
+        let (first, second, third) = (asks[0].order_id, asks[1].order_id, asks[2].order_id);
+        let position = |test_ob: &OrderBook, order_id| test_ob.get_order(order_id).unwrap();
+
+        // A reduction keeps the front of the queue, with the quantity already executed counted
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 101, 4));
+        test_ob.add_order(&bid).unwrap();
+        let reduced = test_ob.modify_order(first, 101, 8).unwrap();
+        assert_eq!(
+            (reduced.remaining, reduced.status),
+            (4, Status::PartiallyFilled)
+        );
+        assert_eq!(position(&test_ob, first).queue_position, 0);
+        assert_eq!(position(&test_ob, first).original_quantity, 8);
+        assert_eq!(test_ob.get_depth(1).asks[0].volume, 24);
+        assert!(matches!(
+            test_ob.modify_order(first, 101, 4),
+            Err(OrderBookError::InvalidQuantity { quantity: 4 })
+        ));
+
+        // An increase goes to the back of the same level
+        test_ob.modify_order(second, 101, 15).unwrap();
+        assert_eq!(position(&test_ob, second).queue_position, 2);
+        assert_eq!(position(&test_ob, third).queue_position, 1);
+        assert_eq!(test_ob.get_depth(1).asks[0].volume, 29);
+
+        // A price change moves to the back of the new level, and matches first if it crosses
+        test_ob
+            .add_order(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Buy,
+                99,
+                6,
+            )))
+            .unwrap();
+        let moved = test_ob.modify_order(third, 99, 10).unwrap();
+        assert_eq!(moved.trades.len(), 1);
+        assert_eq!(
+            (moved.remaining, moved.status),
+            (4, Status::PartiallyFilled)
+        );
+        assert_eq!(position(&test_ob, third).price, 99);
+        assert_eq!(test_ob.get_best_bid(), None);
+        assert_eq!(test_ob.get_best_ask(), Some(99));
+
+        let mirror = mirror.lock().unwrap();
+        assert_eq!(mirror.get_depth(5).asks, test_ob.get_depth(5).asks);
+        assert_eq!(
+            mirror.queue(Side::Sell, 101),
+            vec![(first, 4), (second, 15)]
+        );
+    }
+
+    #[test]
+    fn check_sweep_limit_cancels_the_remainder() {
+        let ladder = |sweep_limit| {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                sweep_limit: Some(sweep_limit),
+                ..OrderBookConfig::default()
+            });
+            for price in 100..104 {
+                let ask = Order::new(OrderType::LimitOrder, Side::Sell, price, 5);
+                test_ob.add_order(&Arc::new(ask)).unwrap();
+            }
+            test_ob
+        };
+        let buy = |order_type, price, quantity| {
+            Arc::new(Order::new(order_type, Side::Buy, price, quantity))
+        };
+
+        // A market order stops after two levels
+        let mut test_ob = ladder(SweepLimit::Levels(2));
+        let result = test_ob
+            .add_order(&buy(OrderType::MarketOrder, 0, 20))
+            .unwrap();
+        assert_eq!(result.trades.len(), 2);
+        assert_eq!((result.remaining, result.status), (10, Status::Canceled));
+        assert_eq!(test_ob.get_best_ask(), Some(102));
+        // a FOK only counts what it may reach
+        let result = test_ob
+            .add_order(&buy(OrderType::FillOrKill, 103, 15))
+            .unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(test_ob.get_depth(5).asks.len(), 2);
+
+        // A limit order one tick deep leaves nothing crossed behind
+        let mut test_ob = ladder(SweepLimit::Ticks(1));
+        let result = test_ob
+            .add_order(&buy(OrderType::LimitOrder, 103, 20))
+            .unwrap();
+        assert_eq!((result.remaining, result.status), (10, Status::Canceled));
+        assert_eq!(test_ob.get_best_bid(), None);
+        // within the limit the remainder rests as usual
+        let result = test_ob
+            .add_order(&buy(OrderType::LimitOrder, 101, 8))
+            .unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(test_ob.get_best_bid(), Some(101));
+    }
+
+    #[test]
+    fn check_protection_price_bounds_market_orders() {
+        let mut test_ob = OrderBook::new();
+        for price in [-1, 0, 1, 2] {
+            let bid = Order::new(OrderType::LimitOrder, Side::Buy, price, 5);
+            test_ob.add_order(&Arc::new(bid)).unwrap();
+        }
+        let sell = Arc::new(
+            Order::new(OrderType::MarketOrder, Side::Sell, 0, 20).with_protection_price(0),
+        );
+        let result = test_ob.add_order(&sell).unwrap();
+        let prices: Vec<Price> = result.trades.iter().map(|trade| trade.price).collect();
+        assert_eq!(prices, vec![2, 1, 0]);
+        assert_eq!((result.remaining, result.status), (5, Status::Canceled));
+        assert_eq!(test_ob.get_best_bid(), Some(-1));
+        assert_eq!(test_ob.get_best_ask(), None);
+    }
+
+    #[test]
+    fn check_bust_trade_restores_resting_orders() {
+        let mut test_ob = OrderBook::new();
+        let maker = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 10));
+        let behind = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 5));
+        test_ob.add_order(&maker).unwrap();
+        test_ob.add_order(&behind).unwrap();
+        let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 4));
+        let trade = test_ob.add_order(&taker).unwrap().trades.remove(0);
+
+        let bust = test_ob.bust_trade(trade.trade_id()).unwrap();
+        assert_eq!(bust.trade, trade);
+        // the taker filled and left the book, only the maker gets its quantity back
+        assert_eq!(bust.restored, vec![maker.order_id]);
+        assert_eq!(bust.sequence, test_ob.sequence());
+        let restored = test_ob.get_order(maker.order_id).unwrap();
+        assert_eq!(
+            (restored.remaining_quantity, restored.status),
+            (10, Status::New)
+        );
+        assert_eq!(restored.queue_position, 1);
+        assert_eq!(test_ob.get_depth(1).asks[0].volume, 15);
+        assert_eq!(test_ob.trade_history().trade_count(), 0);
+        assert!(matches!(
+            test_ob.bust_trade(trade.trade_id()),
+            Err(OrderBookError::TradeNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn check_bust_trade_without_room_leaves_book_untouched() {
+        let mut test_ob = OrderBook::new();
+        let maker = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 10));
+        test_ob.add_order(&maker).unwrap();
+        let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 4));
+        let trade = test_ob.add_order(&taker).unwrap().trades.remove(0);
+        let filler = Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Sell,
+            100,
+            Quantity::MAX - 8,
+        ));
+        test_ob.add_order(&filler).unwrap();
+        let sequence = test_ob.sequence();
+
+        assert!(matches!(
+            test_ob.bust_trade(trade.trade_id()),
+            Err(OrderBookError::QuantityOverflow { .. })
+        ));
+        assert_eq!(test_ob.sequence(), sequence);
+        assert_eq!(test_ob.trade_history().trade_count(), 1);
+        let maker = test_ob.get_order(maker.order_id).unwrap();
+        assert_eq!((maker.remaining_quantity, maker.queue_position), (6, 0));
+    }
+
+    #[test]
+    fn check_trades_carry_maker_taker_fees() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            fee_schedule: FeeSchedule {
+                maker_fee_bps: -10,
+                taker_fee_bps: 20,
+            },
+            ..OrderBookConfig::default()
+        });
+        let maker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 1_000, 50));
+        test_ob.add_order(&maker).unwrap();
+        let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 1_000, 50));
+        let trades = test_ob.add_order(&taker).unwrap().trades;
+        let trade = &trades[0];
+
+        // An incoming sell is the ask side of the trade
+        assert_eq!(trade.bid_order_id(), maker.order_id);
+        assert_eq!(trade.ask_order_id(), taker.order_id);
+        assert_eq!(trade.aggressor_side(), Some(Side::Sell));
+        assert_eq!(trade.maker_order_id(), Some(maker.order_id));
+        assert_eq!(trade.taker_order_id(), Some(taker.order_id));
+        assert_eq!(trade.liquidity(maker.order_id), Some(Liquidity::Added));
+        assert_eq!(trade.liquidity(taker.order_id), Some(Liquidity::Removed));
+        assert_eq!(trade.liquidity(Uuid::new_v4()), None);
+        // notional 50_000
+        assert_eq!(trade.maker_fee(), -50);
+        assert_eq!(trade.taker_fee(), 100);
+    }
+
+    #[test]
+    fn check_open_orders_and_cancel_all_for_owner() {
+        let mut test_ob = OrderBook::new();
+        let first = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 5).with_owner(1));
+        let second = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 12, 5).with_owner(1));
+        let other = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 5).with_owner(2));
+        for order in [&first, &second, &other] {
+            test_ob.add_order(order).unwrap();
+        }
+
+        // A fill leaves the order open with its updated quantity; a full fill closes it
+        let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 0, 2).with_owner(3));
+        test_ob.add_order(&taker).unwrap();
+        let open: Vec<Quantity> = test_ob
+            .open_orders(1)
+            .iter()
+            .map(|order| order.remaining_quantity)
+            .collect();
+        assert_eq!(open.len(), 2);
+        assert!(open.contains(&3));
+        let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 11, 5).with_owner(3));
+        test_ob.add_order(&taker).unwrap();
+        assert!(test_ob.open_orders(2).is_empty());
+
+        let mut canceled = test_ob.cancel_all_for(1).unwrap();
+        canceled.sort();
+        let mut expected = vec![first.order_id, second.order_id];
+        expected.sort();
+        assert_eq!(canceled, expected);
+        assert!(test_ob.open_orders(1).is_empty());
+        assert_eq!(test_ob.resting_order_count(), 0);
+        assert!(test_ob.cancel_all_for(9).unwrap().is_empty());
+    }
+
+    #[test]
+    fn check_config_without_a_tick_or_lot_is_refused() {
+        for (tick_size, lot_size, expected) in [
+            (0, 1, ConfigError::NonPositiveTickSize { tick_size: 0 }),
+            (-5, 1, ConfigError::NonPositiveTickSize { tick_size: -5 }),
+            (1, 0, ConfigError::ZeroLotSize),
+        ] {
+            let config = OrderBookConfig {
+                tick_size,
+                lot_size,
+                ..OrderBookConfig::default()
+            };
+            assert_eq!(
+                OrderBook::try_with_config(config.clone()).err(),
+                Some(expected)
+            );
+            assert!(matches!(
+                OrderBook::restore_with_config(OrderBook::new().snapshot(), config),
+                Err(OrderBookError::InvalidConfig { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn check_tick_array_ranges_are_validated() {
+        let tick_array = |min_price, max_price, tick_size| OrderBookConfig {
+            tick_size,
+            ladder: LadderKind::TickArray {
+                min_price,
+                max_price,
+            },
+            ..OrderBookConfig::default()
+        };
+        for (config, expected) in [
+            (
+                tick_array(10, 5, 1),
+                ConfigError::InvertedTickArrayRange {
+                    min_price: 10,
+                    max_price: 5,
+                },
+            ),
+            (
+                tick_array(3, 100, 5),
+                ConfigError::UnalignedTickArrayRange {
+                    min_price: 3,
+                    max_price: 100,
+                    tick_size: 5,
+                },
+            ),
+            (
+                tick_array(Price::MIN, Price::MAX, 1),
+                ConfigError::TickArrayRangeOverflow {
+                    min_price: Price::MIN,
+                    max_price: Price::MAX,
+                },
+            ),
+            (
+                tick_array(0, 1 << 40, 1),
+                ConfigError::TickArrayTooLarge {
+                    slots: (1 << 40) + 1,
+                    max_slots: MAX_TICK_ARRAY_SLOTS,
+                },
+            ),
+        ] {
+            assert_eq!(OrderBook::try_with_config(config).err(), Some(expected));
+        }
+        // the widest range allowed still builds
+        let widest = (MAX_TICK_ARRAY_SLOTS as Price - 1) * 5;
+        assert!(OrderBook::try_with_config(tick_array(0, widest, 5)).is_ok());
+    }
+
+    #[test]
+    fn check_tick_and_lot_size_validation() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            tick_size: 5,
+            lot_size: 100,
+            ..OrderBookConfig::default()
+        });
+        let off_tick = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 102, 100));
+        assert!(matches!(
+            test_ob.add_order(&off_tick),
+            Err(OrderBookError::InvalidTick {
+                price: 102,
+                tick_size: 5
+            })
+        ));
+        let odd_lot = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 150));
+        assert!(matches!(
+            test_ob.add_order(&odd_lot),
+            Err(OrderBookError::InvalidLot {
+                quantity: 150,
+                lot_size: 100
+            })
+        ));
+        let valid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 105, 300));
+        test_ob.add_order(&valid).unwrap();
+        let market = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 1, 100));
+        assert_eq!(test_ob.add_order(&market).unwrap().trades.len(), 1);
+    }
+
+    #[test]
+    fn check_static_price_limits_reject_fat_finger() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            price_limits: Some((50, 150)),
+            ..OrderBookConfig::default()
+        });
+        let resting_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 10));
+        test_ob.add_order(&resting_order).unwrap();
+
+        let fat_finger = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, Price::MAX, 10));
+        assert!(matches!(
+            test_ob.add_order(&fat_finger),
+            Err(OrderBookError::PriceOutsideBand {
+                low: 50,
+                high: 150,
+                ..
+            })
+        ));
+        assert_eq!(test_ob.get_best_ask(), Some(100));
+        assert_eq!(test_ob.instrument_state(), InstrumentState::Active);
+    }
+
+    #[test]
+    fn check_dynamic_price_band_follows_last_trade() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            price_band: Some(PriceBand {
+                width_bps: 1_000, // 10%
+                breach_policy: BandBreachPolicy::Reject,
+            }),
+            ..OrderBookConfig::default()
+        });
+        // No reference price yet, so the band is inactive
+        let far_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 200, 10));
+        test_ob.add_order(&far_order).unwrap();
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 200, 1));
+        test_ob.add_order(&buy_order).unwrap();
+        assert_eq!(test_ob.reference_price(), Some(200));
+
+        let inside = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 180, 1));
+        test_ob.add_order(&inside).unwrap();
+        let outside = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 179, 1));
+        assert!(matches!(
+            test_ob.add_order(&outside),
+            Err(OrderBookError::PriceOutsideBand {
+                low: 180,
+                high: 220,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn check_wide_price_band_saturates_instead_of_wrapping() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            price_band: Some(PriceBand {
+                width_bps: u32::MAX,
+                breach_policy: BandBreachPolicy::Reject,
+            }),
+            ..OrderBookConfig::default()
+        });
+        test_ob.set_reference_price(1 << 62);
+
+        // A width past Price::MAX admits every price rather than wrapping to a narrow band
+        for price in [1, 1 << 62, Price::MAX - 1] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, price, 1));
+            test_ob.add_order(&order).unwrap();
+        }
+    }
+
+    #[test]
+    fn check_price_band_breach_halts_instrument() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            price_band: Some(PriceBand {
+                width_bps: 500,
+                breach_policy: BandBreachPolicy::Halt,
+            }),
+            ..OrderBookConfig::default()
+        });
+        test_ob.set_reference_price(100);
+
+        let outside = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 90, 1));
+        assert!(test_ob.add_order(&outside).is_err());
+        assert_eq!(test_ob.instrument_state(), InstrumentState::Halted);
+    }
+
+    #[test]
+    fn check_get_depth_aggregates_top_levels() {
+        let mut test_ob = OrderBook::new();
+        for (side, price, quantity) in [
+            (Side::Buy, 9, 10),
+            (Side::Buy, 9, 5),
+            (Side::Buy, 8, 3),
+            (Side::Buy, 7, 1),
+            (Side::Sell, 11, 4),
+            (Side::Sell, 12, 6),
+        ] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            test_ob.add_order(&order).unwrap();
+        }
+
+        let depth = test_ob.get_depth(2);
+        assert_eq!(
+            depth.bids,
+            vec![
+                LevelInfo {
+                    price: 9,
+                    volume: 15
+                },
+                LevelInfo {
+                    price: 8,
+                    volume: 3
+                },
+            ]
+        );
+        assert_eq!(depth.asks.len(), 2);
+        assert_eq!(depth.asks[0].price, 11);
+        assert_eq!(depth.asks[1].volume, 6);
+    }
+
+    #[test]
+    fn check_depth_bucketed_sums_levels() {
+        let mut test_ob = OrderBook::new();
+        for (side, price, quantity) in [
+            (Side::Buy, 100, 1),
+            (Side::Buy, 99, 2),
+            (Side::Buy, 95, 3),
+            (Side::Buy, 94, 4),
+            (Side::Buy, 80, 5),
+            (Side::Sell, 101, 1),
+            (Side::Sell, 105, 2),
+            (Side::Sell, 106, 3),
+        ] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            test_ob.add_order(&order).unwrap();
+        }
+
+        let bucket = |price, volume| LevelInfo { price, volume };
+        let depth = test_ob.get_depth_bucketed(5, 3);
+        assert_eq!(
+            depth.bids,
+            vec![bucket(100, 1), bucket(95, 5), bucket(90, 4)]
+        );
+        assert_eq!(depth.asks, vec![bucket(105, 3), bucket(110, 3)]);
+        // bucket size 1 is plain depth
+        assert_eq!(test_ob.get_depth_bucketed(0, 2), test_ob.get_depth(2));
+    }
+
+    #[test]
+    fn check_orders_ahead_in_queue() {
+        let mut test_ob = OrderBook::new();
+        let first_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 3));
+        let second_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 4));
+        let third_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5));
+        test_ob.add_order(&first_order).unwrap();
+        test_ob.add_order(&second_order).unwrap();
+        test_ob.add_order(&third_order).unwrap();
+
+        assert!(
+            test_ob
+                .orders_ahead(first_order.order_id)
+                .unwrap()
+                .is_empty()
+        );
+
+        let ahead = test_ob.orders_ahead(third_order.order_id).unwrap();
+        assert_eq!(ahead.len(), 2);
+        assert_eq!(ahead[0].order_id, first_order.order_id);
+        assert_eq!(ahead[1].order_id, second_order.order_id);
+        assert_eq!(ahead[1].remaining_quantity, 4);
+
+        // partially filling the front order keeps it ahead with its reduced size
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 2));
+        test_ob.add_order(&buy_order).unwrap();
+        let ahead = test_ob.orders_ahead(third_order.order_id).unwrap();
+        assert_eq!(ahead[0].remaining_quantity, 1);
+    }
+
+    #[test]
+    fn check_watchdog_counts_commands_when_enabled() {
+        let test_ob = OrderBook::new();
+        assert!(test_ob.watchdog_stats().is_none());
+
+        let config = OrderBookConfig {
+            latency_budget: Some(std::time::Duration::ZERO),
+            ..OrderBookConfig::default()
+        };
+        let mut test_ob = OrderBook::with_config(config);
+        let order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        test_ob.add_order(&order).unwrap();
+        test_ob.cancel_order(order.order_id).unwrap();
+
+        let stats = test_ob.watchdog_stats().unwrap();
+        assert_eq!(stats.commands, 2);
+    }
+
+    #[cfg(feature = "latency-histograms")]
+    #[test]
+    fn check_latency_histograms_record_every_command() {
+        let mut test_ob = OrderBook::new();
+        for price in 1..=10 {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, price, 10));
+            test_ob.add_order(&order).unwrap();
+        }
+        let resting = test_ob.open_orders(0)[0].order_id;
+        test_ob.cancel_order(resting).unwrap();
+
+        let add = test_ob.latency_percentiles(CommandKind::AddOrder).unwrap();
+        assert_eq!(add.count, 10);
+        assert!(add.min <= add.p50 && add.p50 <= add.max);
+        let cancel = test_ob
+            .latency_percentiles(CommandKind::CancelOrder)
+            .unwrap();
+        assert_eq!(cancel.count, 1);
+        assert!(
+            test_ob
+                .latency_histograms()
+                .report()
+                .starts_with("AddOrder: n=10")
+        );
+
+        test_ob.reset_latency_histograms();
+        assert!(test_ob.latency_percentiles(CommandKind::AddOrder).is_none());
+    }
+
+    #[test]
+    fn check_listener_receives_lifecycle_events() {
+        let mut test_ob = OrderBook::new();
+        let listener = RecordingListener::default();
+        let events = listener.events.clone();
+        test_ob.add_listener(Box::new(listener));
+
+        let sell_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 4));
+        test_ob.add_order(&sell_order).unwrap();
+        test_ob.add_order(&buy_order).unwrap();
+        test_ob.cancel_order(sell_order.order_id).unwrap();
+        let empty_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 0));
+        assert!(test_ob.add_order(&empty_order).is_err());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "accept Sell",
+                "level Sell 10x10",
+                "accept Buy",
+                "trade 4@10",
+                "level Sell 6x10",
+                "cancel Canceled",
+                "level Sell 0x10",
+                "reject",
+            ]
+        );
+    }
+
+    struct BboListener(Arc<Mutex<Vec<BboUpdate>>>);
+
+    impl OrderBookListener for BboListener {
+        fn on_bbo(&mut self, update: &BboUpdate) {
+            self.0.lock().unwrap().push(*update);
+        }
+    }
+
+    #[test]
+    fn check_bbo_published_only_when_top_of_book_changes() {
+        let mut test_ob = OrderBook::new();
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        test_ob.add_listener(Box::new(BboListener(updates.clone())));
+
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        test_ob.add_order(&ask).unwrap();
+        // behind the best ask, nothing to publish
+        let deep_ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 12, 10));
+        test_ob.add_order(&deep_ask).unwrap();
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 4));
+        test_ob.add_order(&bid).unwrap();
+        test_ob.cancel_order(ask.order_id).unwrap();
+
+        let quotes: Vec<_> = updates
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|bbo| {
+                (
+                    bbo.bid_price,
+                    bbo.bid_qty,
+                    bbo.ask_price,
+                    bbo.ask_qty,
+                    bbo.seq,
+                )
+            })
+            .collect();
+        assert_eq!(
+            quotes,
+            vec![
+                (None, 0, Some(10), 10, 1),
+                (None, 0, Some(10), 6, 4),
+                (None, 0, Some(12), 10, 5),
+            ]
+        );
+        assert_eq!(test_ob.bbo(), *updates.lock().unwrap().last().unwrap());
+    }
+
+    struct ReportListener(Arc<Mutex<Vec<ExecutionReport>>>);
+
+    impl OrderBookListener for ReportListener {
+        fn on_execution_report(&mut self, report: &ExecutionReport) {
+            self.0.lock().unwrap().push(report.clone());
+        }
+    }
+
+    #[test]
+    fn check_execution_reports_follow_every_state_change() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            post_only_policy: PostOnlyPolicy::Reprice,
+            ..OrderBookConfig::default()
+        });
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        test_ob.add_listener(Box::new(ReportListener(reports.clone())));
+
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        let ioc = Arc::new(Order::new(OrderType::ImmediateOrCancel, Side::Buy, 10, 12));
+        let post_only = Arc::new(Order::new(OrderType::PostOnly, Side::Sell, 8, 5));
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 8, 3));
+        test_ob.add_order(&ask).unwrap();
+        test_ob.add_order(&bid).unwrap();
+        test_ob.add_order(&ioc).unwrap();
+        test_ob.add_order(&post_only).unwrap();
+        test_ob.cancel_order(post_only.order_id).unwrap();
+        let empty = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 0));
+        assert!(test_ob.add_order(&empty).is_err());
+
+        let reports = reports.lock().unwrap();
+        let summary: Vec<(OrderId, ExecType, Status, Quantity, Quantity, Quantity)> = reports
+            .iter()
+            .map(|report| {
+                (
+                    report.order_id,
+                    report.exec_type,
+                    report.status,
+                    report.last_quantity,
+                    report.cumulative_quantity,
+                    report.leaves_quantity,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ask.order_id, ExecType::New, Status::New, 0, 0, 10),
+                (bid.order_id, ExecType::New, Status::New, 0, 0, 3),
+                (ioc.order_id, ExecType::New, Status::New, 0, 0, 12),
+                (
+                    ioc.order_id,
+                    ExecType::PartialFill,
+                    Status::PartiallyFilled,
+                    10,
+                    10,
+                    2
+                ),
+                (ask.order_id, ExecType::Fill, Status::Filled, 10, 10, 0),
+                // the IOC remainder is dropped
+                (ioc.order_id, ExecType::Canceled, Status::Canceled, 0, 10, 0),
+                // the post-only sell would cross the bid at 8 and slides to 9
+                (post_only.order_id, ExecType::New, Status::New, 0, 0, 5),
+                (post_only.order_id, ExecType::Replaced, Status::New, 0, 0, 5),
+                (
+                    post_only.order_id,
+                    ExecType::Canceled,
+                    Status::Canceled,
+                    0,
+                    0,
+                    0
+                ),
+                (
+                    empty.order_id,
+                    ExecType::Rejected,
+                    Status::Rejected,
+                    0,
+                    0,
+                    0
+                ),
+            ]
+        );
+        let ioc_fill = &reports[3];
+        assert_eq!(ioc_fill.last_price, Some(10));
+        assert_eq!(ioc_fill.liquidity, Some(Liquidity::Removed));
+        assert_eq!(reports[4].liquidity, Some(Liquidity::Added));
+        assert_eq!(ioc_fill.trade_id, reports[4].trade_id);
+        assert_eq!(reports[7].price, 9);
+        assert!(reports.last().unwrap().reason.is_some());
+        assert!(
+            reports
+                .windows(2)
+                .all(|pair| pair[0].exec_id < pair[1].exec_id)
+        );
+    }
+
+    #[test]
+    fn check_stats_track_executed_trades() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            stats_windows: vec![Duration::from_secs(60), Duration::from_secs(3600)],
+            ..OrderBookConfig::default()
+        });
+        for (price, quantity) in [(10, 1), (20, 3)] {
+            let ask = Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Sell,
+                price,
+                quantity,
+            ));
+            test_ob.add_order(&ask).unwrap();
+        }
+        let sweep = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 4));
+        test_ob.add_order(&sweep).unwrap();
+
+        let stats = test_ob.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].window, Duration::from_secs(3600));
+        assert!(stats.iter().all(|window| window.trade_count == 2
+            && window.volume == 4
+            && window.vwap == Some(17.5)));
+
+        let later = test_ob.trade_history().last_trade().unwrap().timestamp() + 120_000_000;
+        let stats = test_ob.stats_at(later);
+        assert_eq!(stats[0].trade_count, 0);
+        assert_eq!(stats[0].twap, Some(20.0));
+        assert_eq!(stats[1].trade_count, 2);
+    }
+
+    #[test]
+    fn check_tags_are_echoed_on_trades_and_reports() {
+        #[derive(Clone, Default)]
+        struct Reports(Arc<Mutex<Vec<ExecutionReport>>>);
+        impl OrderBookListener for Reports {
+            fn on_execution_report(&mut self, report: &ExecutionReport) {
+                self.0.lock().unwrap().push(report.clone());
+            }
+        }
+
+        let mut test_ob = OrderBook::new();
+        let reports = Reports::default();
+        test_ob.add_listener(Box::new(reports.clone()));
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5).with_tag(vec![7]));
+        test_ob.add_order(&ask).unwrap();
+        let bid =
+            Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 2).with_tag("parent-1"));
+        let trades = test_ob.add_order(&bid).unwrap().trades;
+        assert_eq!(trades[0].bid_tag(), Some(b"parent-1".as_slice()));
+        assert_eq!(trades[0].ask_tag(), Some([7].as_slice()));
+        let tag_of = |order_id| {
+            if order_id == ask.order_id {
+                vec![7]
+            } else {
+                b"parent-1".to_vec()
+            }
+        };
+        let reports = reports.0.lock().unwrap();
+        assert_eq!(reports.len(), 4);
+        assert!(
+            reports
+                .iter()
+                .all(|report| report.tag == Some(tag_of(report.order_id)))
+        );
+        drop(reports);
+
+        let oversized =
+            Order::new(OrderType::LimitOrder, Side::Buy, 9, 1).with_tag(vec![0; MAX_TAG_LEN + 1]);
+        assert!(matches!(
+            test_ob.add_order(&Arc::new(oversized)),
+            Err(OrderBookError::TagTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn check_session_stats_reset_when_the_book_reopens() {
+        let mut test_ob = OrderBook::new();
+        for (price, quantity) in [(20, 3), (10, 1), (15, 2)] {
+            let ask = Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Sell,
+                price,
+                quantity,
+            ));
+            test_ob.add_order(&ask).unwrap();
+            let bid = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, quantity));
+            test_ob.add_order(&bid).unwrap();
+        }
+        let session = test_ob.session_stats().clone();
+        assert_eq!(
+            (session.open, session.high, session.low, session.last),
+            (Some(20), Some(20), Some(10), Some(15))
+        );
+        assert_eq!((session.volume, session.trade_count), (6, 3));
+        assert_eq!(session.notional, 100);
+
+        test_ob.transition_to(BookState::Closed).unwrap();
+        assert_eq!(test_ob.session_stats(), &session);
+        test_ob.transition_to(BookState::Open).unwrap();
+        assert_eq!(test_ob.session_stats(), &SessionStats::default());
+        assert_eq!(test_ob.session_stats().vwap(), None);
+    }
+
+    #[test]
+    fn check_mark_price_follows_last_trade_inside_the_spread() {
+        let mut test_ob = OrderBook::new();
+        let order = |side, price, quantity| {
+            Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+        };
+        test_ob.add_order(&order(Side::Sell, 100, 5)).unwrap();
+        assert_eq!(test_ob.mid_price(), None);
+        assert_eq!(test_ob.mark_price(), None);
+        test_ob.add_order(&order(Side::Buy, 100, 2)).unwrap();
+        assert_eq!(test_ob.last_trade_price(), Some(100));
+
+        test_ob.add_order(&order(Side::Buy, 96, 1)).unwrap();
+        assert_eq!(test_ob.mid_price(), Some(98.0));
+        assert_eq!(test_ob.mark_price(), Some(100));
+        // the ask backs off, the last print is now outside the spread
+        test_ob.add_order(&order(Side::Buy, 100, 3)).unwrap();
+        test_ob.add_order(&order(Side::Sell, 99, 1)).unwrap();
+        assert_eq!(test_ob.last_trade_price(), Some(100));
+        assert_eq!(test_ob.mark_price(), Some(99));
+
+        test_ob.set_mark_price(Some(97));
+        assert_eq!(test_ob.mark_price(), Some(97));
+        test_ob.set_mark_price(None);
+        assert_eq!(test_ob.mark_price(), Some(99));
+    }
+
+    #[test]
+    fn check_mark_price_is_the_indicative_price_in_an_auction() {
+        let mut test_ob = OrderBook::new();
+        let order = |side, price, quantity| {
+            Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+        };
+        test_ob.add_order(&order(Side::Sell, 90, 1)).unwrap();
+        test_ob.add_order(&order(Side::Buy, 90, 1)).unwrap();
+        assert_eq!(test_ob.mark_price(), Some(90));
+
+        test_ob
+            .set_instrument_state(InstrumentState::Auction)
+            .unwrap();
+        assert_eq!(test_ob.mark_price(), None);
+        test_ob.add_order(&order(Side::Buy, 105, 10)).unwrap();
+        test_ob.add_order(&order(Side::Sell, 100, 10)).unwrap();
+        // the last print of 90 is below the bid of 105
+        let indicative = test_ob.indicative_uncross().unwrap().price;
+        assert_eq!(test_ob.mark_price(), Some(indicative));
+        assert!((100..=105).contains(&indicative));
+    }
+
+    #[test]
+    fn check_imbalance_follows_level_changes() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            imbalance_levels: 2,
+            ..OrderBookConfig::default()
+        });
+        assert_eq!(test_ob.imbalance().ratio(), None);
+        assert_eq!(test_ob.microprice(), None);
+
+        let mut orders: Vec<Arc<Order>> = Vec::new();
+        for (side, price, quantity) in [
+            (Side::Buy, 100, 30),
+            (Side::Buy, 99, 10),
+            (Side::Buy, 98, 50),
+            (Side::Sell, 102, 10),
+            (Side::Sell, 103, 10),
+        ] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            test_ob.add_order(&order).unwrap();
+            orders.push(order);
+        }
+        let imbalance = test_ob.imbalance();
+        assert_eq!((imbalance.bid_volume, imbalance.ask_volume), (40, 20));
+        assert_eq!(test_ob.microprice(), Some(101.5));
+
+        // partial fill at the best bid, then the level below the top two moves up
+        let sell = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100, 10));
+        test_ob.add_order(&sell).unwrap();
+        assert_eq!(test_ob.imbalance().bid_volume, 30);
+        test_ob.cancel_order(orders[1].order_id).unwrap();
+        assert_eq!(test_ob.imbalance().bid_volume, 70);
+        // a new best ask pushes 103 out of the top two
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 101, 5));
+        test_ob.add_order(&ask).unwrap();
+        assert_eq!(test_ob.imbalance().ask_volume, 15);
+    }
+
+    #[test]
+    fn check_get_order_reports_fills_and_queue_position() {
+        let mut test_ob = OrderBook::new();
+        let asks: Vec<Arc<Order>> = [4, 6, 5]
+            .into_iter()
+            .map(|quantity| Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, quantity)))
+            .collect();
+        for ask in asks.iter() {
+            test_ob.add_order(ask).unwrap();
         }
+        let view = test_ob.get_order(asks[2].order_id).unwrap();
+        assert_eq!((view.queue_position, view.quantity_ahead), (2, 10));
+        assert_eq!(view.status, Status::New);
 
-        let new_order_remaining_quantity = old_order_arc.remaining_quantity - trade_quantity;
-        let new_order_executed_quantity = old_order_arc.executed_quantity + trade_quantity;
+        let buy = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 6));
+        test_ob.add_order(&buy).unwrap();
+        assert!(test_ob.get_order(asks[0].order_id).is_none());
+        let view = test_ob.get_order(asks[1].order_id).unwrap();
+        assert_eq!(view.status, Status::PartiallyFilled);
+        assert_eq!((view.executed_quantity, view.remaining_quantity), (2, 4));
+        assert_eq!((view.queue_position, view.quantity_ahead), (0, 0));
+        assert_eq!(
+            test_ob.get_order(asks[2].order_id).unwrap().quantity_ahead,
+            4
+        );
+    }
 
-        // 7️⃣ Create new node with updated remaining quantity and other fields
-        let new_order = Arc::new(Order {
-            order_id: old_order_arc.order_id,
-            order_type: old_order_arc.order_type,
-            side: old_order_arc.side,
-            status: if new_order_remaining_quantity == 0 {
-                Status::Filled
-            } else {
-                Status::PartiallyFilled
-            },
-            price: old_order_arc.price,
-            original_quantity: old_order_arc.original_quantity,
-            remaining_quantity: new_order_remaining_quantity,
-            executed_quantity: new_order_executed_quantity,
-            timestamp: Utc::now().timestamp_micros(),
-            // copy other fields if necessary
-        });
+    #[test]
+    fn check_add_orders_reports_each_outcome() {
+        let mut test_ob = OrderBook::new();
+        let ladder: Vec<Arc<Order>> = (0..3)
+            .map(|step| Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 100 + step, 5)))
+            .chain([
+                Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 99, 0)),
+                Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 8)),
+                Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 20)),
+            ])
+            .collect();
+        let results = test_ob.add_orders(&ladder);
+        assert_eq!(results.len(), 6);
+        assert!(results[..3].iter().all(|result| {
+            result
+                .as_ref()
+                .is_ok_and(|result| result.status == Status::New && result.remaining == 5)
+        }));
+        assert!(matches!(
+            results[3],
+            Err(OrderBookError::InvalidQuantity { .. })
+        ));
 
-        let new_node = OrderNode {
-            link: LinkedListLink::new(),
-            order: new_order.clone(),
-        };
+        // fills the 100 level, rests the remainder
+        let partial = results[4].as_ref().unwrap();
+        assert_eq!(partial.trades.len(), 1);
+        assert_eq!(
+            (partial.remaining, partial.status),
+            (3, Status::PartiallyFilled)
+        );
+        // sweeps the rest of the asks and the unfilled 10 is canceled
+        let sweep = results[5].as_ref().unwrap();
+        assert_eq!(sweep.order_id, ladder[5].order_id);
+        assert_eq!(sweep.trades.len(), 2);
+        assert_eq!((sweep.remaining, sweep.status), (10, Status::Canceled));
+    }
 
-        // 8️⃣ Insert new node after old node
-        cursor.insert_after(Box::new(new_node));
+    #[test]
+    fn check_trades_recorded_in_trade_history() {
+        let mut test_ob = OrderBook::new();
+        let sell_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 4));
+        test_ob.add_order(&sell_order).unwrap();
+        test_ob.add_order(&buy_order).unwrap();
 
-        // 9️⃣ Remove old node
-        cursor.remove();
+        let history = test_ob.trade_history();
+        assert_eq!(history.total_volume(), 4);
+        assert_eq!(history.last_trade().unwrap().price(), 10);
+        assert_eq!(history.trades_for_order(sell_order.order_id).len(), 1);
+        assert!(history.trades_at_price(11).is_empty());
+    }
 
-        // Remove filled order from price level
-        if new_order.remaining_quantity == 0 {
-            target_level.orders.pop_front(); // order removal
-            target_level.order_count -= 1; // update order count
-        }
+    #[test]
+    fn check_validate_then_commit() {
+        let mut test_ob = OrderBook::new();
+        let sell_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        let token = test_ob
+            .validate(Command::NewOrder(sell_order.clone()))
+            .unwrap();
+        assert_eq!(test_ob.get_best_ask(), None);
+        test_ob.commit(token).unwrap();
+        assert_eq!(test_ob.get_best_ask().unwrap(), 10);
 
-        // Update HashMap entry
-        if let Some(entry) = self.orders.get_mut(&old_order_arc.order_id) {
-            entry.order = new_order;
-        }
+        let empty_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 0));
+        assert!(test_ob.validate(Command::NewOrder(empty_order)).is_err());
 
-        //1️⃣ Remove empty price level if needed
-        if target_level.orders.is_empty() {
-            self.remove_empty_price_level(best_price, order);
-        };
-        Ok(trade)
+        let token = test_ob
+            .validate(Command::CancelOrder(sell_order.order_id))
+            .unwrap();
+        let canceled = test_ob.commit(token).unwrap();
+        assert_eq!(canceled.order_id, sell_order.order_id);
+        assert_eq!(
+            (canceled.remaining, canceled.status),
+            (10, Status::Canceled)
+        );
+        assert_eq!(test_ob.get_best_ask(), None);
     }
 
-    fn match_at_price_level_optimized(
-        &mut self,
-        best_price: Price,
-        incoming_order: &Arc<Order>,
-        max_quantity: Quantity,
-    ) -> Option<Trade> {
-        let level_ref = match incoming_order.side {
-            Side::Buy => self.asks.get(&best_price)?,
-            Side::Sell => self.bids.get(&Reverse(best_price))?,
-        };
+    #[test]
+    fn check_validation_token_expires_on_book_change() {
+        let mut test_ob = OrderBook::new();
+        let buy_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
+        let token = test_ob.validate(Command::NewOrder(buy_order)).unwrap();
 
-        let level_index = level_ref.index;
-        let price_level = self.price_levels[level_index].as_mut()?;
+        let other_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 10));
+        test_ob.add_order(&other_order).unwrap();
 
-        // Get front order info
-        let front_cursor = price_level.orders.front();
-        let node_ptr = front_cursor
-            .get()
-            .map(|node| node as *const OrderNode as *mut OrderNode)?;
-        let node_ptr = unsafe { NonNull::new_unchecked(node_ptr) };
+        assert!(matches!(
+            test_ob.commit(token),
+            Err(OrderBookError::StaleValidationToken { .. })
+        ));
+        assert_eq!(test_ob.get_best_bid(), None);
+    }
 
-        // Create cursor from pointer for mutation
-        let mut cursor = unsafe { price_level.orders.cursor_mut_from_ptr(node_ptr.as_ptr()) };
+    #[test]
+    fn check_snapshot_restore_round_trip() {
+        let mut test_ob = OrderBook::new();
+        let first_bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 10));
+        let second_bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 5));
+        let ask = Arc::new(Order::new(OrderType::GoodTillDate(100), Side::Sell, 11, 7));
+        test_ob.add_order(&first_bid).unwrap();
+        test_ob.add_order(&second_bid).unwrap();
+        test_ob.add_order(&ask).unwrap();
+        // partial fill so the snapshot carries updated quantities
+        let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 0, 3));
+        test_ob.add_order(&taker).unwrap();
 
-        let resting_order = cursor.get()?.order.clone();
-        let trade_quantity = max_quantity.min(resting_order.remaining_quantity);
-        let trade_price = best_price;
+        let snapshot = test_ob.snapshot();
+        assert_eq!(snapshot.bids[0].orders.len(), 2);
+        assert_eq!(snapshot.bids[0].orders[0].remaining_quantity, 7);
 
-        let trade = Trade::new(
-            incoming_order.order_id,
-            resting_order.order_id,
-            trade_price,
-            trade_quantity,
+        let mut restored = OrderBook::restore(snapshot.clone()).unwrap();
+        assert_eq!(restored.get_depth(5), test_ob.get_depth(5));
+        assert_eq!(
+            restored.orders_ahead(second_bid.order_id).unwrap()[0].order_id,
+            first_bid.order_id
         );
+        assert_eq!(restored.snapshot().bids, snapshot.bids);
+        assert_eq!(restored.expire_due(100).unwrap().len(), 1);
+    }
 
-        if trade_quantity == resting_order.remaining_quantity {
-            // Full fill - remove order
-            cursor.remove();
-            price_level.volume -= trade_quantity;
-            price_level.order_count -= 1;
-            self.orders.remove(&resting_order.order_id);
-        } else {
-            // Partial fill - update using cursor.replace()
-            let new_quantity = resting_order.remaining_quantity - trade_quantity;
-            let mut updated_order = (*resting_order).clone();
-            updated_order.remaining_quantity = new_quantity;
-            updated_order.executed_quantity += trade_quantity;
-            updated_order.status = Status::PartiallyFilled;
+    #[test]
+    fn check_cancel_by_key_until_the_key_goes_stale() {
+        let mut test_ob = OrderBook::new();
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 10));
+        let key = test_ob.add_order(&ask).unwrap().key.unwrap();
+        // a partial fill leaves the order, and its key, in place
+        let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 4));
+        assert_eq!(test_ob.add_order(&taker).unwrap().key, None);
 
-            let updated_node = Box::new(OrderNode::new(Arc::new(updated_order)));
-            cursor.replace_with(updated_node);
+        let canceled = test_ob.cancel_by_key(key).unwrap();
+        assert_eq!((canceled.order_id, canceled.remaining), (ask.order_id, 6));
+        assert!(!test_ob.contains_order(ask.order_id));
+        assert_eq!(test_ob.get_best_ask(), None);
 
-            price_level.volume -= trade_quantity;
-        }
+        // the freed slot goes to the next order under a new generation
+        let next = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 12, 5));
+        let next_key = test_ob.add_order(&next).unwrap().key.unwrap();
+        assert_ne!(next_key, key);
+        assert!(matches!(
+            test_ob.cancel_by_key(key),
+            Err(OrderBookError::StaleOrderKey { .. })
+        ));
+        assert_eq!(test_ob.get_best_ask(), Some(12));
+    }
 
-        if price_level.orders.is_empty() {
-            self.remove_empty_price_level(best_price, incoming_order);
-        }
+    #[test]
+    fn check_restore_refuses_orders_the_ladder_cannot_hold() {
+        let mut test_ob = OrderBook::new();
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 500, 10));
+        test_ob.add_order(&bid).unwrap();
 
-        Some(trade)
+        let config = OrderBookConfig {
+            ladder: LadderKind::TickArray {
+                min_price: 0,
+                max_price: 200,
+            },
+            ..OrderBookConfig::default()
+        };
+        assert!(matches!(
+            OrderBook::restore_with_config(test_ob.snapshot(), config),
+            Err(OrderBookError::PriceOutsideBand {
+                price: 500,
+                low: 0,
+                high: 200
+            })
+        ));
     }
 
-    fn remove_empty_price_level(
-        &mut self,
-        price: Price,
-        order: &Arc<Order>,
-    ) -> Result<(), OrderBookError> {
-        match order.side {
-            Side::Buy => {
-                if let Some(price_level_ref) = self.asks.remove(&price) {
-                    // reset to None
-                    self.price_levels[price_level_ref.index] = None;
-                    // store index for later reuse
-                    self.free_indices.push_back(price_level_ref.index);
-                    // remove by_price
-                    self.by_price.remove(&price);
-                } else {
-                    return Err(OrderBookError::PriceLevelNotFound { price });
-                }
-            }
-            Side::Sell => {
-                if let Some(price_level_ref) = self.bids.remove(&Reverse(price)) {
-                    self.price_levels[price_level_ref.index] = None;
-                    self.free_indices.push_back(price_level_ref.index);
-                    self.by_price.remove(&price);
-                } else {
-                    return Err(OrderBookError::PriceLevelNotFound { price });
-                }
-            }
+    #[test]
+    fn check_restore_refuses_corrupted_snapshots() {
+        let mut test_ob = OrderBook::new();
+        for (side, price, quantity) in [(Side::Buy, 9, 10), (Side::Buy, 9, 5), (Side::Sell, 11, 7)]
+        {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            test_ob.add_order(&order).unwrap();
         }
-        Ok(())
+        let snapshot = test_ob.snapshot();
+        let refused = |corrupt: fn(&mut BookSnapshot)| {
+            let mut snapshot = snapshot.clone();
+            corrupt(&mut snapshot);
+            match OrderBook::restore(snapshot) {
+                Err(OrderBookError::SnapshotCorrupted { line, reason }) => (line, reason),
+                restored => panic!("restored {:?}", restored.map(|book| book.snapshot())),
+            }
+        };
+
+        let duplicated = refused(|snapshot| {
+            snapshot.bids[0].orders[1].order_id = snapshot.bids[0].orders[0].order_id;
+        });
+        assert_eq!(duplicated, (3, "duplicated order id".to_string()));
+        let empty = refused(|snapshot| snapshot.asks[0].orders[0].remaining_quantity = 0);
+        assert_eq!(empty.0, 4);
+        let misfiled = refused(|snapshot| snapshot.bids[0].orders[1].price = 8);
+        assert_eq!(misfiled.0, 3);
+        let wrong_side = refused(|snapshot| snapshot.asks[0].orders[0].side = Side::Buy);
+        assert_eq!(wrong_side.0, 4);
+        let overflow = refused(|snapshot| {
+            snapshot.bids[0].orders[1].remaining_quantity = Quantity::MAX;
+        });
+        assert_eq!(overflow.0, 3);
+        let crossed = refused(|snapshot| {
+            snapshot.asks[0].price = 9;
+            snapshot.asks[0].orders[0].price = 9;
+        });
+        assert_eq!(crossed, (4, "crossed book outside an auction".to_string()));
+
+        // during an auction crossed orders rest as they are
+        let mut auction = snapshot.clone();
+        auction.instrument_state = InstrumentState::Auction;
+        auction.asks[0].price = 9;
+        auction.asks[0].orders[0].price = 9;
+        let restored = OrderBook::restore(auction).unwrap();
+        assert_eq!(restored.get_best_ask(), Some(9));
+        restored.assert_consistent();
     }
 
-    fn match_and_add_to_book(
-        &mut self,
-        order: &Arc<Order>,
-    ) -> Result<Vec<Option<Trade>>, OrderBookError> {
-        let trades: Vec<Option<Trade>> = self.match_order(order).unwrap();
+    #[test]
+    fn check_fork_branches_independently() {
+        let mut test_ob = OrderBook::new();
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 10));
+        test_ob.add_order(&ask).unwrap();
 
-        let traded_quantity: Quantity = trades.iter().map(|t| t.as_ref().unwrap().quantity).sum();
-        let remaining_quantity = order.remaining_quantity - traded_quantity;
+        let mut branch = test_ob.fork().unwrap();
+        let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 10));
+        assert_eq!(branch.add_order(&taker).unwrap().trades.len(), 1);
 
-        if remaining_quantity > 0 {
-            let mut remaining_order = order.as_ref().clone();
-            remaining_order.remaining_quantity = remaining_quantity;
-            self.add_order_to_book(&Arc::new(remaining_order));
-        }
+        assert_eq!(branch.get_best_ask(), None);
+        assert_eq!(test_ob.get_best_ask().unwrap(), 11);
+        test_ob.cancel_order(ask.order_id).unwrap();
+    }
 
-        Ok(trades)
+    #[test]
+    fn check_display_renders_an_aligned_ladder() {
+        let mut test_ob = OrderBook::new();
+        for (side, price, quantity) in [
+            (Side::Sell, 102, 5),
+            (Side::Sell, 101, 6),
+            (Side::Buy, 100, 4),
+            (Side::Buy, 99, 1200),
+        ] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            test_ob.add_order(&order).unwrap();
+        }
+        assert_eq!(
+            test_ob.to_string(),
+            [
+                "bid total   bid  price  ask  ask total",
+                "                   102    5         11",
+                "                   101    6          6",
+                "        4     4    100",
+                "     1204  1200     99",
+            ]
+            .join("\n")
+        );
+        assert_eq!(format!("{:.1}", test_ob), test_ob.render(1));
     }
 
-    fn match_market(&mut self, order: &Arc<Order>) -> Result<Vec<Option<Trade>>, OrderBookError> {
-        let aggressive_price = match order.side {
-            Side::Buy => Price::MAX, // buy at any price
-            Side::Sell => 0,         // sell at any price
-        };
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_snapshot_serializes_to_json() {
+        let mut test_ob = OrderBook::new();
+        let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 10));
+        test_ob.add_order(&bid).unwrap();
 
-        let mut order_arc = order.as_ref().clone();
-        order_arc.price = aggressive_price;
-        self.match_order(&Arc::new(order_arc))
+        let json = serde_json::to_string(&test_ob.snapshot()).unwrap();
+        let snapshot: BookSnapshot = serde_json::from_str(&json).unwrap();
+        let restored = OrderBook::restore(snapshot).unwrap();
+        assert_eq!(restored.orders_ahead(bid.order_id).unwrap(), Vec::new());
+        assert_eq!(restored.get_best_bid().unwrap(), 9);
     }
 
-    fn match_fill_or_kill(
-        &mut self,
-        order: &Arc<Order>,
-    ) -> Result<Vec<Option<Trade>>, OrderBookError> {
-        let available_quantity: Quantity = self.get_available_quantity(order);
+    #[test]
+    fn check_matching_and_cancel_on_every_queue_backend() {
+        use crate::orderbook::order_queue::QueueBackendKind;
 
-        if available_quantity <= order.original_quantity {
-            info!("FOK order is canceled due to insufficient quantity!");
-            Ok(Vec::new())
-        } else {
-            info!("Return FOK match orders");
-            self.match_order(order)
+        for backend in [
+            QueueBackendKind::IntrusiveList,
+            QueueBackendKind::VecDeque,
+            QueueBackendKind::Slab,
+        ] {
+            let config = OrderBookConfig {
+                queue_backend: backend,
+                ..OrderBookConfig::default()
+            };
+            let mut test_ob = OrderBook::with_config(config);
+            let first = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5));
+            let second = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5));
+            let third = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5));
+            test_ob.add_order(&first).unwrap();
+            test_ob.add_order(&second).unwrap();
+            test_ob.add_order(&third).unwrap();
+
+            test_ob.cancel_order(second.order_id).unwrap();
+            let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 7));
+            let trades = test_ob.add_order(&taker).unwrap().trades;
+            assert_eq!(trades.len(), 2, "{:?}", backend);
+            assert_eq!(trades[1].ask_order_id, third.order_id);
+
+            // partially filled order can still be canceled through its handle
+            test_ob.cancel_order(third.order_id).unwrap();
+            assert_eq!(test_ob.get_depth(1), OrderbookLevelInfos::default());
         }
     }
 
-    // Handy function to sum over volume over vector indices
-    fn sum_volume_at<I>(&self, indices: I) -> Quantity
-    where
-        I: IntoIterator<Item = usize>,
-    {
-        indices
-            .into_iter()
-            .filter_map(|i| self.price_levels.get(i).and_then(|opt| opt.as_ref()))
-            .map(|level| level.volume)
-            .sum()
-    }
+    #[test]
+    fn check_hidden_orders_match_but_stay_out_of_market_data() {
+        for hidden_priority in [
+            HiddenPriority::BehindDisplayed,
+            HiddenPriority::TimePriority,
+        ] {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                hidden_priority,
+                ..OrderBookConfig::default()
+            });
+            let hidden_ask =
+                Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5).as_hidden());
+            let displayed_ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 3));
+            let hidden_only =
+                Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 9, 2).as_hidden());
+            test_ob.add_order(&hidden_ask).unwrap();
+            test_ob.add_order(&displayed_ask).unwrap();
+            test_ob.add_order(&hidden_only).unwrap();
 
-    fn get_available_quantity(&self, order: &Arc<Order>) -> Quantity {
-        let side = order.side;
-        let order_price = order.price;
+            // the level at 9 holds only hidden volume and does not show at all
+            assert_eq!(
+                test_ob.get_depth(5).asks,
+                vec![LevelInfo {
+                    price: 10,
+                    volume: 3
+                }]
+            );
+            let bbo = test_ob.bbo();
+            assert_eq!((bbo.ask_price, bbo.ask_qty), (Some(10), 3));
+            assert_eq!(test_ob.get_best_ask(), Some(9));
 
-        let indices: Vec<usize> = match side {
-            Side::Buy => self
-                .bids
-                .range(..=Reverse(order_price))
-                .map(|(_, level_ref)| level_ref.index)
-                .collect(),
-            Side::Sell => self
-                .asks
-                .range(..=order_price)
-                .map(|(_, level_ref)| level_ref.index)
-                .collect(),
-        };
-        self.sum_volume_at(indices)
+            let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 4));
+            let trades = test_ob.add_order(&taker).unwrap().trades;
+            let makers: Vec<OrderId> = trades.iter().map(Trade::ask_order_id).collect();
+            let second_maker = match hidden_priority {
+                HiddenPriority::BehindDisplayed => displayed_ask.order_id,
+                HiddenPriority::TimePriority => hidden_ask.order_id,
+            };
+            assert_eq!(makers, vec![hidden_only.order_id, second_maker]);
+            let bbo = test_ob.bbo();
+            assert_eq!(bbo.ask_price, Some(10), "{:?}", hidden_priority);
+            assert_eq!(test_ob.imbalance().ask_volume, bbo.ask_qty);
+        }
     }
 
-    pub fn get_best_bid(&self) -> Option<Price> {
-        if let Some((Reverse(price), _)) = self.bids.iter().next() {
-            info!("Best ask price: {}", price);
-            Some(*price)
-        } else {
-            info!("No bid price available");
+    #[test]
+    fn check_pegged_orders_follow_their_reference() {
+        let mut test_ob = OrderBook::new();
+        let pegged = |peg_type: PegType, side: Side, offset: Price| {
+            Arc::new(Order::new(OrderType::LimitOrder, side, 0, 1).with_peg(peg_type, offset))
+        };
+        let primary = pegged(PegType::Primary, Side::Buy, 0);
+        assert!(matches!(
+            test_ob.add_order(&primary),
+            Err(OrderBookError::PegReferenceUnavailable { .. })
+        ));
 
-            None
+        test_ob
+            .add_order(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Buy,
+                10,
+                5,
+            )))
+            .unwrap();
+        test_ob
+            .add_order(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Sell,
+                15,
+                5,
+            )))
+            .unwrap();
+        let midpoint_bid = pegged(PegType::Midpoint, Side::Buy, 0);
+        let midpoint_ask = pegged(PegType::Midpoint, Side::Sell, 0);
+        let market = pegged(PegType::Market, Side::Buy, -3);
+        for order in [&primary, &midpoint_bid, &midpoint_ask, &market] {
+            test_ob.add_order(order).unwrap();
         }
+        let price_of =
+            |book: &OrderBook, order: &Arc<Order>| book.get_order(order.order_id).unwrap().price;
+        assert_eq!(price_of(&test_ob, &primary), 10);
+        assert_eq!(price_of(&test_ob, &midpoint_bid), 12);
+        assert_eq!(price_of(&test_ob, &midpoint_ask), 13);
+        assert_eq!(price_of(&test_ob, &market), 12);
+
+        // a better bid drags the primary and midpoint pegs along, and they follow it back
+        let better_bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 12, 5));
+        test_ob.add_order(&better_bid).unwrap();
+        assert_eq!(price_of(&test_ob, &primary), 12);
+        assert_eq!(price_of(&test_ob, &midpoint_bid), 13);
+        assert_eq!(price_of(&test_ob, &midpoint_ask), 14);
+        test_ob.cancel_order(better_bid.order_id).unwrap();
+        assert_eq!(price_of(&test_ob, &primary), 10);
+        assert_eq!(price_of(&test_ob, &midpoint_bid), 12);
     }
 
-    pub fn get_best_ask(&self) -> Option<Price> {
-        if let Some((price, _)) = self.asks.iter().next() {
-            info!("Best ask price: {}", price);
-            Some(*price)
-        } else {
-            info!("No ask price available");
-            None
+    #[test]
+    fn check_pegs_do_not_reference_each_other() {
+        let mut test_ob = OrderBook::new();
+        test_ob
+            .add_order(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Buy,
+                10,
+                5,
+            )))
+            .unwrap();
+        test_ob
+            .add_order(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Sell,
+                20,
+                5,
+            )))
+            .unwrap();
+        // each peg improving on the best bid by a tick would otherwise ratchet up to the ask
+        let first = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Buy, 0, 1).with_peg(PegType::Primary, 1),
+        );
+        let second = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Buy, 0, 1).with_peg(PegType::Primary, 1),
+        );
+        test_ob.add_order(&first).unwrap();
+        test_ob.add_order(&second).unwrap();
+        test_ob
+            .add_order(&Arc::new(Order::new(
+                OrderType::LimitOrder,
+                Side::Sell,
+                19,
+                5,
+            )))
+            .unwrap();
+        assert_eq!(test_ob.get_order(first.order_id).unwrap().price, 11);
+        assert_eq!(
+            test_ob.get_order(second.order_id).unwrap().queue_position,
+            1
+        );
+        assert_eq!(test_ob.get_best_bid(), Some(11));
+    }
+
+    #[test]
+    fn check_pegs_reprice_on_timer_interval() {
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            peg_reprice_interval: Some(Duration::from_millis(100)),
+            ..OrderBookConfig::default()
+        });
+        test_ob.set_clock(Some(1_000_000));
+        let limit = |side, price| Arc::new(Order::new(OrderType::LimitOrder, side, price, 5));
+        test_ob.add_order(&limit(Side::Buy, 10)).unwrap();
+        let pegged = Arc::new(
+            Order::new(OrderType::GoodTillDate(1_150), Side::Buy, 0, 1)
+                .with_peg(PegType::Primary, 0),
+        );
+        test_ob.add_order(&pegged).unwrap();
+        assert_eq!(test_ob.get_order(pegged.order_id).unwrap().price, 10);
+
+        // the better bid is only followed once the interval has elapsed
+        test_ob.add_order(&limit(Side::Buy, 12)).unwrap();
+        assert_eq!(test_ob.get_order(pegged.order_id).unwrap().price, 10);
+        assert!(test_ob.tick(1_099).unwrap().is_empty());
+        assert_eq!(test_ob.get_order(pegged.order_id).unwrap().price, 10);
+        assert!(test_ob.tick(1_100).unwrap().is_empty());
+        assert_eq!(test_ob.get_order(pegged.order_id).unwrap().price, 12);
+
+        let expired = test_ob.tick(1_150).unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].order_id, pegged.order_id);
+        assert!(test_ob.get_order(pegged.order_id).is_none());
+    }
+
+    #[test]
+    fn check_auction_imbalance_published_on_interval() {
+        #[derive(Clone, Default)]
+        struct Imbalances(Arc<Mutex<Vec<IndicativeUncross>>>);
+        impl OrderBookListener for Imbalances {
+            fn on_auction_imbalance(&mut self, indicative: &IndicativeUncross) {
+                self.0.lock().unwrap().push(*indicative);
+            }
         }
+
+        let mut test_ob = OrderBook::with_config(OrderBookConfig {
+            imbalance_interval: Some(Duration::from_millis(100)),
+            ..OrderBookConfig::default()
+        });
+        let imbalances = Imbalances::default();
+        test_ob.add_listener(Box::new(imbalances.clone()));
+        test_ob.set_clock(Some(1_000_000));
+        test_ob
+            .set_instrument_state(InstrumentState::Auction)
+            .unwrap();
+        let limit = |side, price, quantity| {
+            Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+        };
+        test_ob.add_order(&limit(Side::Buy, 10, 8)).unwrap();
+        test_ob.add_order(&limit(Side::Sell, 10, 5)).unwrap();
+
+        test_ob.tick(1_099).unwrap();
+        assert!(imbalances.0.lock().unwrap().is_empty());
+        test_ob.tick(1_100).unwrap();
+        test_ob.add_order(&limit(Side::Sell, 10, 1)).unwrap();
+        test_ob.tick(1_200).unwrap();
+        let published: Vec<(Quantity, Option<Side>)> = imbalances
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|imbalance| (imbalance.surplus, imbalance.surplus_side))
+            .collect();
+        assert_eq!(published, vec![(3, Some(Side::Buy)), (2, Some(Side::Buy))]);
+
+        // nothing once continuous trading resumes
+        test_ob.uncross().unwrap();
+        test_ob
+            .set_instrument_state(InstrumentState::Active)
+            .unwrap();
+        test_ob.tick(1_300).unwrap();
+        assert_eq!(imbalances.0.lock().unwrap().len(), 2);
     }
-}
 
-#[cfg(test)]
-mod orderbook_tests {
-    use super::*;
+    #[test]
+    fn check_min_qty_policies() {
+        for policy in [MinQtyPolicy::Reject, MinQtyPolicy::DropConstraint] {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                min_qty_policy: policy,
+                ..OrderBookConfig::default()
+            });
+            test_ob
+                .add_order(&Arc::new(Order::new(
+                    OrderType::LimitOrder,
+                    Side::Sell,
+                    10,
+                    3,
+                )))
+                .unwrap();
+            test_ob
+                .add_order(&Arc::new(Order::new(
+                    OrderType::LimitOrder,
+                    Side::Sell,
+                    11,
+                    3,
+                )))
+                .unwrap();
+
+            // 6 can trade up to 11, only 3 at 10
+            let ioc = Arc::new(
+                Order::new(OrderType::ImmediateOrCancel, Side::Buy, 11, 10).with_min_qty(6),
+            );
+            assert_eq!(test_ob.add_order(&ioc).unwrap().trades.len(), 2);
+
+            for price in [10, 11] {
+                test_ob
+                    .add_order(&Arc::new(Order::new(
+                        OrderType::LimitOrder,
+                        Side::Sell,
+                        price,
+                        3,
+                    )))
+                    .unwrap();
+            }
+            let ioc = Arc::new(
+                Order::new(OrderType::ImmediateOrCancel, Side::Buy, 10, 10).with_min_qty(4),
+            );
+            let limit =
+                Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10).with_min_qty(4));
+            match policy {
+                MinQtyPolicy::Reject => {
+                    assert!(matches!(
+                        test_ob.add_order(&ioc),
+                        Err(OrderBookError::MinQtyUnavailable { available: 3, .. })
+                    ));
+                    assert!(test_ob.add_order(&limit).is_err());
+                    assert_eq!(test_ob.resting_order_count(), 2);
+                }
+                MinQtyPolicy::DropConstraint => {
+                    // the IOC is canceled untouched, the limit order trades and rests as usual
+                    assert!(test_ob.add_order(&ioc).unwrap().trades.is_empty());
+                    assert_eq!(test_ob.add_order(&limit).unwrap().trades.len(), 1);
+                    assert_eq!(
+                        test_ob
+                            .get_order(limit.order_id)
+                            .unwrap()
+                            .remaining_quantity,
+                        7
+                    );
+                }
+            }
+        }
+    }
 
     #[test]
-    fn check_add_new_limit_order() {
+    fn check_fill_or_kill_needs_the_whole_quantity() {
         let mut test_ob = OrderBook::new();
-        let limit_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
-        let trades = test_ob.add_order(&limit_order).unwrap();
-        assert_eq!(trades, Vec::new());
+        let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 5));
+        test_ob.add_order(&ask).unwrap();
+        let too_big = Arc::new(Order::new(OrderType::FillOrKill, Side::Buy, 10, 6));
+        let killed = test_ob.add_order(&too_big).unwrap();
+        assert!(killed.trades.is_empty());
+        assert_eq!((killed.remaining, killed.status), (6, Status::Canceled));
+        let exact = Arc::new(Order::new(OrderType::FillOrKill, Side::Buy, 10, 5));
+        assert_eq!(test_ob.add_order(&exact).unwrap().trades.len(), 1);
+        assert_eq!(test_ob.resting_order_count(), 0);
     }
 
     #[test]
-    fn check_add_new_limit_order_and_later_comsumed_by_market_order() {
+    fn check_book_id_generator_numbers_trades() {
         let mut test_ob = OrderBook::new();
-        let limit_order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 10));
-        let market_order = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 10, 10));
-
-        // limit order first arrives to the OB
-        {
-            test_ob.add_order(&limit_order).unwrap();
+        test_ob.set_id_generator(Box::new(MonotonicIdGenerator::new(1)));
+        let mut trade_ids: Vec<OrderId> = Vec::new();
+        for _ in 0..2 {
+            let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 1));
+            let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 1));
+            test_ob.add_order(&ask).unwrap();
+            let trades = test_ob.add_order(&bid).unwrap().trades;
+            trade_ids.extend(trades.iter().map(Trade::trade_id));
         }
-        // Market Order arrives later to consume the OB
-        let trades = test_ob.add_order(&market_order).unwrap();
-        assert_eq!(trades.iter().next().unwrap().price, 10);
-        assert_eq!(trades.iter().next().unwrap().quantity, 10);
-        assert_eq!(trades.len(), 1);
+        assert_eq!(
+            trade_ids,
+            vec![Uuid::from_u64_pair(0, 1), Uuid::from_u64_pair(0, 2)]
+        );
     }
 
     #[test]
-    fn check_get_best_bid_ask_in_multiple_limit_orders() {
+    fn check_cancel_by_client_order_id() {
         let mut test_ob = OrderBook::new();
-        {
-            let buy_order_1 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 10));
-            let buy_order_2 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 8, 5));
-            let buy_order_3 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 7, 3));
+        let first = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Sell, 10, 5)
+                .with_owner(1)
+                .with_client_order_id("abc-1"),
+        );
+        test_ob.add_order(&first).unwrap();
+        assert_eq!(
+            test_ob.order_id_for_client(1, "abc-1"),
+            Some(first.order_id)
+        );
+        // client order ids are scoped to their participant
+        assert_eq!(test_ob.order_id_for_client(2, "abc-1"), None);
+        let other_owner = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Sell, 11, 5)
+                .with_owner(2)
+                .with_client_order_id("abc-1"),
+        );
+        test_ob.add_order(&other_owner).unwrap();
 
-            test_ob.add_order(&buy_order_1).unwrap();
-            test_ob.add_order(&buy_order_2).unwrap();
-            test_ob.add_order(&buy_order_3).unwrap();
-        }
+        let duplicate = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Sell, 12, 5)
+                .with_owner(1)
+                .with_client_order_id("abc-1"),
+        );
+        assert!(matches!(
+            test_ob.add_order(&duplicate),
+            Err(OrderBookError::DuplicateClientOrderId { owner: 1, .. })
+        ));
+        let blank = Arc::new(
+            Order::new(OrderType::LimitOrder, Side::Sell, 12, 5).with_client_order_id("a b"),
+        );
+        assert!(matches!(
+            test_ob.add_order(&blank),
+            Err(OrderBookError::InvalidClientOrderId { .. })
+        ));
 
-        {
-            let sell_order_1 = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
-            let sell_order_2 = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 11, 5));
-            let sell_order_3 = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 12, 3));
+        test_ob.cancel_by_client_order_id(1, "abc-1").unwrap();
+        assert!(!test_ob.contains_order(first.order_id));
+        assert!(matches!(
+            test_ob.cancel_by_client_order_id(1, "abc-1"),
+            Err(OrderBookError::ClientOrderNotFound { owner: 1, .. })
+        ));
 
-            test_ob.add_order(&sell_order_1).unwrap();
-            test_ob.add_order(&sell_order_2).unwrap();
-            test_ob.add_order(&sell_order_3).unwrap();
+        // once filled the id is free to be reused
+        let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 5));
+        test_ob.add_order(&taker).unwrap();
+        assert_eq!(test_ob.order_id_for_client(2, "abc-1"), None);
+        test_ob.add_order(&duplicate).unwrap();
+        assert_eq!(
+            test_ob.order_id_for_client(1, "abc-1"),
+            Some(duplicate.order_id)
+        );
+    }
+
+    #[test]
+    fn check_tick_array_ladder_matches_like_btree() {
+        let ladders = [
+            LadderKind::BTree,
+            LadderKind::TickArray {
+                min_price: 50,
+                max_price: 150,
+            },
+        ];
+        for ladder in ladders {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                ladder,
+                ..OrderBookConfig::default()
+            });
+            for price in [101, 103, 102] {
+                let ask = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, price, 5));
+                test_ob.add_order(&ask).unwrap();
+            }
+            let bid = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 99, 5));
+            test_ob.add_order(&bid).unwrap();
+
+            let sweep = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 102, 12));
+            let trades: Vec<Trade> = test_ob.add_order(&sweep).unwrap().trades;
+            let prices: Vec<Price> = trades.iter().map(|trade| trade.price).collect();
+            assert_eq!(prices, vec![101, 102], "{:?}", ladder);
+            // the unfilled 2 rest as the new best bid
+            assert_eq!(test_ob.get_best_bid(), Some(102), "{:?}", ladder);
+            assert_eq!(test_ob.get_best_ask(), Some(103), "{:?}", ladder);
+            assert_eq!(
+                test_ob.get_depth(5).bids,
+                vec![
+                    LevelInfo {
+                        price: 102,
+                        volume: 2
+                    },
+                    LevelInfo {
+                        price: 99,
+                        volume: 5
+                    }
+                ],
+                "{:?}",
+                ladder
+            );
         }
-        assert_eq!(test_ob.get_best_bid().unwrap(), 9);
-        assert_eq!(test_ob.get_best_ask().unwrap(), 10);
+
+        let mut bounded = OrderBook::with_config(OrderBookConfig {
+            ladder: ladders[1],
+            ..OrderBookConfig::default()
+        });
+        let outside = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 151, 5));
+        assert!(matches!(
+            bounded.add_order(&outside),
+            Err(OrderBookError::PriceOutsideBand {
+                price: 151,
+                low: 50,
+                high: 150
+            })
+        ));
     }
 
     #[test]
-    fn check_add_multiples_limit_order_and_later_comsumed_by_an_market_order() {
+    fn check_partial_fills_update_pooled_order_in_place() {
         let mut test_ob = OrderBook::new();
-        let market_order = Arc::new(Order::new(OrderType::MarketOrder, Side::Sell, 0, 10));
+        let resting = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, 10, 10));
+        test_ob.add_order(&resting).unwrap();
+        for _ in 0..4 {
+            let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 10, 2));
+            test_ob.add_order(&taker).unwrap();
+        }
+        let view = test_ob.get_order(resting.order_id).unwrap();
+        assert_eq!(view.remaining_quantity, 2);
+        assert_eq!(view.executed_quantity, 8);
+        assert_eq!(test_ob.get_depth(1).asks[0].volume, 2);
+        // one slot, filled four times without being reallocated
+        assert_eq!(test_ob.pool.capacity(), 1);
+    }
 
-        // limit order first arrives to the OB
-        {
-            let buy_order_1 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 9, 3));
-            let buy_order_2 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 8, 5));
-            let buy_order_3 = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 7, 10));
+    #[test]
+    fn check_top_of_book_cache_follows_levels() {
+        for ladder in [
+            LadderKind::BTree,
+            LadderKind::TickArray {
+                min_price: 0,
+                max_price: 1_000,
+            },
+        ] {
+            let mut test_ob = OrderBook::with_config(OrderBookConfig {
+                ladder,
+                ..OrderBookConfig::default()
+            });
+            let limit = |side, price, quantity| {
+                Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+            };
+            let low_bid = limit(Side::Buy, 98, 5);
+            let high_bid = limit(Side::Buy, 99, 5);
+            test_ob.add_order(&low_bid).unwrap();
+            test_ob.add_order(&high_bid).unwrap();
+            test_ob.add_order(&limit(Side::Buy, 97, 5)).unwrap();
+            assert_eq!(test_ob.get_best_bid(), Some(99));
 
-            test_ob.add_order(&buy_order_1).unwrap();
-            test_ob.add_order(&buy_order_2).unwrap();
-            test_ob.add_order(&buy_order_3).unwrap();
+            // a worse level leaves the top alone, dropping the best one moves it to the next
+            test_ob.cancel_order(low_bid.order_id).unwrap();
+            assert_eq!(test_ob.get_best_bid(), Some(99));
+            test_ob.cancel_order(high_bid.order_id).unwrap();
+            assert_eq!(test_ob.get_best_bid(), Some(97));
+
+            test_ob.add_order(&limit(Side::Sell, 101, 2)).unwrap();
+            test_ob.add_order(&limit(Side::Sell, 102, 2)).unwrap();
+            test_ob.add_order(&limit(Side::Sell, 100, 2)).unwrap();
+            assert_eq!(test_ob.get_best_ask(), Some(100));
+            // a sweep through two levels leaves the third at the top
+            test_ob.add_order(&limit(Side::Buy, 101, 4)).unwrap();
+            assert_eq!(test_ob.get_best_ask(), Some(102));
+            test_ob.add_order(&limit(Side::Buy, 102, 3)).unwrap();
+            assert_eq!(test_ob.get_best_ask(), None);
+            assert_eq!(test_ob.get_best_bid(), Some(102));
+            test_ob.assert_consistent();
         }
-        // Market Order arrives later to consume the OB
-        let trades = test_ob.add_order(&market_order).unwrap();
-        assert_eq!(trades.len(), 3);
     }
 
     #[test]
@@ -632,3 +6078,262 @@ mod orderbook_tests {
     #[test]
     fn check_consume_limit_order_by_fok_order() {}
 }
+
+#[cfg(test)]
+mod orderbook_proptests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::orderbook::config::HiddenPriority;
+    use crate::orderbook::order_queue::QueueBackendKind;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Add {
+            order_type: OrderType,
+            side: Side,
+            price: Price,
+            quantity: Quantity,
+            hidden: bool,
+            peg: Option<Peg>,
+        },
+        // Index into the orders submitted so far, which may have left the book already
+        Cancel(usize),
+    }
+
+    fn order_type() -> impl Strategy<Value = OrderType> {
+        prop_oneof![
+            4 => Just(OrderType::LimitOrder),
+            2 => Just(OrderType::GoodTillCancel),
+            1 => Just(OrderType::MarketOrder),
+            1 => Just(OrderType::ImmediateOrCancel),
+            1 => Just(OrderType::FillOrKill),
+            1 => Just(OrderType::PostOnly),
+        ]
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            3 => (
+                order_type(),
+                prop_oneof![Just(Side::Buy), Just(Side::Sell)],
+                90..=110 as Price,
+                1..=20 as Quantity,
+                prop::bool::weighted(0.2),
+                prop::option::weighted(0.1, peg())
+            )
+                .prop_map(|(order_type, side, price, quantity, hidden, peg)| Op::Add {
+                    order_type,
+                    side,
+                    price,
+                    quantity,
+                    hidden,
+                    peg
+                }),
+            1 => any::<usize>().prop_map(Op::Cancel),
+        ]
+    }
+
+    fn peg() -> impl Strategy<Value = Peg> {
+        let peg_type = prop_oneof![
+            Just(PegType::Primary),
+            Just(PegType::Midpoint),
+            Just(PegType::Market),
+        ];
+        (peg_type, -2..=2 as Price).prop_map(|(peg_type, offset)| Peg { peg_type, offset })
+    }
+
+    fn config() -> impl Strategy<Value = OrderBookConfig> {
+        let backend = prop_oneof![
+            Just(QueueBackendKind::IntrusiveList),
+            Just(QueueBackendKind::VecDeque),
+            Just(QueueBackendKind::Slab),
+        ];
+        let ladder = prop_oneof![
+            Just(LadderKind::BTree),
+            Just(LadderKind::TickArray {
+                min_price: 0,
+                max_price: 200,
+            }),
+        ];
+        let hidden_priority = prop_oneof![
+            Just(HiddenPriority::TimePriority),
+            Just(HiddenPriority::BehindDisplayed),
+        ];
+        (backend, ladder, hidden_priority).prop_map(|(queue_backend, ladder, hidden_priority)| {
+            OrderBookConfig {
+                queue_backend,
+                ladder,
+                hidden_priority,
+                ..OrderBookConfig::default()
+            }
+        })
+    }
+
+    #[derive(Debug, Clone)]
+    enum Input {
+        Add {
+            order_type: OrderType,
+            side: Side,
+            price: Price,
+            quantity: Quantity,
+            min_qty: Option<Quantity>,
+            protection_price: Option<Price>,
+            peg: Option<Peg>,
+        },
+        Modify(usize, Price, Quantity),
+        Cancel(usize),
+        // Into an auction, or uncrossed back to continuous trading
+        Auction(bool),
+        Tick(i64),
+    }
+
+    // Any value at all, extremes included
+    fn price() -> impl Strategy<Value = Price> {
+        prop_oneof![
+            Just(Price::MIN),
+            Just(Price::MAX),
+            Just(0),
+            -3..=3 as Price,
+            any::<Price>(),
+        ]
+    }
+
+    fn quantity() -> impl Strategy<Value = Quantity> {
+        prop_oneof![
+            Just(0),
+            Just(Quantity::MAX),
+            1..=3 as Quantity,
+            any::<Quantity>()
+        ]
+    }
+
+    fn input() -> impl Strategy<Value = Input> {
+        let order_type = prop_oneof![
+            order_type(),
+            any::<i64>().prop_map(OrderType::GoodTillDate),
+            Just(OrderType::GoodForAuction),
+        ];
+        let peg_type = prop_oneof![
+            Just(PegType::Primary),
+            Just(PegType::Midpoint),
+            Just(PegType::Market),
+        ];
+        let peg = (peg_type, price()).prop_map(|(peg_type, offset)| Peg { peg_type, offset });
+        prop_oneof![
+            3 => (
+                order_type,
+                prop_oneof![Just(Side::Buy), Just(Side::Sell)],
+                price(),
+                quantity(),
+                prop::option::weighted(0.1, quantity()),
+                prop::option::weighted(0.1, price()),
+                prop::option::weighted(0.1, peg),
+            )
+                .prop_map(
+                    |(order_type, side, price, quantity, min_qty, protection_price, peg)| {
+                        Input::Add {
+                            order_type,
+                            side,
+                            price,
+                            quantity,
+                            min_qty,
+                            protection_price,
+                            peg,
+                        }
+                    }
+                ),
+            1 => (any::<usize>(), price(), quantity())
+                .prop_map(|(index, price, quantity)| Input::Modify(index, price, quantity)),
+            1 => any::<usize>().prop_map(Input::Cancel),
+            1 => any::<bool>().prop_map(Input::Auction),
+            1 => any::<i64>().prop_map(Input::Tick),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn check_arbitrary_input_is_rejected_not_panicked_on(
+            tick_array in any::<bool>(),
+            inputs in prop::collection::vec(input(), 1..100),
+        ) {
+            let ladder = if tick_array {
+                LadderKind::TickArray { min_price: -5, max_price: 5 }
+            } else {
+                LadderKind::BTree
+            };
+            let mut book = OrderBook::with_config(OrderBookConfig {
+                ladder,
+                ..OrderBookConfig::default()
+            });
+            let mut submitted: Vec<OrderId> = Vec::new();
+            for input in inputs {
+                match input {
+                    Input::Add {
+                        order_type,
+                        side,
+                        price,
+                        quantity,
+                        min_qty,
+                        protection_price,
+                        peg,
+                    } => {
+                        let order = Arc::new(Order {
+                            min_qty,
+                            protection_price,
+                            peg,
+                            ..Order::new(order_type, side, price, quantity)
+                        });
+                        submitted.push(order.order_id);
+                        let _ = book.add_order(&order);
+                    }
+                    Input::Modify(index, price, quantity) if !submitted.is_empty() => {
+                        let _ = book.modify_order(submitted[index % submitted.len()], price, quantity);
+                    }
+                    Input::Cancel(index) if !submitted.is_empty() => {
+                        let _ = book.cancel_order(submitted[index % submitted.len()]);
+                    }
+                    Input::Auction(true) => {
+                        let _ = book.set_instrument_state(InstrumentState::Auction).unwrap();
+                    }
+                    Input::Auction(false) => {
+                        book.uncross().unwrap();
+                        let _ = book.set_instrument_state(InstrumentState::Active).unwrap();
+                    }
+                    Input::Tick(now) => {
+                        book.tick(now).unwrap();
+                    }
+                    Input::Modify(..) | Input::Cancel(_) => {}
+                }
+                book.assert_consistent();
+            }
+        }
+
+        #[test]
+        fn check_invariants_hold_after_every_command(
+            config in config(),
+            ops in prop::collection::vec(op(), 1..200),
+        ) {
+            let mut book = OrderBook::with_config(config);
+            let mut submitted: Vec<OrderId> = Vec::new();
+            for op in ops {
+                match op {
+                    Op::Add { order_type, side, price, quantity, hidden, peg } => {
+                        let mut order = Order::new(order_type, side, price, quantity);
+                        order.hidden = hidden;
+                        order.peg = peg;
+                        let order = Arc::new(order);
+                        submitted.push(order.order_id);
+                        // Rejections (e.g. a crossing post-only) are fine, corruption is not
+                        let _ = book.add_order(&order);
+                    }
+                    Op::Cancel(index) if !submitted.is_empty() => {
+                        let _ = book.cancel_order(submitted[index % submitted.len()]);
+                    }
+                    Op::Cancel(_) => {}
+                }
+                book.assert_consistent();
+            }
+        }
+    }
+}
@@ -0,0 +1,602 @@
+//! Parquet export of the trade tape and of periodic book samples, so engine output loads straight
+//! into pandas, polars or DuckDB. The writer is self-contained: one row group with one
+//! uncompressed, PLAIN-encoded data page per column, and the metadata in Thrift's compact
+//! protocol as the format specifies. Each column chunk is written out as soon as it is encoded.
+
+use std::io::{self, Write};
+
+use crate::orderbook::order::Side;
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::sampler::BookSample;
+use crate::orderbook::types::OrderId;
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Enum values from parquet.thrift
+const TYPE_INT64: i32 = 2;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REQUIRED: i32 = 0;
+const OPTIONAL: i32 = 1;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_DATA: i32 = 0;
+const CONVERTED_UTF8: i32 = 0;
+const CONVERTED_TIMESTAMP_MILLIS: i32 = 9;
+const CONVERTED_TIMESTAMP_MICROS: i32 = 10;
+const CONVERTED_UINT_64: i32 = 14;
+
+// Compact protocol type ids
+const COMPACT_I32: u8 = 5;
+const COMPACT_I64: u8 = 6;
+const COMPACT_BINARY: u8 = 8;
+const COMPACT_LIST: u8 = 9;
+const COMPACT_STRUCT: u8 = 12;
+
+/// Write `trades`, e.g. `book.trade_history().trades_between(i64::MIN, i64::MAX)`, as a Parquet
+/// file with one row per trade. Timestamps are epoch micros, quantities unsigned.
+pub fn write_trades<W: Write>(out: W, trades: &[Trade]) -> io::Result<()> {
+    let ids = |id: fn(&Trade) -> OrderId| trades.iter().map(move |t| Some(id(t).to_string()));
+    let columns = [
+        Column::utf8("trade_id", false, ids(Trade::trade_id)),
+        Column::int64(
+            "timestamp",
+            Some(CONVERTED_TIMESTAMP_MICROS),
+            trades.iter().map(|t| Some(t.timestamp())),
+        ),
+        Column::int64(
+            "sequence",
+            Some(CONVERTED_UINT_64),
+            trades.iter().map(|t| Some(t.sequence() as i64)),
+        ),
+        Column::int64("price", None, trades.iter().map(|t| Some(t.price()))),
+        Column::int64(
+            "quantity",
+            Some(CONVERTED_UINT_64),
+            trades.iter().map(|t| Some(t.quantity() as i64)),
+        ),
+        Column::utf8("bid_order_id", false, ids(Trade::bid_order_id)),
+        Column::utf8("ask_order_id", false, ids(Trade::ask_order_id)),
+        // Null for auction trades, which have no aggressor
+        Column::utf8(
+            "aggressor",
+            true,
+            trades.iter().map(|t| {
+                t.aggressor_side().map(|side| match side {
+                    Side::Buy => "buy".to_string(),
+                    Side::Sell => "sell".to_string(),
+                })
+            }),
+        ),
+        Column::int64(
+            "maker_fee",
+            None,
+            trades.iter().map(|t| Some(t.maker_fee())),
+        ),
+        Column::int64(
+            "taker_fee",
+            None,
+            trades.iter().map(|t| Some(t.taker_fee())),
+        ),
+    ];
+    write_table(out, trades.len(), &columns)
+}
+
+/// Write the book samples a `BookSampler` took at its interval as a Parquet file with one row
+/// per sample. Timestamps are epoch millis; prices and the spread are null on an empty side.
+pub fn write_samples<W: Write>(out: W, samples: &[BookSample]) -> io::Result<()> {
+    let quantity =
+        |quantity: fn(&BookSample) -> u64| samples.iter().map(move |s| Some(quantity(s) as i64));
+    let columns = [
+        Column::int64(
+            "timestamp",
+            Some(CONVERTED_TIMESTAMP_MILLIS),
+            samples.iter().map(|s| Some(s.timestamp)),
+        ),
+        Column::optional_int64("best_bid", samples.iter().map(|s| s.best_bid)),
+        Column::optional_int64("best_ask", samples.iter().map(|s| s.best_ask)),
+        Column::optional_int64("spread", samples.iter().map(BookSample::spread)),
+        Column::int64(
+            "best_bid_qty",
+            Some(CONVERTED_UINT_64),
+            quantity(|s| s.best_bid_qty),
+        ),
+        Column::int64(
+            "best_ask_qty",
+            Some(CONVERTED_UINT_64),
+            quantity(|s| s.best_ask_qty),
+        ),
+        Column::int64(
+            "bid_depth",
+            Some(CONVERTED_UINT_64),
+            quantity(|s| s.bid_depth),
+        ),
+        Column::int64(
+            "ask_depth",
+            Some(CONVERTED_UINT_64),
+            quantity(|s| s.ask_depth),
+        ),
+    ];
+    write_table(out, samples.len(), &columns)
+}
+
+enum Values {
+    Int64(Vec<Option<i64>>),
+    Utf8(Vec<Option<String>>),
+}
+
+struct Column {
+    name: &'static str,
+    values: Values,
+    optional: bool,
+    // Logical type of the physical values, None for plain signed integers
+    converted_type: Option<i32>,
+}
+
+impl Column {
+    fn int64(
+        name: &'static str,
+        converted_type: Option<i32>,
+        values: impl Iterator<Item = Option<i64>>,
+    ) -> Self {
+        Column {
+            name,
+            values: Values::Int64(values.collect()),
+            optional: false,
+            converted_type,
+        }
+    }
+
+    fn optional_int64(name: &'static str, values: impl Iterator<Item = Option<i64>>) -> Self {
+        Column {
+            optional: true,
+            ..Column::int64(name, None, values)
+        }
+    }
+
+    fn utf8(
+        name: &'static str,
+        optional: bool,
+        values: impl Iterator<Item = Option<String>>,
+    ) -> Self {
+        Column {
+            name,
+            values: Values::Utf8(values.collect()),
+            optional,
+            converted_type: Some(CONVERTED_UTF8),
+        }
+    }
+
+    fn physical_type(&self) -> i32 {
+        match self.values {
+            Values::Int64(_) => TYPE_INT64,
+            Values::Utf8(_) => TYPE_BYTE_ARRAY,
+        }
+    }
+
+    fn defined(&self) -> Vec<bool> {
+        match &self.values {
+            Values::Int64(values) => values.iter().map(Option::is_some).collect(),
+            Values::Utf8(values) => values.iter().map(Option::is_some).collect(),
+        }
+    }
+
+    // Data page body: definition levels for an optional column, then the non-null values
+    fn page(&self) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        if self.optional {
+            let levels = rle_levels(&self.defined());
+            body.extend_from_slice(&length(levels.len(), "definition levels")?.to_le_bytes());
+            body.extend_from_slice(&levels);
+        }
+        match &self.values {
+            Values::Int64(values) => {
+                for value in values.iter().flatten() {
+                    body.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Values::Utf8(values) => {
+                for value in values.iter().flatten() {
+                    body.extend_from_slice(&length(value.len(), "string")?.to_le_bytes());
+                    body.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+        Ok(body)
+    }
+}
+
+// Definition levels (bit width 1) in the RLE/bit-packing hybrid, as RLE runs only
+fn rle_levels(defined: &[bool]) -> Vec<u8> {
+    let mut levels = Vec::new();
+    let mut start = 0;
+    while start < defined.len() {
+        let run = defined[start..]
+            .iter()
+            .take_while(|level| **level == defined[start])
+            .count();
+        write_varint(&mut levels, (run as u64) << 1);
+        levels.push(defined[start] as u8);
+        start += run;
+    }
+    levels
+}
+
+// Written as it goes, one column chunk at a time, so only the page being written is buffered.
+// Pass a `BufWriter` when writing to a file.
+fn write_table<W: Write>(mut out: W, num_rows: usize, columns: &[Column]) -> io::Result<()> {
+    // Page headers count values in an i32
+    let page_rows = i32::try_from(num_rows).map_err(|_| too_large("row count", num_rows))?;
+    out.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as i64;
+    // (offset, size including the page header) of each column chunk
+    let mut chunks = Vec::with_capacity(columns.len());
+    for column in columns {
+        let body = column.page()?;
+        let page_size = i32::try_from(body.len()).map_err(|_| too_large("page", body.len()))?;
+        let mut header = Compact::new();
+        header.i32(1, PAGE_DATA);
+        header.i32(2, page_size);
+        header.i32(3, page_size);
+        header.begin_struct(5);
+        header.i32(1, page_rows);
+        header.i32(2, ENCODING_PLAIN);
+        header.i32(3, ENCODING_RLE);
+        header.i32(4, ENCODING_RLE);
+        header.end_struct();
+        let header = header.finish();
+
+        out.write_all(&header)?;
+        out.write_all(&body)?;
+        let size = (header.len() + body.len()) as i64;
+        chunks.push((offset, size));
+        offset += size;
+    }
+
+    let mut footer = Compact::new();
+    footer.i32(1, 1);
+    footer.list(2, COMPACT_STRUCT, columns.len() + 1);
+    footer.begin_element();
+    footer.binary(4, b"schema");
+    footer.i32(5, columns.len() as i32);
+    footer.end_struct();
+    for column in columns {
+        footer.begin_element();
+        footer.i32(1, column.physical_type());
+        footer.i32(3, if column.optional { OPTIONAL } else { REQUIRED });
+        footer.binary(4, column.name.as_bytes());
+        if let Some(converted_type) = column.converted_type {
+            footer.i32(6, converted_type);
+        }
+        footer.end_struct();
+    }
+    footer.i64(3, page_rows.into());
+    footer.list(4, COMPACT_STRUCT, 1);
+    footer.begin_element();
+    footer.list(1, COMPACT_STRUCT, columns.len());
+    for (column, (offset, size)) in columns.iter().zip(chunks.iter()) {
+        footer.begin_element();
+        footer.i64(2, *offset);
+        footer.begin_struct(3);
+        footer.i32(1, column.physical_type());
+        footer.list(2, COMPACT_I32, 2);
+        footer.element_i32(ENCODING_PLAIN);
+        footer.element_i32(ENCODING_RLE);
+        footer.list(3, COMPACT_BINARY, 1);
+        footer.element_binary(column.name.as_bytes());
+        footer.i32(4, CODEC_UNCOMPRESSED);
+        footer.i64(5, page_rows.into());
+        footer.i64(6, *size);
+        footer.i64(7, *size);
+        footer.i64(9, *offset);
+        footer.end_struct();
+        footer.end_struct();
+    }
+    footer.i64(2, chunks.iter().map(|(_, size)| size).sum());
+    footer.i64(3, page_rows.into());
+    footer.end_struct();
+    footer.binary(6, b"orderbook-rs");
+    let footer = footer.finish();
+
+    out.write_all(&footer)?;
+    out.write_all(&length(footer.len(), "footer")?.to_le_bytes())?;
+    out.write_all(MAGIC)?;
+    out.flush()
+}
+
+// A length Parquet stores in a u32
+fn length(len: usize, what: &str) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| too_large(what, len))
+}
+
+fn too_large(what: &str, len: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} ({}) does not fit in a Parquet file", what, len),
+    )
+}
+
+// Thrift compact protocol writer for the few types the Parquet metadata uses
+struct Compact {
+    buffer: Vec<u8>,
+    // Id of the last field written in each open struct, field ids are delta-encoded
+    last_field: Vec<i16>,
+}
+
+impl Compact {
+    fn new() -> Self {
+        Compact {
+            buffer: Vec::new(),
+            last_field: vec![0],
+        }
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_field.last_mut().expect("Field outside a struct!");
+        match id - *last {
+            delta @ 1..=15 => self.buffer.push((delta as u8) << 4 | kind),
+            _ => {
+                self.buffer.push(kind);
+                write_varint(&mut self.buffer, zigzag(id as i64));
+            }
+        }
+        *last = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, COMPACT_I32);
+        self.element_i32(value);
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, COMPACT_I64);
+        write_varint(&mut self.buffer, zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, COMPACT_BINARY);
+        self.element_binary(value);
+    }
+
+    fn list(&mut self, id: i16, element_kind: u8, len: usize) {
+        self.field(id, COMPACT_LIST);
+        if len < 15 {
+            self.buffer.push((len as u8) << 4 | element_kind);
+        } else {
+            self.buffer.push(0xF0 | element_kind);
+            write_varint(&mut self.buffer, len as u64);
+        }
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, COMPACT_STRUCT);
+        self.begin_element();
+    }
+
+    // A struct element of a list, closed with `end_struct`
+    fn begin_element(&mut self) {
+        self.last_field.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.buffer.push(0);
+        self.last_field.pop();
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        write_varint(&mut self.buffer, zigzag(value as i64));
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        write_varint(&mut self.buffer, value.len() as u64);
+        self.buffer.extend_from_slice(value);
+    }
+
+    // Close the outermost struct
+    fn finish(mut self) -> Vec<u8> {
+        self.end_struct();
+        self.buffer
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+#[cfg(test)]
+mod parquet_tests {
+    use std::fs::File;
+    use std::sync::Arc;
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::{Field, Row, RowAccessor};
+
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType};
+    use crate::orderbook::orderbook_impl::OrderBook;
+    use crate::orderbook::sampler::BookSampler;
+
+    // Minimal compact protocol reader: the integer fields of one struct by id, nested structs
+    // and lists skipped
+    struct Reader<'a>(&'a [u8], usize);
+
+    impl Reader<'_> {
+        fn byte(&mut self) -> u8 {
+            self.1 += 1;
+            self.0[self.1 - 1]
+        }
+
+        fn varint(&mut self) -> u64 {
+            let (mut value, mut shift) = (0, 0);
+            loop {
+                let byte = self.byte();
+                value |= ((byte & 0x7F) as u64) << shift;
+                if byte < 0x80 {
+                    return value;
+                }
+                shift += 7;
+            }
+        }
+
+        fn integer(&mut self) -> i64 {
+            let value = self.varint();
+            (value >> 1) as i64 ^ -((value & 1) as i64)
+        }
+
+        fn skip(&mut self, kind: u8) {
+            match kind {
+                COMPACT_I32 | COMPACT_I64 => {
+                    self.varint();
+                }
+                COMPACT_BINARY => self.1 += self.varint() as usize,
+                COMPACT_LIST => {
+                    let header = self.byte();
+                    let len = match header >> 4 {
+                        15 => self.varint() as usize,
+                        len => len as usize,
+                    };
+                    for _ in 0..len {
+                        self.skip(header & 0x0F);
+                    }
+                }
+                COMPACT_STRUCT => {
+                    self.integers();
+                }
+                _ => panic!("unexpected type {}", kind),
+            }
+        }
+
+        fn integers(&mut self) -> Vec<(i16, i64)> {
+            let (mut fields, mut id) = (Vec::new(), 0);
+            loop {
+                let header = self.byte();
+                if header == 0 {
+                    return fields;
+                }
+                id += (header >> 4) as i16;
+                match header & 0x0F {
+                    COMPACT_I32 | COMPACT_I64 => fields.push((id, self.integer())),
+                    kind => self.skip(kind),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_trades_and_samples_round_trip_through_pages() {
+        let mut book = OrderBook::new();
+        let mut sampler = BookSampler::new(5, 1);
+        sampler.sample(&book, 1_000);
+        for (side, price, quantity) in [
+            (Side::Sell, 101, 5),
+            (Side::Sell, 102, 5),
+            (Side::Buy, 102, 7),
+        ] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+            book.add_order(&order).unwrap();
+        }
+        sampler.sample(&book, 2_000);
+        let trades = book.trade_history().trades_between(i64::MIN, i64::MAX);
+
+        let mut file = Vec::new();
+        write_trades(&mut file, &trades).unwrap();
+        assert!(file.starts_with(MAGIC) && file.ends_with(MAGIC));
+        let footer_len =
+            u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap());
+        let mut footer = Reader(&file[file.len() - 8 - footer_len as usize..], 0);
+        assert!(footer.integers().contains(&(3, 2)));
+
+        // Pages follow each other from the magic on; the fourth column holds the prices
+        let mut reader = Reader(&file, MAGIC.len());
+        for column in 0..4 {
+            let header = reader.integers();
+            assert!(header.contains(&(1, PAGE_DATA as i64)));
+            let size = header.iter().find(|(id, _)| *id == 3).unwrap().1 as usize;
+            if column == 3 {
+                let prices: Vec<i64> = file[reader.1..reader.1 + size]
+                    .chunks(8)
+                    .map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect();
+                assert_eq!(prices, vec![101, 102]);
+            }
+            reader.1 += size;
+        }
+
+        // The empty book's sample has no best ask: definition levels 0 then 1, one value
+        let mut file = Vec::new();
+        write_samples(&mut file, sampler.samples()).unwrap();
+        let mut reader = Reader(&file, MAGIC.len());
+        for _ in 0..2 {
+            let header = reader.integers();
+            reader.1 += header.iter().find(|(id, _)| *id == 3).unwrap().1 as usize;
+        }
+        let header = reader.integers();
+        assert!(header.contains(&(3, 4 + 4 + 8)));
+        let page = &file[reader.1..reader.1 + 16];
+        assert_eq!(&page[..8], &[4, 0, 0, 0, 2, 0, 2, 1]);
+        assert_eq!(i64::from_le_bytes(page[8..].try_into().unwrap()), 102);
+    }
+
+    // Rows of `file` as read back by the parquet crate
+    fn read_rows(name: &str, file: &[u8]) -> Vec<Row> {
+        let path = std::env::temp_dir().join(format!("{}-{}.parquet", name, uuid::Uuid::new_v4()));
+        std::fs::write(&path, file).unwrap();
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        rows
+    }
+
+    #[test]
+    fn check_files_load_in_the_parquet_crate() {
+        let mut book = OrderBook::new();
+        let mut sampler = BookSampler::new(5, 1);
+        sampler.sample(&book, 1_000);
+        let maker = Arc::new(Order::new(OrderType::LimitOrder, Side::Sell, -3, 5));
+        book.add_order(&maker).unwrap();
+        let taker = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, -3, 2));
+        book.add_order(&taker).unwrap();
+        sampler.sample(&book, 2_000);
+        let trades = book.trade_history().trades_between(i64::MIN, i64::MAX);
+
+        let mut file = Vec::new();
+        write_trades(&mut file, &trades).unwrap();
+        let rows = read_rows("trades", &file);
+        assert_eq!(rows.len(), 1);
+        let (row, trade) = (&rows[0], &trades[0]);
+        assert_eq!(row.get_string(0).unwrap(), &trade.trade_id().to_string());
+        assert_eq!(row.get_timestamp_micros(1).unwrap(), trade.timestamp());
+        assert_eq!(row.get_ulong(2).unwrap(), trade.sequence());
+        assert_eq!(
+            (row.get_long(3).unwrap(), row.get_ulong(4).unwrap()),
+            (-3, 2)
+        );
+        assert_eq!(row.get_string(5).unwrap(), &taker.order_id.to_string());
+        assert_eq!(row.get_string(6).unwrap(), &maker.order_id.to_string());
+        assert_eq!(row.get_string(7).unwrap(), "buy");
+
+        let mut file = Vec::new();
+        write_samples(&mut file, sampler.samples()).unwrap();
+        let rows = read_rows("samples", &file);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_timestamp_millis(0).unwrap(), 1_000);
+        // nothing rested yet in the first sample
+        let first: Vec<_> = rows[0].get_column_iter().map(|(_, field)| field).collect();
+        assert_eq!(first[1..4], [&Field::Null, &Field::Null, &Field::Null]);
+        assert!(rows[1].get_long(1).is_err());
+        assert_eq!(rows[1].get_long(2).unwrap(), -3);
+        assert_eq!(rows[1].get_ulong(5).unwrap(), 3);
+    }
+}
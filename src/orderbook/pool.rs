@@ -0,0 +1,161 @@
+use crate::orderbook::order::Order;
+use crate::orderbook::order_queue::QueueHandle;
+
+/// Generational index of an order in an `OrderPool`. A key goes stale once its order is removed,
+/// even if the slot is later reused for another order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OrderKey {
+    index: u32,
+    generation: u32,
+}
+
+struct PoolSlot {
+    generation: u32,
+    order: Option<Order>,
+    // Position of the order in its level's queue, once queued
+    handle: Option<QueueHandle>,
+}
+
+/// Slab owning the resting orders of a book along with their queue positions, so a key alone
+/// finds an order and unlinks it from its level. Fills update quantities in place and freed
+/// slots are reused, so once the pool has grown to the book's working size it stops allocating.
+#[derive(Default)]
+pub struct OrderPool {
+    slots: Vec<PoolSlot>,
+    free_slots: Vec<u32>,
+    len: usize,
+}
+
+impl OrderPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        OrderPool {
+            slots: Vec::with_capacity(capacity),
+            free_slots: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, order: Order) -> OrderKey {
+        self.len += 1;
+        match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.order = Some(order);
+                slot.handle = None;
+                OrderKey {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                self.slots.push(PoolSlot {
+                    generation: 0,
+                    order: Some(order),
+                    handle: None,
+                });
+                OrderKey {
+                    index,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, key: OrderKey) -> Option<&Order> {
+        let slot = self.slots.get(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        slot.order.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: OrderKey) -> Option<&mut Order> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        slot.order.as_mut()
+    }
+
+    /// Queue position recorded for the order at `key`, None for a stale key or an order not
+    /// queued yet.
+    pub fn handle(&self, key: OrderKey) -> Option<QueueHandle> {
+        let slot = self.slots.get(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        slot.handle
+    }
+
+    /// Record where the order at `key` was queued; false for a stale key.
+    pub fn set_handle(&mut self, key: OrderKey, handle: QueueHandle) -> bool {
+        match self.slots.get_mut(key.index as usize) {
+            Some(slot) if slot.generation == key.generation && slot.order.is_some() => {
+                slot.handle = Some(handle);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn remove(&mut self, key: OrderKey) -> Option<Order> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        let order = slot.order.take()?;
+        slot.handle = None;
+        // Outstanding keys to this slot are stale from here on
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(key.index);
+        self.len -= 1;
+        Some(order)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Slots allocated so far, occupied or free.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+#[cfg(test)]
+mod pool_tests {
+    use super::*;
+    use crate::orderbook::order::{OrderType, Side};
+
+    #[test]
+    fn check_slots_are_reused_and_old_keys_go_stale() {
+        let mut pool = OrderPool::new();
+        let first = pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 10, 5));
+        let second = pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 11, 6));
+
+        pool.get_mut(first).unwrap().remaining_quantity -= 2;
+        assert_eq!(pool.get(first).unwrap().remaining_quantity, 3);
+
+        assert_eq!(pool.remove(first).unwrap().remaining_quantity, 3);
+        assert!(pool.get(first).is_none());
+        assert!(pool.remove(first).is_none());
+
+        // the freed slot is reused under a new generation
+        let third = pool.insert(Order::new(OrderType::LimitOrder, Side::Sell, 12, 7));
+        assert_eq!(pool.capacity(), 2);
+        assert_ne!(first, third);
+        assert!(pool.get(first).is_none());
+        assert_eq!(pool.get(third).unwrap().price, 12);
+        assert_eq!(pool.get(second).unwrap().price, 11);
+        assert_eq!(pool.len(), 2);
+    }
+}
@@ -1,206 +1,252 @@
-use std::boxed::Box;
-use std::ptr::NonNull;
-use std::sync::Arc;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::orderbook::order::{Order, Status};
+use crate::orderbook::config::HiddenPriority;
+use crate::orderbook::order::{Side, Status};
+use crate::orderbook::order_queue::{OrderQueue, QueueBackend, QueueBackendKind, QueueHandle};
+use crate::orderbook::pool::OrderKey;
 use crate::orderbook::types::{OrderId, Price, Quantity};
 
-use intrusive_collections::linked_list::CursorMut;
-use intrusive_collections::{intrusive_adapter, KeyAdapter, LinkedList, LinkedListLink};
-
-#[derive(Debug)]
-pub struct OrderNode {
-    pub link: LinkedListLink,
-    pub order: Arc<Order>,
-}
-
+/// FIFO of the orders resting at one price, with their total remaining quantity. The orders
+/// themselves live in the book's `OrderPool`; callers pass the quantities that change, and
+/// whether they belong to a hidden order.
 #[derive(Debug)]
 pub struct PriceLevel {
-    pub price: Price,
-    pub orders: LinkedList<OrderNodeAdapter>,
-    pub volume: Quantity,
-    pub order_count: usize,
+    price: Price,
+    orders: QueueBackend,
+    // Hidden orders, queued behind every displayed order under `HiddenPriority::BehindDisplayed`
+    hidden_orders: QueueBackend,
+    hidden_behind: bool,
+    volume: Quantity,
+    // Part of `volume` belonging to hidden orders
+    hidden_volume: Quantity,
+    order_count: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LevelInfo {
     pub price: Price,
     pub volume: Quantity,
 }
 
-pub struct OrderEntry {
-    pub order: Arc<Order>,
-    pub cursor: NonNull<OrderNode>, // pub cursor: CursorMut<'a, OrderNodeAdapter>,
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OrderbookLevelInfos {
+    pub bids: Vec<LevelInfo>, // best (highest) bid first
+    pub asks: Vec<LevelInfo>, // best (lowest) ask first
 }
 
-impl OrderNode {
-    pub fn new(order: Arc<Order>) -> Self {
-        Self {
-            link: LinkedListLink::new(),
-            order,
-        }
-    }
+/// Top of book: best bid and ask with the volume resting at each, stamped with the book sequence
+/// number of the change that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BboUpdate {
+    pub bid_price: Option<Price>,
+    pub bid_qty: Quantity,
+    pub ask_price: Option<Price>,
+    pub ask_qty: Quantity,
+    pub seq: u64,
 }
 
-// Register adapter
-intrusive_adapter!(
-    pub OrderNodeAdapter = Box<OrderNode>: OrderNode { link: LinkedListLink }
-);
+impl BboUpdate {
+    // Same top of book, regardless of when it was observed
+    pub fn same_quote(&self, other: &BboUpdate) -> bool {
+        (self.bid_price, self.bid_qty, self.ask_price, self.ask_qty)
+            == (
+                other.bid_price,
+                other.bid_qty,
+                other.ask_price,
+                other.ask_qty,
+            )
+    }
+}
 
-// Implement KeyAdapter
-impl<'a> KeyAdapter<'a> for OrderNodeAdapter {
-    type Key = OrderId;
+/// Current state of a resting order, as returned by `OrderBook::get_order`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OrderView {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub price: Price,
+    pub status: Status,
+    pub original_quantity: Quantity,
+    pub executed_quantity: Quantity,
+    pub remaining_quantity: Quantity,
+    // Orders ahead in the level's queue, 0 at the front
+    pub queue_position: usize,
+    pub quantity_ahead: Quantity,
+}
 
-    fn get_key(&self, value: &'a OrderNode) -> Self::Key {
-        value.order.order_id
-    }
+// An order resting ahead of another in the FIFO queue of a price level
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueEntry {
+    pub order_id: OrderId,
+    pub remaining_quantity: Quantity,
+    pub timestamp: i64,
 }
 
 impl PriceLevel {
-    pub fn new(price: Price) -> Self {
+    pub fn new(price: Price, backend: QueueBackendKind, hidden_priority: HiddenPriority) -> Self {
         Self {
             price,
-            orders: LinkedList::new(OrderNodeAdapter::new()),
+            orders: QueueBackend::new(backend),
+            hidden_orders: QueueBackend::new(backend),
+            hidden_behind: hidden_priority == HiddenPriority::BehindDisplayed,
             volume: 0,
+            hidden_volume: 0,
             order_count: 0,
         }
     }
 
-    /// Add an order to the back of the list
-    pub fn add_order(&mut self, order: Arc<Order>) -> CursorMut<'_, OrderNodeAdapter> {
-        let node = Box::new(OrderNode::new(order.clone()));
-        self.volume += order.remaining_quantity;
+    /// Add an order with `quantity` remaining to the back of its queue
+    pub fn add_order(&mut self, order: OrderKey, quantity: Quantity, hidden: bool) -> QueueHandle {
+        // `OrderBook` rejects orders that would overflow a level, saturating is only a backstop
+        self.volume = self.volume.saturating_add(quantity);
+        if hidden {
+            self.hidden_volume = self.hidden_volume.saturating_add(quantity);
+        }
         self.order_count += 1;
-        self.orders.push_back(node);
-
-        // Return a cursor pointing to the new back element
-        let cursor = self.orders.cursor_mut();
-        cursor
+        self.queue_mut(hidden).push_back(order)
     }
 
-    /// Remove an order at the cursor
+    /// Remove the order behind `handle`, which had `quantity` remaining
     pub fn remove_order(
         &mut self,
-        mut cursor: CursorMut<'_, OrderNodeAdapter>,
-    ) -> Option<Arc<Order>> {
-        if let Some(node) = cursor.remove() {
-            self.volume -= node.order.remaining_quantity;
-            self.order_count -= 1;
-            Some(node.order)
-        } else {
-            None
-        }
+        handle: QueueHandle,
+        quantity: Quantity,
+        hidden: bool,
+    ) -> Option<OrderKey> {
+        let order = self.queue_mut(hidden).remove(handle)?;
+        self.take_volume(quantity, hidden);
+        self.order_count -= 1;
+        Some(order)
     }
 
-    pub fn add_order_return_ptr(&mut self, order: Arc<Order>) -> NonNull<OrderNode> {
-        self.volume += order.remaining_quantity;
-        self.order_count += 1;
+    /// Get frontmost order
+    pub fn front(&self) -> Option<OrderKey> {
+        self.orders.front().or_else(|| self.hidden_orders.front())
+    }
 
-        // Push the Box<OrderNode> into the list (list owns it)
-        self.orders.push_back(Box::new(OrderNode::new(order)));
-
-        // Now get a pointer to the back element we just pushed
-        // Safety: back().get() returns Some(&OrderNode) because we just pushed
-        let ptr = self
-            .orders
-            .back()
-            .get()
-            .expect("just pushed, so back exists") as *const OrderNode
-            as *mut OrderNode;
-        // Create NonNull (safe because pointer is non-null)
-        // cast to NonNull; no check - we know it's non-null
-        unsafe { NonNull::new_unchecked(ptr) }
-    }
-
-    /// Remove by node pointer (returns Arc<Order> if removed)
-    pub fn remove_by_ptr(&mut self, ptr: NonNull<OrderNode>) -> Option<Arc<Order>> {
-        // Create cursor mut from ptr — this method consumes &mut self (the list).
-        // Safety: ptr must point to a node that is currently in this list.
-        let mut cursor = unsafe { self.orders.cursor_mut_from_ptr(ptr.as_ptr()) };
-        if let Some(node) = cursor.remove() {
-            self.volume -= node.order.remaining_quantity;
-            self.order_count -= 1;
-            Some(node.order)
-        } else {
-            None
-        }
+    /// Iterate orders in priority order, front first
+    pub fn iter(&self) -> impl Iterator<Item = OrderKey> + '_ {
+        self.orders.iter().chain(self.hidden_orders.iter())
     }
 
-    /// Get frontmost order
-    pub fn front(&self) -> Option<&Arc<Order>> {
-        self.orders.front().get().map(|node| &node.order)
+    /// Pop the first order, which had `quantity` remaining
+    pub fn pop_front(&mut self, quantity: Quantity, hidden: bool) -> Option<OrderKey> {
+        let order = match self.orders.pop_front() {
+            Some(order) => order,
+            None => self.hidden_orders.pop_front()?,
+        };
+        self.take_volume(quantity, hidden);
+        self.order_count -= 1;
+        Some(order)
     }
 
-    /// Pop the first order
-    pub fn pop_front(&mut self) -> Option<Arc<Order>> {
-        if let Some(node) = self.orders.pop_front() {
-            self.volume -= node.order.remaining_quantity;
-            self.order_count -= 1;
-            Some(node.order)
-        } else {
-            None
-        }
+    /// Account for `quantity` executed from an order that keeps its place in the queue
+    pub fn fill(&mut self, quantity: Quantity, hidden: bool) {
+        self.take_volume(quantity, hidden);
     }
 
-    pub fn update_order(
-        &mut self,
-        mut cursor: CursorMut<'_, OrderNodeAdapter>,
-        new_quantity: Quantity,
-    ) -> Option<Arc<Order>> {
-        if let Some(old_node) = cursor.remove() {
-            // Calculate delta
-            let old_quantity = old_node.order.remaining_quantity;
-            self.volume = self.volume - old_quantity + new_quantity;
-
-            // Create updated order
-            let mut new_order = (*old_node.order).clone();
-            new_order.remaining_quantity = new_quantity;
-            let new_arc = Arc::new(new_order);
-
-            // Insert new node at the same place
-            let new_node = Box::new(OrderNode::new(new_arc.clone()));
-            cursor.insert_before(new_node);
-
-            Some(new_arc)
-        } else {
-            None
+    fn take_volume(&mut self, quantity: Quantity, hidden: bool) {
+        self.volume = self.volume.saturating_sub(quantity);
+        if hidden {
+            self.hidden_volume = self.hidden_volume.saturating_sub(quantity);
         }
     }
 
-    pub fn get_level_info(&self) -> LevelInfo {
-        LevelInfo {
-            price: self.price,
-            volume: self.volume,
+    fn queue_mut(&mut self, hidden: bool) -> &mut QueueBackend {
+        if hidden && self.hidden_behind {
+            &mut self.hidden_orders
+        } else {
+            &mut self.orders
         }
     }
 
-    pub fn update_front_order_quantity(&mut self, new_quantity: Quantity) -> Option<Quantity> {
-        let mut cursor = self.orders.front_mut();
+    pub fn price(&self) -> Price {
+        self.price
+    }
 
-        if let Some(front_node) = cursor.get() {
-            let old_quantity = front_node.order.remaining_quantity;
-            let delta = old_quantity as i64 - new_quantity as i64;
+    /// Remaining quantity of every order, hidden ones included.
+    pub fn volume(&self) -> Quantity {
+        self.volume
+    }
 
-            // Create updated order
-            let mut updated_order = (*front_node.order).clone();
-            updated_order.remaining_quantity = new_quantity;
-            updated_order.executed_quantity += delta.max(0) as Quantity;
-            updated_order.status = if new_quantity == 0 {
-                Status::Filled
-            } else {
-                Status::PartiallyFilled
-            };
+    /// Remaining quantity shown to the market.
+    pub fn displayed_volume(&self) -> Quantity {
+        self.volume.saturating_sub(self.hidden_volume)
+    }
 
-            // Replace the node using cursor.replace()
-            let updated_node = Box::new(OrderNode::new(Arc::new(updated_order)));
-            let _ = cursor.replace_with(updated_node);
+    pub fn order_count(&self) -> usize {
+        self.order_count
+    }
 
-            // Update price level volume
-            self.volume = self.volume.saturating_sub(delta.unsigned_abs());
+    pub fn is_empty(&self) -> bool {
+        self.order_count == 0
+    }
 
-            Some(old_quantity)
-        } else {
-            None
+    /// Price and displayed volume, as published to the market.
+    pub fn get_level_info(&self) -> LevelInfo {
+        LevelInfo {
+            price: self.price,
+            volume: self.displayed_volume(),
         }
     }
 }
+
+#[cfg(test)]
+mod price_level_tests {
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType};
+    use crate::orderbook::pool::OrderPool;
+
+    #[test]
+    fn check_volume_and_count_follow_the_queue() {
+        let mut pool = OrderPool::new();
+        let mut level = PriceLevel::new(10, QueueBackendKind::Slab, HiddenPriority::TimePriority);
+        let first = pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 10, 5));
+        let second = pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 10, 7));
+        level.add_order(first, 5, false);
+        let second_handle = level.add_order(second, 7, false);
+        assert_eq!((level.volume(), level.order_count()), (12, 2));
+
+        // a partial fill keeps the order at the front
+        level.fill(2, false);
+        assert_eq!(level.front(), Some(first));
+        assert_eq!(level.volume(), 10);
+
+        assert_eq!(level.remove_order(second_handle, 7, false), Some(second));
+        assert_eq!(level.pop_front(3, false), Some(first));
+        assert!(level.is_empty());
+        assert_eq!(
+            level.get_level_info(),
+            LevelInfo {
+                price: 10,
+                volume: 0
+            }
+        );
+    }
+
+    #[test]
+    fn check_hidden_orders_queue_behind_displayed_ones() {
+        let mut pool = OrderPool::new();
+        let mut level = PriceLevel::new(
+            10,
+            QueueBackendKind::VecDeque,
+            HiddenPriority::BehindDisplayed,
+        );
+        let hidden = pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 10, 4));
+        let displayed = pool.insert(Order::new(OrderType::LimitOrder, Side::Buy, 10, 6));
+        level.add_order(hidden, 4, true);
+        level.add_order(displayed, 6, false);
+        assert_eq!(level.iter().collect::<Vec<_>>(), vec![displayed, hidden]);
+        assert_eq!((level.volume(), level.displayed_volume()), (10, 6));
+        assert_eq!(level.get_level_info().volume, 6);
+
+        assert_eq!(level.pop_front(6, false), Some(displayed));
+        assert_eq!(level.front(), Some(hidden));
+        level.fill(1, true);
+        assert_eq!((level.volume(), level.displayed_volume()), (3, 0));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::orderbook::orderbook_impl::OrderBookError;
+use crate::orderbook::types::{ParticipantId, Symbol};
+
+/// What happens to a command sent while its participant's bucket is empty.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BurstPolicy {
+    // Reject the command outright
+    Reject,
+    // Hold up to `max_queued` commands per participant until tokens are available again
+    Queue { max_queued: usize },
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct RateLimitConfig {
+    // Largest burst a participant can send at once
+    pub capacity: u32,
+    pub refill_per_second: u32,
+    // Give every (participant, instrument) pair its own bucket instead of one per participant
+    pub per_instrument: bool,
+    pub burst_policy: BurstPolicy,
+}
+
+/// Classic token bucket: starts full, refills continuously, one token per command.
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_per_second: u32, now: Instant) -> Self {
+        TokenBucket {
+            capacity: capacity as f64,
+            refill_per_second: refill_per_second as f64,
+            tokens: capacity as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Take one token, or return how long until one is available.
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.refill_per_second == 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.refill_per_second,
+        ))
+    }
+
+    pub fn available(&mut self, now: Instant) -> u32 {
+        self.refill(now);
+        self.tokens as u32
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = self.last_refill.max(now);
+    }
+}
+
+type BucketKey = (ParticipantId, Option<Symbol>);
+
+/// Token buckets keyed by participant, and by instrument when `per_instrument` is set.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<BucketKey, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> RateLimitConfig {
+        self.config
+    }
+
+    /// Charge one command by `participant_id` on `symbol` at `now`.
+    pub fn acquire(
+        &mut self,
+        participant_id: ParticipantId,
+        symbol: &str,
+        now: Instant,
+    ) -> Result<(), OrderBookError> {
+        let key = (
+            participant_id,
+            self.config.per_instrument.then(|| symbol.to_string()),
+        );
+        let config = self.config;
+        self.buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(config.capacity, config.refill_per_second, now))
+            .try_acquire(now)
+            .map_err(|retry_after| OrderBookError::RateLimited {
+                participant_id,
+                retry_after,
+                queued: false,
+            })
+    }
+}
+
+#[cfg(test)]
+mod rate_limit_tests {
+    use super::*;
+
+    #[test]
+    fn check_token_bucket_allows_bursts_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3, 1, start);
+        for _ in 0..3 {
+            bucket.try_acquire(start).unwrap();
+        }
+        assert_eq!(bucket.try_acquire(start), Err(Duration::from_secs(1)));
+        // one token back per second, never more than the capacity
+        bucket.try_acquire(start + Duration::from_secs(1)).unwrap();
+        assert_eq!(bucket.available(start + Duration::from_secs(60)), 3);
+    }
+
+    #[test]
+    fn check_rate_limiter_keys_by_participant_and_instrument() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(RateLimitConfig {
+            capacity: 1,
+            refill_per_second: 0,
+            per_instrument: true,
+            burst_policy: BurstPolicy::Reject,
+        });
+        limiter.acquire(1, "BTCUSDT", now).unwrap();
+        limiter.acquire(1, "ETHUSDT", now).unwrap();
+        limiter.acquire(2, "BTCUSDT", now).unwrap();
+        assert!(matches!(
+            limiter.acquire(1, "BTCUSDT", now),
+            Err(OrderBookError::RateLimited {
+                participant_id: 1,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,721 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::orderbook::command::Command;
+use crate::orderbook::config::OrderBookConfig;
+use crate::orderbook::journal::{
+    Journal, decode_command, decode_instrument_state, encode_command, encode_instrument_state,
+};
+use crate::orderbook::order::Side;
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError};
+use crate::orderbook::snapshot::{BookSnapshot, LevelSnapshot};
+use crate::orderbook::state::InstrumentState;
+
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+const SNAPSHOT_EXTENSION: &str = "snap";
+const JOURNAL_PREFIX: &str = "journal-";
+const JOURNAL_EXTENSION: &str = "log";
+const SHUTDOWN_FILE: &str = "shutdown.state";
+
+/// What `Recovery::recover` found on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryReport {
+    // Sequence number of the snapshot the book was loaded from, None when starting empty
+    pub snapshot_sequence: Option<u64>,
+    // Journal entries applied on top of the snapshot
+    pub replayed: usize,
+    pub journal_segments: usize,
+    // Damaged snapshots passed over for an older one whose journal reaches past them
+    pub skipped_snapshots: usize,
+    // Book sequence number after recovery; feed consumers resume from the next one
+    pub sequence: u64,
+    // `state_hash` of the recovered book
+    pub state_hash: u64,
+    // Whether a `shutdown` record was found and matched the recovered book
+    pub state_hash_verified: bool,
+    pub duration: Duration,
+}
+
+/// Snapshots plus journal segments in one directory, tied together for restarts.
+///
+/// `checkpoint` writes `snapshot-<sequence>.snap`, starts the book on a fresh
+/// `journal-<sequence>.log` and deletes the snapshots and segments older than the retained
+/// snapshots. `recover` loads the latest snapshot that reads back intact, replays the journal
+/// tail with every entry's sequence number checked against the book's, checks the result
+/// against the state `shutdown` recorded, and resumes journaling where the tail ended.
+pub struct Recovery {
+    dir: PathBuf,
+    config: OrderBookConfig,
+    // Commands and trades between automatic checkpoints, None to only checkpoint on request
+    snapshot_interval: Option<u64>,
+    retained_snapshots: usize,
+    last_checkpoint: u64,
+    recovered_sequence: Option<u64>,
+}
+
+impl Recovery {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Recovery {
+            dir: dir.as_ref().to_path_buf(),
+            config: OrderBookConfig::default(),
+            snapshot_interval: None,
+            retained_snapshots: 1,
+            last_checkpoint: 0,
+            recovered_sequence: None,
+        }
+    }
+
+    /// Config the recovered book is built with.
+    pub fn with_config(mut self, config: OrderBookConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Let `maybe_checkpoint` take a snapshot once the book sequence has moved `interval` past
+    /// the last one.
+    pub fn with_snapshot_interval(mut self, interval: u64) -> Self {
+        self.snapshot_interval = Some(interval);
+        self
+    }
+
+    /// Keep the last `count` snapshots, and the journal since the oldest of them, so a damaged
+    /// latest snapshot can be rebuilt from an earlier one. Defaults to 1.
+    pub fn with_retained_snapshots(mut self, count: usize) -> Self {
+        self.retained_snapshots = count.max(1);
+        self
+    }
+
+    /// Sequence number the last `recover` ended at.
+    pub fn recovered_sequence(&self) -> Option<u64> {
+        self.recovered_sequence
+    }
+
+    /// Rebuild the book from the latest intact snapshot and the journal written after it, then
+    /// attach a new journal segment so accepted commands keep being recorded. An empty or
+    /// missing directory recovers an empty book.
+    ///
+    /// A snapshot that is corrupted is passed over for the one before it, as long as the
+    /// journal kept since (see `with_retained_snapshots`) replays past the damaged one;
+    /// otherwise its `SnapshotCorrupted` is returned. If `shutdown` recorded the book's state,
+    /// the recovered book must end at the same sequence number (`SequenceMismatch`) with the
+    /// same state hash (`StateHashMismatch`).
+    pub fn recover(&mut self) -> Result<(OrderBook, RecoveryReport), OrderBookError> {
+        let started = Instant::now();
+        fs::create_dir_all(&self.dir)?;
+        let mut restored = None;
+        // The newest snapshot passed over, which the journal has to replay past
+        let mut damaged = None;
+        let mut skipped_snapshots = 0;
+        for (file_sequence, path) in self
+            .files(SNAPSHOT_PREFIX, SNAPSHOT_EXTENSION)?
+            .iter()
+            .rev()
+        {
+            let result = read_snapshot(path)
+                .and_then(|snapshot| OrderBook::restore_with_config(snapshot, self.config.clone()));
+            match result {
+                Ok(book) => {
+                    restored = Some(book);
+                    break;
+                }
+                Err(error @ OrderBookError::SnapshotCorrupted { .. }) => {
+                    skipped_snapshots += 1;
+                    damaged.get_or_insert((*file_sequence, error));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        let snapshot_sequence = restored.as_ref().map(|book| book.sequence());
+        let mut book = match restored {
+            Some(book) => book,
+            None => OrderBook::try_with_config(self.config.clone())?,
+        };
+
+        let segments = self.files(JOURNAL_PREFIX, JOURNAL_EXTENSION)?;
+        let replayed = replay_segments(&mut book, &segments);
+        if let Some((damaged_sequence, error)) = damaged
+            && (replayed.is_err() || book.sequence() < damaged_sequence)
+        {
+            return Err(error);
+        }
+        let replayed = replayed?;
+        // Back to the wall clock for live trading
+        book.set_clock(None);
+
+        let sequence = book.sequence();
+        let hash = state_hash(&book.snapshot());
+        let state_hash_verified = self.verify_shutdown_state(sequence, hash)?;
+        book.set_journal(Journal::open(self.journal_path(sequence))?);
+        self.last_checkpoint = snapshot_sequence.unwrap_or(0);
+        self.recovered_sequence = Some(sequence);
+        Ok((
+            book,
+            RecoveryReport {
+                snapshot_sequence,
+                replayed,
+                journal_segments: segments.len(),
+                skipped_snapshots,
+                sequence,
+                state_hash: hash,
+                state_hash_verified,
+                duration: started.elapsed(),
+            },
+        ))
+    }
+
+    /// Record the book's sequence number and `state_hash` for the next `recover` to check the
+    /// rebuilt book against. Call once the book has taken its last command. Returns the hash.
+    pub fn shutdown(&self, book: &OrderBook) -> Result<u64, OrderBookError> {
+        fs::create_dir_all(&self.dir)?;
+        let hash = state_hash(&book.snapshot());
+        let path = self.dir.join(SHUTDOWN_FILE);
+        let temporary = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        writeln!(writer, "H seq={} hash={:016x}", book.sequence(), hash)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&temporary, &path)?;
+        Ok(hash)
+    }
+
+    /// Snapshot `book`, move its journal to a new segment and truncate everything the snapshot
+    /// covers. Returns the snapshot's sequence number.
+    pub fn checkpoint(&mut self, book: &mut OrderBook) -> Result<u64, OrderBookError> {
+        fs::create_dir_all(&self.dir)?;
+        let snapshot = book.snapshot();
+        let sequence = snapshot.sequence;
+
+        // Written under a temporary name and renamed, so a crash never leaves half a snapshot
+        let path = self.snapshot_path(sequence);
+        let temporary = path.with_extension("tmp");
+        write_snapshot(&temporary, &snapshot)?;
+        fs::rename(&temporary, &path)?;
+        book.set_journal(Journal::open(self.journal_path(sequence))?);
+
+        // Everything from the oldest retained snapshot on stays, all of it while there are fewer
+        let snapshots = self.files(SNAPSHOT_PREFIX, SNAPSHOT_EXTENSION)?;
+        let retained_from = snapshots
+            .iter()
+            .rev()
+            .nth(self.retained_snapshots - 1)
+            .map_or(0, |(file_sequence, _)| *file_sequence);
+        for (file_sequence, path) in snapshots
+            .into_iter()
+            .chain(self.files(JOURNAL_PREFIX, JOURNAL_EXTENSION)?)
+        {
+            if file_sequence < retained_from {
+                fs::remove_file(path)?;
+            }
+        }
+        self.last_checkpoint = sequence;
+        Ok(sequence)
+    }
+
+    /// `checkpoint` if the snapshot interval has passed since the last one.
+    pub fn maybe_checkpoint(
+        &mut self,
+        book: &mut OrderBook,
+    ) -> Result<Option<u64>, OrderBookError> {
+        match self.snapshot_interval {
+            Some(interval) if book.sequence() >= self.last_checkpoint + interval => {
+                self.checkpoint(book).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Check the recovered book against the `shutdown` record, which is then removed since the
+    // book trades on from here. Returns whether there was one.
+    fn verify_shutdown_state(&self, sequence: u64, hash: u64) -> Result<bool, OrderBookError> {
+        let path = self.dir.join(SHUTDOWN_FILE);
+        let record = match fs::read_to_string(&path) {
+            Ok(record) => record,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error.into()),
+        };
+        let field = |key: &str| {
+            record
+                .split_whitespace()
+                .skip(1)
+                .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+        };
+        let (Some(recorded_sequence), Some(recorded_hash)) = (
+            field("seq").and_then(|value| value.parse().ok()),
+            field("hash").and_then(|value| u64::from_str_radix(value, 16).ok()),
+        ) else {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidData, "malformed shutdown record").into(),
+            );
+        };
+        if recorded_sequence != sequence {
+            return Err(OrderBookError::SequenceMismatch {
+                expected: recorded_sequence,
+                found: sequence,
+            });
+        }
+        if recorded_hash != hash {
+            return Err(OrderBookError::StateHashMismatch {
+                sequence,
+                expected: recorded_hash,
+                found: hash,
+            });
+        }
+        fs::remove_file(&path)?;
+        Ok(true)
+    }
+
+    fn snapshot_path(&self, sequence: u64) -> PathBuf {
+        self.dir.join(format!(
+            "{}{:020}.{}",
+            SNAPSHOT_PREFIX, sequence, SNAPSHOT_EXTENSION
+        ))
+    }
+
+    fn journal_path(&self, sequence: u64) -> PathBuf {
+        self.dir.join(format!(
+            "{}{:020}.{}",
+            JOURNAL_PREFIX, sequence, JOURNAL_EXTENSION
+        ))
+    }
+
+    // Files named `<prefix><sequence>.<extension>`, by ascending sequence
+    fn files(&self, prefix: &str, extension: &str) -> Result<Vec<(u64, PathBuf)>, OrderBookError> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+                continue;
+            }
+            let sequence = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix(prefix))
+                .and_then(|sequence| sequence.parse().ok());
+            if let Some(sequence) = sequence {
+                files.push((sequence, path));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+// Replay segments oldest first, skipping entries the book already holds. Returns how many were
+// applied.
+fn replay_segments(
+    book: &mut OrderBook,
+    segments: &[(u64, PathBuf)],
+) -> Result<usize, OrderBookError> {
+    let mut replayed = 0;
+    for (_, path) in segments {
+        for entry in Journal::read_entries(path)? {
+            if entry
+                .sequence
+                .is_some_and(|sequence| sequence <= book.sequence())
+            {
+                continue;
+            }
+            entry.apply(book)?;
+            replayed += 1;
+        }
+    }
+    Ok(replayed)
+}
+
+/// FNV-1a hash of the book state in `snapshot`: its sequence number, instrument state and every
+/// resting order in book order. The timestamp is left out, so two books that took the same
+/// commands hash the same whenever they are snapshotted.
+pub fn state_hash(snapshot: &BookSnapshot) -> u64 {
+    let mut hash = fnv1a(
+        0xcbf2_9ce4_8422_2325,
+        format!(
+            "{} {}\n",
+            snapshot.sequence,
+            encode_instrument_state(snapshot.instrument_state)
+        )
+        .as_bytes(),
+    );
+    for order in snapshot
+        .bids
+        .iter()
+        .chain(snapshot.asks.iter())
+        .flat_map(|level| level.orders.iter())
+    {
+        let line = encode_command(&Command::NewOrder(Arc::new(order.clone())));
+        hash = fnv1a(hash, line.as_bytes());
+        hash = fnv1a(hash, b"\n");
+    }
+    hash
+}
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Write `snapshot` to `path` as a `S seq=<sequence> ts=<timestamp> orders=<count> state=<state> hash=<state hash>` header
+/// followed by one journal-encoded new order line per resting order, bids best first then asks,
+/// each level in FIFO order. The file is synced before returning.
+pub fn write_snapshot<P: AsRef<Path>>(
+    path: P,
+    snapshot: &BookSnapshot,
+) -> Result<(), OrderBookError> {
+    let orders: Vec<_> = snapshot
+        .bids
+        .iter()
+        .chain(snapshot.asks.iter())
+        .flat_map(|level| level.orders.iter())
+        .collect();
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        "S seq={} ts={} orders={} state={} hash={:016x}",
+        snapshot.sequence,
+        snapshot.timestamp,
+        orders.len(),
+        encode_instrument_state(snapshot.instrument_state),
+        state_hash(snapshot)
+    )?;
+    for order in orders {
+        writeln!(
+            writer,
+            "{}",
+            encode_command(&Command::NewOrder(Arc::new(order.clone())))
+        )?;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
+
+/// Read a snapshot written by `write_snapshot`, checking the orders against the header's state
+/// hash when it has one.
+pub fn read_snapshot<P: AsRef<Path>>(path: P) -> Result<BookSnapshot, OrderBookError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines();
+    let corrupted = |line: usize, reason: &str| OrderBookError::SnapshotCorrupted {
+        line,
+        reason: reason.to_string(),
+    };
+
+    let header = lines.next().ok_or(corrupted(1, "empty file"))??;
+    let field = |key: &str| {
+        header
+            .split_whitespace()
+            .skip(1)
+            .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+    };
+    if !header.starts_with("S ") {
+        return Err(corrupted(1, "not a snapshot header"));
+    }
+    let (Some(sequence), Some(timestamp), Some(count)) = (
+        field("seq").and_then(|value| value.parse().ok()),
+        field("ts").and_then(|value| value.parse().ok()),
+        field("orders").and_then(|value| value.parse::<usize>().ok()),
+    ) else {
+        return Err(corrupted(1, "incomplete header"));
+    };
+
+    // Snapshots written before the state was recorded were taken while trading was active
+    let instrument_state = match field("state") {
+        Some(state) => {
+            decode_instrument_state(state).ok_or(corrupted(1, "unknown instrument state"))?
+        }
+        None => InstrumentState::Active,
+    };
+    let mut snapshot = BookSnapshot {
+        timestamp,
+        sequence,
+        instrument_state,
+        bids: Vec::new(),
+        asks: Vec::new(),
+    };
+    let mut read = 0;
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        let Some(Command::NewOrder(order)) = decode_command(&line) else {
+            return Err(corrupted(index + 2, "not an order"));
+        };
+        let order = Arc::unwrap_or_clone(order);
+        let levels = match order.side {
+            Side::Buy => &mut snapshot.bids,
+            Side::Sell => &mut snapshot.asks,
+        };
+        match levels.last_mut() {
+            Some(level) if level.price == order.price => level.orders.push(order),
+            _ => levels.push(LevelSnapshot {
+                price: order.price,
+                orders: vec![order],
+            }),
+        }
+        read += 1;
+    }
+    // A short file means the write never completed
+    if read != count {
+        return Err(corrupted(read + 2, "truncated"));
+    }
+    // Snapshots written before the hash was recorded are taken as they read
+    if let Some(hash) = field("hash")
+        && u64::from_str_radix(hash, 16).ok() != Some(state_hash(&snapshot))
+    {
+        return Err(corrupted(1, "state hash mismatch"));
+    }
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod recovery_tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", name, Uuid::new_v4()))
+    }
+
+    fn limit(side: Side, price: i64, quantity: u64) -> Arc<Order> {
+        Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity))
+    }
+
+    #[test]
+    fn check_restart_from_snapshot_and_journal_tail() {
+        let dir = temp_dir("recovery");
+        let mut recovery = Recovery::new(&dir).with_snapshot_interval(3);
+        let (mut book, report) = recovery.recover().unwrap();
+        assert_eq!(report.snapshot_sequence, None);
+        assert_eq!(report.sequence, 0);
+
+        let bid = limit(Side::Buy, 99, 10);
+        book.add_order(&bid).unwrap();
+        book.add_order(&limit(Side::Sell, 101, 5)).unwrap();
+        assert_eq!(recovery.maybe_checkpoint(&mut book).unwrap(), None);
+        book.add_order(&limit(Side::Sell, 99, 4)).unwrap();
+        // the fill took sequence 4, so the interval of 3 has passed
+        assert_eq!(recovery.maybe_checkpoint(&mut book).unwrap(), Some(4));
+        book.add_order(&limit(Side::Buy, 100, 2)).unwrap();
+        book.cancel_order(bid.order_id).unwrap();
+        let expected = book.snapshot();
+        drop(book);
+
+        // the snapshot covers the first segment, which was truncated
+        assert_eq!(
+            recovery
+                .files(JOURNAL_PREFIX, JOURNAL_EXTENSION)
+                .unwrap()
+                .len(),
+            1
+        );
+        let mut restarted = Recovery::new(&dir);
+        let (mut book, report) = restarted.recover().unwrap();
+        assert_eq!(report.snapshot_sequence, Some(4));
+        assert_eq!(report.replayed, 2);
+        assert_eq!(report.sequence, expected.sequence);
+        assert_eq!(restarted.recovered_sequence(), Some(expected.sequence));
+        let recovered = book.snapshot();
+        assert_eq!(recovered.bids, expected.bids);
+        assert_eq!(recovered.asks, expected.asks);
+
+        // the recovered book keeps journaling, so a second restart sees its commands too
+        book.add_order(&limit(Side::Sell, 105, 1)).unwrap();
+        let (book, report) = Recovery::new(&dir).recover().unwrap();
+        assert_eq!(report.replayed, 3);
+        assert_eq!(book.snapshot().asks.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_expiries_and_auctions_replay_after_restart() {
+        let dir = temp_dir("recovery-timers");
+        let mut recovery = Recovery::new(&dir);
+        let (mut book, _) = recovery.recover().unwrap();
+        let expiring = Arc::new(Order::new(OrderType::GoodTillDate(1_000), Side::Buy, 95, 1));
+        book.add_order(&expiring).unwrap();
+        book.add_order(&limit(Side::Buy, 96, 1)).unwrap();
+        assert_eq!(book.tick(2_000).unwrap().len(), 1);
+
+        // a snapshot taken mid-auction restores the auction, not continuous trading
+        book.set_instrument_state(InstrumentState::Auction).unwrap();
+        book.add_order(&limit(Side::Buy, 101, 5)).unwrap();
+        recovery.checkpoint(&mut book).unwrap();
+        book.add_order(&limit(Side::Sell, 100, 3)).unwrap();
+        book.add_order(&limit(Side::Sell, 99, 1)).unwrap();
+        assert_eq!(book.uncross().unwrap().len(), 2);
+        book.set_instrument_state(InstrumentState::Active).unwrap();
+        let expected = book.snapshot();
+        drop(book);
+
+        let (book, report) = Recovery::new(&dir).recover().unwrap();
+        assert_eq!(report.replayed, 4);
+        assert_eq!(book.snapshot().bids, expected.bids);
+        assert_eq!(book.snapshot().asks, expected.asks);
+        assert_eq!(book.sequence(), expected.sequence);
+        assert_eq!(book.instrument_state(), InstrumentState::Active);
+        assert!(book.get_order(expiring.order_id).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_busted_trades_replay_after_restart() {
+        let dir = temp_dir("recovery-bust");
+        let (mut book, _) = Recovery::new(&dir).recover().unwrap();
+        let maker = limit(Side::Sell, 100, 10);
+        book.add_order(&maker).unwrap();
+        let trade = book
+            .add_order(&limit(Side::Buy, 100, 4))
+            .unwrap()
+            .trades
+            .remove(0);
+        book.bust_trade(trade.trade_id()).unwrap();
+        let sequence = book.sequence();
+        drop(book);
+
+        let (book, report) = Recovery::new(&dir).recover().unwrap();
+        assert_eq!((report.sequence, book.sequence()), (sequence, sequence));
+        assert_eq!(
+            book.get_order(maker.order_id).unwrap().remaining_quantity,
+            10
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_recovered_state_is_verified_against_shutdown() {
+        let dir = temp_dir("recovery-shutdown");
+        let mut recovery = Recovery::new(&dir);
+        let (mut book, _) = recovery.recover().unwrap();
+        book.add_order(&limit(Side::Buy, 99, 10)).unwrap();
+        recovery.checkpoint(&mut book).unwrap();
+        book.add_order(&limit(Side::Sell, 101, 5)).unwrap();
+        let hash = recovery.shutdown(&book).unwrap();
+        assert_eq!(hash, state_hash(&book.snapshot()));
+        let sequence = book.sequence();
+        drop(book);
+
+        let (mut book, report) = Recovery::new(&dir).recover().unwrap();
+        assert!(report.state_hash_verified);
+        assert_eq!((report.sequence, report.state_hash), (sequence, hash));
+
+        // the record only holds for the state it was taken at
+        book.add_order(&limit(Side::Sell, 102, 1)).unwrap();
+        drop(book);
+        let (book, report) = Recovery::new(&dir).recover().unwrap();
+        assert!(!report.state_hash_verified);
+        recovery.shutdown(&book).unwrap();
+        drop(book);
+
+        // a journal that lost its tail ends short of the record
+        let segment = recovery.journal_path(sequence);
+        let contents = fs::read_to_string(&segment).unwrap();
+        fs::write(&segment, "").unwrap();
+        assert!(matches!(
+            Recovery::new(&dir).recover(),
+            Err(OrderBookError::SequenceMismatch { found, .. }) if found == sequence
+        ));
+
+        fs::write(&segment, contents).unwrap();
+        fs::write(
+            dir.join(SHUTDOWN_FILE),
+            format!("H seq={} hash={:016x}\n", sequence + 1, hash),
+        )
+        .unwrap();
+        assert!(matches!(
+            Recovery::new(&dir).recover(),
+            Err(OrderBookError::StateHashMismatch { expected, .. }) if expected == hash
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_damaged_snapshot_falls_back_to_an_older_one() {
+        let dir = temp_dir("recovery-fallback");
+        let mut recovery = Recovery::new(&dir).with_retained_snapshots(2);
+        let (mut book, _) = recovery.recover().unwrap();
+        book.add_order(&limit(Side::Buy, 99, 10)).unwrap();
+        let first = recovery.checkpoint(&mut book).unwrap();
+        book.add_order(&limit(Side::Sell, 101, 5)).unwrap();
+        let second = recovery.checkpoint(&mut book).unwrap();
+        book.add_order(&limit(Side::Sell, 103, 5)).unwrap();
+        let third = recovery.checkpoint(&mut book).unwrap();
+        book.add_order(&limit(Side::Buy, 98, 2)).unwrap();
+        let expected = book.snapshot();
+        drop(book);
+
+        // only the last two snapshots and the journal since the older one are kept
+        let snapshots = recovery.files(SNAPSHOT_PREFIX, SNAPSHOT_EXTENSION).unwrap();
+        let segments = recovery.files(JOURNAL_PREFIX, JOURNAL_EXTENSION).unwrap();
+        assert_eq!(
+            snapshots.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+            [second, third]
+        );
+        assert_eq!(
+            segments.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+            [second, third]
+        );
+        assert!(first < second);
+
+        // an order edited in place no longer matches the header's hash
+        let latest = recovery.snapshot_path(third);
+        let contents = fs::read_to_string(&latest).unwrap();
+        fs::write(&latest, contents.replacen(" 103 ", " 104 ", 1)).unwrap();
+        assert!(matches!(
+            read_snapshot(&latest),
+            Err(OrderBookError::SnapshotCorrupted { line: 1, ref reason }) if reason == "state hash mismatch"
+        ));
+
+        let (book, report) = Recovery::new(&dir).recover().unwrap();
+        assert_eq!(report.skipped_snapshots, 1);
+        assert_eq!(report.snapshot_sequence, Some(second));
+        assert_eq!(report.replayed, 2);
+        assert_eq!(book.snapshot().bids, expected.bids);
+        assert_eq!(book.snapshot().asks, expected.asks);
+        drop(book);
+
+        // without the journal to replay past it the damaged snapshot is refused
+        for (_, path) in segments {
+            fs::remove_file(path).unwrap();
+        }
+        assert!(matches!(
+            Recovery::new(&dir).recover(),
+            Err(OrderBookError::SnapshotCorrupted { line: 1, .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_gaps_and_torn_snapshots_are_refused() {
+        let dir = temp_dir("recovery-gap");
+        let mut recovery = Recovery::new(&dir);
+        let (mut book, _) = recovery.recover().unwrap();
+        book.add_order(&limit(Side::Buy, 99, 10)).unwrap();
+        recovery.checkpoint(&mut book).unwrap();
+        drop(book);
+
+        // a segment starting past the snapshot leaves a hole in the sequence
+        let mut journal = Journal::open(recovery.journal_path(5)).unwrap();
+        journal
+            .append(7, 0, &Command::NewOrder(limit(Side::Sell, 101, 1)))
+            .unwrap();
+        drop(journal);
+        assert!(matches!(
+            Recovery::new(&dir).recover(),
+            Err(OrderBookError::SequenceMismatch {
+                expected: 2,
+                found: 7
+            })
+        ));
+
+        let snapshot_path = recovery.snapshot_path(1);
+        let contents = fs::read_to_string(&snapshot_path).unwrap();
+        fs::write(&snapshot_path, contents.lines().next().unwrap()).unwrap();
+        assert!(matches!(
+            read_snapshot(&snapshot_path),
+            Err(OrderBookError::SnapshotCorrupted { line: 2, .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Keeps the producer's and consumer's indexes on separate cache lines
+#[repr(align(64))]
+struct CachePadded(AtomicUsize);
+
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    // Next slot to read, only advanced by the consumer
+    head: CachePadded,
+    // Next slot to write, only advanced by the producer
+    tail: CachePadded,
+}
+
+// SAFETY: a slot is only written by the producer while it is outside [head, tail) and only read
+// by the consumer while it is inside, and the index stores that move a slot between the two
+// publish the slot with release/acquire ordering.
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        for index in head..tail {
+            // SAFETY: every slot in [head, tail) holds a value that was pushed and never popped
+            unsafe { self.slots[index & self.mask].get_mut().assume_init_drop() };
+        }
+    }
+}
+
+/// Create a single-producer single-consumer ring of at least `capacity` slots, rounded up to a
+/// power of two. All slots are allocated here; pushing and popping never allocate or lock.
+pub fn spsc_ring<T: Send>(capacity: usize) -> (RingProducer<T>, RingConsumer<T>) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let ring = Arc::new(Ring {
+        slots: (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        mask: capacity - 1,
+        head: CachePadded(AtomicUsize::new(0)),
+        tail: CachePadded(AtomicUsize::new(0)),
+    });
+    (
+        RingProducer {
+            ring: ring.clone(),
+            tail: 0,
+            cached_head: 0,
+        },
+        RingConsumer {
+            ring,
+            head: 0,
+            cached_tail: 0,
+        },
+    )
+}
+
+/// Writing end of an `spsc_ring`, e.g. held by the gateway thread.
+pub struct RingProducer<T> {
+    ring: Arc<Ring<T>>,
+    tail: usize,
+    // Last head seen, re-read only when the ring looks full
+    cached_head: usize,
+}
+
+impl<T> RingProducer<T> {
+    /// Push `value`, handing it back if the ring is full.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        let capacity = self.ring.slots.len();
+        if self.tail - self.cached_head == capacity {
+            self.cached_head = self.ring.head.0.load(Ordering::Acquire);
+            if self.tail - self.cached_head == capacity {
+                return Err(value);
+            }
+        }
+        // SAFETY: the slot is outside [head, tail), so the consumer is not reading it
+        unsafe { (*self.ring.slots[self.tail & self.ring.mask].get()).write(value) };
+        self.tail += 1;
+        self.ring.tail.0.store(self.tail, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.slots.len()
+    }
+}
+
+/// Reading end of an `spsc_ring`, e.g. held by the matching thread.
+pub struct RingConsumer<T> {
+    ring: Arc<Ring<T>>,
+    head: usize,
+    // Last tail seen, re-read only when the ring looks empty
+    cached_tail: usize,
+}
+
+impl<T> RingConsumer<T> {
+    pub fn try_pop(&mut self) -> Option<T> {
+        if self.head == self.cached_tail {
+            self.cached_tail = self.ring.tail.0.load(Ordering::Acquire);
+            if self.head == self.cached_tail {
+                return None;
+            }
+        }
+        // SAFETY: the slot is inside [head, tail), so the producer has written it and will not
+        // touch it again until head moves past it
+        let value =
+            unsafe { (*self.ring.slots[self.head & self.ring.mask].get()).assume_init_read() };
+        self.head += 1;
+        self.ring.head.0.store(self.head, Ordering::Release);
+        Some(value)
+    }
+
+    /// Values pushed but not popped yet.
+    pub fn len(&self) -> usize {
+        self.ring.tail.0.load(Ordering::Acquire) - self.head
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod ring_tests {
+    use std::thread;
+
+    use super::*;
+    use crate::orderbook::command::Command;
+    use crate::orderbook::order::{Order, OrderType, Side};
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    #[test]
+    fn check_ring_is_bounded_and_fifo() {
+        let (mut producer, mut consumer) = spsc_ring(3);
+        assert_eq!(producer.capacity(), 4);
+        for value in 0..4 {
+            producer.try_push(value).unwrap();
+        }
+        assert_eq!(producer.try_push(4), Err(4));
+        assert_eq!(consumer.try_pop(), Some(0));
+        producer.try_push(4).unwrap();
+        assert_eq!(consumer.len(), 4);
+        let drained: Vec<i32> = std::iter::from_fn(|| consumer.try_pop()).collect();
+        assert_eq!(drained, vec![1, 2, 3, 4]);
+        assert!(consumer.is_empty());
+
+        // values left in the ring are dropped with it
+        let order = Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, 100, 1));
+        let (mut producer, consumer) = spsc_ring(2);
+        producer.try_push(order.clone()).unwrap();
+        drop((producer, consumer));
+        assert_eq!(Arc::strong_count(&order), 1);
+    }
+
+    #[test]
+    fn check_gateway_to_matching_thread() {
+        let (mut producer, mut consumer) = spsc_ring::<Command>(16);
+        let gateway = thread::spawn(move || {
+            for index in 0..1_000 {
+                let side = if index % 2 == 0 {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                };
+                let mut command =
+                    Command::NewOrder(Arc::new(Order::new(OrderType::LimitOrder, side, 100, 1)));
+                while let Err(rejected) = producer.try_push(command) {
+                    command = rejected;
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut book = OrderBook::new();
+        let mut applied = 0;
+        while applied < 1_000 {
+            match consumer.try_pop() {
+                Some(command) => {
+                    book.apply_command(&command).unwrap();
+                    applied += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        gateway.join().unwrap();
+        assert_eq!(book.resting_order_count(), 0);
+        assert_eq!(book.trade_history().trade_count(), 500);
+    }
+}
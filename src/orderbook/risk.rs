@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use crate::orderbook::order::{Order, OrderType};
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError};
+use crate::orderbook::types::{ParticipantId, Quantity};
+
+/// Pre-trade check run on every new order after the book's own validation and before it reaches
+/// matching. Returning an error rejects the order.
+pub trait RiskCheck: Send {
+    fn check(&self, order: &Order, book: &OrderBook) -> Result<(), OrderBookError>;
+
+    /// Check `replacement`, the order a resting order becomes when amended to a new price or a
+    /// larger quantity, with its new total as `original_quantity`. The resting order is still
+    /// in `book`. Runs `check` by default.
+    fn check_amendment(&self, replacement: &Order, book: &OrderBook) -> Result<(), OrderBookError> {
+        self.check(replacement, book)
+    }
+}
+
+/// Limits applied to a single participant; `None` leaves that dimension unchecked.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct RiskLimits {
+    pub max_order_size: Option<Quantity>,
+    // Limit on price * quantity of a single order, in price units
+    pub max_notional: Option<u128>,
+    // Resting orders, counted before the new order is added
+    pub max_open_orders: Option<usize>,
+}
+
+/// Per-participant `RiskLimits`, falling back to a default for participants without their own.
+#[derive(Clone, Debug, Default)]
+pub struct ParticipantLimits {
+    default_limits: RiskLimits,
+    limits: HashMap<ParticipantId, RiskLimits>,
+}
+
+impl ParticipantLimits {
+    pub fn new(default_limits: RiskLimits) -> Self {
+        ParticipantLimits {
+            default_limits,
+            limits: HashMap::new(),
+        }
+    }
+
+    pub fn with_limits(mut self, owner: ParticipantId, limits: RiskLimits) -> Self {
+        self.set_limits(owner, limits);
+        self
+    }
+
+    pub fn set_limits(&mut self, owner: ParticipantId, limits: RiskLimits) {
+        self.limits.insert(owner, limits);
+    }
+
+    pub fn limits_for(&self, owner: ParticipantId) -> RiskLimits {
+        self.limits
+            .get(&owner)
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+}
+
+impl ParticipantLimits {
+    // The size and notional limits, which apply to amendments as much as to new orders
+    fn check_size(&self, order: &Order) -> Result<(), OrderBookError> {
+        let owner = order.owner;
+        let limits = self.limits_for(owner);
+        if let Some(max_order_size) = limits.max_order_size
+            && order.original_quantity > max_order_size
+        {
+            return Err(OrderBookError::MaxOrderSizeExceeded {
+                owner,
+                quantity: order.original_quantity,
+                max_order_size,
+            });
+        }
+        // A market order's price is meaningless, so only its size is limited
+        if let Some(max_notional) = limits.max_notional
+            && order.order_type != OrderType::MarketOrder
+        {
+            let notional = order.price.unsigned_abs() as u128 * order.original_quantity as u128;
+            if notional > max_notional {
+                return Err(OrderBookError::MaxNotionalExceeded {
+                    owner,
+                    notional,
+                    max_notional,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RiskCheck for ParticipantLimits {
+    fn check(&self, order: &Order, book: &OrderBook) -> Result<(), OrderBookError> {
+        self.check_size(order)?;
+        let owner = order.owner;
+        let limits = self.limits_for(owner);
+        if let Some(max_open_orders) = limits.max_open_orders {
+            let open_orders = book.open_order_count(owner);
+            if open_orders >= max_open_orders {
+                return Err(OrderBookError::MaxOpenOrdersExceeded {
+                    owner,
+                    open_orders,
+                    max_open_orders,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // An amended order is already one of the participant's open orders
+    fn check_amendment(&self, replacement: &Order, _: &OrderBook) -> Result<(), OrderBookError> {
+        self.check_size(replacement)
+    }
+}
+
+#[cfg(test)]
+mod risk_tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::orderbook::order::Side;
+
+    fn limit_order(owner: ParticipantId, price: i64, quantity: Quantity) -> Arc<Order> {
+        Arc::new(Order::new(OrderType::LimitOrder, Side::Buy, price, quantity).with_owner(owner))
+    }
+
+    #[test]
+    fn check_participant_limits_reject_before_matching() {
+        let limits = ParticipantLimits::new(RiskLimits {
+            max_order_size: Some(10),
+            max_notional: Some(500),
+            max_open_orders: Some(2),
+        })
+        .with_limits(
+            9,
+            RiskLimits {
+                max_order_size: Some(100),
+                ..RiskLimits::default()
+            },
+        );
+        let mut book = OrderBook::new();
+        book.add_risk_check(Box::new(limits));
+
+        assert!(matches!(
+            book.add_order(&limit_order(1, 10, 11)),
+            Err(OrderBookError::MaxOrderSizeExceeded {
+                owner: 1,
+                quantity: 11,
+                max_order_size: 10
+            })
+        ));
+        assert!(matches!(
+            book.add_order(&limit_order(1, 60, 10)),
+            Err(OrderBookError::MaxNotionalExceeded {
+                notional: 600,
+                max_notional: 500,
+                ..
+            })
+        ));
+
+        book.add_order(&limit_order(1, 10, 5)).unwrap();
+        book.add_order(&limit_order(1, 11, 5)).unwrap();
+        assert!(matches!(
+            book.add_order(&limit_order(1, 12, 5)),
+            Err(OrderBookError::MaxOpenOrdersExceeded {
+                open_orders: 2,
+                max_open_orders: 2,
+                ..
+            })
+        ));
+        // other participants have their own count
+        book.add_order(&limit_order(2, 12, 5)).unwrap();
+        // and overrides replace the default limits entirely
+        book.add_order(&limit_order(9, 100, 50)).unwrap();
+        assert_eq!(book.resting_order_count(), 4);
+    }
+
+    #[test]
+    fn check_amendments_are_held_to_the_same_limits() {
+        let mut book = OrderBook::new();
+        book.add_risk_check(Box::new(ParticipantLimits::new(RiskLimits {
+            max_order_size: Some(10),
+            max_notional: Some(1_000),
+            max_open_orders: Some(1),
+        })));
+        let order = limit_order(1, 10, 10);
+        book.add_order(&order).unwrap();
+        let sequence = book.sequence();
+
+        assert!(matches!(
+            book.modify_order(order.order_id, 10, 1_000_000),
+            Err(OrderBookError::MaxOrderSizeExceeded {
+                quantity: 1_000_000,
+                ..
+            })
+        ));
+        assert!(matches!(
+            book.modify_order(order.order_id, 200, 10),
+            Err(OrderBookError::MaxNotionalExceeded {
+                notional: 2_000,
+                ..
+            })
+        ));
+        assert_eq!(book.sequence(), sequence);
+        assert_eq!(
+            book.get_order(order.order_id).unwrap().remaining_quantity,
+            10
+        );
+        // the amended order is not counted against the open order limit a second time
+        book.modify_order(order.order_id, 11, 10).unwrap();
+        assert_eq!(book.get_best_bid(), Some(11));
+    }
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::orderbook::order::{Order, OrderType, Side};
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError, Trade};
+use crate::orderbook::types::{Price, Quantity};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TieBreak {
+    // Rotate which venue goes first each time venues tie at a price
+    RoundRobin,
+    LargestSizeFirst,
+    LowestLatencyFirst,
+}
+
+pub struct Venue {
+    pub name: String,
+    pub book: OrderBook,
+    pub latency: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildOrder {
+    pub venue: usize, // index into the router's venues
+    pub price: Price,
+    pub quantity: Quantity,
+}
+
+/// Splits an aggressive order across venues trading the same instrument, best price first.
+pub struct SweepRouter {
+    venues: Vec<Venue>,
+    tie_break: TieBreak,
+    round_robin_start: usize,
+}
+
+impl SweepRouter {
+    pub fn new(tie_break: TieBreak) -> Self {
+        SweepRouter {
+            venues: Vec::new(),
+            tie_break,
+            round_robin_start: 0,
+        }
+    }
+
+    pub fn add_venue(&mut self, name: &str, book: OrderBook, latency: Duration) -> usize {
+        self.venues.push(Venue {
+            name: name.to_string(),
+            book,
+            latency,
+        });
+        self.venues.len() - 1
+    }
+
+    pub fn venue(&self, index: usize) -> Option<&Venue> {
+        self.venues.get(index)
+    }
+
+    pub fn venue_mut(&mut self, index: usize) -> Option<&mut Venue> {
+        self.venues.get_mut(index)
+    }
+
+    /// Child orders needed to take `quantity` on `side`, never past `limit_price` if given.
+    pub fn plan_sweep(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        limit_price: Option<Price>,
+    ) -> Vec<ChildOrder> {
+        // (price, venue, volume) for every opposite-side level across venues
+        let mut levels: Vec<(Price, usize, Quantity)> = Vec::new();
+        for (venue_index, venue) in self.venues.iter().enumerate() {
+            let depth = venue.book.get_depth(usize::MAX);
+            let opposite = match side {
+                Side::Buy => depth.asks,
+                Side::Sell => depth.bids,
+            };
+            levels.extend(
+                opposite
+                    .into_iter()
+                    .map(|level| (level.price, venue_index, level.volume)),
+            );
+        }
+        match side {
+            Side::Buy => levels.sort_by_key(|&(price, _, _)| price),
+            Side::Sell => levels.sort_by_key(|&(price, _, _)| std::cmp::Reverse(price)),
+        }
+
+        let mut children: Vec<ChildOrder> = Vec::new();
+        let mut remaining = quantity;
+        let mut start = 0;
+        while start < levels.len() && remaining > 0 {
+            let price = levels[start].0;
+            let within_limit = match (side, limit_price) {
+                (_, None) => true,
+                (Side::Buy, Some(limit)) => price <= limit,
+                (Side::Sell, Some(limit)) => price >= limit,
+            };
+            if !within_limit {
+                break;
+            }
+            let end = start + levels[start..].partition_point(|level| level.0 == price);
+            let mut tied = levels[start..end].to_vec();
+            self.order_tied_venues(&mut tied);
+
+            for (price, venue, volume) in tied {
+                if remaining == 0 {
+                    break;
+                }
+                let child_quantity = volume.min(remaining);
+                remaining -= child_quantity;
+                children.push(ChildOrder {
+                    venue,
+                    price,
+                    quantity: child_quantity,
+                });
+            }
+            start = end;
+        }
+        children
+    }
+
+    fn order_tied_venues(&mut self, tied: &mut [(Price, usize, Quantity)]) {
+        if tied.len() < 2 {
+            return;
+        }
+        match self.tie_break {
+            TieBreak::RoundRobin => {
+                let venue_count = self.venues.len();
+                let first = self.round_robin_start;
+                tied.sort_by_key(|&(_, venue, _)| (venue + venue_count - first) % venue_count);
+                self.round_robin_start = (first + 1) % venue_count;
+            }
+            TieBreak::LargestSizeFirst => {
+                tied.sort_by_key(|&(_, venue, volume)| (std::cmp::Reverse(volume), venue))
+            }
+            TieBreak::LowestLatencyFirst => {
+                tied.sort_by_key(|&(_, venue, _)| (self.venues[venue].latency, venue))
+            }
+        }
+    }
+
+    /// Plan a sweep and send each child as a limit order at its level price.
+    pub fn sweep(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        limit_price: Option<Price>,
+    ) -> Result<Vec<(usize, Trade)>, OrderBookError> {
+        let children = self.plan_sweep(side, quantity, limit_price);
+        let mut fills: Vec<(usize, Trade)> = Vec::new();
+        for child in children {
+            let order = Arc::new(Order::new(
+                OrderType::LimitOrder,
+                side,
+                child.price,
+                child.quantity,
+            ));
+            let result = self.venues[child.venue].book.add_order(&order)?;
+            fills.extend(result.trades.into_iter().map(|t| (child.venue, t)));
+        }
+        Ok(fills)
+    }
+}
+
+#[cfg(test)]
+mod router_tests {
+    use super::*;
+
+    fn venue_with_ask(price: Price, quantity: Quantity) -> OrderBook {
+        let mut book = OrderBook::new();
+        let order = Arc::new(Order::new(
+            OrderType::LimitOrder,
+            Side::Sell,
+            price,
+            quantity,
+        ));
+        book.add_order(&order).unwrap();
+        book
+    }
+
+    fn router(tie_break: TieBreak) -> SweepRouter {
+        let mut router = SweepRouter::new(tie_break);
+        router.add_venue("slow", venue_with_ask(100, 5), Duration::from_micros(500));
+        router.add_venue("fast", venue_with_ask(100, 8), Duration::from_micros(50));
+        router.add_venue("wide", venue_with_ask(101, 10), Duration::from_micros(10));
+        router
+    }
+
+    #[test]
+    fn check_largest_size_first() {
+        let children = router(TieBreak::LargestSizeFirst).plan_sweep(Side::Buy, 10, None);
+        assert_eq!(
+            children,
+            vec![
+                ChildOrder {
+                    venue: 1,
+                    price: 100,
+                    quantity: 8
+                },
+                ChildOrder {
+                    venue: 0,
+                    price: 100,
+                    quantity: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_lowest_latency_first_and_limit() {
+        let children = router(TieBreak::LowestLatencyFirst).plan_sweep(Side::Buy, 20, Some(100));
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].venue, 1);
+        assert_eq!(children[1].venue, 0);
+        assert_eq!(children.iter().map(|c| c.quantity).sum::<Quantity>(), 13);
+    }
+
+    #[test]
+    fn check_round_robin_rotates_and_sweep_fills() {
+        let mut router = router(TieBreak::RoundRobin);
+        assert_eq!(router.plan_sweep(Side::Buy, 1, None)[0].venue, 0);
+        assert_eq!(router.plan_sweep(Side::Buy, 1, None)[0].venue, 1);
+
+        let fills = router.sweep(Side::Buy, 15, None).unwrap();
+        assert_eq!(
+            fills.iter().map(|(_, t)| t.quantity()).sum::<Quantity>(),
+            15
+        );
+        assert_eq!(router.venue(0).unwrap().book.get_best_ask(), None);
+        assert_eq!(router.venue(2).unwrap().book.get_best_ask().unwrap(), 101);
+    }
+}
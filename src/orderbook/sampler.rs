@@ -0,0 +1,225 @@
+use std::fmt::Write;
+
+use crate::orderbook::orderbook_impl::OrderBook;
+use crate::orderbook::types::{Price, Quantity};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookSample {
+    pub timestamp: i64, // epoch millis
+    pub best_bid: Option<Price>,
+    pub best_ask: Option<Price>,
+    pub best_bid_qty: Quantity,
+    pub best_ask_qty: Quantity,
+    pub bid_depth: Quantity, // volume summed over the sampled levels
+    pub ask_depth: Quantity,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MarketQualityReport {
+    pub samples: usize,
+    pub time_weighted_spread: Option<f64>,
+    pub average_bid_depth: f64,
+    pub average_ask_depth: f64,
+    // Mean time for the spread to narrow back to its time-weighted average after
+    // widening past it, weighted by the depth resting when it widened
+    pub depth_weighted_resilience_ms: Option<f64>,
+}
+
+/// Records book samples at a fixed interval over a session.
+pub struct BookSampler {
+    depth_levels: usize,
+    interval_ms: i64,
+    samples: Vec<BookSample>,
+}
+
+impl BookSample {
+    pub fn spread(&self) -> Option<Price> {
+        Some(self.best_ask? - self.best_bid?)
+    }
+}
+
+impl BookSampler {
+    pub fn new(depth_levels: usize, interval_ms: i64) -> Self {
+        BookSampler {
+            depth_levels,
+            interval_ms,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Sample `book` if at least one interval has passed since the last sample.
+    pub fn sample(&mut self, book: &OrderBook, now: i64) -> bool {
+        if let Some(last) = self.samples.last()
+            && now - last.timestamp < self.interval_ms
+        {
+            return false;
+        }
+
+        let depth = book.get_depth(self.depth_levels);
+        self.samples.push(BookSample {
+            timestamp: now,
+            best_bid: depth.bids.first().map(|level| level.price),
+            best_ask: depth.asks.first().map(|level| level.price),
+            best_bid_qty: depth.bids.first().map_or(0, |level| level.volume),
+            best_ask_qty: depth.asks.first().map_or(0, |level| level.volume),
+            bid_depth: depth.bids.iter().map(|level| level.volume).sum(),
+            ask_depth: depth.asks.iter().map(|level| level.volume).sum(),
+        });
+        true
+    }
+
+    pub fn samples(&self) -> &[BookSample] {
+        &self.samples
+    }
+
+    pub fn report(&self) -> MarketQualityReport {
+        if self.samples.is_empty() {
+            return MarketQualityReport::default();
+        }
+        let count = self.samples.len() as f64;
+        let time_weighted_spread = self.time_weighted_spread();
+
+        MarketQualityReport {
+            samples: self.samples.len(),
+            time_weighted_spread,
+            average_bid_depth: self.samples.iter().map(|s| s.bid_depth as f64).sum::<f64>() / count,
+            average_ask_depth: self.samples.iter().map(|s| s.ask_depth as f64).sum::<f64>() / count,
+            depth_weighted_resilience_ms: time_weighted_spread
+                .and_then(|mean_spread| self.depth_weighted_resilience(mean_spread)),
+        }
+    }
+
+    // Each two-sided sample's spread holds until the next sample
+    fn time_weighted_spread(&self) -> Option<f64> {
+        let mut weighted_sum = 0.0;
+        let mut total_time = 0.0;
+        for pair in self.samples.windows(2) {
+            if let Some(spread) = pair[0].spread() {
+                let duration = (pair[1].timestamp - pair[0].timestamp) as f64;
+                weighted_sum += spread as f64 * duration;
+                total_time += duration;
+            }
+        }
+        if total_time > 0.0 {
+            Some(weighted_sum / total_time)
+        } else {
+            self.samples.last()?.spread().map(|spread| spread as f64)
+        }
+    }
+
+    fn depth_weighted_resilience(&self, mean_spread: f64) -> Option<f64> {
+        let mut weighted_sum = 0.0;
+        let mut total_weight = 0.0;
+        // (timestamp, depth) of the sample where the spread widened
+        let mut widened_at: Option<(i64, f64)> = None;
+
+        for sample in self.samples.iter() {
+            let Some(spread) = sample.spread() else {
+                continue;
+            };
+            let is_wide = spread as f64 > mean_spread;
+            match widened_at {
+                None if is_wide => {
+                    let depth = (sample.bid_depth + sample.ask_depth) as f64;
+                    widened_at = Some((sample.timestamp, depth));
+                }
+                Some((start, depth)) if !is_wide => {
+                    weighted_sum += (sample.timestamp - start) as f64 * depth;
+                    total_weight += depth;
+                    widened_at = None;
+                }
+                _ => {}
+            }
+        }
+        (total_weight > 0.0).then(|| weighted_sum / total_weight)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "timestamp,best_bid,best_ask,spread,best_bid_qty,best_ask_qty,bid_depth,ask_depth\n",
+        );
+        for s in self.samples.iter() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                s.timestamp,
+                format_optional(s.best_bid),
+                format_optional(s.best_ask),
+                format_optional(s.spread()),
+                s.best_bid_qty,
+                s.best_ask_qty,
+                s.bid_depth,
+                s.ask_depth
+            );
+        }
+        csv
+    }
+}
+
+impl MarketQualityReport {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"samples\":{},\"time_weighted_spread\":{},\"average_bid_depth\":{},\"average_ask_depth\":{},\"depth_weighted_resilience_ms\":{}}}",
+            self.samples,
+            self.time_weighted_spread
+                .map_or("null".to_string(), |v| v.to_string()),
+            self.average_bid_depth,
+            self.average_ask_depth,
+            self.depth_weighted_resilience_ms
+                .map_or("null".to_string(), |v| v.to_string()),
+        )
+    }
+}
+
+fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or(String::new(), |v| v.to_string())
+}
+
+#[cfg(test)]
+mod sampler_tests {
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType, Side};
+    use std::sync::Arc;
+
+    fn add_limit(book: &mut OrderBook, side: Side, price: Price, quantity: Quantity) {
+        let order = Arc::new(Order::new(OrderType::LimitOrder, side, price, quantity));
+        book.add_order(&order).unwrap();
+    }
+
+    #[test]
+    fn check_sampler_respects_interval() {
+        let book = OrderBook::new();
+        let mut sampler = BookSampler::new(5, 100);
+        assert!(sampler.sample(&book, 0));
+        assert!(!sampler.sample(&book, 50));
+        assert!(sampler.sample(&book, 100));
+        assert_eq!(sampler.samples().len(), 2);
+        assert_eq!(sampler.samples()[0].spread(), None);
+    }
+
+    #[test]
+    fn check_time_weighted_spread_and_resilience() {
+        let mut book = OrderBook::new();
+        add_limit(&mut book, Side::Buy, 99, 10);
+        add_limit(&mut book, Side::Sell, 101, 10);
+        add_limit(&mut book, Side::Sell, 105, 10);
+
+        let mut sampler = BookSampler::new(5, 1);
+        sampler.sample(&book, 0); // spread 2 for 300ms
+
+        // taker sweeps the best ask, spread widens to 6 for 100ms
+        let taker = Arc::new(Order::new(OrderType::MarketOrder, Side::Buy, 0, 10));
+        book.add_order(&taker).unwrap();
+        sampler.sample(&book, 300);
+
+        add_limit(&mut book, Side::Sell, 101, 10);
+        sampler.sample(&book, 400);
+
+        let report = sampler.report();
+        assert_eq!(report.samples, 3);
+        assert_eq!(report.time_weighted_spread, Some(3.0));
+        assert_eq!(report.depth_weighted_resilience_ms, Some(100.0));
+        assert!(report.to_json().starts_with("{\"samples\":3,"));
+        assert_eq!(sampler.to_csv().lines().count(), 4);
+    }
+}
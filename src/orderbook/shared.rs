@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::orderbook::order::Order;
+use crate::orderbook::orderbook_impl::{MatchResult, OrderBook, OrderBookError};
+use crate::orderbook::price_level::{BboUpdate, OrderView, OrderbookLevelInfos};
+use crate::orderbook::types::{OrderId, Price};
+
+/// `OrderBook` behind a mutex, so any number of threads can submit, cancel and query orders.
+///
+/// The book itself is `Send` but not `Sync`: its queues hold raw cursors into intrusive lists and
+/// its analytics cache lives in a `Cell`, both of which assume a single user at a time. Every
+/// method here holds the lock for the whole call, so each call sees and leaves the book in a
+/// consistent state and all calls are applied in one total order, the order they acquire the
+/// lock. Nothing is promised between two calls: use `with_book` to run several steps atomically.
+/// Listeners run while the lock is held and must not call back into the same `SharedOrderBook`.
+#[derive(Clone)]
+pub struct SharedOrderBook {
+    book: Arc<Mutex<OrderBook>>,
+}
+
+impl SharedOrderBook {
+    pub fn new(book: OrderBook) -> Self {
+        SharedOrderBook {
+            book: Arc::new(Mutex::new(book)),
+        }
+    }
+
+    pub fn add_order(&self, order: &Arc<Order>) -> Result<MatchResult, OrderBookError> {
+        self.lock().add_order(order)
+    }
+
+    pub fn cancel_order(&self, order_id: OrderId) -> Result<(), OrderBookError> {
+        self.lock().cancel_order(order_id)
+    }
+
+    pub fn get_order(&self, order_id: OrderId) -> Option<OrderView> {
+        self.lock().get_order(order_id)
+    }
+
+    pub fn get_depth(&self, depth: usize) -> OrderbookLevelInfos {
+        self.lock().get_depth(depth)
+    }
+
+    pub fn get_best_bid(&self) -> Option<Price> {
+        self.lock().get_best_bid()
+    }
+
+    pub fn get_best_ask(&self) -> Option<Price> {
+        self.lock().get_best_ask()
+    }
+
+    pub fn bbo(&self) -> BboUpdate {
+        self.lock().bbo()
+    }
+
+    /// Run `f` with exclusive access to the book; no other call interleaves with it.
+    pub fn with_book<R>(&self, f: impl FnOnce(&mut OrderBook) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    // A panic while the lock was held may have left the book half-updated, so poisoning is
+    // propagated rather than recovered from
+    fn lock(&self) -> MutexGuard<'_, OrderBook> {
+        self.book.lock().expect("order book lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod shared_tests {
+    use std::thread;
+
+    use super::*;
+    use crate::orderbook::order::{OrderType, Side};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn check_orders_from_many_threads() {
+        assert_send_sync::<SharedOrderBook>();
+        let book = SharedOrderBook::new(OrderBook::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|thread_index| {
+                let book = book.clone();
+                thread::spawn(move || {
+                    let side = if thread_index % 2 == 0 {
+                        Side::Buy
+                    } else {
+                        Side::Sell
+                    };
+                    for _ in 0..50 {
+                        let order = Arc::new(Order::new(OrderType::LimitOrder, side, 100, 1));
+                        book.add_order(&order).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // 100 units bought and 100 sold at one price all cross
+        book.with_book(|book| {
+            assert_eq!(book.resting_order_count(), 0);
+            assert_eq!(book.trade_history().trade_count(), 100);
+        });
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use rand::prelude::*;
+
+use crate::orderbook::command::Command;
+use crate::orderbook::id::{IdGenerator, MonotonicIdGenerator};
+use crate::orderbook::order::{Order, OrderType, Side};
+use crate::orderbook::orderbook_impl::{OrderBook, Trade};
+use crate::orderbook::types::{OrderId, ParticipantId, Price, Quantity};
+
+/// A participant in a simulation. Each step the simulator shows every agent the book and applies
+/// the commands it returns, drawing all randomness from the simulator's seeded `rng`.
+pub trait Agent: Send {
+    /// Participant the agent's orders are attributed to.
+    fn owner(&self) -> ParticipantId;
+
+    fn act(&mut self, book: &OrderBook, rng: &mut StdRng) -> Vec<Command>;
+}
+
+/// Midpoint of the best bid and ask rounded down to the tick, the one best price if only one
+/// side is quoted, else `fallback`.
+pub fn mid_price(book: &OrderBook, fallback: Price) -> Price {
+    let tick_size = book.config().tick_size;
+    match (book.get_best_bid(), book.get_best_ask()) {
+        (Some(bid), Some(ask)) => ((bid + ask) / 2).div_euclid(tick_size) * tick_size,
+        (Some(price), None) | (None, Some(price)) => price,
+        (None, None) => fallback,
+    }
+}
+
+// Open orders of `owner` oldest first; ids from the simulator increase, so the order is the same
+// from run to run
+fn open_order_ids(book: &OrderBook, owner: ParticipantId) -> Vec<OrderId> {
+    let mut order_ids: Vec<OrderId> = book
+        .open_orders(owner)
+        .iter()
+        .map(|order| order.order_id)
+        .collect();
+    order_ids.sort();
+    order_ids
+}
+
+fn new_order(order_type: OrderType, side: Side, price: Price, quantity: Quantity) -> Command {
+    Command::NewOrder(Arc::new(Order::new(order_type, side, price, quantity)))
+}
+
+fn random_side(rng: &mut StdRng) -> Side {
+    if rng.gen_bool(0.5) {
+        Side::Buy
+    } else {
+        Side::Sell
+    }
+}
+
+/// Uninformed flow: limit orders scattered around the mid, the odd market order, and random
+/// cancels of its own resting orders.
+pub struct NoiseTrader {
+    pub owner: ParticipantId,
+    // Mid assumed while the book is empty
+    pub initial_price: Price,
+    // Limit prices are drawn up to this many ticks either side of the mid
+    pub max_offset_ticks: Price,
+    pub max_quantity: Quantity,
+    // Chance per step of sending a market order instead of a limit order
+    pub market_probability: f64,
+    // Chance per step of canceling one of its resting orders
+    pub cancel_probability: f64,
+}
+
+impl Agent for NoiseTrader {
+    fn owner(&self) -> ParticipantId {
+        self.owner
+    }
+
+    fn act(&mut self, book: &OrderBook, rng: &mut StdRng) -> Vec<Command> {
+        let mut commands = Vec::new();
+        if rng.gen_bool(self.cancel_probability) {
+            let order_ids = open_order_ids(book, self.owner);
+            if let Some(order_id) = order_ids.choose(rng) {
+                commands.push(Command::CancelOrder(*order_id));
+            }
+        }
+        let side = random_side(rng);
+        let quantity = rng.gen_range(1..=self.max_quantity);
+        if rng.gen_bool(self.market_probability) {
+            commands.push(new_order(OrderType::MarketOrder, side, 0, quantity));
+        } else {
+            let offset = rng.gen_range(-self.max_offset_ticks..=self.max_offset_ticks);
+            let price = mid_price(book, self.initial_price) + offset * book.config().tick_size;
+            if price > 0 {
+                commands.push(new_order(OrderType::LimitOrder, side, price, quantity));
+            }
+        }
+        commands
+    }
+}
+
+/// Liquidity provider: every step it pulls its quotes and requotes `levels` prices a side,
+/// `half_spread_ticks` from the mid and one tick apart, shading both sides against its inventory.
+pub struct MarketMaker {
+    pub owner: ParticipantId,
+    pub initial_price: Price,
+    pub half_spread_ticks: Price,
+    pub levels: usize,
+    pub quantity: Quantity,
+    // Ticks the quotes move against each `quantity` of net position, 0 to quote symmetrically
+    pub skew_ticks: Price,
+    // Net filled position, positive when long
+    position: i64,
+    // Executed quantity already counted into `position`, per quote
+    filled: Vec<(OrderId, Side, Quantity)>,
+}
+
+impl MarketMaker {
+    pub fn new(owner: ParticipantId, initial_price: Price, half_spread_ticks: Price) -> Self {
+        MarketMaker {
+            owner,
+            initial_price,
+            half_spread_ticks,
+            levels: 1,
+            quantity: 10,
+            skew_ticks: 0,
+            position: 0,
+            filled: Vec::new(),
+        }
+    }
+
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    // Count fills since the last step: quotes still resting by how much they executed, quotes
+    // gone from the book as fully filled, since the maker never leaves them to be canceled
+    fn update_position(&mut self, book: &OrderBook) {
+        let open_orders = book.open_orders(self.owner);
+        for (order_id, side, counted) in self.filled.drain(..) {
+            let executed = open_orders
+                .iter()
+                .find(|order| order.order_id == order_id)
+                .map_or(self.quantity, |order| order.executed_quantity);
+            let delta = (executed - counted) as i64;
+            self.position += match side {
+                Side::Buy => delta,
+                Side::Sell => -delta,
+            };
+        }
+    }
+}
+
+impl Agent for MarketMaker {
+    fn owner(&self) -> ParticipantId {
+        self.owner
+    }
+
+    fn act(&mut self, book: &OrderBook, _rng: &mut StdRng) -> Vec<Command> {
+        self.update_position(book);
+        let mut commands: Vec<Command> = open_order_ids(book, self.owner)
+            .into_iter()
+            .map(Command::CancelOrder)
+            .collect();
+        let tick_size = book.config().tick_size;
+        let skew = self.position / self.quantity.max(1) as i64 * self.skew_ticks;
+        let mid = mid_price(book, self.initial_price) - skew * tick_size;
+        for level in 0..self.levels as Price {
+            let distance = (self.half_spread_ticks + level) * tick_size;
+            for (side, price) in [(Side::Buy, mid - distance), (Side::Sell, mid + distance)] {
+                if price <= 0 {
+                    continue;
+                }
+                let order = Arc::new(Order::new(
+                    OrderType::LimitOrder,
+                    side,
+                    price,
+                    self.quantity,
+                ));
+                commands.push(Command::NewOrder(order));
+            }
+        }
+        commands
+    }
+}
+
+/// Trend follower: sends a market order in the direction the mid has moved when it has moved at
+/// least `threshold_ticks` over the last `lookback` steps.
+pub struct MomentumTrader {
+    pub owner: ParticipantId,
+    pub lookback: usize,
+    pub threshold_ticks: Price,
+    pub quantity: Quantity,
+    history: VecDeque<Price>,
+}
+
+impl MomentumTrader {
+    pub fn new(owner: ParticipantId, lookback: usize, threshold_ticks: Price) -> Self {
+        MomentumTrader {
+            owner,
+            lookback,
+            threshold_ticks,
+            quantity: 10,
+            history: VecDeque::with_capacity(lookback + 1),
+        }
+    }
+}
+
+impl Agent for MomentumTrader {
+    fn owner(&self) -> ParticipantId {
+        self.owner
+    }
+
+    fn act(&mut self, book: &OrderBook, _rng: &mut StdRng) -> Vec<Command> {
+        let (Some(bid), Some(ask)) = (book.get_best_bid(), book.get_best_ask()) else {
+            return Vec::new();
+        };
+        let mid = (bid + ask) / 2;
+        self.history.push_back(mid);
+        if self.history.len() <= self.lookback {
+            return Vec::new();
+        }
+        let start = self.history.pop_front().unwrap_or(mid);
+        let threshold = self.threshold_ticks * book.config().tick_size;
+        let side = if mid - start >= threshold {
+            Side::Buy
+        } else if start - mid >= threshold {
+            Side::Sell
+        } else {
+            return Vec::new();
+        };
+        vec![new_order(OrderType::MarketOrder, side, 0, self.quantity)]
+    }
+}
+
+/// Totals of a simulation run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SimulationSummary {
+    pub steps: u64,
+    pub orders: u64,
+    pub cancels: u64,
+    // Commands the book refused, e.g. a market order against an empty side
+    pub rejected: u64,
+    pub trades: u64,
+    pub volume: Quantity,
+}
+
+/// Drives a book with agents from one seeded RNG. Each step the agents act in a random order and
+/// their commands are applied as they come. Order ids and timestamps come from the simulator,
+/// which pins the book clock to simulated time, so with the same seed, agents and starting book a
+/// run reproduces the same commands and book states; trade ids also repeat when the book is given
+/// a `MonotonicIdGenerator`.
+pub struct Simulator {
+    book: OrderBook,
+    agents: Vec<Box<dyn Agent>>,
+    rng: StdRng,
+    order_ids: MonotonicIdGenerator,
+    // Simulated epoch millis, advanced by `step_millis` after each step
+    time: i64,
+    step_millis: i64,
+    summary: SimulationSummary,
+}
+
+impl Simulator {
+    pub fn new(book: OrderBook, seed: u64) -> Self {
+        Simulator {
+            book,
+            agents: Vec::new(),
+            rng: StdRng::seed_from_u64(seed),
+            // Out of the range a MonotonicIdGenerator::new(1) trade id generator reaches
+            order_ids: MonotonicIdGenerator::new(1 << 48),
+            time: 0,
+            step_millis: 1,
+            summary: SimulationSummary::default(),
+        }
+    }
+
+    /// Start simulated time at `start_millis` and advance it `step_millis` per step, instead of
+    /// from 0 by one millisecond.
+    pub fn with_clock(mut self, start_millis: i64, step_millis: i64) -> Self {
+        self.time = start_millis;
+        self.step_millis = step_millis;
+        self
+    }
+
+    pub fn add_agent(&mut self, agent: Box<dyn Agent>) {
+        self.agents.push(agent);
+    }
+
+    /// Let every agent act once, returning the trades of the step.
+    pub fn step(&mut self) -> Vec<Trade> {
+        let mut turns: Vec<usize> = (0..self.agents.len()).collect();
+        turns.shuffle(&mut self.rng);
+        let mut trades = Vec::new();
+        self.book.set_clock(Some(self.time * 1_000));
+        for index in turns {
+            let owner = self.agents[index].owner();
+            let commands = self.agents[index].act(&self.book, &mut self.rng);
+            for command in commands {
+                let command = match command {
+                    Command::NewOrder(order) => {
+                        self.summary.orders += 1;
+                        let mut order = (*order).clone();
+                        order.order_id = self.order_ids.next_id();
+                        order.owner = owner;
+                        order.timestamp = self.time;
+                        Command::NewOrder(Arc::new(order))
+                    }
+                    Command::CancelOrder(order_id) => {
+                        self.summary.cancels += 1;
+                        Command::CancelOrder(order_id)
+                    }
+                    command => command,
+                };
+                match self.book.apply_command(&command) {
+                    Ok(result) => trades.extend(result.trades),
+                    Err(_) => self.summary.rejected += 1,
+                }
+            }
+        }
+        self.summary.steps += 1;
+        self.time += self.step_millis;
+        self.summary.trades += trades.len() as u64;
+        self.summary.volume += trades.iter().map(Trade::quantity).sum::<Quantity>();
+        trades
+    }
+
+    /// Run `steps` steps, returning the totals since the simulator was created.
+    pub fn run(&mut self, steps: u64) -> SimulationSummary {
+        for _ in 0..steps {
+            self.step();
+        }
+        self.summary
+    }
+
+    pub fn summary(&self) -> SimulationSummary {
+        self.summary
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn book_mut(&mut self) -> &mut OrderBook {
+        &mut self.book
+    }
+
+    pub fn into_book(self) -> OrderBook {
+        self.book
+    }
+}
+
+#[cfg(test)]
+mod simulator_tests {
+    use super::*;
+
+    fn simulation(seed: u64) -> Simulator {
+        let mut simulator = Simulator::new(OrderBook::new(), seed);
+        let mut maker = MarketMaker::new(1, 1_000, 2);
+        maker.levels = 3;
+        maker.skew_ticks = 1;
+        simulator.add_agent(Box::new(maker));
+        simulator.add_agent(Box::new(NoiseTrader {
+            owner: 2,
+            initial_price: 1_000,
+            max_offset_ticks: 5,
+            max_quantity: 20,
+            market_probability: 0.2,
+            cancel_probability: 0.3,
+        }));
+        simulator.add_agent(Box::new(MomentumTrader::new(3, 5, 2)));
+        simulator
+    }
+
+    #[test]
+    fn check_seeded_simulation_is_reproducible() {
+        let mut first = simulation(7);
+        let summary = first.run(100);
+        assert_eq!(summary.steps, 100);
+        assert!(summary.trades > 0 && summary.cancels > 0);
+        first.book().assert_consistent();
+
+        let mut second = simulation(7);
+        assert_eq!(second.run(100), summary);
+        assert_eq!(second.book().snapshot().bids, first.book().snapshot().bids);
+        assert_eq!(second.book().snapshot().asks, first.book().snapshot().asks);
+
+        // the market maker keeps both sides quoted
+        assert!(first.book().get_best_bid().is_some() && first.book().get_best_ask().is_some());
+    }
+}
@@ -0,0 +1,29 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::orderbook::order::Order;
+use crate::orderbook::state::InstrumentState;
+use crate::orderbook::types::Price;
+
+/// One price level with its resting orders in FIFO order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LevelSnapshot {
+    pub price: Price,
+    pub orders: Vec<Order>,
+}
+
+/// Point-in-time copy of every resting order, enough to rebuild the book.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BookSnapshot {
+    pub timestamp: i64, // epoch millis
+    // Book sequence number the snapshot was taken at, journal entries after it are not included
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sequence: u64,
+    // Trading state at the time, restored with the orders so replay resumes under the same rules
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub instrument_state: InstrumentState,
+    pub bids: Vec<LevelSnapshot>, // best (highest) bid first
+    pub asks: Vec<LevelSnapshot>, // best (lowest) ask first
+}
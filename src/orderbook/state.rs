@@ -0,0 +1,89 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InstrumentState {
+    #[default]
+    Active,
+    // Call auction: orders rest without matching until `OrderBook::uncross`
+    Auction,
+    // Only cancels are accepted, e.g. ahead of a halt or the close
+    CancelOnly,
+    Halted,
+    Closed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstrumentStateChange {
+    pub previous: InstrumentState,
+    pub current: InstrumentState,
+    pub timestamp: i64,
+}
+
+impl InstrumentState {
+    pub fn accepts_new_orders(&self) -> bool {
+        matches!(self, InstrumentState::Active | InstrumentState::Auction)
+    }
+
+    pub fn accepts_cancels(&self) -> bool {
+        matches!(
+            self,
+            InstrumentState::Active | InstrumentState::Auction | InstrumentState::CancelOnly
+        )
+    }
+}
+
+/// Trading phase of a book, driven through `OrderBook::transition_to`. Each phase is backed by an
+/// `InstrumentState`, which is what order entry actually consults.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BookState {
+    // Orders accumulate without matching (call auction)
+    PreOpen,
+    // Continuous matching
+    Open,
+    // Only cancels are accepted
+    Halted,
+    Closed,
+}
+
+impl BookState {
+    pub fn instrument_state(&self) -> InstrumentState {
+        match self {
+            BookState::PreOpen => InstrumentState::Auction,
+            BookState::Open => InstrumentState::Active,
+            BookState::Halted => InstrumentState::CancelOnly,
+            BookState::Closed => InstrumentState::Closed,
+        }
+    }
+
+    pub fn can_transition_to(&self, next: BookState) -> bool {
+        use BookState::*;
+        matches!(
+            (self, next),
+            (Closed, PreOpen)
+                | (Closed, Open)
+                | (PreOpen, Open)
+                | (PreOpen, Halted)
+                | (PreOpen, Closed)
+                | (Open, PreOpen)
+                | (Open, Halted)
+                | (Open, Closed)
+                | (Halted, PreOpen)
+                | (Halted, Open)
+                | (Halted, Closed)
+        )
+    }
+}
+
+impl From<InstrumentState> for BookState {
+    // A full halt, which also rejects cancels, is still reported as Halted
+    fn from(state: InstrumentState) -> Self {
+        match state {
+            InstrumentState::Active => BookState::Open,
+            InstrumentState::Auction => BookState::PreOpen,
+            InstrumentState::CancelOnly | InstrumentState::Halted => BookState::Halted,
+            InstrumentState::Closed => BookState::Closed,
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::types::{Price, Quantity};
+
+/// VWAP and TWAP over the trailing `window`, as of the time the stats were taken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowStats {
+    pub window: Duration,
+    pub trade_count: usize,
+    pub volume: Quantity,
+    pub vwap: Option<f64>,
+    // Average of the last traded price over the window, weighted by how long each price stood
+    pub twap: Option<f64>,
+}
+
+// Trades inside one window with running sums, so a query only has to account for the few trades
+// that aged out since the last trade was recorded
+struct RollingWindow {
+    window: Duration,
+    trades: VecDeque<(i64, Price, Quantity)>,
+    notional: i128,
+    volume: Quantity,
+    // Last trade that aged out, the price standing when the window opens
+    price_before: Option<Price>,
+}
+
+impl RollingWindow {
+    fn new(window: Duration) -> Self {
+        RollingWindow {
+            window,
+            trades: VecDeque::new(),
+            notional: 0,
+            volume: 0,
+            price_before: None,
+        }
+    }
+
+    fn cutoff(&self, now: i64) -> i64 {
+        now.saturating_sub(self.window.as_micros() as i64)
+    }
+
+    fn record(&mut self, timestamp: i64, price: Price, quantity: Quantity) {
+        self.trades.push_back((timestamp, price, quantity));
+        // Wrapping, so a removal undoes its addition exactly even past i128 in between
+        self.notional = self.notional.wrapping_add(price as i128 * quantity as i128);
+        self.volume = self.volume.saturating_add(quantity);
+
+        let cutoff = self.cutoff(timestamp);
+        while let Some(&(trade_time, price, quantity)) = self.trades.front() {
+            if trade_time >= cutoff {
+                break;
+            }
+            self.trades.pop_front();
+            self.notional = self.notional.wrapping_sub(price as i128 * quantity as i128);
+            self.volume = self.volume.saturating_sub(quantity);
+            self.price_before = Some(price);
+        }
+    }
+
+    fn stats_at(&self, now: i64) -> WindowStats {
+        let cutoff = self.cutoff(now);
+        let stale = self
+            .trades
+            .iter()
+            .take_while(|(trade_time, ..)| *trade_time < cutoff);
+        let mut notional = self.notional;
+        let mut volume = self.volume;
+        let mut price_before = self.price_before;
+        let mut stale_count = 0;
+        for &(_, price, quantity) in stale {
+            notional = notional.wrapping_sub(price as i128 * quantity as i128);
+            volume = volume.saturating_sub(quantity);
+            price_before = Some(price);
+            stale_count += 1;
+        }
+        let live = self.trades.iter().skip(stale_count);
+
+        // Without a price standing at the cutoff, the average starts at the first live trade
+        let mut standing = price_before.map(|price| (cutoff, price));
+        let mut weighted: i128 = 0;
+        let mut elapsed: i64 = 0;
+        for &(trade_time, price, _) in live {
+            if let Some((since, standing_price)) = standing {
+                weighted += standing_price as i128 * (trade_time - since) as i128;
+                elapsed += trade_time - since;
+            }
+            standing = Some((trade_time, price));
+        }
+        if let Some((since, standing_price)) = standing
+            && now > since
+        {
+            weighted += standing_price as i128 * (now - since) as i128;
+            elapsed += now - since;
+        }
+
+        WindowStats {
+            window: self.window,
+            trade_count: self.trades.len() - stale_count,
+            volume,
+            vwap: (volume > 0).then(|| notional as f64 / volume as f64),
+            twap: if elapsed > 0 {
+                Some(weighted as f64 / elapsed as f64)
+            } else {
+                standing.map(|(_, price)| price as f64)
+            },
+        }
+    }
+}
+
+/// Rolling VWAP/TWAP over a set of trailing windows, fed with every trade the book executes.
+pub struct TradeStats {
+    windows: Vec<RollingWindow>,
+}
+
+impl TradeStats {
+    pub fn new(windows: &[Duration]) -> Self {
+        TradeStats {
+            windows: windows.iter().copied().map(RollingWindow::new).collect(),
+        }
+    }
+
+    pub fn record(&mut self, trade: &Trade) {
+        for window in self.windows.iter_mut() {
+            window.record(trade.timestamp(), trade.price(), trade.quantity());
+        }
+    }
+
+    /// Stats per window, in configuration order, as of `now` (epoch micros like trade timestamps).
+    pub fn stats_at(&self, now: i64) -> Vec<WindowStats> {
+        self.windows
+            .iter()
+            .map(|window| window.stats_at(now))
+            .collect()
+    }
+}
+
+/// Open, high, low and last price, volume, trade count and notional of the trading session,
+/// updated with every trade until `OrderBook::reset_session` starts the next one.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SessionStats {
+    pub open: Option<Price>,
+    pub high: Option<Price>,
+    pub low: Option<Price>,
+    pub last: Option<Price>,
+    pub volume: Quantity,
+    pub trade_count: u64,
+    // Sum of price * quantity, in ticks times lots
+    pub notional: i128,
+}
+
+impl SessionStats {
+    pub fn record(&mut self, trade: &Trade) {
+        let price = trade.price();
+        self.open.get_or_insert(price);
+        self.high = Some(self.high.map_or(price, |high| high.max(price)));
+        self.low = Some(self.low.map_or(price, |low| low.min(price)));
+        self.last = Some(price);
+        self.volume = self.volume.saturating_add(trade.quantity());
+        self.trade_count += 1;
+        self.notional = self
+            .notional
+            .saturating_add(price as i128 * trade.quantity() as i128);
+    }
+
+    /// Volume-weighted average price of the session, None before the first trade.
+    pub fn vwap(&self) -> Option<f64> {
+        (self.volume > 0).then(|| self.notional as f64 / self.volume as f64)
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+
+    #[test]
+    fn check_vwap_and_twap_over_rolling_window() {
+        let mut window = RollingWindow::new(Duration::from_micros(100));
+        window.record(0, 10, 1);
+        window.record(50, 20, 3);
+
+        // 10 stood for 50us, 20 for the last 50us
+        let stats = window.stats_at(100);
+        assert_eq!(stats.trade_count, 2);
+        assert_eq!(stats.volume, 4);
+        assert_eq!(stats.vwap, Some(17.5));
+        assert_eq!(stats.twap, Some(15.0));
+
+        // the first trade has aged out but its price still stands until 50
+        let stats = window.stats_at(125);
+        assert_eq!(stats.trade_count, 1);
+        assert_eq!(stats.vwap, Some(20.0));
+        assert_eq!(stats.twap, Some(17.5));
+
+        window.record(200, 30, 1);
+        let stats = window.stats_at(200);
+        assert_eq!(stats.vwap, Some(30.0));
+        assert_eq!(stats.twap, Some(20.0));
+        assert_eq!(
+            RollingWindow::new(Duration::from_secs(1)).stats_at(0),
+            WindowStats {
+                window: Duration::from_secs(1),
+                trade_count: 0,
+                volume: 0,
+                vwap: None,
+                twap: None,
+            }
+        );
+    }
+}
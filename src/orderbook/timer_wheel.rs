@@ -0,0 +1,196 @@
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+// Enough levels of 64 slots to cover every u64 deadline
+const LEVELS: usize = 11;
+
+/// Hierarchical timer wheel with millisecond resolution: 11 levels of 64 slots, level `l`
+/// slots `64^l` ms wide. A timer is filed at the lowest level whose slot size separates its
+/// deadline from the wheel's time, and moves down a level each time the wheel reaches its slot,
+/// so scheduling is O(1) and `advance` only visits occupied slots.
+#[derive(Debug)]
+pub struct TimerWheel<T> {
+    levels: Vec<Vec<Vec<(u64, T)>>>,
+    // Bit i set when slot i of the level holds timers
+    occupied: [u64; LEVELS],
+    // First millisecond not yet processed; everything due before it has fired
+    next: u64,
+    // Timers scheduled at or before a time already processed, fired by the next `advance`
+    overdue: Vec<T>,
+    len: usize,
+}
+
+impl<T> Default for TimerWheel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new() -> Self {
+        TimerWheel {
+            levels: (0..LEVELS)
+                .map(|_| (0..SLOTS).map(|_| Vec::new()).collect())
+                .collect(),
+            occupied: [0; LEVELS],
+            next: 0,
+            overdue: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Fire `item` once the wheel reaches `deadline` (epoch millis). Deadlines already passed
+    /// fire on the next `advance`.
+    pub fn schedule(&mut self, deadline: i64, item: T) {
+        self.len += 1;
+        self.insert(deadline.max(0) as u64, item);
+    }
+
+    /// Move the wheel to `now` (epoch millis), returning every timer due at or before it in
+    /// deadline order.
+    pub fn advance(&mut self, now: i64) -> Vec<T> {
+        let now = now.max(0) as u64;
+        let mut fired = std::mem::take(&mut self.overdue);
+        while self.next <= now {
+            self.cascade();
+            match self.next_occupied() {
+                Some(time) if time == self.next => {
+                    let slot = &mut self.levels[0][slot_index(time, 0)];
+                    fired.extend(slot.drain(..).map(|(_, item)| item));
+                    self.occupied[0] &= !(1 << slot_index(time, 0));
+                    self.next += 1;
+                }
+                Some(time) if time <= now => self.next = time,
+                _ => self.next = now + 1,
+            }
+        }
+        self.len -= fired.len();
+        fired
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn insert(&mut self, deadline: u64, item: T) {
+        if deadline < self.next {
+            self.overdue.push(item);
+            return;
+        }
+        // Lowest level whose next-higher window holds both the deadline and the wheel's time
+        let level = (0..LEVELS)
+            .find(|&level| window(deadline, level + 1) == window(self.next, level + 1))
+            .unwrap_or(LEVELS - 1);
+        let slot = slot_index(deadline, level);
+        self.levels[level][slot].push((deadline, item));
+        self.occupied[level] |= 1 << slot;
+    }
+
+    // Redistribute the slots the wheel has just entered, highest level first
+    fn cascade(&mut self) {
+        for level in (1..LEVELS).rev() {
+            if !self.next.is_multiple_of(slot_width(level)) {
+                continue;
+            }
+            let slot = slot_index(self.next, level);
+            if self.occupied[level] & (1 << slot) == 0 {
+                continue;
+            }
+            self.occupied[level] &= !(1 << slot);
+            for (deadline, item) in std::mem::take(&mut self.levels[level][slot]) {
+                self.insert(deadline, item);
+            }
+        }
+    }
+
+    // Start of the earliest occupied slot at or after `next`: a level 0 slot fires there, a
+    // higher one cascades
+    fn next_occupied(&self) -> Option<u64> {
+        (0..LEVELS)
+            .filter_map(|level| {
+                let current = slot_index(self.next, level);
+                // Past cascades leave the current slot of every higher level empty
+                let from = if level == 0 { current } else { current + 1 };
+                if from == SLOTS {
+                    return None;
+                }
+                let mask = self.occupied[level] & (u64::MAX << from);
+                (mask != 0).then(|| {
+                    let slot = mask.trailing_zeros() as u64;
+                    let window_start = window(self.next, level + 1)
+                        .checked_shl(SLOT_BITS * (level as u32 + 1))
+                        .unwrap_or(0);
+                    window_start + (slot << (SLOT_BITS * level as u32))
+                })
+            })
+            .min()
+    }
+}
+
+fn slot_width(level: usize) -> u64 {
+    1 << (SLOT_BITS * level as u32)
+}
+
+fn slot_index(time: u64, level: usize) -> usize {
+    (window(time, level) & (SLOTS as u64 - 1)) as usize
+}
+
+// `time` in units of a level's slot width, 0 past the top of the u64 range
+fn window(time: u64, level: usize) -> u64 {
+    time.checked_shr(SLOT_BITS * level as u32).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod timer_wheel_tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn check_timers_fire_in_deadline_order() {
+        let start = 1_700_000_000_000;
+        let mut wheel: TimerWheel<i64> = TimerWheel::new();
+        wheel.advance(start);
+        for offset in [5_000_000, 70, 3, 64, 4_096, 3, 200_000] {
+            wheel.schedule(start + offset, offset);
+        }
+        assert_eq!(wheel.len(), 7);
+        assert_eq!(wheel.advance(start + 2), Vec::<i64>::new());
+        assert_eq!(wheel.advance(start + 64), vec![3, 3, 64]);
+        assert_eq!(wheel.advance(start + 300_000), vec![70, 4_096, 200_000]);
+        // a deadline already passed fires on the next advance
+        wheel.schedule(start, -1);
+        assert_eq!(wheel.advance(start + 300_000), vec![-1]);
+        assert_eq!(wheel.advance(i64::MAX), vec![5_000_000]);
+        assert!(wheel.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn check_wheel_matches_sorted_deadlines(
+            deadlines in prop::collection::vec(0i64..1_000_000, 1..100),
+            steps in prop::collection::vec(1i64..50_000, 1..40),
+        ) {
+            let mut wheel = TimerWheel::new();
+            for deadline in deadlines.iter() {
+                wheel.schedule(*deadline, *deadline);
+            }
+            let mut now = 0;
+            let mut fired = Vec::new();
+            for step in steps {
+                now += step;
+                let due = wheel.advance(now);
+                prop_assert!(due.iter().all(|deadline| *deadline <= now));
+                fired.extend(due);
+            }
+            let mut expected: Vec<i64> =
+                deadlines.iter().copied().filter(|deadline| *deadline <= now).collect();
+            expected.sort_unstable();
+            prop_assert_eq!(fired, expected);
+            prop_assert_eq!(wheel.len(), deadlines.iter().filter(|d| **d > now).count());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+/// Storage for executed trades, queried after the fact.
+pub trait TradeStore: Send {
+    fn record(&mut self, trade: &Trade);
+
+    fn trade(&self, trade_id: OrderId) -> Option<&Trade>;
+
+    // Take a busted trade off the tape, None if it was never recorded or is already busted
+    fn bust(&mut self, trade_id: OrderId) -> Option<Trade>;
+
+    // Trades with `from <= timestamp <= to`, timestamps in epoch micros
+    fn trades_between(&self, from: i64, to: i64) -> Vec<Trade>;
+
+    fn trades_for_order(&self, order_id: OrderId) -> Vec<Trade>;
+
+    fn trades_at_price(&self, price: Price) -> Vec<Trade>;
+
+    fn last_trade(&self) -> Option<&Trade>;
+
+    fn trade_count(&self) -> usize;
+
+    fn total_volume(&self) -> Quantity;
+}
+
+/// Trade tape kept in memory, in execution order.
+#[derive(Default)]
+pub struct InMemoryTradeStore {
+    trades: Vec<Trade>,
+    total_volume: Quantity,
+}
+
+impl InMemoryTradeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+}
+
+impl TradeStore for InMemoryTradeStore {
+    fn record(&mut self, trade: &Trade) {
+        self.total_volume = self.total_volume.saturating_add(trade.quantity());
+        self.trades.push(trade.clone());
+    }
+
+    fn trade(&self, trade_id: OrderId) -> Option<&Trade> {
+        self.trades.iter().find(|t| t.trade_id() == trade_id)
+    }
+
+    fn bust(&mut self, trade_id: OrderId) -> Option<Trade> {
+        let index = self.trades.iter().position(|t| t.trade_id() == trade_id)?;
+        let trade = self.trades.remove(index);
+        self.total_volume = self.total_volume.saturating_sub(trade.quantity());
+        Some(trade)
+    }
+
+    fn trades_between(&self, from: i64, to: i64) -> Vec<Trade> {
+        // trades are appended in time order, so the range is contiguous
+        let start = self.trades.partition_point(|t| t.timestamp() < from);
+        let end = self.trades.partition_point(|t| t.timestamp() <= to);
+        self.trades[start..end.max(start)].to_vec()
+    }
+
+    fn trades_for_order(&self, order_id: OrderId) -> Vec<Trade> {
+        self.trades
+            .iter()
+            .filter(|t| t.bid_order_id() == order_id || t.ask_order_id() == order_id)
+            .cloned()
+            .collect()
+    }
+
+    fn trades_at_price(&self, price: Price) -> Vec<Trade> {
+        self.trades
+            .iter()
+            .filter(|t| t.price() == price)
+            .cloned()
+            .collect()
+    }
+
+    fn last_trade(&self) -> Option<&Trade> {
+        self.trades.last()
+    }
+
+    fn trade_count(&self) -> usize {
+        self.trades.len()
+    }
+
+    fn total_volume(&self) -> Quantity {
+        self.total_volume
+    }
+}
+
+#[cfg(test)]
+mod trade_store_tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn check_trade_store_queries() {
+        let mut store = InMemoryTradeStore::new();
+        let bid_id = Uuid::new_v4();
+        let first_trade = Trade::new(bid_id, Uuid::new_v4(), 10, 5);
+        let second_trade = Trade::new(Uuid::new_v4(), Uuid::new_v4(), 11, 7);
+        store.record(&first_trade);
+        store.record(&second_trade);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.total_volume(), 12);
+        assert_eq!(store.last_trade(), Some(&second_trade));
+        assert_eq!(store.trades_for_order(bid_id), vec![first_trade.clone()]);
+        assert_eq!(store.trades_at_price(11), vec![second_trade.clone()]);
+        assert_eq!(
+            store
+                .trades_between(first_trade.timestamp(), second_trade.timestamp())
+                .len(),
+            2
+        );
+        assert!(
+            store
+                .trades_between(second_trade.timestamp() + 1, i64::MAX)
+                .is_empty()
+        );
+
+        assert_eq!(
+            store.bust(first_trade.trade_id()),
+            Some(first_trade.clone())
+        );
+        assert_eq!(store.bust(first_trade.trade_id()), None);
+        assert_eq!((store.len(), store.total_volume()), (1, 7));
+    }
+}
@@ -1,5 +1,13 @@
 use uuid::Uuid;
 
-pub type Price = i64;
+// The only place numeric widths are chosen; everything else, including the market data,
+// journal and gateway encodings, goes through these aliases.
+pub type Price = i64; // signed ticks, see `fixed_point` for decimal prices
 pub type Quantity = u64;
 pub type OrderId = Uuid;
+pub type ParticipantId = u64;
+// Chosen by the client, unique among the resting orders of its participant
+pub type ClientOrderId = String;
+// Opaque client bytes carried on an order and echoed on its trades and execution reports
+pub type OrderTag = Vec<u8>;
+pub type Symbol = String;
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use tracing::warn;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CommandKind {
+    AddOrder,
+    CancelOrder,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct WatchdogStats {
+    pub commands: u64,
+    pub slow_commands: u64,
+    pub max_latency: Duration,
+    pub total_latency: Duration,
+}
+
+pub struct LatencyWatchdog {
+    budget: Duration,
+    stats: WatchdogStats,
+}
+
+impl LatencyWatchdog {
+    pub fn new(budget: Duration) -> Self {
+        LatencyWatchdog {
+            budget,
+            stats: WatchdogStats::default(),
+        }
+    }
+
+    /// Record one processed command, logging a breakdown if it ran over budget.
+    pub fn record(
+        &mut self,
+        command: CommandKind,
+        elapsed: Duration,
+        levels_touched: usize,
+        fills: usize,
+    ) {
+        self.stats.commands += 1;
+        self.stats.total_latency += elapsed;
+        self.stats.max_latency = self.stats.max_latency.max(elapsed);
+
+        if elapsed > self.budget {
+            self.stats.slow_commands += 1;
+            warn!(
+                ?command,
+                ?elapsed,
+                budget = ?self.budget,
+                levels_touched,
+                fills,
+                "slow command over budget"
+            );
+        }
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn stats(&self) -> WatchdogStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod watchdog_tests {
+    use super::*;
+
+    #[test]
+    fn check_slow_commands_are_counted() {
+        let mut watchdog = LatencyWatchdog::new(Duration::from_micros(10));
+        watchdog.record(CommandKind::AddOrder, Duration::from_micros(5), 1, 1);
+        watchdog.record(CommandKind::AddOrder, Duration::from_micros(50), 20, 40);
+        watchdog.record(CommandKind::CancelOrder, Duration::from_micros(10), 1, 0);
+
+        let stats = watchdog.stats();
+        assert_eq!(stats.commands, 3);
+        assert_eq!(stats.slow_commands, 1);
+        assert_eq!(stats.max_latency, Duration::from_micros(50));
+        assert_eq!(stats.total_latency, Duration::from_micros(65));
+    }
+}
@@ -0,0 +1,736 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::orderbook::command::Command;
+use crate::orderbook::exec_report::{ExecType, ExecutionReport};
+use crate::orderbook::order::{Order, OrderType, Side, Status};
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::price_level::LevelInfo;
+use crate::orderbook::types::{OrderId, ParticipantId, Price, Quantity};
+
+// SBE-style framing: every message is an 8-byte header (block length, template id, schema id,
+// schema version, each u16) followed by a fixed-layout block. All integers are little-endian and
+// every field sits at a fixed offset, so a decoder reads fields in place without copying the
+// message. Strings (client order ids, reject reasons) are not carried.
+pub const HEADER_LEN: usize = 8;
+pub const SCHEMA_ID: u16 = 1;
+pub const SCHEMA_VERSION: u16 = 1;
+
+pub const NEW_ORDER_TEMPLATE: u16 = 1;
+pub const CANCEL_ORDER_TEMPLATE: u16 = 2;
+pub const TRADE_TEMPLATE: u16 = 3;
+pub const EXECUTION_REPORT_TEMPLATE: u16 = 4;
+pub const LEVEL_UPDATE_TEMPLATE: u16 = 5;
+pub const MODIFY_ORDER_TEMPLATE: u16 = 6;
+
+// order_id 16 | owner u64 | price i64 | quantity u64 | expire_time i64 | min_qty u64 (0 for none)
+// | side u8 | order_type u8 | flags u8 (bit 0 hidden) | padding 5
+const NEW_ORDER_LEN: usize = 64;
+// order_id 16
+const CANCEL_ORDER_LEN: usize = 16;
+// order_id 16 | price i64 | quantity u64
+const MODIFY_ORDER_LEN: usize = 32;
+// trade_id 16 | bid_order_id 16 | ask_order_id 16 | price i64 | quantity u64 | timestamp i64
+// | sequence u64 | aggressor u8 (0 for none) | padding 7
+const TRADE_LEN: usize = 88;
+// exec_id u64 | order_id 16 | trade_id 16 (nil for none) | owner u64 | price i64
+// | last_quantity u64 | last_price i64 (i64::MIN for none) | cumulative_quantity u64
+// | leaves_quantity u64 | exec_type u8 | status u8 | side u8 | padding 5
+const EXECUTION_REPORT_LEN: usize = 96;
+// price i64 | volume u64 | side u8 | padding 7
+const LEVEL_UPDATE_LEN: usize = 24;
+
+// Sentinel for an absent last price
+const NO_PRICE: Price = Price::MIN;
+
+/// A decoded message, borrowing the bytes it was decoded from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message<'a> {
+    NewOrder(NewOrderView<'a>),
+    CancelOrder(CancelOrderView<'a>),
+    ModifyOrder(ModifyOrderView<'a>),
+    Trade(TradeView<'a>),
+    ExecutionReport(ExecutionReportView<'a>),
+    LevelUpdate(LevelUpdateView<'a>),
+}
+
+impl Message<'_> {
+    /// The engine command carried by a `NewOrder`, `CancelOrder` or `ModifyOrder` message.
+    pub fn to_command(&self) -> Option<Command> {
+        match self {
+            Message::NewOrder(view) => Some(Command::NewOrder(Arc::new(view.to_order()))),
+            Message::CancelOrder(view) => Some(Command::CancelOrder(view.order_id())),
+            Message::ModifyOrder(view) => Some(Command::ModifyOrder {
+                order_id: view.order_id(),
+                price: view.price(),
+                quantity: view.quantity(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Appends encoded messages to one contiguous buffer.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buffer: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder {
+            buffer: Vec::with_capacity(4096),
+        }
+    }
+
+    /// Encode an order entry command. Operator and timer commands have no template and are
+    /// left out, returning false.
+    pub fn encode_command(&mut self, command: &Command) -> bool {
+        match command {
+            Command::NewOrder(order) => self.encode_new_order(order),
+            Command::CancelOrder(order_id) => {
+                self.header(CANCEL_ORDER_TEMPLATE, CANCEL_ORDER_LEN);
+                self.buffer.extend_from_slice(order_id.as_bytes());
+            }
+            Command::ModifyOrder {
+                order_id,
+                price,
+                quantity,
+            } => {
+                self.header(MODIFY_ORDER_TEMPLATE, MODIFY_ORDER_LEN);
+                self.buffer.extend_from_slice(order_id.as_bytes());
+                self.buffer.extend_from_slice(&price.to_le_bytes());
+                self.buffer.extend_from_slice(&quantity.to_le_bytes());
+            }
+            Command::Tick(_)
+            | Command::SetInstrumentState(_)
+            | Command::Uncross
+            | Command::BustTrade { .. } => return false,
+        }
+        true
+    }
+
+    pub fn encode_new_order(&mut self, order: &Order) {
+        let start = self.header(NEW_ORDER_TEMPLATE, NEW_ORDER_LEN);
+        let expire_time = match order.order_type {
+            OrderType::GoodTillDate(expiry) => expiry,
+            _ => 0,
+        };
+        self.buffer.extend_from_slice(order.order_id.as_bytes());
+        self.buffer.extend_from_slice(&order.owner.to_le_bytes());
+        self.buffer.extend_from_slice(&order.price.to_le_bytes());
+        self.buffer
+            .extend_from_slice(&order.original_quantity.to_le_bytes());
+        self.buffer.extend_from_slice(&expire_time.to_le_bytes());
+        self.buffer
+            .extend_from_slice(&order.min_qty.unwrap_or(0).to_le_bytes());
+        self.buffer.push(encode_side(order.side));
+        self.buffer.push(encode_order_type(order.order_type));
+        self.buffer.push(order.hidden as u8);
+        self.pad(start, NEW_ORDER_LEN);
+    }
+
+    pub fn encode_trade(&mut self, trade: &Trade) {
+        let start = self.header(TRADE_TEMPLATE, TRADE_LEN);
+        self.buffer.extend_from_slice(trade.trade_id().as_bytes());
+        self.buffer
+            .extend_from_slice(trade.bid_order_id().as_bytes());
+        self.buffer
+            .extend_from_slice(trade.ask_order_id().as_bytes());
+        self.buffer.extend_from_slice(&trade.price().to_le_bytes());
+        self.buffer
+            .extend_from_slice(&trade.quantity().to_le_bytes());
+        self.buffer
+            .extend_from_slice(&trade.timestamp().to_le_bytes());
+        self.buffer
+            .extend_from_slice(&trade.sequence().to_le_bytes());
+        self.buffer
+            .push(trade.aggressor_side().map_or(0, encode_side));
+        self.pad(start, TRADE_LEN);
+    }
+
+    pub fn encode_execution_report(&mut self, report: &ExecutionReport) {
+        let start = self.header(EXECUTION_REPORT_TEMPLATE, EXECUTION_REPORT_LEN);
+        self.buffer.extend_from_slice(&report.exec_id.to_le_bytes());
+        self.buffer.extend_from_slice(report.order_id.as_bytes());
+        self.buffer
+            .extend_from_slice(report.trade_id.unwrap_or(Uuid::nil()).as_bytes());
+        self.buffer.extend_from_slice(&report.owner.to_le_bytes());
+        self.buffer.extend_from_slice(&report.price.to_le_bytes());
+        self.buffer
+            .extend_from_slice(&report.last_quantity.to_le_bytes());
+        self.buffer
+            .extend_from_slice(&report.last_price.unwrap_or(NO_PRICE).to_le_bytes());
+        self.buffer
+            .extend_from_slice(&report.cumulative_quantity.to_le_bytes());
+        self.buffer
+            .extend_from_slice(&report.leaves_quantity.to_le_bytes());
+        self.buffer.push(encode_exec_type(report.exec_type));
+        self.buffer.push(encode_status(report.status));
+        self.buffer.push(encode_side(report.side));
+        self.pad(start, EXECUTION_REPORT_LEN);
+    }
+
+    pub fn encode_level_update(&mut self, side: Side, level: &LevelInfo) {
+        let start = self.header(LEVEL_UPDATE_TEMPLATE, LEVEL_UPDATE_LEN);
+        self.buffer.extend_from_slice(&level.price.to_le_bytes());
+        self.buffer.extend_from_slice(&level.volume.to_le_bytes());
+        self.buffer.push(encode_side(side));
+        self.pad(start, LEVEL_UPDATE_LEN);
+    }
+
+    // Write the header, returning where the block starts
+    fn header(&mut self, template_id: u16, block_length: usize) -> usize {
+        self.buffer.reserve(HEADER_LEN + block_length);
+        for field in [block_length as u16, template_id, SCHEMA_ID, SCHEMA_VERSION] {
+            self.buffer.extend_from_slice(&field.to_le_bytes());
+        }
+        self.buffer.len()
+    }
+
+    fn pad(&mut self, start: usize, block_length: usize) {
+        self.buffer.resize(start + block_length, 0);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Hand out the encoded bytes and start a fresh buffer.
+    pub fn take_bytes(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+/// Decode the first message of `bytes` in place, returning it with the number of bytes consumed.
+/// None if the message is incomplete, from another schema, of an unknown template or carries an
+/// invalid enum value. A block longer than this version expects is accepted, the extra bytes
+/// being fields added by a later version.
+pub fn decode(bytes: &[u8]) -> Option<(Message<'_>, usize)> {
+    let header = bytes.get(..HEADER_LEN)?;
+    let field = |index: usize| u16::from_le_bytes([header[2 * index], header[2 * index + 1]]);
+    let (block_length, template_id) = (field(0) as usize, field(1));
+    if field(2) != SCHEMA_ID {
+        return None;
+    }
+    let block = bytes.get(HEADER_LEN..HEADER_LEN + block_length)?;
+    let fixed = |len: usize| (block_length >= len).then(|| &block[..len]);
+    let message = match template_id {
+        NEW_ORDER_TEMPLATE => {
+            let view = NewOrderView(fixed(NEW_ORDER_LEN)?);
+            decode_side(view.0[56])?;
+            decode_order_type(view.0[57], 0)?;
+            Message::NewOrder(view)
+        }
+        CANCEL_ORDER_TEMPLATE => Message::CancelOrder(CancelOrderView(fixed(CANCEL_ORDER_LEN)?)),
+        MODIFY_ORDER_TEMPLATE => Message::ModifyOrder(ModifyOrderView(fixed(MODIFY_ORDER_LEN)?)),
+        TRADE_TEMPLATE => {
+            let view = TradeView(fixed(TRADE_LEN)?);
+            if view.0[80] != 0 {
+                decode_side(view.0[80])?;
+            }
+            Message::Trade(view)
+        }
+        EXECUTION_REPORT_TEMPLATE => {
+            let view = ExecutionReportView(fixed(EXECUTION_REPORT_LEN)?);
+            decode_exec_type(view.0[88])?;
+            decode_status(view.0[89])?;
+            decode_side(view.0[90])?;
+            Message::ExecutionReport(view)
+        }
+        LEVEL_UPDATE_TEMPLATE => {
+            let view = LevelUpdateView(fixed(LEVEL_UPDATE_LEN)?);
+            decode_side(view.0[16])?;
+            Message::LevelUpdate(view)
+        }
+        _ => return None,
+    };
+    Some((message, HEADER_LEN + block_length))
+}
+
+/// Decode every complete message in `bytes`, stopping at the first one that does not decode.
+pub fn decode_all(mut bytes: &[u8]) -> Vec<Message<'_>> {
+    let mut messages: Vec<Message> = Vec::new();
+    while let Some((message, consumed)) = decode(bytes) {
+        messages.push(message);
+        bytes = &bytes[consumed..];
+    }
+    messages
+}
+
+pub(crate) fn read_u64(block: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(block[offset..offset + 8].try_into().unwrap())
+}
+
+pub(crate) fn read_i64(block: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(block[offset..offset + 8].try_into().unwrap())
+}
+
+pub(crate) fn read_id(block: &[u8], offset: usize) -> OrderId {
+    Uuid::from_bytes(block[offset..offset + 16].try_into().unwrap())
+}
+
+// Enum fields are checked by `decode`, so the views can unwrap them
+
+/// `NewOrder` command read in place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewOrderView<'a>(&'a [u8]);
+
+impl NewOrderView<'_> {
+    pub fn order_id(&self) -> OrderId {
+        read_id(self.0, 0)
+    }
+
+    pub fn owner(&self) -> ParticipantId {
+        read_u64(self.0, 16)
+    }
+
+    pub fn price(&self) -> Price {
+        read_i64(self.0, 24)
+    }
+
+    pub fn quantity(&self) -> Quantity {
+        read_u64(self.0, 32)
+    }
+
+    pub fn min_qty(&self) -> Option<Quantity> {
+        Some(read_u64(self.0, 48)).filter(|min_qty| *min_qty > 0)
+    }
+
+    pub fn side(&self) -> Side {
+        decode_side(self.0[56]).unwrap()
+    }
+
+    pub fn order_type(&self) -> OrderType {
+        decode_order_type(self.0[57], read_i64(self.0, 40)).unwrap()
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.0[58] & 1 != 0
+    }
+
+    /// The order this message submits, under the id the sender gave it.
+    pub fn to_order(&self) -> Order {
+        let mut order = Order::new(
+            self.order_type(),
+            self.side(),
+            self.price(),
+            self.quantity(),
+        )
+        .with_owner(self.owner());
+        order.order_id = self.order_id();
+        order.hidden = self.hidden();
+        order.min_qty = self.min_qty();
+        order
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CancelOrderView<'a>(&'a [u8]);
+
+impl CancelOrderView<'_> {
+    pub fn order_id(&self) -> OrderId {
+        read_id(self.0, 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModifyOrderView<'a>(&'a [u8]);
+
+impl ModifyOrderView<'_> {
+    pub fn order_id(&self) -> OrderId {
+        read_id(self.0, 0)
+    }
+
+    pub fn price(&self) -> Price {
+        read_i64(self.0, 16)
+    }
+
+    pub fn quantity(&self) -> Quantity {
+        read_u64(self.0, 24)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeView<'a>(&'a [u8]);
+
+impl TradeView<'_> {
+    pub fn trade_id(&self) -> OrderId {
+        read_id(self.0, 0)
+    }
+
+    pub fn bid_order_id(&self) -> OrderId {
+        read_id(self.0, 16)
+    }
+
+    pub fn ask_order_id(&self) -> OrderId {
+        read_id(self.0, 32)
+    }
+
+    pub fn price(&self) -> Price {
+        read_i64(self.0, 48)
+    }
+
+    pub fn quantity(&self) -> Quantity {
+        read_u64(self.0, 56)
+    }
+
+    // Epoch micros
+    pub fn timestamp(&self) -> i64 {
+        read_i64(self.0, 64)
+    }
+
+    pub fn sequence(&self) -> u64 {
+        read_u64(self.0, 72)
+    }
+
+    pub fn aggressor_side(&self) -> Option<Side> {
+        decode_side(self.0[80])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionReportView<'a>(&'a [u8]);
+
+impl ExecutionReportView<'_> {
+    pub fn exec_id(&self) -> u64 {
+        read_u64(self.0, 0)
+    }
+
+    pub fn order_id(&self) -> OrderId {
+        read_id(self.0, 8)
+    }
+
+    pub fn trade_id(&self) -> Option<OrderId> {
+        Some(read_id(self.0, 24)).filter(|trade_id| !trade_id.is_nil())
+    }
+
+    pub fn owner(&self) -> ParticipantId {
+        read_u64(self.0, 40)
+    }
+
+    pub fn price(&self) -> Price {
+        read_i64(self.0, 48)
+    }
+
+    pub fn last_quantity(&self) -> Quantity {
+        read_u64(self.0, 56)
+    }
+
+    pub fn last_price(&self) -> Option<Price> {
+        Some(read_i64(self.0, 64)).filter(|price| *price != NO_PRICE)
+    }
+
+    pub fn cumulative_quantity(&self) -> Quantity {
+        read_u64(self.0, 72)
+    }
+
+    pub fn leaves_quantity(&self) -> Quantity {
+        read_u64(self.0, 80)
+    }
+
+    pub fn exec_type(&self) -> ExecType {
+        decode_exec_type(self.0[88]).unwrap()
+    }
+
+    pub fn status(&self) -> Status {
+        decode_status(self.0[89]).unwrap()
+    }
+
+    pub fn side(&self) -> Side {
+        decode_side(self.0[90]).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelUpdateView<'a>(&'a [u8]);
+
+impl LevelUpdateView<'_> {
+    pub fn price(&self) -> Price {
+        read_i64(self.0, 0)
+    }
+
+    pub fn volume(&self) -> Quantity {
+        read_u64(self.0, 8)
+    }
+
+    pub fn side(&self) -> Side {
+        decode_side(self.0[16]).unwrap()
+    }
+}
+
+pub(crate) fn encode_side(side: Side) -> u8 {
+    match side {
+        Side::Buy => 1,
+        Side::Sell => 2,
+    }
+}
+
+pub(crate) fn decode_side(byte: u8) -> Option<Side> {
+    match byte {
+        1 => Some(Side::Buy),
+        2 => Some(Side::Sell),
+        _ => None,
+    }
+}
+
+pub(crate) fn encode_order_type(order_type: OrderType) -> u8 {
+    match order_type {
+        OrderType::LimitOrder => 0,
+        OrderType::MarketOrder => 1,
+        OrderType::ImmediateOrCancel => 2,
+        OrderType::FillOrKill => 3,
+        OrderType::GoodTillCancel => 4,
+        OrderType::GoodTillDate(_) => 5,
+        OrderType::PostOnly => 6,
+        OrderType::GoodForAuction => 7,
+    }
+}
+
+pub(crate) fn decode_order_type(byte: u8, expire_time: i64) -> Option<OrderType> {
+    Some(match byte {
+        0 => OrderType::LimitOrder,
+        1 => OrderType::MarketOrder,
+        2 => OrderType::ImmediateOrCancel,
+        3 => OrderType::FillOrKill,
+        4 => OrderType::GoodTillCancel,
+        5 => OrderType::GoodTillDate(expire_time),
+        6 => OrderType::PostOnly,
+        7 => OrderType::GoodForAuction,
+        _ => return None,
+    })
+}
+
+fn encode_exec_type(exec_type: ExecType) -> u8 {
+    match exec_type {
+        ExecType::New => 0,
+        ExecType::PartialFill => 1,
+        ExecType::Fill => 2,
+        ExecType::Canceled => 3,
+        ExecType::Replaced => 4,
+        ExecType::Rejected => 5,
+    }
+}
+
+fn decode_exec_type(byte: u8) -> Option<ExecType> {
+    Some(match byte {
+        0 => ExecType::New,
+        1 => ExecType::PartialFill,
+        2 => ExecType::Fill,
+        3 => ExecType::Canceled,
+        4 => ExecType::Replaced,
+        5 => ExecType::Rejected,
+        _ => return None,
+    })
+}
+
+pub(crate) fn encode_status(status: Status) -> u8 {
+    match status {
+        Status::New => 0,
+        Status::PartiallyFilled => 1,
+        Status::Filled => 2,
+        Status::Canceled => 3,
+        Status::Expired => 4,
+        Status::Rejected => 5,
+    }
+}
+
+pub(crate) fn decode_status(byte: u8) -> Option<Status> {
+    Some(match byte {
+        0 => Status::New,
+        1 => Status::PartiallyFilled,
+        2 => Status::Filled,
+        3 => Status::Canceled,
+        4 => Status::Expired,
+        5 => Status::Rejected,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod wire_tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn check_commands_and_events_round_trip() {
+        let mut order = Order::new(OrderType::GoodTillDate(1_700_000), Side::Sell, -25, 100)
+            .with_owner(7)
+            .with_min_qty(10);
+        order.hidden = true;
+        let trade = Trade::new(Uuid::new_v4(), order.order_id, 101, 40)
+            .with_aggressor(Side::Buy)
+            .with_timestamp(123)
+            .with_sequence(9);
+        let report = ExecutionReport::fill(&order, 40, 101).with_trade(&trade);
+
+        let mut encoder = Encoder::new();
+        encoder.encode_command(&Command::NewOrder(Arc::new(order.clone())));
+        encoder.encode_command(&Command::CancelOrder(order.order_id));
+        encoder.encode_trade(&trade);
+        encoder.encode_execution_report(&report);
+        encoder.encode_level_update(
+            Side::Buy,
+            &LevelInfo {
+                price: 99,
+                volume: 5,
+            },
+        );
+        assert_eq!(&encoder.as_bytes()[..4], &[64, 0, 1, 0]);
+
+        let bytes = encoder.take_bytes();
+        let messages = decode_all(&bytes);
+        assert_eq!(messages.len(), 5);
+        let Message::NewOrder(new_order) = messages[0] else {
+            panic!("unexpected {:?}", messages[0]);
+        };
+        let decoded = new_order.to_order();
+        assert_eq!(decoded.order_id, order.order_id);
+        assert_eq!(decoded.order_type, OrderType::GoodTillDate(1_700_000));
+        assert_eq!(
+            (decoded.side, decoded.price, decoded.remaining_quantity),
+            (Side::Sell, -25, 100)
+        );
+        assert_eq!(
+            (decoded.owner, decoded.min_qty, decoded.hidden),
+            (7, Some(10), true)
+        );
+        assert!(matches!(
+            messages[1].to_command(),
+            Some(Command::CancelOrder(order_id)) if order_id == order.order_id
+        ));
+        let Message::Trade(decoded) = messages[2] else {
+            panic!("unexpected {:?}", messages[2]);
+        };
+        assert_eq!(decoded.trade_id(), trade.trade_id());
+        assert_eq!((decoded.price(), decoded.quantity()), (101, 40));
+        assert_eq!((decoded.timestamp(), decoded.sequence()), (123, 9));
+        assert_eq!(decoded.aggressor_side(), Some(Side::Buy));
+        let Message::ExecutionReport(decoded) = messages[3] else {
+            panic!("unexpected {:?}", messages[3]);
+        };
+        assert_eq!(decoded.exec_type(), report.exec_type);
+        assert_eq!(decoded.trade_id(), report.trade_id);
+        assert_eq!(decoded.last_price(), Some(101));
+        assert_eq!(decoded.leaves_quantity(), report.leaves_quantity);
+        let Message::LevelUpdate(decoded) = messages[4] else {
+            panic!("unexpected {:?}", messages[4]);
+        };
+        assert_eq!(
+            (decoded.side(), decoded.price(), decoded.volume()),
+            (Side::Buy, 99, 5)
+        );
+        assert!(messages[4].to_command().is_none());
+    }
+
+    #[test]
+    fn check_malformed_messages_are_refused() {
+        let mut encoder = Encoder::new();
+        encoder.encode_command(&Command::CancelOrder(Uuid::new_v4()));
+        let bytes = encoder.take_bytes();
+        assert!(decode(&bytes[..bytes.len() - 1]).is_none());
+
+        let mut other_schema = bytes.clone();
+        other_schema[4] = 2;
+        assert!(decode(&other_schema).is_none());
+
+        // a longer block from a later schema version still decodes, skipping the new fields
+        let mut extended = bytes.clone();
+        extended[0] += 8;
+        extended.extend_from_slice(&[0xff; 8]);
+        extended.extend_from_slice(&bytes);
+        assert_eq!(decode_all(&extended).len(), 2);
+    }
+
+    fn command() -> impl Strategy<Value = Command> {
+        let order_type = prop_oneof![
+            Just(OrderType::LimitOrder),
+            Just(OrderType::MarketOrder),
+            Just(OrderType::FillOrKill),
+            any::<i64>().prop_map(OrderType::GoodTillDate),
+        ];
+        let side = prop_oneof![Just(Side::Buy), Just(Side::Sell)];
+        prop_oneof![
+            (
+                order_type,
+                side,
+                any::<Price>(),
+                any::<Quantity>(),
+                any::<u64>()
+            )
+                .prop_map(|(order_type, side, price, quantity, owner)| {
+                    let order = Order::new(order_type, side, price, quantity).with_owner(owner);
+                    Command::NewOrder(Arc::new(order))
+                }),
+            any::<u128>().prop_map(|id| Command::CancelOrder(Uuid::from_u128(id))),
+            (any::<u128>(), any::<Price>(), any::<Quantity>()).prop_map(|(id, price, quantity)| {
+                Command::ModifyOrder {
+                    order_id: Uuid::from_u128(id),
+                    price,
+                    quantity,
+                }
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn check_random_commands_round_trip(commands in prop::collection::vec(command(), 1..50)) {
+            let mut encoder = Encoder::new();
+            for command in commands.iter() {
+                encoder.encode_command(command);
+            }
+            let messages = decode_all(encoder.as_bytes());
+            prop_assert_eq!(messages.len(), commands.len());
+            for (message, command) in messages.iter().zip(commands.iter()) {
+                match (message.to_command().unwrap(), command) {
+                    (Command::NewOrder(decoded), Command::NewOrder(order)) => {
+                        prop_assert_eq!(decoded.order_id, order.order_id);
+                        prop_assert_eq!(decoded.order_type, order.order_type);
+                        prop_assert_eq!((decoded.price, decoded.original_quantity), (order.price, order.original_quantity));
+                        prop_assert_eq!((decoded.side, decoded.owner), (order.side, order.owner));
+                    }
+                    (Command::CancelOrder(decoded), Command::CancelOrder(order_id)) => {
+                        prop_assert_eq!(decoded, *order_id);
+                    }
+                    (
+                        Command::ModifyOrder { order_id, price, quantity },
+                        Command::ModifyOrder { order_id: expected_id, price: expected_price, quantity: expected_quantity },
+                    ) => {
+                        prop_assert_eq!((order_id, price, quantity), (*expected_id, *expected_price, *expected_quantity));
+                    }
+                    (decoded, command) => prop_assert!(false, "{:?} decoded as {:?}", command, decoded),
+                }
+            }
+        }
+
+        // Fuzz the decoder: arbitrary bytes never panic and never read past the input
+        #[test]
+        fn check_decoder_survives_arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            let mut rest = bytes.as_slice();
+            while let Some((message, consumed)) = decode(rest) {
+                prop_assert!(consumed <= rest.len());
+                let _ = message.to_command();
+                rest = &rest[consumed..];
+            }
+        }
+
+        #[test]
+        fn check_decoder_survives_corrupted_messages(
+            position in any::<prop::sample::Index>(),
+            byte in any::<u8>(),
+        ) {
+            let mut encoder = Encoder::new();
+            let order = Order::new(OrderType::LimitOrder, Side::Buy, 100, 5);
+            encoder.encode_command(&Command::NewOrder(Arc::new(order)));
+            let mut bytes = encoder.take_bytes();
+            let index = position.index(bytes.len());
+            bytes[index] = byte;
+            if let Some((message, _)) = decode(&bytes) {
+                let _ = message.to_command();
+            }
+        }
+    }
+}
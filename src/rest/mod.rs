@@ -0,0 +1,245 @@
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::orderbook::order::{Order, OrderType, Side};
+use crate::orderbook::orderbook_impl::{OrderBook, OrderBookError, Trade};
+use crate::orderbook::price_level::{OrderView, OrderbookLevelInfos};
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+pub type SharedBook = Arc<Mutex<OrderBook>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewOrderRequest {
+    pub side: Side,
+    pub order_type: OrderType,
+    #[serde(default)]
+    pub price: Price,
+    pub quantity: Quantity,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewOrderResponse {
+    pub order: Order,
+    pub trades: Vec<Trade>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DepthQuery {
+    pub levels: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TradesQuery {
+    pub limit: Option<usize>,
+}
+
+/// `OrderBookError` rendered as a JSON body with a matching HTTP status.
+pub struct ApiError(OrderBookError);
+
+impl From<OrderBookError> for ApiError {
+    fn from(error: OrderBookError) -> Self {
+        ApiError(error)
+    }
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self.0 {
+            OrderBookError::OrderNotFound { .. }
+            | OrderBookError::ClientOrderNotFound { .. }
+            | OrderBookError::SymbolNotFound { .. } => StatusCode::NOT_FOUND,
+            OrderBookError::InvalidPrice { .. }
+            | OrderBookError::InvalidQuantity { .. }
+            | OrderBookError::InvalidPriceFormat { .. }
+            | OrderBookError::InvalidTick { .. }
+            | OrderBookError::InvalidLot { .. }
+            | OrderBookError::InvalidClientOrderId { .. }
+            | OrderBookError::PriceOutsideBand { .. }
+            | OrderBookError::Reject { .. } => StatusCode::BAD_REQUEST,
+            OrderBookError::OrderAlreadyExists { .. }
+            | OrderBookError::DuplicateClientOrderId { .. }
+            | OrderBookError::SymbolAlreadyExists { .. }
+            | OrderBookError::PostOnlyWouldCross { .. }
+            | OrderBookError::MinQtyUnavailable { .. }
+            | OrderBookError::PegReferenceUnavailable { .. }
+            | OrderBookError::StaleValidationToken { .. }
+            | OrderBookError::InvalidStateTransition { .. } => StatusCode::CONFLICT,
+            OrderBookError::MessageBudgetExceeded { .. } | OrderBookError::RateLimited { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            OrderBookError::MaxOrderSizeExceeded { .. }
+            | OrderBookError::MaxNotionalExceeded { .. }
+            | OrderBookError::MaxOpenOrdersExceeded { .. } => StatusCode::FORBIDDEN,
+            OrderBookError::InstrumentStateRejected { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = ErrorResponse {
+            error: self.0.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+/// HTTP routes over a single shared book:
+/// `POST /orders`, `GET /orders/{id}`, `DELETE /orders/{id}`, `GET /depth?levels=N` and
+/// `GET /trades?limit=N`.
+pub fn router(book: SharedBook) -> Router {
+    Router::new()
+        .route("/orders", post(submit_order))
+        .route("/orders/{id}", get(get_order).delete(cancel_order))
+        .route("/depth", get(depth))
+        .route("/trades", get(trades))
+        .with_state(book)
+}
+
+async fn submit_order(
+    State(book): State<SharedBook>,
+    Json(request): Json<NewOrderRequest>,
+) -> Result<(StatusCode, Json<NewOrderResponse>), ApiError> {
+    let order = Arc::new(Order::new(
+        request.order_type,
+        request.side,
+        request.price,
+        request.quantity,
+    ));
+    let result = book.lock().unwrap().add_order(&order)?;
+    let response = NewOrderResponse {
+        order: (*order).clone(),
+        trades: result.trades,
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+async fn get_order(
+    State(book): State<SharedBook>,
+    Path(order_id): Path<OrderId>,
+) -> Result<Json<OrderView>, ApiError> {
+    book.lock()
+        .unwrap()
+        .get_order(order_id)
+        .map(Json)
+        .ok_or(ApiError(OrderBookError::OrderNotFound { order_id }))
+}
+
+async fn cancel_order(
+    State(book): State<SharedBook>,
+    Path(order_id): Path<OrderId>,
+) -> Result<StatusCode, ApiError> {
+    book.lock().unwrap().cancel_order(order_id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn depth(
+    State(book): State<SharedBook>,
+    Query(query): Query<DepthQuery>,
+) -> Json<OrderbookLevelInfos> {
+    Json(book.lock().unwrap().get_depth(query.levels.unwrap_or(10)))
+}
+
+// Most recent trades, oldest first
+async fn trades(
+    State(book): State<SharedBook>,
+    Query(query): Query<TradesQuery>,
+) -> Json<Vec<Trade>> {
+    let book = book.lock().unwrap();
+    let trades = book.trade_history().trades_between(i64::MIN, i64::MAX);
+    let limit = query.limit.unwrap_or(100);
+    let skip = trades.len().saturating_sub(limit);
+    Json(trades.into_iter().skip(skip).collect())
+}
+
+#[cfg(test)]
+mod rest_tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn call(app: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn check_order_lifecycle_over_http() {
+        let app = router(Arc::new(Mutex::new(OrderBook::new())));
+
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/orders",
+            r#"{"side":"Sell","order_type":"LimitOrder","price":100,"quantity":5}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let ask: NewOrderResponse = serde_json::from_str(&body).unwrap();
+
+        let (_, body) = call(
+            &app,
+            "POST",
+            "/orders",
+            r#"{"side":"Buy","order_type":"MarketOrder","quantity":2}"#,
+        )
+        .await;
+        let bid: NewOrderResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(bid.trades.len(), 1);
+
+        let (_, body) = call(&app, "GET", "/depth?levels=1", "").await;
+        let depth: OrderbookLevelInfos = serde_json::from_str(&body).unwrap();
+        assert_eq!(depth.asks[0].volume, 3);
+
+        let (_, body) = call(&app, "GET", "/trades", "").await;
+        let trades: Vec<Trade> = serde_json::from_str(&body).unwrap();
+        assert_eq!(trades, bid.trades);
+
+        let uri = format!("/orders/{}", ask.order.order_id);
+        let (_, body) = call(&app, "GET", &uri, "").await;
+        let view: OrderView = serde_json::from_str(&body).unwrap();
+        assert_eq!(view.remaining_quantity, 3);
+        assert_eq!(
+            call(&app, "DELETE", &uri, "").await.0,
+            StatusCode::NO_CONTENT
+        );
+        let (status, body) = call(&app, "DELETE", &uri, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.contains("Order not found"));
+    }
+
+    #[tokio::test]
+    async fn check_invalid_quantity_is_bad_request() {
+        let app = router(Arc::new(Mutex::new(OrderBook::new())));
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/orders",
+            r#"{"side":"Buy","order_type":"LimitOrder","price":100,"quantity":0}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
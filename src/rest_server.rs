@@ -0,0 +1,26 @@
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use log::{LevelFilter, info};
+use tokio::net::TcpListener;
+
+use orderbook::orderbook::orderbook_impl::OrderBook;
+use orderbook::rest;
+
+// Usage: rest_server [addr], defaults to 127.0.0.1:8080
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Warn)
+        .init();
+
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let book = Arc::new(Mutex::new(OrderBook::new()));
+    let listener = TcpListener::bind(&addr).await.expect("failed to bind");
+    info!("Serving REST API on http://{}", addr);
+    axum::serve(listener, rest::router(book))
+        .await
+        .expect("server error");
+}
@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use orderbook::orderbook::order::{Order, OrderType, Side};
+use orderbook::orderbook::orderbook_impl::{OrderBook, Trade};
+use orderbook::orderbook::simulator::{MarketMaker, MomentumTrader, NoiseTrader, Simulator};
+use orderbook::orderbook::types::{ParticipantId, Price, Quantity};
+
+// Participant the keyboard orders are attributed to, apart from the simulated agents
+const USER: ParticipantId = 100;
+const LEVELS: usize = 12;
+const TRADES_SHOWN: usize = 30;
+const BAR_WIDTH: usize = 24;
+const TICK: Duration = Duration::from_millis(200);
+
+// Usage: tui [seed]
+// Renders a book driven by simulated agents. Type `buy <qty> [price]`, `sell <qty> [price]`
+// (no price sends a market order) or `cancel` and press Enter; Tab pauses the simulation, Esc
+// quits.
+fn main() -> io::Result<()> {
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(42);
+    let mut terminal = ratatui::init();
+    let result = App::new(seed).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+struct App {
+    simulator: Simulator,
+    trades: VecDeque<Trade>,
+    input: String,
+    status: String,
+    paused: bool,
+}
+
+impl App {
+    fn new(seed: u64) -> Self {
+        let mut simulator = Simulator::new(OrderBook::new(), seed);
+        let mut maker = MarketMaker::new(1, 1_000, 1);
+        maker.levels = 6;
+        maker.skew_ticks = 1;
+        simulator.add_agent(Box::new(maker));
+        for owner in 2..5 {
+            simulator.add_agent(Box::new(NoiseTrader {
+                owner,
+                initial_price: 1_000,
+                max_offset_ticks: 8,
+                max_quantity: 30,
+                market_probability: 0.1,
+                cancel_probability: 0.4,
+            }));
+        }
+        simulator.add_agent(Box::new(MomentumTrader::new(5, 10, 3)));
+        App {
+            simulator,
+            trades: VecDeque::with_capacity(TRADES_SHOWN),
+            input: String::new(),
+            status: "Tab pauses, Esc quits".to_string(),
+            paused: false,
+        }
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let mut last_step = Instant::now();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let timeout = TICK.saturating_sub(last_step.elapsed());
+            if event::poll(timeout)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match key.code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(());
+                    }
+                    KeyCode::Tab => self.paused = !self.paused,
+                    KeyCode::Enter => {
+                        let input = std::mem::take(&mut self.input);
+                        self.status = self.submit(input.trim());
+                    }
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Char(c) => self.input.push(c),
+                    _ => {}
+                }
+            }
+            if last_step.elapsed() >= TICK {
+                last_step = Instant::now();
+                if !self.paused {
+                    let trades = self.simulator.step();
+                    self.record_trades(trades);
+                }
+            }
+        }
+    }
+
+    fn record_trades(&mut self, trades: Vec<Trade>) {
+        for trade in trades {
+            if self.trades.len() == TRADES_SHOWN {
+                self.trades.pop_back();
+            }
+            self.trades.push_front(trade);
+        }
+    }
+
+    // Apply one typed command, returning the line to show in the status bar
+    fn submit(&mut self, input: &str) -> String {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let side = match words.first() {
+            Some(&"buy") | Some(&"b") => Side::Buy,
+            Some(&"sell") | Some(&"s") => Side::Sell,
+            Some(&"cancel") | Some(&"c") => {
+                return match self.simulator.book_mut().cancel_all_for(USER) {
+                    Ok(order_ids) => format!("Canceled {} orders", order_ids.len()),
+                    Err(error) => error.to_string(),
+                };
+            }
+            _ => return format!("Unknown command {:?}", input),
+        };
+        let Some(Ok(quantity)) = words.get(1).map(|word| word.parse::<Quantity>()) else {
+            return "Expected a quantity".to_string();
+        };
+        let (order_type, price) = match words.get(2).map(|word| word.parse::<Price>()) {
+            Some(Ok(price)) => (OrderType::LimitOrder, price),
+            Some(Err(_)) => return "Expected a price".to_string(),
+            None => (OrderType::MarketOrder, 0),
+        };
+        let order = Arc::new(Order::new(order_type, side, price, quantity).with_owner(USER));
+        match self.simulator.book_mut().add_order(&order) {
+            Ok(result) => {
+                let status = format!(
+                    "{:?} {:?}: {} trades, {} remaining",
+                    side,
+                    result.status,
+                    result.trades.len(),
+                    result.remaining
+                );
+                self.record_trades(result.trades);
+                status
+            }
+            Err(error) => error.to_string(),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, input, status] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [ladder, side_panel] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(36)]).areas(main);
+        let [bbo, trades] =
+            Layout::vertical([Constraint::Length(5), Constraint::Min(0)]).areas(side_panel);
+
+        frame.render_widget(self.ladder(), ladder);
+        frame.render_widget(self.bbo(), bbo);
+        frame.render_widget(self.trade_tape(), trades);
+        frame.render_widget(
+            Paragraph::new(format!("> {}", self.input)).block(Block::bordered().title("Order")),
+            input,
+        );
+        let paused = if self.paused { " [paused]" } else { "" };
+        frame.render_widget(Line::from(format!("{}{}", self.status, paused)), status);
+    }
+
+    // Asks above bids, best prices meeting in the middle, each with a bar scaled to the largest
+    // level shown
+    fn ladder(&self) -> Table<'static> {
+        let book = self.simulator.book();
+        let depth = book.get_depth(LEVELS);
+        let largest = depth
+            .bids
+            .iter()
+            .chain(depth.asks.iter())
+            .map(|level| level.volume)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let bar =
+            |volume: Quantity| "█".repeat((volume as usize * BAR_WIDTH).div_ceil(largest as usize));
+        let asks = depth.asks.iter().rev().map(|level| {
+            Row::new(vec![
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(level.price.to_string()).red(),
+                Cell::from(level.volume.to_string()).red(),
+                Cell::from(bar(level.volume)).red(),
+            ])
+        });
+        let bids = depth.bids.iter().map(|level| {
+            Row::new(vec![
+                Cell::from(format!("{:>width$}", bar(level.volume), width = BAR_WIDTH)).green(),
+                Cell::from(level.volume.to_string()).green(),
+                Cell::from(level.price.to_string()).green(),
+                Cell::from(""),
+                Cell::from(""),
+            ])
+        });
+        let widths = [
+            Constraint::Length(BAR_WIDTH as u16),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(BAR_WIDTH as u16),
+        ];
+        Table::new(asks.chain(bids).collect::<Vec<Row>>(), widths)
+            .header(Row::new(vec!["", "Bid qty", "Price", "Ask qty", ""]).bold())
+            .block(Block::bordered().title(format!("Ladder (seq {})", book.sequence())))
+    }
+
+    fn bbo(&self) -> Paragraph<'static> {
+        let bbo = self.simulator.book().bbo();
+        let price = |price: Option<Price>| price.map_or("-".to_string(), |price| price.to_string());
+        Paragraph::new(vec![
+            Line::from(format!("Bid {} x {}", price(bbo.bid_price), bbo.bid_qty)).green(),
+            Line::from(format!("Ask {} x {}", price(bbo.ask_price), bbo.ask_qty)).red(),
+            Line::from(format!(
+                "Open orders {}",
+                self.simulator.book().open_order_count(USER)
+            )),
+        ])
+        .block(Block::bordered().title("BBO"))
+    }
+
+    fn trade_tape(&self) -> Paragraph<'static> {
+        let lines: Vec<Line> = self
+            .trades
+            .iter()
+            .map(|trade| {
+                let style = match trade.aggressor_side() {
+                    Some(Side::Buy) => Style::new().green(),
+                    Some(Side::Sell) => Style::new().red(),
+                    None => Style::new(),
+                };
+                Line::styled(
+                    format!("{:>8} @ {}", trade.quantity(), trade.price()),
+                    style,
+                )
+            })
+            .collect();
+        Paragraph::new(lines).block(Block::bordered().title("Last trades"))
+    }
+}
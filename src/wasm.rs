@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::orderbook::order::{Order, OrderType, Side, Status};
+use crate::orderbook::orderbook_impl::{MatchResult, OrderBook, Trade};
+use crate::orderbook::types::{OrderId, Price, Quantity};
+
+/// Order entry as a JS object, e.g. `{ side: "Buy", price: 101, quantity: 10 }`. `orderType`
+/// defaults to `"LimitOrder"`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsNewOrder {
+    pub side: Side,
+    #[serde(default)]
+    pub price: Price,
+    pub quantity: Quantity,
+    #[serde(default = "limit_order")]
+    pub order_type: OrderType,
+}
+
+fn limit_order() -> OrderType {
+    OrderType::LimitOrder
+}
+
+/// A trade as handed to JS, ids as strings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsTrade {
+    pub trade_id: String,
+    pub bid_order_id: String,
+    pub ask_order_id: String,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub aggressor: Option<Side>,
+    // Epoch micros
+    pub timestamp: i64,
+}
+
+impl From<&Trade> for JsTrade {
+    fn from(trade: &Trade) -> Self {
+        JsTrade {
+            trade_id: trade.trade_id().to_string(),
+            bid_order_id: trade.bid_order_id().to_string(),
+            ask_order_id: trade.ask_order_id().to_string(),
+            price: trade.price(),
+            quantity: trade.quantity(),
+            aggressor: trade.aggressor_side(),
+            timestamp: trade.timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsMatchResult {
+    pub order_id: String,
+    pub status: Status,
+    pub remaining: Quantity,
+    pub trades: Vec<JsTrade>,
+}
+
+impl From<&MatchResult> for JsMatchResult {
+    fn from(result: &MatchResult) -> Self {
+        JsMatchResult {
+            order_id: result.order_id.to_string(),
+            status: result.status,
+            remaining: result.remaining,
+            trades: result.trades.iter().map(JsTrade::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsLevel {
+    pub price: Price,
+    pub volume: Quantity,
+}
+
+/// Both sides of the book, best price first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsDepth {
+    pub bids: Vec<JsLevel>,
+    pub asks: Vec<JsLevel>,
+}
+
+/// One order book for use from JavaScript, e.g. behind a browser visualization. Values cross
+/// the boundary as plain JS objects; prices and quantities must stay within
+/// `Number.MAX_SAFE_INTEGER`.
+#[wasm_bindgen]
+pub struct WasmOrderBook {
+    book: OrderBook,
+}
+
+impl Default for WasmOrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmOrderBook {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        WasmOrderBook {
+            book: OrderBook::new(),
+        }
+    }
+
+    /// Submit a `JsNewOrder`, returning its `JsMatchResult`.
+    pub fn add(&mut self, order: JsValue) -> Result<JsValue, JsError> {
+        let order: JsNewOrder = serde_wasm_bindgen::from_value(order)?;
+        let result = self.add_order(&order)?;
+        to_js(&result)
+    }
+
+    /// Cancel the resting order with the given id string.
+    pub fn cancel(&mut self, order_id: &str) -> Result<(), JsError> {
+        let order_id: OrderId = order_id.parse()?;
+        self.book.cancel_order(order_id)?;
+        Ok(())
+    }
+
+    /// Top `levels` levels of each side as a `JsDepth`.
+    pub fn depth(&self, levels: usize) -> Result<JsValue, JsError> {
+        to_js(&self.depth_view(levels))
+    }
+
+    /// The latest `limit` trades, oldest first, as `JsTrade`s.
+    pub fn trades(&self, limit: usize) -> Result<JsValue, JsError> {
+        to_js(&self.trade_views(limit))
+    }
+
+    #[wasm_bindgen(js_name = bestBid)]
+    pub fn best_bid(&self) -> Option<f64> {
+        self.book.get_best_bid().map(|price| price as f64)
+    }
+
+    #[wasm_bindgen(js_name = bestAsk)]
+    pub fn best_ask(&self) -> Option<f64> {
+        self.book.get_best_ask().map(|price| price as f64)
+    }
+}
+
+// The JS-independent halves of the bindings, testable off wasm
+impl WasmOrderBook {
+    fn add_order(&mut self, order: &JsNewOrder) -> Result<JsMatchResult, JsError> {
+        let new_order = Order::new(order.order_type, order.side, order.price, order.quantity);
+        let result = self.book.add_order(&Arc::new(new_order))?;
+        Ok(JsMatchResult::from(&result))
+    }
+
+    fn depth_view(&self, levels: usize) -> JsDepth {
+        let depth = self.book.get_depth(levels);
+        let view = |side: &[crate::orderbook::price_level::LevelInfo]| {
+            side.iter()
+                .map(|level| JsLevel {
+                    price: level.price,
+                    volume: level.volume,
+                })
+                .collect()
+        };
+        JsDepth {
+            bids: view(&depth.bids),
+            asks: view(&depth.asks),
+        }
+    }
+
+    fn trade_views(&self, limit: usize) -> Vec<JsTrade> {
+        let trades = self.book.trade_history().trades_between(i64::MIN, i64::MAX);
+        let skip = trades.len().saturating_sub(limit);
+        trades.iter().skip(skip).map(JsTrade::from).collect()
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+#[cfg(test)]
+mod wasm_tests {
+    use super::*;
+
+    #[test]
+    fn check_js_views_of_the_book() {
+        let mut book = WasmOrderBook::new();
+        let ask = JsNewOrder {
+            side: Side::Sell,
+            price: 101,
+            quantity: 10,
+            order_type: OrderType::LimitOrder,
+        };
+        book.add_order(&ask).unwrap();
+        let result = book
+            .add_order(&JsNewOrder {
+                side: Side::Buy,
+                price: 0,
+                quantity: 4,
+                order_type: OrderType::MarketOrder,
+            })
+            .unwrap();
+        assert_eq!((result.status, result.remaining), (Status::Filled, 0));
+        assert_eq!(result.trades[0].price, 101);
+        assert_eq!(result.trades[0].bid_order_id, result.order_id);
+
+        let depth = book.depth_view(5);
+        assert!(depth.bids.is_empty());
+        assert_eq!(
+            depth.asks,
+            vec![JsLevel {
+                price: 101,
+                volume: 6
+            }]
+        );
+        assert_eq!(book.trade_views(10), result.trades);
+        assert_eq!(book.best_ask(), Some(101.0));
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::orderbook::order::{Order, OrderType, Side};
+use crate::orderbook::orderbook_impl::{OrderBook, Trade};
+use crate::orderbook::price_level::LevelInfo;
+use crate::orderbook::types::{OrderId, Price, Quantity, Symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Depth,
+    Trades,
+}
+
+/// JSON messages accepted from WebSocket clients, tagged by `"type"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientRequest {
+    Submit {
+        symbol: Symbol,
+        side: Side,
+        order_type: OrderType,
+        #[serde(default)]
+        price: Price,
+        quantity: Quantity,
+    },
+    Cancel {
+        symbol: Symbol,
+        order_id: OrderId,
+    },
+    Subscribe {
+        symbol: Symbol,
+        channel: Channel,
+    },
+    Unsubscribe {
+        symbol: Symbol,
+        channel: Channel,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeEvent {
+    pub symbol: Symbol,
+    pub bid_order_id: OrderId,
+    pub ask_order_id: OrderId,
+    pub price: Price,
+    pub quantity: Quantity,
+    // None for auction trades
+    pub aggressor: Option<Side>,
+    pub timestamp: i64,
+    // The book's sequence number, for gap detection per symbol
+    pub sequence: u64,
+}
+
+impl TradeEvent {
+    fn new(symbol: &str, trade: &Trade) -> Self {
+        TradeEvent {
+            symbol: symbol.to_string(),
+            bid_order_id: trade.bid_order_id(),
+            ask_order_id: trade.ask_order_id(),
+            price: trade.price(),
+            quantity: trade.quantity(),
+            aggressor: trade.aggressor_side(),
+            timestamp: trade.timestamp(),
+            sequence: trade.sequence(),
+        }
+    }
+}
+
+/// JSON messages sent to WebSocket clients: direct replies and channel updates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    Accepted {
+        symbol: Symbol,
+        order_id: OrderId,
+        trades: Vec<TradeEvent>,
+    },
+    Canceled {
+        symbol: Symbol,
+        order_id: OrderId,
+    },
+    Rejected {
+        reason: String,
+    },
+    Subscribed {
+        symbol: Symbol,
+        channel: Channel,
+    },
+    Unsubscribed {
+        symbol: Symbol,
+        channel: Channel,
+    },
+    Trade(TradeEvent),
+    Depth {
+        symbol: Symbol,
+        bids: Vec<(Price, Quantity)>, // best first
+        asks: Vec<(Price, Quantity)>,
+    },
+}
+
+impl ServerEvent {
+    /// The channel a broadcast update belongs to; `None` for direct replies.
+    pub fn channel(&self) -> Option<(&str, Channel)> {
+        match self {
+            ServerEvent::Trade(trade) => Some((&trade.symbol, Channel::Trades)),
+            ServerEvent::Depth { symbol, .. } => Some((symbol, Channel::Depth)),
+            _ => None,
+        }
+    }
+}
+
+enum BookCommand {
+    Submit(Arc<Order>, oneshot::Sender<ServerEvent>),
+    Cancel(OrderId, oneshot::Sender<ServerEvent>),
+}
+
+/// Routes requests to one matching thread per book and fans market data out on a broadcast channel.
+pub struct Engine {
+    books: HashMap<Symbol, mpsc::UnboundedSender<BookCommand>>,
+    updates: broadcast::Sender<ServerEvent>,
+}
+
+impl Engine {
+    pub fn new(symbols: &[Symbol], depth_levels: usize) -> Self {
+        let (updates, _) = broadcast::channel(1024);
+        let books = symbols
+            .iter()
+            .map(|symbol| {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                let symbol_for_thread = symbol.clone();
+                let updates_for_thread = updates.clone();
+                thread::Builder::new()
+                    .name(format!("book-{symbol}"))
+                    .spawn(move || {
+                        run_book(
+                            symbol_for_thread,
+                            depth_levels,
+                            receiver,
+                            updates_for_thread,
+                        )
+                    })
+                    .expect("failed to spawn matching thread");
+                (symbol.clone(), sender)
+            })
+            .collect();
+        Engine { books, updates }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.updates.subscribe()
+    }
+
+    pub fn has_symbol(&self, symbol: &str) -> bool {
+        self.books.contains_key(symbol)
+    }
+
+    /// Execute a submit or cancel on the owning book's thread. Subscriptions are per connection,
+    /// so they are acknowledged here but tracked by the caller.
+    pub async fn handle(&self, request: ClientRequest) -> ServerEvent {
+        let (symbol, command, reply) = match request {
+            ClientRequest::Submit {
+                symbol,
+                side,
+                order_type,
+                price,
+                quantity,
+            } => {
+                let (reply, response) = oneshot::channel();
+                let order = Arc::new(Order::new(order_type, side, price, quantity));
+                (symbol, BookCommand::Submit(order, reply), response)
+            }
+            ClientRequest::Cancel { symbol, order_id } => {
+                let (reply, response) = oneshot::channel();
+                (symbol, BookCommand::Cancel(order_id, reply), response)
+            }
+            ClientRequest::Subscribe { symbol, channel } => {
+                return match self.has_symbol(&symbol) {
+                    true => ServerEvent::Subscribed { symbol, channel },
+                    false => unknown_symbol(&symbol),
+                };
+            }
+            ClientRequest::Unsubscribe { symbol, channel } => {
+                return ServerEvent::Unsubscribed { symbol, channel };
+            }
+        };
+
+        let Some(book) = self.books.get(&symbol) else {
+            return unknown_symbol(&symbol);
+        };
+        if book.send(command).is_err() {
+            return ServerEvent::Rejected {
+                reason: format!("matching thread for {symbol} has stopped"),
+            };
+        }
+        reply.await.unwrap_or_else(|_| ServerEvent::Rejected {
+            reason: format!("matching thread for {symbol} has stopped"),
+        })
+    }
+}
+
+fn unknown_symbol(symbol: &str) -> ServerEvent {
+    ServerEvent::Rejected {
+        reason: format!("unknown symbol: {symbol}"),
+    }
+}
+
+fn run_book(
+    symbol: Symbol,
+    depth_levels: usize,
+    mut commands: mpsc::UnboundedReceiver<BookCommand>,
+    updates: broadcast::Sender<ServerEvent>,
+) {
+    let mut book = OrderBook::new();
+    while let Some(command) = commands.blocking_recv() {
+        let (reply, response, trades) = match command {
+            BookCommand::Submit(order, reply) => match book.add_order(&order) {
+                Ok(result) => {
+                    let trades: Vec<TradeEvent> = result
+                        .trades
+                        .iter()
+                        .map(|trade| TradeEvent::new(&symbol, trade))
+                        .collect();
+                    let response = ServerEvent::Accepted {
+                        symbol: symbol.clone(),
+                        order_id: order.order_id,
+                        trades: trades.clone(),
+                    };
+                    (reply, response, trades)
+                }
+                Err(error) => (
+                    reply,
+                    ServerEvent::Rejected {
+                        reason: error.to_string(),
+                    },
+                    Vec::new(),
+                ),
+            },
+            BookCommand::Cancel(order_id, reply) => match book.cancel_order(order_id) {
+                Ok(()) => (
+                    reply,
+                    ServerEvent::Canceled {
+                        symbol: symbol.clone(),
+                        order_id,
+                    },
+                    Vec::new(),
+                ),
+                Err(error) => (
+                    reply,
+                    ServerEvent::Rejected {
+                        reason: error.to_string(),
+                    },
+                    Vec::new(),
+                ),
+            },
+        };
+        let changed = !matches!(response, ServerEvent::Rejected { .. });
+
+        // Publish before replying so the caller never sees its ack ahead of the market data.
+        // Send errors only mean nobody is subscribed
+        for trade in trades {
+            let _ = updates.send(ServerEvent::Trade(trade));
+        }
+        if changed {
+            let depth = book.get_depth(depth_levels);
+            let levels = |levels: Vec<LevelInfo>| {
+                levels
+                    .into_iter()
+                    .map(|level| (level.price, level.volume))
+                    .collect()
+            };
+            let _ = updates.send(ServerEvent::Depth {
+                symbol: symbol.clone(),
+                bids: levels(depth.bids),
+                asks: levels(depth.asks),
+            });
+        }
+        let _ = reply.send(response);
+    }
+}
+
+#[cfg(test)]
+mod ws_tests {
+    use super::*;
+
+    #[test]
+    fn check_client_request_json() {
+        let request: ClientRequest = serde_json::from_str(
+            r#"{"type":"submit","symbol":"BTC","side":"Buy","order_type":"LimitOrder","price":100,"quantity":5}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request,
+            ClientRequest::Submit {
+                symbol: "BTC".to_string(),
+                side: Side::Buy,
+                order_type: OrderType::LimitOrder,
+                price: 100,
+                quantity: 5,
+            }
+        );
+
+        let subscribe: ClientRequest =
+            serde_json::from_str(r#"{"type":"subscribe","symbol":"BTC","channel":"trades"}"#)
+                .unwrap();
+        assert!(matches!(
+            subscribe,
+            ClientRequest::Subscribe {
+                channel: Channel::Trades,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn check_engine_matches_and_broadcasts() {
+        let engine = Engine::new(&["BTC".to_string()], 5);
+        let mut updates = engine.subscribe();
+
+        let submit = |side, quantity| ClientRequest::Submit {
+            symbol: "BTC".to_string(),
+            side,
+            order_type: OrderType::LimitOrder,
+            price: 100,
+            quantity,
+        };
+        let ServerEvent::Accepted { order_id, .. } = engine.handle(submit(Side::Sell, 5)).await
+        else {
+            panic!("sell was not accepted");
+        };
+        let ServerEvent::Accepted { trades, .. } = engine.handle(submit(Side::Buy, 2)).await else {
+            panic!("buy was not accepted");
+        };
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].quantity, 2);
+
+        let canceled = engine
+            .handle(ClientRequest::Cancel {
+                symbol: "BTC".to_string(),
+                order_id,
+            })
+            .await;
+        assert!(matches!(canceled, ServerEvent::Canceled { .. }));
+
+        let unknown = engine
+            .handle(ClientRequest::Subscribe {
+                symbol: "ETH".to_string(),
+                channel: Channel::Depth,
+            })
+            .await;
+        assert!(matches!(unknown, ServerEvent::Rejected { .. }));
+
+        let mut channels: Vec<Channel> = Vec::new();
+        while let Ok(update) = updates.try_recv() {
+            channels.push(update.channel().unwrap().1);
+        }
+        // depth after the sell, trade + depth after the buy, depth after the cancel
+        assert_eq!(
+            channels,
+            vec![
+                Channel::Depth,
+                Channel::Trades,
+                Channel::Depth,
+                Channel::Depth
+            ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::env;
+use std::sync::Arc;
+
+use futures_util::{SinkExt, StreamExt};
+use log::{LevelFilter, info, warn};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::Message;
+
+use orderbook::ws::{Channel, ClientRequest, Engine, ServerEvent};
+
+// Usage: server [addr] [symbol...]
+// Defaults to 127.0.0.1:9001 with a single BTC-USD book.
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Info)
+        .init();
+
+    let mut args = env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:9001".to_string());
+    let mut symbols: Vec<String> = args.collect();
+    if symbols.is_empty() {
+        symbols.push("BTC-USD".to_string());
+    }
+
+    let engine = Arc::new(Engine::new(&symbols, 10));
+    let listener = TcpListener::bind(&addr).await.expect("failed to bind");
+    info!("Listening on ws://{} for {:?}", addr, symbols);
+
+    while let Ok((stream, peer)) = listener.accept().await {
+        let engine = Arc::clone(&engine);
+        tokio::spawn(async move {
+            if let Err(error) = serve_connection(engine, stream).await {
+                warn!("Connection {} closed with error: {}", peer, error);
+            }
+        });
+    }
+}
+
+async fn serve_connection(
+    engine: Arc<Engine>,
+    stream: TcpStream,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let socket = tokio_tungstenite::accept_async(stream).await?;
+    let (mut outgoing, mut incoming) = socket.split();
+    let mut updates = engine.subscribe();
+    let mut subscriptions: HashSet<(String, Channel)> = HashSet::new();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(error)) => return Err(error),
+                };
+                let reply = match serde_json::from_str::<ClientRequest>(&text) {
+                    Ok(request) => {
+                        let reply = engine.handle(request).await;
+                        match &reply {
+                            ServerEvent::Subscribed { symbol, channel } => {
+                                subscriptions.insert((symbol.clone(), *channel));
+                            }
+                            ServerEvent::Unsubscribed { symbol, channel } => {
+                                subscriptions.remove(&(symbol.clone(), *channel));
+                            }
+                            _ => {}
+                        }
+                        reply
+                    }
+                    Err(error) => ServerEvent::Rejected { reason: error.to_string() },
+                };
+                send(&mut outgoing, &reply).await?;
+            }
+            update = updates.recv() => {
+                let update = match update {
+                    Ok(update) => update,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Slow subscriber dropped {} updates", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+                let subscribed = update
+                    .channel()
+                    .is_some_and(|(symbol, channel)| subscriptions.contains(&(symbol.to_string(), channel)));
+                if subscribed {
+                    send(&mut outgoing, &update).await?;
+                }
+            }
+        }
+    }
+}
+
+async fn send<S>(
+    outgoing: &mut S,
+    event: &ServerEvent,
+) -> Result<(), tokio_tungstenite::tungstenite::Error>
+where
+    S: SinkExt<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    let json = serde_json::to_string(event).expect("server events always serialize");
+    outgoing.send(Message::Text(json)).await
+}
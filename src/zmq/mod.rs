@@ -0,0 +1,517 @@
+//! ZeroMQ transport for co-located strategy processes, speaking ZMTP 3.0 with the NULL
+//! security mechanism over TCP, so plain libzmq/pyzmq sockets connect to it:
+//!
+//! - `ZmqPublisher` is a PUB socket and a book listener. Each trade, BBO change and level change
+//!   is sent as a two-frame message: the topic (`trade`, `bbo` or `level`) and its JSON. SUB
+//!   sockets filter by topic prefix as usual. Messages are queued per subscriber and written by
+//!   the subscriber's own thread, and a subscriber that falls behind is disconnected.
+//! - `ZmqCommandReceiver` is a PULL socket. PUSH peers send one `wire`-encoded new order,
+//!   cancel or modify per message, and the receiver hands them out as `Command`s.
+//!
+//! A peer sending a frame or message over 1 MiB is disconnected before anything is allocated
+//! for it.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::orderbook::command::Command;
+use crate::orderbook::listener::OrderBookListener;
+use crate::orderbook::order::Side;
+use crate::orderbook::orderbook_impl::Trade;
+use crate::orderbook::price_level::{BboUpdate, LevelInfo};
+use crate::orderbook::wire;
+
+const GREETING_LEN: usize = 64;
+// Frame flag bits
+const MORE: u8 = 0x01;
+const LONG: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+
+// Messages queued for one subscriber; a subscriber this far behind is disconnected rather
+// than stalling the book
+const SUBSCRIBER_QUEUE_LEN: usize = 4_096;
+// A subscriber slower than this on one message is disconnected by its writer thread
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+// Largest frame, or multi-frame message, a peer may send before it is disconnected. Commands are
+// a few hundred bytes, order tags included.
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+// Received commands waiting for `recv`; pushers wait once this many are queued
+const COMMAND_QUEUE_LEN: usize = 65_536;
+
+struct Subscriber {
+    // Kept to disconnect the subscriber
+    stream: TcpStream,
+    // Messages for the subscriber's writer thread
+    queue: SyncSender<Arc<[u8]>>,
+    // Topic prefixes subscribed to; an empty prefix matches everything
+    topics: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+/// PUB socket publishing a book's market data; clones share the socket, so one clone can be
+/// added as a listener while another is kept to watch subscribers.
+#[derive(Clone)]
+pub struct ZmqPublisher {
+    local_addr: SocketAddr,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+#[derive(Serialize)]
+struct LevelUpdate<'a> {
+    side: Side,
+    #[serde(flatten)]
+    level: &'a LevelInfo,
+}
+
+impl ZmqPublisher {
+    /// Bind the PUB socket to `addr`, e.g. `127.0.0.1:5556`, and accept SUB sockets in the
+    /// background.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let publisher = ZmqPublisher {
+            local_addr: listener.local_addr()?,
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+        let subscribers = publisher.subscribers.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let subscribers = subscribers.clone();
+                thread::spawn(move || {
+                    if let Err(error) = serve_subscriber(stream, subscribers) {
+                        debug!(%error, "subscriber disconnected");
+                    }
+                });
+            }
+        });
+        Ok(publisher)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Topic subscriptions held by connected subscribers.
+    pub fn subscriptions(&self) -> usize {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|subscriber| subscriber.topics.lock().unwrap().len())
+            .sum()
+    }
+
+    /// Queue `payload` under `topic` for every subscriber whose subscriptions match it. Each
+    /// subscriber has a writer thread of its own, so this never waits on the network; a
+    /// subscriber whose queue is full, or whose writer has failed, is disconnected.
+    pub fn publish(&self, topic: &str, payload: &[u8]) {
+        let mut message = frame(topic.as_bytes(), MORE);
+        message.extend_from_slice(&frame(payload, 0));
+        let message: Arc<[u8]> = message.into();
+        self.subscribers.lock().unwrap().retain(|subscriber| {
+            let subscribed = subscriber
+                .topics
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|prefix| topic.as_bytes().starts_with(prefix));
+            if !subscribed || subscriber.queue.try_send(message.clone()).is_ok() {
+                return true;
+            }
+            debug!("disconnected a subscriber that fell behind");
+            let _ = subscriber.stream.shutdown(Shutdown::Both);
+            false
+        });
+    }
+
+    fn publish_json<T: Serialize>(&self, topic: &str, value: &T) {
+        match serde_json::to_vec(value) {
+            Ok(payload) => self.publish(topic, &payload),
+            Err(error) => warn!(%error, topic, "market data did not serialize"),
+        }
+    }
+}
+
+impl OrderBookListener for ZmqPublisher {
+    fn on_trade(&mut self, trade: &Trade) {
+        self.publish_json("trade", trade);
+    }
+
+    fn on_bbo(&mut self, update: &BboUpdate) {
+        self.publish_json("bbo", update);
+    }
+
+    fn on_level_change(&mut self, side: Side, level: &LevelInfo) {
+        self.publish_json("level", &LevelUpdate { side, level });
+    }
+}
+
+// Handshake with one SUB socket, then follow its subscriptions until it goes away
+fn serve_subscriber(
+    mut stream: TcpStream,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+) -> io::Result<()> {
+    let peer_type = handshake(&mut stream, "PUB")?;
+    if peer_type != "SUB" && peer_type != "XSUB" {
+        return Err(invalid(format!("{} cannot subscribe", peer_type)));
+    }
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let (queue, queued) = mpsc::sync_channel(SUBSCRIBER_QUEUE_LEN);
+    let out = stream.try_clone()?;
+    // Runs until the subscriber leaves the list and its queue closes, or a write fails
+    thread::spawn(move || write_messages(out, queued));
+    let topics = Arc::new(Mutex::new(Vec::new()));
+    subscribers.lock().unwrap().push(Subscriber {
+        stream: stream.try_clone()?,
+        queue,
+        topics: topics.clone(),
+    });
+
+    let result = follow_subscriptions(&mut stream, &topics);
+    subscribers
+        .lock()
+        .unwrap()
+        .retain(|subscriber| !Arc::ptr_eq(&subscriber.topics, &topics));
+    result
+}
+
+// Write queued messages to a subscriber, disconnecting it on the first failure
+fn write_messages(mut out: TcpStream, queued: Receiver<Arc<[u8]>>) {
+    for message in queued {
+        if out.write_all(&message).is_err() {
+            let _ = out.shutdown(Shutdown::Both);
+            return;
+        }
+    }
+}
+
+fn follow_subscriptions(stream: &mut TcpStream, topics: &Mutex<Vec<Vec<u8>>>) -> io::Result<()> {
+    loop {
+        let (flags, body) = read_frame(stream)?;
+        // ZMTP 3.0 peers subscribe with 0x01/0x00 prefixed messages, 3.1 ones with commands
+        let change = if flags & COMMAND != 0 {
+            match command_name(&body) {
+                Some((b"SUBSCRIBE", topic)) => Some((true, topic.to_vec())),
+                Some((b"CANCEL", topic)) => Some((false, topic.to_vec())),
+                _ => None,
+            }
+        } else {
+            match body.split_first() {
+                Some((1, topic)) => Some((true, topic.to_vec())),
+                Some((0, topic)) => Some((false, topic.to_vec())),
+                _ => None,
+            }
+        };
+        let mut topics = topics.lock().unwrap();
+        match change {
+            Some((true, topic)) => topics.push(topic),
+            Some((false, topic)) => {
+                if let Some(index) = topics.iter().position(|prefix| *prefix == topic) {
+                    topics.remove(index);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// PULL socket taking order commands from PUSH peers. Received commands queue up to a bound,
+/// after which pushers wait for `recv` to catch up.
+pub struct ZmqCommandReceiver {
+    local_addr: SocketAddr,
+    commands: Receiver<Command>,
+}
+
+impl ZmqCommandReceiver {
+    /// Bind the PULL socket to `addr` and accept PUSH sockets in the background.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, commands) = mpsc::sync_channel(COMMAND_QUEUE_LEN);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    if let Err(error) = serve_pusher(stream, sender) {
+                        debug!(%error, "pusher disconnected");
+                    }
+                });
+            }
+        });
+        Ok(ZmqCommandReceiver {
+            local_addr,
+            commands,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The next command received, waiting for one.
+    pub fn recv(&self) -> Option<Command> {
+        self.commands.recv().ok()
+    }
+
+    /// The next command received, None if none arrives within `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Command> {
+        self.commands.recv_timeout(timeout).ok()
+    }
+}
+
+fn serve_pusher(mut stream: TcpStream, commands: SyncSender<Command>) -> io::Result<()> {
+    let peer_type = handshake(&mut stream, "PULL")?;
+    if peer_type != "PUSH" {
+        return Err(invalid(format!("{} cannot push", peer_type)));
+    }
+    let mut message = Vec::new();
+    loop {
+        let (flags, body) = read_frame(&mut stream)?;
+        if flags & COMMAND != 0 {
+            continue;
+        }
+        if message.len() + body.len() > MAX_MESSAGE_LEN {
+            return Err(too_long(message.len() + body.len()));
+        }
+        message.extend_from_slice(&body);
+        if flags & MORE != 0 {
+            continue;
+        }
+        match wire::decode(&message).and_then(|(decoded, _)| decoded.to_command()) {
+            Some(command) => {
+                if commands.send(command).is_err() {
+                    return Ok(());
+                }
+            }
+            None => debug!(len = message.len(), "ignored message without a command"),
+        }
+        message.clear();
+    }
+}
+
+// Exchange greetings and READY commands, returning the peer's socket type
+fn handshake(stream: &mut TcpStream, socket_type: &str) -> io::Result<String> {
+    stream.set_nodelay(true)?;
+    let mut greeting = [0u8; GREETING_LEN];
+    greeting[0] = 0xFF;
+    greeting[9] = 0x7F;
+    // Version 3.0, NULL mechanism, as-server 0
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    stream.write_all(&greeting)?;
+
+    let mut peer = [0u8; GREETING_LEN];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xFF || peer[9] != 0x7F || peer[10] < 3 {
+        return Err(invalid("peer does not speak ZMTP 3".to_string()));
+    }
+    if &peer[12..17] != b"NULL\0" {
+        return Err(invalid("only the NULL mechanism is supported".to_string()));
+    }
+
+    let mut ready = vec![5];
+    ready.extend_from_slice(b"READY");
+    ready.push(11);
+    ready.extend_from_slice(b"Socket-Type");
+    ready.extend_from_slice(&(socket_type.len() as u32).to_be_bytes());
+    ready.extend_from_slice(socket_type.as_bytes());
+    stream.write_all(&frame(&ready, COMMAND))?;
+
+    let (flags, body) = read_frame(stream)?;
+    let properties = match command_name(&body) {
+        Some((b"READY", properties)) if flags & COMMAND != 0 => properties,
+        _ => return Err(invalid("expected READY".to_string())),
+    };
+    property(properties, "Socket-Type")
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+        .ok_or_else(|| invalid("READY without Socket-Type".to_string()))
+}
+
+// Value of the metadata property `name`, matched case-insensitively
+fn property<'a>(mut properties: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    while let Some((&name_len, rest)) = properties.split_first() {
+        let (key, rest) = rest.split_at_checked(name_len as usize)?;
+        let (len, rest) = rest.split_at_checked(4)?;
+        let len = u32::from_be_bytes(len.try_into().ok()?) as usize;
+        let (value, rest) = rest.split_at_checked(len)?;
+        if key.eq_ignore_ascii_case(name.as_bytes()) {
+            return Some(value);
+        }
+        properties = rest;
+    }
+    None
+}
+
+// Split a command body into its name and data
+fn command_name(body: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (&len, rest) = body.split_first()?;
+    rest.split_at_checked(len as usize)
+}
+
+fn frame(body: &[u8], flags: u8) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(body.len() + 9);
+    match u8::try_from(body.len()) {
+        Ok(len) => frame.extend_from_slice(&[flags, len]),
+        Err(_) => {
+            frame.push(flags | LONG);
+            frame.extend_from_slice(&(body.len() as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(body);
+    frame
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut flags = [0u8; 1];
+    stream.read_exact(&mut flags)?;
+    let len = if flags[0] & LONG != 0 {
+        let mut len = [0u8; 8];
+        stream.read_exact(&mut len)?;
+        usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX)
+    } else {
+        let mut len = [0u8; 1];
+        stream.read_exact(&mut len)?;
+        len[0] as usize
+    };
+    // Checked before allocating, the length comes straight from the peer
+    if len > MAX_MESSAGE_LEN {
+        return Err(too_long(len));
+    }
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    Ok((flags[0], body))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn too_long(len: usize) -> io::Error {
+    invalid(format!(
+        "message of {} bytes exceeds {} bytes",
+        len, MAX_MESSAGE_LEN
+    ))
+}
+
+#[cfg(test)]
+mod zmq_tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::orderbook::order::{Order, OrderType};
+    use crate::orderbook::orderbook_impl::OrderBook;
+
+    // A peer socket of `socket_type`, connected and past the handshake
+    fn peer(addr: SocketAddr, socket_type: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        handshake(&mut stream, socket_type).unwrap();
+        stream
+    }
+
+    // Wait until `publisher` has `expected` subscriptions
+    fn await_subscriptions(publisher: &ZmqPublisher, expected: usize) {
+        let start = Instant::now();
+        while publisher.subscriptions() != expected {
+            assert!(start.elapsed() < Duration::from_secs(5), "never subscribed");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn check_a_subscriber_that_falls_behind_is_disconnected() {
+        let publisher = ZmqPublisher::bind("127.0.0.1:0").unwrap();
+        let mut subscriber = peer(publisher.local_addr(), "SUB");
+        subscriber.write_all(&frame(b"\x01", 0)).unwrap();
+        await_subscriptions(&publisher, 1);
+
+        // the subscriber never reads; publishing carries on without waiting for it
+        let payload = vec![b'x'; 1_024];
+        let start = Instant::now();
+        let mut published = 0;
+        while publisher.subscriptions() == 1 {
+            assert!(published < 1_000_000, "subscriber never dropped");
+            publisher.publish("level", &payload);
+            published += 1;
+        }
+        assert!(start.elapsed() < WRITE_TIMEOUT);
+        assert!(published > SUBSCRIBER_QUEUE_LEN);
+    }
+
+    fn assert_disconnected(stream: &mut TcpStream) {
+        match stream.read(&mut [0u8; 1]) {
+            Ok(0) => {}
+            Err(error) if error.kind() == io::ErrorKind::ConnectionReset => {}
+            read => panic!("still connected: {:?}", read),
+        }
+    }
+
+    #[test]
+    fn check_oversized_frames_disconnect_the_peer() {
+        let receiver = ZmqCommandReceiver::bind("127.0.0.1:0").unwrap();
+        let mut pusher = peer(receiver.local_addr(), "PUSH");
+        // a LONG frame claiming an absurd length, with no body behind it
+        let mut header = vec![LONG];
+        header.extend_from_slice(&u64::MAX.to_be_bytes());
+        pusher.write_all(&header).unwrap();
+        assert_disconnected(&mut pusher);
+
+        // multi-frame messages are capped as a whole
+        let mut pusher = peer(receiver.local_addr(), "PUSH");
+        let chunk = vec![0u8; MAX_MESSAGE_LEN / 2 + 1];
+        pusher.write_all(&frame(&chunk, MORE)).unwrap();
+        let _ = pusher.write_all(&frame(&chunk, 0));
+        assert_disconnected(&mut pusher);
+
+        // other peers carry on
+        let mut pusher = peer(receiver.local_addr(), "PUSH");
+        let mut encoder = wire::Encoder::new();
+        encoder.encode_command(&Command::CancelOrder(uuid::Uuid::nil()));
+        pusher.write_all(&frame(encoder.as_bytes(), 0)).unwrap();
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Some(Command::CancelOrder(_))
+        ));
+    }
+
+    #[test]
+    fn check_pub_sub_and_push_pull_over_zmtp() {
+        let publisher = ZmqPublisher::bind("127.0.0.1:0").unwrap();
+        let mut subscriber = peer(publisher.local_addr(), "SUB");
+        subscriber.write_all(&frame(b"\x01trade", 0)).unwrap();
+        await_subscriptions(&publisher, 1);
+
+        let mut book = OrderBook::new();
+        book.add_listener(Box::new(publisher.clone()));
+        for side in [Side::Sell, Side::Buy] {
+            let order = Arc::new(Order::new(OrderType::LimitOrder, side, 100, 5));
+            book.add_order(&order).unwrap();
+        }
+        // BBO and level updates are filtered out, the trade arrives as topic and JSON
+        let (flags, topic) = read_frame(&mut subscriber).unwrap();
+        assert_eq!((flags, topic.as_slice()), (MORE, b"trade".as_slice()));
+        let (_, payload) = read_frame(&mut subscriber).unwrap();
+        let trade: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(trade["price"], 100);
+
+        let receiver = ZmqCommandReceiver::bind("127.0.0.1:0").unwrap();
+        let mut pusher = peer(receiver.local_addr(), "PUSH");
+        let order = Order::new(OrderType::LimitOrder, Side::Buy, 99, 3);
+        let mut encoder = wire::Encoder::new();
+        encoder.encode_new_order(&order);
+        pusher.write_all(&frame(encoder.as_bytes(), 0)).unwrap();
+        match receiver.recv_timeout(Duration::from_secs(5)) {
+            Some(Command::NewOrder(received)) => {
+                assert_eq!((received.order_id, received.price), (order.order_id, 99))
+            }
+            received => panic!("received {:?}", received),
+        }
+    }
+}